@@ -2,25 +2,116 @@ use anyhow::{bail, Context, Result};
 use colored::Colorize;
 use std::{
     io::{self, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
 };
 
 use crate::{
     config, credentials,
-    sequence::{self, AccountEntry, AuthKind, SequenceFile, now_utc},
+    errors::CcswitchError,
+    hooks, platform,
+    sequence::{self, AccountEntry, AuditAction, AuditEvent, AuthKind, SequenceFile, now_utc},
 };
 
+// ── Output options ────────────────────────────────────────────────────────────
+
+/// Set by `--quiet`: the display functions print nothing to stdout, so only
+/// errors (on stderr) and the exit code remain. Prompts are still shown.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// `println!` that respects `--quiet`.
+macro_rules! outln {
+    ($($arg:tt)*) => {
+        if !is_quiet() {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// `print!` that respects `--quiet`.
+macro_rules! out {
+    ($($arg:tt)*) => {
+        if !is_quiet() {
+            print!($($arg)*);
+        }
+    };
+}
+
+/// Output format selected by the global `--format` flag.
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// CLI options that shape how a switch is performed and reported.
+#[derive(Debug, Clone, Default)]
+pub struct SwitchOptions {
+    pub format: OutputFormat,
+    /// Print the list of files written by the switch.
+    pub verbose: bool,
+    /// Emit Prometheus metrics to stderr after the switch.
+    pub metrics: bool,
+    /// Write Prometheus metrics to this file instead of stderr.
+    pub metrics_file: Option<PathBuf>,
+    /// Show what the switch would change without writing anything.
+    pub dry_run: bool,
+    /// Leave out the "Restart Claude Code to apply." line.
+    pub no_restart_hint: bool,
+}
+
+/// Options for `ccswitch add`.
+#[derive(Debug, Clone, Default)]
+pub struct AddOptions {
+    /// Switch priority for the new account.
+    pub priority: u32,
+    /// Add a token account from `$CLAUDE_CODE_OAUTH_TOKEN`, even when an
+    /// OAuth account is logged in.
+    pub from_env: bool,
+    /// Label for a token account instead of asking for one.
+    pub label: Option<String>,
+    /// Never prompt: read a token missing from the environment from stdin,
+    /// and fail instead of asking for a label or replacement token.
+    pub no_prompt: bool,
+    /// Replace the token of a token account that already has the label, and
+    /// succeed without changes when the token is already managed.
+    pub force: bool,
+}
+
 // ── Core functions (no stdout, return descriptive string) ─────────────────────
 
-pub(crate) fn core_add() -> Result<String> {
+/// `user@hostname` recorded as `created_by` on accounts added here.
+pub(crate) fn created_by() -> String {
+    format!("{}@{}", sequence::current_user(), platform::hostname())
+}
+
+/// Add the account Claude Code is logged in to, backing up its credentials
+/// and config. Returns a message for the user; adding an account that is
+/// already managed is not an error.
+pub fn core_add(opts: &AddOptions) -> Result<String, CcswitchError> {
     sequence::setup_dirs()?;
 
-    let email = config::current_email()
-        .context("No active Claude account found. Please log in to Claude Code first.")?;
+    let email = config::current_email().ok_or(CcswitchError::NoActiveAccount)?;
 
-    let mut seq = sequence::load()?;
+    let _lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load().map_err(CcswitchError::from_sequence)?;
 
-    if seq.account_exists(&email) {
+    if let Some(num) = seq.find_by_email(&email) {
+        if seq.accounts[&num.to_string()].uuid.is_empty() {
+            if let Some(oauth) = config::load().ok().and_then(|c| c.get("oauthAccount").cloned()) {
+                backfill_uuid(&mut seq, num, &oauth);
+                sequence::save(&seq)?;
+            }
+        }
         return Ok(format!("Account {} is already managed.", email));
     }
 
@@ -29,11 +120,13 @@ pub(crate) fn core_add() -> Result<String> {
     let now = now_utc();
 
     let live_creds =
-        credentials::read_live().context("Cannot read credentials for the current account")?;
+        credentials::read_live().map_err(|e| CcswitchError::credential_read(None, e))?;
     let live_config = config::load().context("Cannot read current Claude config")?;
-    let live_config_str = serde_json::to_string_pretty(&live_config)?;
+    let live_config_str =
+        serde_json::to_string_pretty(&live_config).map_err(CcswitchError::ConfigCorrupted)?;
 
-    credentials::write_backup(account_num, &email, &live_creds)?;
+    credentials::write_backup(account_num, &email, &live_creds)
+        .map_err(CcswitchError::from_store)?;
     write_config_backup(account_num, &email, &live_config_str)?;
 
     // Record the credential format fingerprint so future switches/refreshes
@@ -50,142 +143,305 @@ pub(crate) fn core_add() -> Result<String> {
             uuid,
             added: now.clone(),
             auth_kind: AuthKind::Oauth,
+            has_config_backup: true,
+            priority: opts.priority,
+            notes: None,
+            last_used: None,
+            switch_count: 0,
+            tags: Vec::new(),
+            expires_at: stored_expiry(&live_creds),
+            created_by: Some(created_by()),
+            machine_id: platform::machine_id(),
+            env_vars: Default::default(),
         },
     );
     seq.sequence.push(account_num);
+    let first = seq.active_account_number.is_none();
     seq.active_account_number = Some(account_num);
     seq.last_updated = now;
 
     sequence::save(&seq)?;
+    record_audit(AuditAction::Add, account_num, &email);
+    if first {
+        let _ = sequence::write_active_account_file(&email);
+    }
 
     Ok(format!("Added {} as Account {}", email, account_num))
 }
 
-pub(crate) fn core_switch(target_num: u32) -> Result<String> {
-    let mut seq = sequence::load()?;
+/// Outcome of `core_switch`: a human-readable message plus every file touched.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SwitchResult {
+    /// What happened, for showing to the user.
+    pub message: String,
+    /// Files written during the switch (keychain entries on macOS are not files).
+    pub files_written: Vec<PathBuf>,
+    /// Number of the account that is now active.
+    pub active_account: u32,
+    /// Set when the switch succeeded but the post-switch hook failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_hook_error: Option<String>,
+    /// Set when `~/.ccswitchrc` now exports different account env vars.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub env_changed: bool,
+}
 
+/// The target's entry plus the current account's number and backup email.
+fn switch_endpoints(
+    seq: &SequenceFile,
+    target_num: u32,
+) -> Result<(AccountEntry, u32, String), CcswitchError> {
     let target_entry = seq
         .accounts
         .get(&target_num.to_string())
         .cloned()
-        .with_context(|| format!("Account {target_num} does not exist"))?;
-    let target_email = target_entry.email.clone();
-    let target_auth_kind = target_entry.auth_kind.clone();
-
+        .ok_or_else(|| CcswitchError::AccountNotFound(target_num.to_string()))?;
     // Resolve current account — works for both OAuth (config) and token (seq state)
-    let (current_num, current_slot_email) = resolve_current_account(&seq)?;
+    let (current_num, current_email) = resolve_current_account(seq)?;
+    Ok((target_entry, current_num, current_email))
+}
 
-    if target_num == current_num {
-        return Ok(format!(
-            "Already using {} (Account {}).",
-            target_email, target_num
-        ));
+/// Make account `target_num` the one Claude Code uses: back up the current
+/// account's credentials and config, then restore the target's.
+pub fn core_switch(target_num: u32) -> Result<SwitchResult, CcswitchError> {
+    switch_account(target_num, false)
+}
+
+/// Switch to `target_num`. With `undo`, the switch only goes ahead if
+/// `target_num` is still the previous account once the lock is held, and
+/// forgets the previous account in the same save, so an undo cannot race
+/// another switch or be applied twice.
+fn switch_account(target_num: u32, undo: bool) -> Result<SwitchResult, CcswitchError> {
+    // The pre-switch hook runs before the lock is taken, like the post-switch
+    // hook after it is released, so the hook itself may run ccswitch.
+    let hooks = hooks::load()?;
+    if let Some(pre) = &hooks.pre_switch {
+        let seq = sequence::load().map_err(CcswitchError::from_sequence)?;
+        let (target_entry, current_num, current_email) = switch_endpoints(&seq, target_num)?;
+        if current_num != target_num {
+            let env = hooks::switch_env(current_num, &current_email, target_num, &target_entry.email);
+            hooks::run_hook(pre, &env).map_err(CcswitchError::HookAborted)?;
+        }
     }
 
-    let current_auth_kind = seq
-        .accounts
-        .get(&current_num.to_string())
-        .map(|e| e.auth_kind.clone())
-        .unwrap_or_default();
+    let lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load().map_err(CcswitchError::from_sequence)?;
+    let mut files_written: Vec<PathBuf> = Vec::new();
+    if undo && undo_target(&seq)? != target_num {
+        return Err(anyhow::anyhow!(
+            "Cannot undo: another switch happened meanwhile. Run `ccswitch undo` again."
+        )
+        .into());
+    }
 
-    // Step 1: Snapshot current account
-    // OAuth accounts: save live credentials + config (they can be refreshed by Claude Code)
-    // Token accounts: skip — the token is static and was already stored during `add`
-    if current_auth_kind == AuthKind::Oauth {
-        let live_creds = credentials::read_live().context("Cannot read current credentials")?;
-        warn_if_format_changed(&seq, &live_creds);
-        let live_config = config::load().context("Cannot read current Claude config")?;
-        let live_config_str = serde_json::to_string_pretty(&live_config)?;
+    let (target_entry, current_num, current_slot_email) = switch_endpoints(&seq, target_num)?;
+    let target_email = target_entry.email.clone();
+    let target_auth_kind = target_entry.auth_kind.clone();
 
-        credentials::write_backup(current_num, &current_slot_email, &live_creds)?;
-        write_config_backup(current_num, &current_slot_email, &live_config_str)?;
+    if target_num == current_num {
+        return Ok(SwitchResult {
+            message: format!("Already using {} (Account {}).", target_email, target_num),
+            files_written,
+            active_account: target_num,
+            post_hook_error: None,
+            env_changed: false,
+        });
     }
 
-    // Step 2: Read target credentials backup
-    let target_creds = credentials::read_backup(target_num, &target_email)
-        .with_context(|| format!("Missing credentials backup for Account {target_num}"))?;
+    let hook_env = hooks::switch_env(current_num, &current_slot_email, target_num, &target_email);
 
-    // Step 3: Activate target account
-    match target_auth_kind {
-        AuthKind::Oauth => {
-            let target_config_str = read_config_backup(target_num, &target_email)
-                .with_context(|| format!("Missing config backup for Account {target_num}"))?;
-            let target_config: serde_json::Value = serde_json::from_str(&target_config_str)
-                .context("Invalid JSON in config backup")?;
-            let target_oauth = target_config
-                .get("oauthAccount")
-                .cloned()
-                .context("Missing oauthAccount in config backup")?;
-
-            credentials::write_live(&target_creds).context("Failed to write credentials")?;
-
-            let mut active_config =
-                config::load().context("Cannot read live config for merge")?;
-            active_config["oauthAccount"] = target_oauth;
-            config::save(&active_config).context("Failed to save merged config")?;
+    // Steps 1–4 run together so every attempt, successful or not, is logged.
+    let outcome = (|| -> Result<(), CcswitchError> {
+        let current_auth_kind = seq
+            .accounts
+            .get(&current_num.to_string())
+            .map(|e| e.auth_kind.clone())
+            .unwrap_or_default();
+
+        // Step 1: Snapshot current account
+        // OAuth accounts: save live credentials + config (they can be refreshed by Claude Code)
+        // Token accounts: skip — the token is static and was already stored during `add`
+        if current_auth_kind == AuthKind::Oauth {
+            let live_creds = credentials::read_live()
+                .map_err(|e| CcswitchError::credential_read(None, e))?;
+            warn_if_format_changed(&seq, &live_creds);
+            let live_config = config::load().context("Cannot read current Claude config")?;
+            let live_config_str = serde_json::to_string_pretty(&live_config)
+                .map_err(CcswitchError::ConfigCorrupted)?;
+
+            if let Some(entry) = seq.accounts.get_mut(&current_num.to_string()) {
+                entry.expires_at = stored_expiry(&live_creds);
+            }
+            if let Some(oauth) = live_config.get("oauthAccount") {
+                backfill_uuid(&mut seq, current_num, oauth);
+            }
+            credentials::write_backup(current_num, &current_slot_email, &live_creds)
+                .map_err(CcswitchError::from_store)?;
+            files_written.extend(credentials::backup_file_path(current_num, &current_slot_email));
+            write_config_backup(current_num, &current_slot_email, &live_config_str)?;
+            files_written.push(config_backup_path(current_num, &current_slot_email));
         }
-        AuthKind::Token => {
-            let token = extract_access_token(&target_creds)?;
-            // Write directly to the live credentials keychain so Claude Code
-            // picks it up on next restart — no CLAUDE_CODE_OAUTH_TOKEN needed.
-            credentials::write_live_token(&token)
-                .context("Failed to write token to live credentials")?;
-            // Keep ccswitch-active-token updated for verification purposes.
-            let _ = credentials::write_active_token(&token);
-            // Clear oauthAccount from config — token accounts have no profile.
-            if let Ok(mut cfg) = config::load() {
-                if let Some(obj) = cfg.as_object_mut() {
-                    obj.remove("oauthAccount");
+
+        // Step 2: Read target credentials backup
+        let target_creds = credentials::read_backup(target_num, &target_email)
+            .map_err(|e| CcswitchError::credential_read(Some(target_num), e))?;
+
+        // Step 3: Activate target account
+        match target_auth_kind {
+            AuthKind::Oauth => {
+                let target_config_str = read_config_backup(target_num, &target_email)
+                    .with_context(|| format!("Missing config backup for Account {target_num}"))?;
+                let target_config: serde_json::Value = serde_json::from_str(&target_config_str)
+                    .map_err(CcswitchError::ConfigCorrupted)?;
+                let target_oauth = target_config.get("oauthAccount").cloned().ok_or_else(|| {
+                    CcswitchError::ConfigCorrupted(serde::de::Error::missing_field("oauthAccount"))
+                })?;
+                backfill_uuid(&mut seq, target_num, &target_oauth);
+
+                if seq.live_is_symlink {
+                    let backup = credentials::link_live(target_num, &target_email)
+                        .context("Failed to link credentials")?;
+                    files_written.push(backup);
+                } else {
+                    credentials::write_live(&target_creds)
+                        .context("Failed to write credentials")?;
+                }
+                files_written.extend(credentials::live_file_path());
+
+                let mut active_config =
+                    config::load_with_path().context("Cannot read live config for merge")?;
+                active_config.value["oauthAccount"] = target_oauth;
+                config::save(&active_config.path, &active_config.value)
+                    .context("Failed to save merged config")?;
+                files_written.push(active_config.path);
+            }
+            AuthKind::Token => {
+                let token = extract_access_token(&target_creds)?;
+                // Write directly to the live credentials keychain so Claude Code
+                // picks it up on next restart — no CLAUDE_CODE_OAUTH_TOKEN needed.
+                credentials::write_live_token(&token)
+                    .context("Failed to write token to live credentials")?;
+                files_written.extend(credentials::live_file_path());
+                // Token backups aren't in Claude Code's format, so writing
+                // the token replaced any `ccswitch link` symlink.
+                seq.live_is_symlink = false;
+                // Keep ccswitch-active-token updated for verification purposes.
+                if credentials::write_active_token(&token).is_ok()
+                    && crate::platform::detect() != crate::platform::Platform::MacOS
+                {
+                    files_written.push(credentials::active_token_file_path());
+                }
+                // Clear oauthAccount from config — token accounts have no profile.
+                if let Ok(mut cfg) = config::load_with_path() {
+                    if let Some(obj) = cfg.value.as_object_mut() {
+                        obj.remove("oauthAccount");
+                    }
+                    if config::save(&cfg.path, &cfg.value).is_ok() {
+                        files_written.push(cfg.path);
+                    }
                 }
-                let _ = config::save(&cfg);
             }
         }
-    }
 
-    // Step 4: Persist updated state
-    seq.active_account_number = Some(target_num);
-    seq.last_updated = now_utc();
-    sequence::save(&seq)?;
+        // Step 4: Persist updated state
+        let now = now_utc();
+        if let Some(entry) = seq.accounts.get_mut(&target_num.to_string()) {
+            entry.switch_count += 1;
+            entry.last_used = Some(now.clone());
+            entry.expires_at = stored_expiry(&target_creds);
+        }
+        // An undo forgets the previous account so it cannot be undone twice.
+        seq.previous_account_number = (!undo).then_some(current_num);
+        seq.active_account_number = Some(target_num);
+        seq.last_updated = now;
+        sequence::save(&seq)?;
+        files_written.push(sequence::sequence_path());
+        Ok(())
+    })();
+    // The switch itself already succeeded or failed; a log write error must not change that.
+    let _ = sequence::append_switch_log(current_num, target_num, outcome.is_ok());
+    let _ = sequence::append_audit_log(AuditEvent {
+        ok: outcome.is_ok(),
+        ..AuditEvent::new(AuditAction::Switch, target_num, &target_email)
+    });
+    outcome?;
+    // Like the log above, a failed rc or .active write must not fail a finished switch.
+    let _ = sequence::write_active_account_file(&target_email);
+    let env_changed = write_rc_env(&target_email, &target_entry.env_vars).unwrap_or(false);
+
+    // Release the lock first so the hook itself may run ccswitch commands.
+    drop(lock);
+    let post_hook_error = hooks
+        .post_switch
+        .as_ref()
+        .and_then(|post| hooks::run_hook(post, &hook_env).err())
+        .map(|e| e.to_string());
+
+    if crate::config_user::load_user_config().is_ok_and(|c| c.notify) {
+        let body = format!("Switched to {} (Account {})", target_email, target_num);
+        let _ = crate::notifications::send("ccswitch", &body);
+    }
 
-    Ok(format!(
-        "Switched {} → {} (Account {}). Restart Claude Code to apply.",
-        current_slot_email, target_email, target_num
-    ))
+    Ok(SwitchResult {
+        message: format!(
+            "Switched {} → {} (Account {}). Restart Claude Code to apply.",
+            current_slot_email, target_email, target_num
+        ),
+        files_written,
+        active_account: target_num,
+        post_hook_error,
+        env_changed,
+    })
 }
 
-pub(crate) fn core_remove(num: u32, email: &str) -> Result<String> {
-    let mut seq = sequence::load()?;
+/// Stop managing account `num`, deleting its credential and config backups.
+pub fn core_remove(num: u32, email: &str) -> Result<String, CcswitchError> {
+    let _lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load().map_err(CcswitchError::from_sequence)?;
+    refuse_if_linked(&seq, Some(num), "Removing this account")?;
 
-    credentials::delete_backup(num, email)?;
+    credentials::delete_backup(num, email).map_err(CcswitchError::from_store)?;
     let _ = std::fs::remove_file(config_backup_path(num, email));
 
     seq.accounts.remove(&num.to_string());
     seq.sequence.retain(|&n| n != num);
+    if seq.previous_account_number == Some(num) {
+        seq.previous_account_number = None;
+    }
+    if seq.pinned_account == Some(num) {
+        seq.pinned_account = None;
+    }
+    seq.schedules.retain(|s| s.account_num != num);
+    seq.aliases.retain(|_, &mut n| n != num);
     seq.last_updated = now_utc();
 
     sequence::save(&seq)?;
+    record_audit(AuditAction::Remove, num, email);
 
     Ok(format!("Removed Account {} ({})", num, email))
 }
 
 // ── Add current account ───────────────────────────────────────────────────────
 
-pub fn add() -> Result<()> {
+pub fn add(opts: &AddOptions) -> Result<()> {
     // Route to the token flow when:
     // 1. No oauthAccount in config (pure token user), OR
     // 2. CLAUDE_CODE_OAUTH_TOKEN is set — the env var takes priority over the
     //    credentials file, so even if a stale oauthAccount exists in config,
     //    the user is effectively running in token mode.
-    if config::current_email().is_none() || config::has_env_token() {
-        return token_add_flow();
+    if opts.from_env || config::current_email().is_none() || config::has_env_token() {
+        return token_add_flow(opts);
+    }
+    if opts.label.is_some() {
+        bail!("--label only applies to token accounts; an OAuth account is named by its email.");
     }
 
-    match core_add()? {
+    match core_add(opts)? {
         msg if msg.contains("already managed") => {
-            println!("  {} {}", "·".yellow(), msg);
+            outln!("  {} {}", "·".yellow(), msg);
         }
         msg => {
-            println!("  {} {}", "✓".green().bold(), msg);
+            outln!("  {} {}", "✓".green().bold(), msg);
         }
     }
     Ok(())
@@ -193,28 +449,39 @@ pub fn add() -> Result<()> {
 
 // ── Interactive token-account add (CLI only) ──────────────────────────────────
 
-fn token_add_flow() -> Result<()> {
-    println!();
-    println!(
-        "  {} No active Claude account found via OAuth.",
-        "·".yellow()
-    );
-    println!(
-        "  {} Looks like you're using a long-lived token (claude setup-token).",
-        "·".yellow()
-    );
-    println!();
+fn token_add_flow(opts: &AddOptions) -> Result<()> {
+    outln!();
+    if !opts.from_env {
+        outln!(
+            "  {} No active Claude account found via OAuth.",
+            "·".yellow()
+        );
+        outln!(
+            "  {} Looks like you're using a long-lived token (claude setup-token).",
+            "·".yellow()
+        );
+        outln!();
+    }
 
     // If the token is already in the environment, use it directly — no need to paste.
-    let token = if let Ok(env_token) = std::env::var("CLAUDE_CODE_OAUTH_TOKEN") {
-        let t = env_token.trim().to_string();
-        if !t.is_empty() {
-            println!("  {} Using token from $CLAUDE_CODE_OAUTH_TOKEN.", "·".cyan());
-            println!();
-            t
-        } else {
-            prompt_token()?
+    let env_token = std::env::var("CLAUDE_CODE_OAUTH_TOKEN")
+        .map(|t| t.trim().to_string())
+        .unwrap_or_default();
+    let token = if !env_token.is_empty() {
+        outln!("  {} Using token from $CLAUDE_CODE_OAUTH_TOKEN.", "·".cyan());
+        outln!();
+        env_token
+    } else if opts.no_prompt {
+        use std::io::IsTerminal;
+        if io::stdin().is_terminal() {
+            bail!(
+                "No token in $CLAUDE_CODE_OAUTH_TOKEN, and --no-prompt forbids asking for one. \
+                 Pipe the token on stdin instead."
+            );
         }
+        read_piped_token(io::stdin().lock())?
+    } else if opts.from_env {
+        bail!("--from-env was given but $CLAUDE_CODE_OAUTH_TOKEN is not set.");
     } else {
         prompt_token()?
     };
@@ -225,16 +492,23 @@ fn token_add_flow() -> Result<()> {
 
     // Check whether this token is already managed (by value, not just label)
     sequence::setup_dirs()?;
+    let _lock = sequence::acquire_lock()?;
     let mut seq = sequence::load()?;
 
     let token = if let Some((existing_num, existing_email)) = find_account_by_token(&seq, &token) {
-        println!(
+        outln!(
             "  {} Already managed as {} {}",
             "·".yellow(),
             existing_email.bold(),
             format!("(Account {})", existing_num).dimmed()
         );
-        println!();
+        outln!();
+        if opts.force {
+            return Ok(());
+        }
+        if opts.no_prompt {
+            bail!("Token is already managed as {} (Account {}).", existing_email, existing_num);
+        }
         let new = rpassword::prompt_password(
             "  Paste a different token to add another account (Enter to cancel): ",
         )?;
@@ -259,12 +533,18 @@ fn token_add_flow() -> Result<()> {
     let default_label = token_default_label();
     let display_default = email_hint.as_deref().unwrap_or(&default_label);
 
-    print!("  Email / label for this account [{}]: ", display_default);
-    io::stdout().flush()?;
+    let label = match opts.label.as_deref().map(str::trim) {
+        Some(label) if !label.is_empty() => label.to_string(),
+        _ if opts.no_prompt => String::new(),
+        _ => {
+            print!("  Email / label for this account [{}]: ", display_default);
+            io::stdout().flush()?;
 
-    let mut label_input = String::new();
-    io::stdin().read_line(&mut label_input)?;
-    let label = label_input.trim().to_string();
+            let mut label_input = String::new();
+            io::stdin().read_line(&mut label_input)?;
+            label_input.trim().to_string()
+        }
+    };
 
     let email = if label.is_empty() {
         email_hint.unwrap_or(default_label)
@@ -272,24 +552,24 @@ fn token_add_flow() -> Result<()> {
         label
     };
 
-    // Check for duplicate label
-    if seq.account_exists(&email) {
-        bail!("Account {} is already managed.", email);
+    // Check for duplicate label; --force replaces a token account's token.
+    let replaced = seq.find_by_email(&email);
+    if let Some(num) = replaced {
+        if !opts.force {
+            bail!("Account {} is already managed. Pass --force to replace its token.", email);
+        }
+        if seq.accounts[&num.to_string()].auth_kind != AuthKind::Token {
+            bail!("Account {} is an OAuth account; --force only replaces tokens.", email);
+        }
     }
 
-    let account_num = seq.next_account_number();
+    let account_num = replaced.unwrap_or_else(|| seq.next_account_number());
     let now = now_utc();
 
     // Store token as a JSON blob so it can be round-tripped by extract_access_token
     let token_json = serde_json::json!({ "token": token }).to_string();
     credentials::write_backup(account_num, &email, &token_json)?;
 
-    // Store a config snapshot (may lack oauthAccount — that's fine for token accounts)
-    let config_backup = config::load()
-        .map(|v| serde_json::to_string_pretty(&v).unwrap_or_else(|_| "{}".to_string()))
-        .unwrap_or_else(|_| "{}".to_string());
-    write_config_backup(account_num, &email, &config_backup)?;
-
     // Write token to the live credentials keychain so Claude Code reads it
     // directly — no CLAUDE_CODE_OAUTH_TOKEN env var needed.
     credentials::write_live_token(&token)?;
@@ -300,57 +580,83 @@ fn token_add_flow() -> Result<()> {
     let newly_created = credentials::ensure_ccswitchrc()?;
 
     // Persist to sequence
-    seq.accounts.insert(
-        account_num.to_string(),
-        AccountEntry {
-            email: email.clone(),
-            uuid: String::new(),
-            added: now.clone(),
-            auth_kind: AuthKind::Token,
-        },
-    );
-    seq.sequence.push(account_num);
+    if replaced.is_none() {
+        seq.accounts.insert(
+            account_num.to_string(),
+            AccountEntry {
+                email: email.clone(),
+                uuid: String::new(),
+                added: now.clone(),
+                auth_kind: AuthKind::Token,
+                // Token accounts never restore oauthAccount, so no config snapshot is kept.
+                has_config_backup: false,
+                priority: opts.priority,
+                notes: None,
+                last_used: None,
+                switch_count: 0,
+                tags: Vec::new(),
+                expires_at: None,
+                created_by: Some(created_by()),
+                machine_id: platform::machine_id(),
+                env_vars: Default::default(),
+            },
+        );
+        seq.sequence.push(account_num);
+    }
     seq.active_account_number = Some(account_num);
     seq.last_updated = now;
 
     sequence::save(&seq)?;
-
-    println!();
-    println!("  {} Token stored securely.", "✓".green().bold());
-    println!(
-        "  {} Added {} as Account {} {}",
-        "✓".green().bold(),
-        email.bold(),
-        account_num,
-        "(token)".dimmed()
-    );
+    record_audit(AuditAction::Add, account_num, &email);
+    // The token is now the live login, as after a switch.
+    let _ = sequence::write_active_account_file(&email);
+
+    outln!();
+    outln!("  {} Token stored securely.", "✓".green().bold());
+    if replaced.is_some() {
+        outln!(
+            "  {} Replaced the token of {} (Account {})",
+            "✓".green().bold(),
+            email.bold(),
+            account_num
+        );
+    } else {
+        outln!(
+            "  {} Added {} as Account {} {}",
+            "✓".green().bold(),
+            email.bold(),
+            account_num,
+            "(token)".dimmed()
+        );
+    }
 
     if newly_created {
         let rc_path = credentials::ccswitchrc_path();
-        println!();
-        println!(
+        outln!();
+        outln!(
             "  {}",
             "── One-time setup ──────────────────────────────────────────".dimmed()
         );
-        println!(
+        outln!(
             "  Add this line to {} (or {}):\n",
             "~/.zshrc".cyan().bold(),
             "~/.bashrc".cyan()
         );
-        println!(
+        outln!(
             "      source {}",
             rc_path.display().to_string().cyan().bold()
         );
-        println!();
-        println!("  This clears CLAUDE_CODE_OAUTH_TOKEN so Claude Code");
-        println!("  reads credentials from the keychain on every restart.");
-        println!(
+        outln!();
+        outln!("  This clears CLAUDE_CODE_OAUTH_TOKEN so Claude Code");
+        outln!("  reads credentials from the keychain on every restart.");
+        outln!("  Or run {} to add it for you.", "ccswitch shell-setup".cyan().bold());
+        outln!(
             "  {}",
             "────────────────────────────────────────────────────────────".dimmed()
         );
     }
 
-    println!();
+    outln!();
     Ok(())
 }
 
@@ -360,6 +666,17 @@ fn prompt_token() -> Result<String> {
     Ok(token.trim().to_string())
 }
 
+/// The token piped to `add --no-prompt`: the first non-blank line of `input`.
+fn read_piped_token(input: impl io::BufRead) -> Result<String> {
+    for line in input.lines() {
+        let line = line.context("Failed to read token from stdin")?;
+        if !line.trim().is_empty() {
+            return Ok(line.trim().to_string());
+        }
+    }
+    bail!("No token on stdin.")
+}
+
 /// Generate a unique default label for a token account.
 fn token_default_label() -> String {
     // Use a hex timestamp so each invocation gets a distinct default
@@ -379,38 +696,35 @@ pub(crate) fn is_invalid_grant_error(e: &anyhow::Error) -> bool {
 /// Interactive CLI prompt shown when a refresh token is permanently invalid.
 /// Prints re-auth instructions, then offers to remove the account.
 pub(crate) fn interactive_reauth_prompt(num: u32, email: &str) -> Result<()> {
-    println!();
-    println!(
+    outln!();
+    outln!(
         "  {} Refresh token for Account {} ({}) has expired (invalid_grant).",
         "!".red().bold(),
         num,
         email.yellow()
     );
-    println!(
+    outln!(
         "  {} The token cannot be renewed automatically — \
          you need to log in again.",
         " ".normal()
     );
-    println!();
-    println!("  To re-authenticate this account:");
-    println!(
-        "    {}  {}  {}",
+    outln!();
+    outln!("  To re-authenticate this account:");
+    outln!(
+        "    {}  Switch to it:     {}",
         "1.".cyan().bold(),
-        "Switch to it:   ",
         format!("ccswitch switch {}", num).cyan().bold()
     );
-    println!(
-        "    {}  {}",
-        "2.".cyan().bold(),
-        "Open Claude Code and log in (run: claude)"
+    outln!(
+        "    {}  Open Claude Code and log in (run: claude)",
+        "2.".cyan().bold()
     );
-    println!(
-        "    {}  {}  {}",
+    outln!(
+        "    {}  Save the session:  {}",
         "3.".cyan().bold(),
-        "Save the session:",
         "ccswitch add".cyan().bold()
     );
-    println!();
+    outln!();
 
     print!("  Remove Account {} ({}) now? [y/N] ", num, email);
     io::stdout().flush()?;
@@ -420,18 +734,20 @@ pub(crate) fn interactive_reauth_prompt(num: u32, email: &str) -> Result<()> {
 
     if matches!(input.trim(), "y" | "Y") {
         let msg = core_remove(num, email)?;
-        println!("\n  {} {}", "✓".green().bold(), msg);
+        outln!("\n  {} {}", "✓".green().bold(), msg);
     } else {
-        println!("  {} Kept — re-authenticate when ready.", "·".dimmed());
+        outln!("  {} Kept — re-authenticate when ready.", "·".dimmed());
     }
-    println!();
+    outln!();
 
     Ok(())
 }
 
 // ── Refresh OAuth token ───────────────────────────────────────────────────────
 
-pub(crate) fn core_refresh(target_num: u32) -> Result<String> {
+/// Refresh the OAuth session of account `target_num` and store the new
+/// credentials. Token accounts have nothing to refresh and return a message.
+pub fn core_refresh(target_num: u32) -> Result<String> {
     let seq = sequence::load()?;
 
     let entry = seq
@@ -473,9 +789,21 @@ pub(crate) fn core_refresh(target_num: u32) -> Result<String> {
     })?;
 
     credentials::write_backup(target_num, &entry.email, &new_creds)?;
-    if is_active {
+    if is_active && seq.live_is_symlink {
+        // Rewrite the linked backup bare and keep the link in place.
+        credentials::link_live(target_num, &entry.email)
+            .context("Failed to relink refreshed credentials")?;
+    } else if is_active {
         credentials::write_live(&new_creds).context("Failed to write refreshed credentials")?;
     }
+    {
+        let _lock = sequence::acquire_lock()?;
+        let mut seq = sequence::load()?;
+        if let Some(e) = seq.accounts.get_mut(&target_num.to_string()) {
+            e.expires_at = stored_expiry(&new_creds);
+            sequence::save(&seq)?;
+        }
+    }
 
     Ok(format!("Refreshed token for Account {} ({})", target_num, entry.email))
 }
@@ -508,10 +836,10 @@ pub fn refresh(identifier: Option<&str>, all: bool) -> Result<()> {
             .context("No active account found. Pass an account number or email.")?
     };
 
-    println!();
+    outln!();
     match core_refresh(target_num) {
         Ok(msg) => {
-            println!("  {} {}\n", "✓".green().bold(), msg);
+            outln!("  {} {}\n", "✓".green().bold(), msg);
         }
         Err(e) if is_invalid_grant_error(&e) => {
             let email = seq
@@ -533,7 +861,7 @@ fn refresh_all(seq: &SequenceFile) -> Result<()> {
     let mut n_skipped = 0u32;
     let mut failures: Vec<String> = vec![];
 
-    println!();
+    outln!();
 
     for &num in &seq.sequence {
         let Some(entry) = seq.accounts.get(&num.to_string()) else {
@@ -541,7 +869,7 @@ fn refresh_all(seq: &SequenceFile) -> Result<()> {
         };
 
         if entry.auth_kind == AuthKind::Token {
-            println!(
+            outln!(
                 "  {}  Account {} ({}) — skipped (token account)",
                 "·".dimmed(),
                 num,
@@ -561,11 +889,11 @@ fn refresh_all(seq: &SequenceFile) -> Result<()> {
         let needs_refresh = match creds_result {
             Err(_) => true,
             Ok(ref c) => credentials::oauth_secs_remaining(c)
-                .map_or(false, |secs| secs <= THRESHOLD_SECS),
+                .is_some_and(|secs| secs <= THRESHOLD_SECS),
         };
 
         if !needs_refresh {
-            println!(
+            outln!(
                 "  {}  Account {} ({}) — healthy, skipped",
                 "·".dimmed(),
                 num,
@@ -577,13 +905,13 @@ fn refresh_all(seq: &SequenceFile) -> Result<()> {
 
         match core_refresh(num) {
             Ok(msg) => {
-                println!("  {}  {}", "✓".green().bold(), msg);
+                outln!("  {}  {}", "✓".green().bold(), msg);
                 n_refreshed += 1;
             }
             Err(e) => {
                 let first_line = e.to_string();
                 let first_line = first_line.lines().next().unwrap_or("error");
-                println!(
+                outln!(
                     "  {}  Account {} ({}) — {}",
                     "✗".red().bold(),
                     num,
@@ -595,8 +923,8 @@ fn refresh_all(seq: &SequenceFile) -> Result<()> {
         }
     }
 
-    println!();
-    println!(
+    outln!();
+    outln!(
         "  Summary — refreshed: {}  skipped: {}  failed: {}",
         n_refreshed.to_string().bold(),
         n_skipped.to_string().dimmed(),
@@ -606,7 +934,7 @@ fn refresh_all(seq: &SequenceFile) -> Result<()> {
             failures.len().to_string().red().bold()
         }
     );
-    println!();
+    outln!();
 
     if !failures.is_empty() {
         bail!("{} account(s) failed to refresh", failures.len());
@@ -615,9 +943,116 @@ fn refresh_all(seq: &SequenceFile) -> Result<()> {
     Ok(())
 }
 
+// ── Replace a token account's token ───────────────────────────────────────────
+
+/// Store `token` as the new token for token account `num`, keeping its label,
+/// notes, tags and place in the rotation. Also rewrites the live token when
+/// `num` is the active account.
+pub(crate) fn core_refresh_token(num: u32, token: &str) -> Result<String> {
+    let token = token.trim();
+    if token.is_empty() {
+        bail!("No token provided.");
+    }
+    if !token.starts_with("sk-ant-") {
+        bail!("That doesn't look like a Claude token (expected it to start with sk-ant-).");
+    }
+
+    let _lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load()?;
+    let entry = seq
+        .accounts
+        .get(&num.to_string())
+        .cloned()
+        .with_context(|| format!("Account {num} does not exist"))?;
+    if entry.auth_kind != AuthKind::Token {
+        bail!(
+            "Account {} ({}) is an OAuth account; use `ccswitch refresh {}` instead.",
+            num,
+            entry.email,
+            num
+        );
+    }
+    if let Some((other, email)) = find_account_by_token(&seq, token).filter(|(n, _)| *n != num) {
+        bail!("That token is already managed as {} (Account {}).", email, other);
+    }
+
+    let token_json = serde_json::json!({ "token": token }).to_string();
+    credentials::write_backup(num, &entry.email, &token_json)?;
+    if seq.active_account_number == Some(num) {
+        credentials::write_live_token(token).context("Failed to write token to live credentials")?;
+        let _ = credentials::write_active_token(token);
+    }
+
+    seq.last_updated = now_utc();
+    sequence::save(&seq)?;
+
+    Ok(format!("Replaced the token for Account {} ({})", num, entry.email))
+}
+
+/// `token validate`: ask the Claude API whether a token works, without storing
+/// it. The token comes from `$CLAUDE_CODE_OAUTH_TOKEN` with `from_env`, else
+/// from stdin: piped, or typed at a prompt that does not echo. Only 401 and
+/// 403 mean the token is invalid; any other failure leaves it unverified.
+pub fn validate_token(from_env: bool) -> Result<()> {
+    use std::io::IsTerminal;
+    let token = if from_env {
+        std::env::var("CLAUDE_CODE_OAUTH_TOKEN")
+            .context("$CLAUDE_CODE_OAUTH_TOKEN is not set")?
+            .trim()
+            .to_string()
+    } else if io::stdin().is_terminal() {
+        prompt_token()?
+    } else {
+        read_piped_token(io::stdin().lock())?
+    };
+    if token.is_empty() {
+        bail!("No token entered.");
+    }
+    match credentials::check_token(&token)? {
+        200..=299 => outln!("  {} Token is valid", "✓".green().bold()),
+        status @ (401 | 403) => bail!("Token is invalid: HTTP {status}"),
+        status => bail!("Could not verify the token: HTTP {status}"),
+    }
+    Ok(())
+}
+
+/// Prompt for a new token for token account `num` (see `core_refresh_token`).
+pub fn refresh_token(num: u32) -> Result<()> {
+    let seq = sequence::load()?;
+    let entry = seq
+        .accounts
+        .get(&num.to_string())
+        .with_context(|| format!("Account {num} does not exist"))?;
+    if entry.auth_kind != AuthKind::Token {
+        bail!(
+            "Account {} ({}) is an OAuth account; use `ccswitch refresh {}` instead.",
+            num,
+            entry.email,
+            num
+        );
+    }
+
+    outln!();
+    outln!(
+        "  {} New token for {} {}",
+        "·".cyan(),
+        entry.email.bold(),
+        format!("(Account {})", num).dimmed()
+    );
+    let token = prompt_token()?;
+    let msg = core_refresh_token(num, &token)?;
+    outln!("  {} {}", "✓".green().bold(), msg);
+    if seq.active_account_number == Some(num) {
+        outln!("  {} Restart Claude Code to use the new token.", "·".cyan());
+    }
+    outln!();
+    Ok(())
+}
+
 // ── Remove account ────────────────────────────────────────────────────────────
 
-pub fn remove(identifier: &str) -> Result<()> {
+/// Remove an account, asking for confirmation unless `force` is set.
+pub fn remove(identifier: &str, force: bool) -> Result<()> {
     let seq = sequence::load()?;
 
     if seq.accounts.is_empty() {
@@ -626,7 +1061,7 @@ pub fn remove(identifier: &str) -> Result<()> {
 
     let account_num = seq
         .resolve(identifier)
-        .with_context(|| format!("No account found matching '{identifier}'"))?;
+        .ok_or_else(|| no_match_error(&seq, identifier))?;
 
     let entry = seq
         .accounts
@@ -635,7 +1070,7 @@ pub fn remove(identifier: &str) -> Result<()> {
         .with_context(|| format!("Account {account_num} does not exist"))?;
 
     if seq.active_account_number == Some(account_num) {
-        println!(
+        outln!(
             "  {} Account {} ({}) is currently active.",
             "!".yellow().bold(),
             account_num,
@@ -643,29 +1078,185 @@ pub fn remove(identifier: &str) -> Result<()> {
         );
     }
 
-    print!(
-        "\n  Remove {} ({})? [y/N] ",
-        format!("Account {account_num}").bold(),
-        entry.email
-    );
-    io::stdout().flush()?;
+    if !force {
+        print!(
+            "\n  Remove {} ({})? [y/N] ",
+            format!("Account {account_num}").bold(),
+            entry.email
+        );
+        io::stdout().flush()?;
 
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
 
-    if !matches!(input.trim(), "y" | "Y") {
-        println!("  Cancelled.");
-        return Ok(());
+        if !matches!(input.trim(), "y" | "Y") {
+            outln!("  Cancelled.");
+            return Ok(());
+        }
     }
 
     let msg = core_remove(account_num, &entry.email)?;
-    println!("\n  {} {}", "✓".green().bold(), msg);
+    outln!("\n  {} {}", "✓".green().bold(), msg);
+    Ok(())
+}
+
+// ── Migrate from a legacy ~/.ccswitch.json ────────────────────────────────────
+
+/// A token account read from a legacy accounts file.
+#[derive(Debug, Clone, PartialEq)]
+struct LegacyAccount {
+    email: String,
+    token: String,
+}
+
+fn legacy_account(v: &serde_json::Value) -> Result<LegacyAccount, String> {
+    let field = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|n| v.get(*n).and_then(|x| x.as_str()))
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+    };
+    let email = field(&["email", "label"]).ok_or("entry has no email")?;
+    let token = field(&["token", "accessToken"])
+        .ok_or_else(|| format!("{email}: entry has no token"))?;
+    Ok(LegacyAccount { email, token })
+}
+
+/// Read the accounts out of a legacy file. Version 1 (or no version) keeps an
+/// `accounts` array of `{ email, token }`; version 2 keys the same entries by
+/// account number in an `accounts` object.
+fn parse_legacy_accounts(
+    v: &serde_json::Value,
+) -> Result<Vec<Result<LegacyAccount, String>>> {
+    let accounts = v.get("accounts").context("No `accounts` field; not a legacy ccswitch file")?;
+    let version = v.get("version").and_then(|x| x.as_u64());
+    let entries: Vec<&serde_json::Value> = match (version, accounts) {
+        (None | Some(1), serde_json::Value::Array(list)) => list.iter().collect(),
+        (None | Some(2), serde_json::Value::Object(map)) => map.values().collect(),
+        (Some(n @ (1 | 2)), _) => bail!("Version {n} file has a malformed `accounts` field"),
+        (Some(n), _) => bail!("Unsupported legacy format version {n}"),
+        (None, _) => bail!("`accounts` must be an array or an object"),
+    };
+    Ok(entries.into_iter().map(legacy_account).collect())
+}
+
+/// Outcome of `core_migrate`, listed by email (or error for failures).
+#[derive(Debug, Default)]
+pub(crate) struct MigrateSummary {
+    pub imported: Vec<String>,
+    pub skipped: Vec<String>,
+    pub failed: Vec<String>,
+}
+
+pub(crate) fn core_migrate(source: &std::path::Path) -> Result<MigrateSummary> {
+    let raw = std::fs::read_to_string(source)
+        .with_context(|| format!("Cannot read {}", source.display()))?;
+    let value: serde_json::Value = serde_json::from_str(&raw)
+        .with_context(|| format!("Invalid JSON in {}", source.display()))?;
+    let entries = parse_legacy_accounts(&value)?;
+
+    sequence::setup_dirs()?;
+    let _lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load()?;
+    let mut summary = MigrateSummary::default();
+
+    for entry in entries {
+        let account = match entry {
+            Ok(account) => account,
+            Err(e) => {
+                summary.failed.push(e);
+                continue;
+            }
+        };
+        if seq.account_exists(&account.email) {
+            summary.skipped.push(account.email);
+            continue;
+        }
+        let num = seq.next_account_number();
+        let token_json = serde_json::json!({ "token": account.token }).to_string();
+        if let Err(e) = credentials::write_backup(num, &account.email, &token_json) {
+            summary.failed.push(format!("{}: {e:#}", account.email));
+            continue;
+        }
+        seq.accounts.insert(
+            num.to_string(),
+            AccountEntry {
+                email: account.email.clone(),
+                uuid: String::new(),
+                added: now_utc(),
+                auth_kind: AuthKind::Token,
+                has_config_backup: false,
+                priority: 0,
+                notes: None,
+                last_used: None,
+                switch_count: 0,
+                tags: Vec::new(),
+                expires_at: None,
+                created_by: Some(created_by()),
+                machine_id: platform::machine_id(),
+                env_vars: Default::default(),
+            },
+        );
+        seq.sequence.push(num);
+        summary.imported.push(account.email);
+    }
+
+    if !summary.imported.is_empty() {
+        seq.last_updated = now_utc();
+        sequence::save(&seq)?;
+    }
+    Ok(summary)
+}
+
+pub fn migrate(source: &std::path::Path) -> Result<()> {
+    let summary = core_migrate(source)?;
+    outln!();
+    for email in &summary.imported {
+        outln!("  {} Imported {}", "✓".green().bold(), email);
+    }
+    for email in &summary.skipped {
+        outln!("  {} Skipped {} (already managed)", "·".yellow(), email);
+    }
+    for err in &summary.failed {
+        outln!("  {} {}", "✗".red().bold(), err.red());
+    }
+    if !summary.imported.is_empty() {
+        credentials::ensure_ccswitchrc()?;
+    }
+    outln!(
+        "\n  {} imported, {} skipped, {} failed.\n",
+        summary.imported.len(),
+        summary.skipped.len(),
+        summary.failed.len()
+    );
     Ok(())
 }
 
 // ── Alias ─────────────────────────────────────────────────────────────────────
 
-pub fn set_alias(account: &str, name: &str) -> Result<()> {
+/// Aliases are looked up after numbers and emails, so reject names that one
+/// of those would always win over.
+fn validate_alias(seq: &SequenceFile, name: &str) -> Result<()> {
+    if name.is_empty() || name.chars().any(char::is_whitespace) {
+        bail!("Alias must be a single word");
+    }
+    if name.chars().all(|c| c.is_ascii_digit()) {
+        bail!("Alias '{name}' would be read as an account number");
+    }
+    if name.contains('@') || seq.account_exists(name) {
+        bail!("Alias '{name}' looks like an email address; pick a short name");
+    }
+    if let Some(&existing_num) = seq.aliases.get(name) {
+        bail!("Alias '{}' is already used by Account {}", name, existing_num);
+    }
+    Ok(())
+}
+
+/// Give the account matching `account` (number, email or alias) the alias `name`.
+pub fn core_set_alias(account: &str, name: &str) -> Result<String> {
+    let _lock = sequence::acquire_lock()?;
     let mut seq = sequence::load()?;
 
     if seq.accounts.is_empty() {
@@ -675,10 +1266,7 @@ pub fn set_alias(account: &str, name: &str) -> Result<()> {
     let num = seq
         .resolve(account)
         .with_context(|| format!("No account found matching '{account}'"))?;
-
-    if let Some(&existing_num) = seq.aliases.get(name) {
-        bail!("Alias '{}' is already used by Account {}", name, existing_num);
-    }
+    validate_alias(&seq, name)?;
 
     let email = seq.accounts[&num.to_string()].email.clone();
 
@@ -686,392 +1274,467 @@ pub fn set_alias(account: &str, name: &str) -> Result<()> {
     seq.last_updated = sequence::now_utc();
     sequence::save(&seq)?;
 
-    println!(
-        "\n  {} Alias '{}' → Account {} ({})\n",
-        "✓".green().bold(),
-        name,
-        num,
-        email
-    );
+    Ok(format!("Alias '{name}' → Account {num} ({email})"))
+}
+
+/// Remove the alias `name`.
+pub fn core_unalias(name: &str) -> Result<String> {
+    let _lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load()?;
+    let num = seq
+        .aliases
+        .remove(name)
+        .with_context(|| format!("No alias named '{name}'"))?;
+    seq.last_updated = sequence::now_utc();
+    sequence::save(&seq)?;
+    Ok(format!("Removed alias '{name}' (Account {num})"))
+}
+
+pub fn set_alias(account: &str, name: &str) -> Result<()> {
+    let msg = core_set_alias(account, name)?;
+    outln!("\n  {} {}\n", "✓".green().bold(), msg);
     Ok(())
 }
 
-// ── List accounts ─────────────────────────────────────────────────────────────
+pub fn unalias(name: &str) -> Result<()> {
+    let msg = core_unalias(name)?;
+    outln!("\n  {} {}\n", "✓".green().bold(), msg);
+    Ok(())
+}
 
-pub fn list() -> Result<()> {
-    let seq = sequence::load()?;
+// ── Pin ───────────────────────────────────────────────────────────────────────
 
-    if seq.accounts.is_empty() {
-        println!("\n  {}\n", "No accounts managed yet.".dimmed());
-        println!("  Run {} to add the current account.\n", "ccswitch add".cyan().bold());
-        return Ok(());
-    }
-
-    // Prefer seq state (works for token users without oauthAccount in config)
-    let active_num = seq
-        .active_account_number
-        .or_else(|| config::current_email().as_deref().and_then(|e| seq.find_by_email(e)));
+/// Pin account `num` as the default: when no account is known to be active,
+/// rotation starts there. It does not stop rotation from moving off it.
+pub fn core_pin(num: u32) -> Result<String> {
+    let _lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load()?;
+    let email = seq
+        .accounts
+        .get(&num.to_string())
+        .with_context(|| format!("Account {num} does not exist"))?
+        .email
+        .clone();
+    seq.pinned_account = Some(num);
+    seq.last_updated = now_utc();
+    sequence::save(&seq)?;
+    Ok(format!("Pinned Account {num} ({email})"))
+}
 
-    println!("\n  {}", "Managed Accounts".bold());
-    println!("  {}", "─".repeat(40).dimmed());
+/// Unpin whichever account is pinned.
+pub fn core_unpin() -> Result<String> {
+    let _lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load()?;
+    let num = seq.pinned_account.take().context("No account is pinned")?;
+    seq.last_updated = now_utc();
+    sequence::save(&seq)?;
+    Ok(format!("Unpinned Account {num}"))
+}
 
-    // Build reverse alias map: account_num -> alias name
-    let alias_for: std::collections::HashMap<u32, &str> = seq
-        .aliases
-        .iter()
-        .map(|(name, &num)| (num, name.as_str()))
-        .collect();
+pub fn pin(account: &str) -> Result<()> {
+    let num = resolve_account(account)?;
+    let msg = core_pin(num)?;
+    outln!("\n  {} {}\n", "✓".green().bold(), msg);
+    Ok(())
+}
 
-    for &num in &seq.sequence {
-        let Some(entry) = seq.accounts.get(&num.to_string()) else {
-            continue;
-        };
+pub fn unpin() -> Result<()> {
+    let msg = core_unpin()?;
+    outln!("\n  {} {}\n", "✓".green().bold(), msg);
+    Ok(())
+}
 
-        let is_active = active_num == Some(num);
+// ── Link live credentials ─────────────────────────────────────────────────────
 
-        // Read credentials to check session expiry (best-effort; skip on error)
-        let expiry_badge: Option<String> = if entry.auth_kind == AuthKind::Oauth {
-            let creds = if is_active {
-                credentials::read_live().ok()
-            } else {
-                credentials::read_backup(num, &entry.email).ok()
-            };
-            creds.map(|c| session_expiry_badge(&c))
-        } else {
-            None
-        };
+/// Fail when `ccswitch link` points the live credentials at account `num`'s
+/// backup file (at any account's when `num` is `None`), which `what` would
+/// rename or delete.
+fn refuse_if_linked(seq: &SequenceFile, num: Option<u32>, what: &str) -> Result<()> {
+    let Some(active) = seq.active_account_number.filter(|_| seq.live_is_symlink) else {
+        return Ok(());
+    };
+    if num.is_none_or(|n| n == active) {
+        bail!(
+            "{what} would leave the live credentials linked to a missing backup \
+             (Account {active} is linked). Run `ccswitch unlink` first."
+        );
+    }
+    Ok(())
+}
 
-        let kind_badge = if entry.auth_kind == AuthKind::Token {
-            " [token]"
-        } else {
-            ""
-        };
+/// Make the live credentials file a symlink to account `num`'s backup,
+/// switching to it first if needed. OAuth only: token backups are not in the
+/// format Claude Code reads.
+pub(crate) fn core_link(num: u32) -> Result<String> {
+    let seq = sequence::load()?;
+    let entry = seq
+        .accounts
+        .get(&num.to_string())
+        .with_context(|| format!("Account {num} does not exist"))?
+        .clone();
+    if entry.auth_kind != AuthKind::Oauth {
+        bail!(
+            "Account {num} ({}) is a token account; only OAuth credentials can be linked.",
+            entry.email
+        );
+    }
+    let (live, _) = credentials::link_paths(num, &entry.email)?;
+    if resolve_current_account(&seq).ok().map(|(n, _)| n) != Some(num) {
+        core_switch(num)?;
+    }
 
-        let alias_badge = alias_for
-            .get(&num)
-            .map(|a| format!(" [{}]", a))
-            .unwrap_or_default();
+    let _lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load()?;
+    if !seq.live_is_symlink {
+        // Keep anything Claude Code refreshed since the last switch.
+        let creds = credentials::read_live()?;
+        credentials::write_backup(num, &entry.email, &creds)?;
+    }
+    let backup = credentials::link_live(num, &entry.email)?;
+    seq.live_is_symlink = true;
+    seq.last_updated = now_utc();
+    sequence::save(&seq)?;
+    Ok(format!("Linked {} → {}", live.display(), backup.display()))
+}
 
-        if is_active {
-            print!(
-                "  {}  {}{}{}",
-                format!("▶ {num:>2}").green().bold(),
-                entry.email.green().bold(),
-                kind_badge.green().dimmed(),
-                alias_badge.green().dimmed(),
-            );
-            if let Some(ref eb) = expiry_badge {
-                if eb.starts_with("[expired]") {
-                    print!("  {}", eb.red().bold());
-                } else if !eb.is_empty() {
-                    print!("  {}", eb.yellow());
-                }
-            }
-            println!("  {}", "(active)".green().dimmed());
-        } else {
-            print!(
-                "  {}  {}{}{}",
-                format!("  {num:>2}").dimmed(),
-                entry.email,
-                kind_badge.dimmed(),
-                alias_badge.dimmed(),
-            );
-            if let Some(ref eb) = expiry_badge {
-                if eb.starts_with("[expired]") {
-                    print!("  {}", eb.red().bold());
-                } else if !eb.is_empty() {
-                    print!("  {}", eb.yellow());
-                }
-            }
-            println!();
+/// Undo `core_link`: the live credentials become a regular file again.
+pub(crate) fn core_unlink() -> Result<String> {
+    let _lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load()?;
+    if !credentials::unlink_live()? && !seq.live_is_symlink {
+        bail!("The live credentials are not linked");
+    }
+    // Re-save the formerly linked backup so it has a checksum again.
+    if let Some(num) = seq.active_account_number {
+        if let Some(entry) = seq.accounts.get(&num.to_string()) {
+            let creds = credentials::read_backup_unverified(num, &entry.email)?;
+            credentials::write_backup(num, &entry.email, &creds)?;
         }
     }
+    seq.live_is_symlink = false;
+    seq.last_updated = now_utc();
+    sequence::save(&seq)?;
+    Ok("Live credentials are a regular file again".to_string())
+}
 
-    println!("  {}\n", "─".repeat(40).dimmed());
+pub fn link(account: &str) -> Result<()> {
+    let num = resolve_account(account)?;
+    let msg = core_link(num)?;
+    outln!("\n  {} {}", "✓".green().bold(), msg);
+    outln!(
+        "  {} Claude Code now writes straight into this backup, so switches re-point",
+        "!".yellow().bold()
+    );
+    outln!("    the link instead of snapshotting credentials. `ccswitch unlink` undoes this.\n");
     Ok(())
 }
 
-// ── Status ────────────────────────────────────────────────────────────────────
-
-pub fn status() -> Result<()> {
-    let seq = sequence::load()?;
-
-    // Resolve active account — prefer seq state so token accounts show correctly
-    let active = seq
-        .active_account_number
-        .and_then(|num| seq.accounts.get(&num.to_string()).map(|e| (num, e.clone())))
-        .or_else(|| {
-            config::current_email().and_then(|email| {
-                seq.find_by_email(&email)
-                    .and_then(|num| seq.accounts.get(&num.to_string()).map(|e| (num, e.clone())))
-            })
-        });
+pub fn unlink() -> Result<()> {
+    let msg = core_unlink()?;
+    outln!("\n  {} {}\n", "✓".green().bold(), msg);
+    Ok(())
+}
 
-    match active {
-        None => {
-            if config::has_env_token() {
-                println!(
-                    "\n  {} {} {}\n",
-                    "·".yellow().bold(),
-                    "Token active".bold(),
-                    "(not managed — run `ccswitch add`)".dimmed()
-                );
-            } else {
-                println!("\n  {} Not logged in to Claude Code.\n", "✗".red().bold());
-            }
-        }
-        Some((num, entry)) => {
-            let kind_badge = if entry.auth_kind == AuthKind::Token {
-                " [token]"
-            } else {
-                ""
-            };
+// ── Set active (bookkeeping only) ─────────────────────────────────────────────
 
-            let expiry_str = if entry.auth_kind == AuthKind::Oauth {
-                credentials::read_live().ok().map(|c| {
-                    match credentials::oauth_secs_remaining(&c) {
-                        None => String::new(),
-                        Some(secs) if secs <= 0 => " — session expired".red().bold().to_string(),
-                        Some(secs) => {
-                            let days = secs / 86400;
-                            let hours = (secs % 86400) / 3600;
-                            if days > 7 {
-                                format!(" — expires in {}d", days).dimmed().to_string()
-                            } else if days >= 1 {
-                                format!(" — expires in {}d {}h", days, hours).yellow().to_string()
-                            } else {
-                                format!(" — expires in {}h", hours).yellow().bold().to_string()
-                            }
-                        }
-                    }
-                })
-            } else {
-                None
-            };
+/// Mark account `num` active in sequence.json without touching credentials or
+/// config — for when the live credentials were restored by hand.
+pub(crate) fn core_set_active(num: u32) -> Result<String> {
+    let _lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load()?;
+    let email = seq
+        .accounts
+        .get(&num.to_string())
+        .with_context(|| format!("Account {num} does not exist"))?
+        .email
+        .clone();
+    seq.active_account_number = Some(num);
+    seq.last_updated = now_utc();
+    sequence::save(&seq)?;
+    Ok(format!("Marked Account {num} ({email}) as active"))
+}
 
-            println!(
-                "\n  {} {}{}{} {}\n",
-                "▶".green().bold(),
-                entry.email.bold(),
-                kind_badge.dimmed(),
-                expiry_str.as_deref().unwrap_or(""),
-                format!("(Account {num})").dimmed()
-            );
-        }
-    }
+pub fn set_active(account: &str) -> Result<()> {
+    let num = resolve_account(account)?;
+    let msg = core_set_active(num)?;
+    outln!("\n  {} {}", "✓".green().bold(), msg);
+    outln!(
+        "  {} This only updates ccswitch's records; credentials were NOT switched.",
+        "!".yellow().bold()
+    );
+    outln!("    Use `ccswitch switch {num}` to actually switch.\n");
     Ok(())
 }
 
-// ── Switch (rotate to next) ───────────────────────────────────────────────────
+// ── Priority ──────────────────────────────────────────────────────────────────
+
+pub(crate) fn core_set_priority(num: u32, priority: u32) -> Result<String> {
+    let _lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load()?;
+    let entry = seq
+        .accounts
+        .get_mut(&num.to_string())
+        .with_context(|| format!("Account {num} does not exist"))?;
+    entry.priority = priority;
+    let email = entry.email.clone();
+    seq.last_updated = now_utc();
+    sequence::save(&seq)?;
 
-pub fn switch_next() -> Result<()> {
+    Ok(format!("Priority for Account {} ({}) set to {}", num, email, priority))
+}
+
+pub fn set_priority(account: &str, priority: u32) -> Result<()> {
     let seq = sequence::load()?;
 
     if seq.accounts.is_empty() {
         bail!("No accounts managed yet. Run `ccswitch add` first.");
     }
 
-    if seq.sequence.len() < 2 {
-        bail!("Only one account managed. Add another with `ccswitch add`.");
-    }
+    let num = seq
+        .resolve(account)
+        .with_context(|| format!("No account found matching '{account}'"))?;
 
-    // For token accounts, active_account_number is the source of truth
-    let active_num = if let Some(num) = seq.active_account_number {
-        num
-    } else {
-        let current_email = config::current_email()
-            .context("No active Claude account found")?;
+    let msg = core_set_priority(num, priority)?;
+    outln!("\n  {} {}\n", "✓".green().bold(), msg);
+    Ok(())
+}
 
-        if !seq.account_exists(&current_email) {
-            println!(
-                "\n  {} Active account '{}' is not managed — adding it...",
-                "·".yellow(),
-                current_email
-            );
-            add()?;
-            println!(
-                "\n  Run {} again to switch to the next account.\n",
-                "ccswitch switch".cyan().bold()
-            );
-            return Ok(());
-        }
+// ── Reorder ───────────────────────────────────────────────────────────────────
 
-        seq.find_by_email(&current_email)
-            .context("Cannot find account number for current email")?
-    };
+/// Move an account to `new_pos` (1-based, clamped) in the rotation sequence.
+pub(crate) fn core_move(num: u32, new_pos: usize) -> Result<String> {
+    let _lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load()?;
+    let from = seq
+        .sequence
+        .iter()
+        .position(|&n| n == num)
+        .with_context(|| format!("Account {num} is not in the rotation sequence"))?;
 
-    let current_idx = seq.sequence.iter().position(|&n| n == active_num).unwrap_or(0);
-    let next_idx = (current_idx + 1) % seq.sequence.len();
-    let next_num = seq.sequence[next_idx];
+    seq.sequence.remove(from);
+    let index = new_pos.clamp(1, seq.sequence.len() + 1) - 1;
+    seq.sequence.insert(index, num);
+    seq.last_updated = now_utc();
+    sequence::save(&seq)?;
 
-    do_switch(next_num)
+    Ok(format!("Moved Account {} to position {}", num, index + 1))
 }
 
-// ── Switch to specific account ────────────────────────────────────────────────
-
-pub fn switch_to(identifier: &str) -> Result<()> {
+pub fn move_account(account: &str, position: usize) -> Result<()> {
     let seq = sequence::load()?;
 
     if seq.accounts.is_empty() {
         bail!("No accounts managed yet. Run `ccswitch add` first.");
     }
 
-    let target_num = seq
-        .resolve(identifier)
-        .with_context(|| format!("No account found matching '{identifier}'"))?;
+    let num = seq
+        .resolve(account)
+        .with_context(|| format!("No account found matching '{account}'"))?;
 
-    do_switch(target_num)
+    let msg = core_move(num, position)?;
+    outln!("\n  {} {}\n", "✓".green().bold(), msg);
+    Ok(())
 }
 
-// ── CLI switch wrapper ────────────────────────────────────────────────────────
+/// Exchange the rotation positions of accounts `num_a` and `num_b`. Account
+/// numbers stay the same; only the order in `sequence` changes.
+pub(crate) fn core_swap(num_a: u32, num_b: u32) -> Result<String> {
+    let _lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load()?;
+    let position = |num: u32| {
+        seq.sequence
+            .iter()
+            .position(|&n| n == num)
+            .with_context(|| format!("Account {num} is not in the rotation sequence"))
+    };
+    let (a, b) = (position(num_a)?, position(num_b)?);
+    if a == b {
+        return Ok(format!("Account {num_a} is already at position {}", a + 1));
+    }
 
-fn do_switch(target_num: u32) -> Result<()> {
-    let seq = sequence::load()?;
+    seq.sequence.swap(a, b);
+    seq.last_updated = now_utc();
+    sequence::save(&seq)?;
+    Ok(format!(
+        "Swapped Account {num_a} (now position {}) and Account {num_b} (now position {})",
+        b + 1,
+        a + 1
+    ))
+}
 
-    let target_entry = seq
+pub fn swap(a: &str, b: &str) -> Result<()> {
+    let msg = core_swap(resolve_account(a)?, resolve_account(b)?)?;
+    outln!("\n  {} {}\n", "✓".green().bold(), msg);
+    Ok(())
+}
+
+// ── Tags ──────────────────────────────────────────────────────────────────────
+
+/// Tags are stored lower-case and may not contain whitespace.
+fn normalize_tag(tag: &str) -> Result<String> {
+    let tag = tag.trim().trim_start_matches('#').to_lowercase();
+    if tag.is_empty() || tag.chars().any(char::is_whitespace) {
+        bail!("Tag must be a single non-empty word");
+    }
+    Ok(tag)
+}
+
+/// Tag account `num` with `tag`; tagging it twice is not an error.
+pub fn core_tag(num: u32, tag: &str) -> Result<String> {
+    let tag = normalize_tag(tag)?;
+    let _lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load()?;
+    let entry = seq
         .accounts
-        .get(&target_num.to_string())
-        .cloned()
-        .with_context(|| format!("Account {target_num} does not exist"))?;
-    let target_email = target_entry.email.clone();
-    // Determine display name for current account — handles both OAuth and token
-    let current_slot_email = if let Some(num) = seq.active_account_number {
-        seq.accounts
-            .get(&num.to_string())
-            .map(|e| e.email.clone())
-            .unwrap_or_else(|| "unknown".to_string())
-    } else {
-        config::current_email().unwrap_or_else(|| "unknown".to_string())
-    };
+        .get_mut(&num.to_string())
+        .with_context(|| format!("Account {num} does not exist"))?;
+    if entry.tags.contains(&tag) {
+        return Ok(format!("Account {} ({}) is already tagged [{}]", num, entry.email, tag));
+    }
+    entry.tags.push(tag.clone());
+    let email = entry.email.clone();
+    seq.last_updated = now_utc();
+    sequence::save(&seq)?;
 
-    // Already on the target?
-    if seq.active_account_number == Some(target_num) {
-        println!(
-            "\n  {} Already using {} (Account {target_num}).\n",
-            "·".cyan(),
-            target_email.bold()
-        );
-        return Ok(());
+    Ok(format!("Tagged Account {} ({}) [{}]", num, email, tag))
+}
+
+/// Remove `tag` from account `num`.
+pub fn core_untag(num: u32, tag: &str) -> Result<String> {
+    let tag = normalize_tag(tag)?;
+    let _lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load()?;
+    let entry = seq
+        .accounts
+        .get_mut(&num.to_string())
+        .with_context(|| format!("Account {num} does not exist"))?;
+    let before = entry.tags.len();
+    entry.tags.retain(|t| *t != tag);
+    if entry.tags.len() == before {
+        bail!("Account {} ({}) is not tagged [{}]", num, entry.email, tag);
     }
+    let email = entry.email.clone();
+    seq.last_updated = now_utc();
+    sequence::save(&seq)?;
 
-    // If the target is an OAuth account with an expired session, warn and
-    // optionally refresh before switching.
-    if target_entry.auth_kind == AuthKind::Oauth {
-        if let Ok(backup_creds) = credentials::read_backup(target_num, &target_email) {
-            if !credentials::is_oauth_active(&backup_creds) {
-                println!(
-                    "\n  {} Account {} ({}) has an expired session.",
-                    "!".yellow().bold(),
-                    target_num,
-                    target_email
-                );
+    Ok(format!("Removed tag [{}] from Account {} ({})", tag, num, email))
+}
 
-                use std::io::IsTerminal;
-                if io::stdin().is_terminal() {
-                    print!("  Refresh now? [y/N] ");
-                    io::stdout().flush()?;
-                    let mut input = String::new();
-                    io::stdin().read_line(&mut input)?;
-                    if matches!(input.trim(), "y" | "Y") {
-                        println!();
-                        match core_refresh(target_num) {
-                            Ok(msg) => println!("  {} {}\n", "✓".green().bold(), msg),
-                            Err(e) => {
-                                println!("  {} Refresh failed: {e}", "✗".red().bold());
-                                println!(
-                                    "  {} Switching anyway — Claude Code may reject the expired session.\n",
-                                    "!".yellow().bold()
-                                );
-                            }
-                        }
-                    } else {
-                        println!(
-                            "  {} Switching with expired session — Claude Code may reject it.\n",
-                            "!".yellow().bold()
-                        );
-                    }
-                } else {
-                    println!(
-                        "  {} Switching with expired session — Claude Code may reject it.\n",
-                        "!".yellow().bold()
-                    );
-                }
-            }
-        }
-    }
-
-    println!(
-        "\n  {} {}  {}  {}",
-        "→".cyan().bold(),
-        current_slot_email.dimmed(),
-        "→".dimmed(),
-        target_email.cyan().bold()
-    );
-
-    core_switch(target_num)?;
-
-    // Upgrade ~/.ccswitchrc to the new keychain-only format if needed.
-    let _ = credentials::ensure_ccswitchrc();
+pub fn tag(account: &str, tag: &str) -> Result<()> {
+    let num = resolve_account(account)?;
+    let msg = core_tag(num, tag)?;
+    outln!("\n  {} {}\n", "✓".green().bold(), msg);
+    Ok(())
+}
 
-    list()?;
+pub fn untag(account: &str, tag: &str) -> Result<()> {
+    let num = resolve_account(account)?;
+    let msg = core_untag(num, tag)?;
+    outln!("\n  {} {}\n", "✓".green().bold(), msg);
+    Ok(())
+}
 
-    println!(
-        "  {} Restart Claude Code to apply.\n",
-        "✓".green().bold()
-    );
+/// Resolve a CLI account identifier, failing when nothing is managed or nothing matches.
+pub fn resolve_account(account: &str) -> Result<u32> {
+    let seq = sequence::load()?;
 
-    // Warn if CLAUDE_CODE_OAUTH_TOKEN is set — it overrides the keychain and
-    // will cause Claude Code to ignore the switch until it is cleared.
-    if std::env::var("CLAUDE_CODE_OAUTH_TOKEN").is_ok() {
-        let rc = credentials::ccswitchrc_path();
-        println!(
-            "  {} {} is set in this shell.",
-            "!".yellow().bold(),
-            "CLAUDE_CODE_OAUTH_TOKEN".yellow().bold(),
-        );
-        println!(
-            "  {} Run {} or {} to clear it before restarting Claude Code.\n",
-            " ".normal(),
-            "unset CLAUDE_CODE_OAUTH_TOKEN".cyan().bold(),
-            format!("source {}", rc.display()).cyan(),
-        );
+    if seq.accounts.is_empty() {
+        bail!("No accounts managed yet. Run `ccswitch add` first.");
     }
 
-    Ok(())
+    seq.resolve(account).ok_or_else(|| no_match_error(&seq, account))
 }
 
-// ── Edit account label ────────────────────────────────────────────────────────
+/// Error for an identifier `resolve` could not match, listing the candidates
+/// when a partial email matched several accounts.
+fn no_match_error(seq: &SequenceFile, identifier: &str) -> anyhow::Error {
+    let candidates = seq.partial_matches(identifier);
+    if candidates.len() < 2 {
+        return anyhow::anyhow!("No account found matching '{identifier}'");
+    }
+    let list = candidates
+        .iter()
+        .map(|n| format!("{n} ({})", seq.accounts[&n.to_string()].email))
+        .collect::<Vec<_>>()
+        .join(", ");
+    anyhow::anyhow!(
+        "'{identifier}' matches {} accounts. Did you mean: {list}?",
+        candidates.len()
+    )
+}
 
-pub(crate) fn core_edit_account(num: u32, old_email: &str, new_label: &str) -> Result<String> {
-    // Read existing credentials backup before touching anything
-    let creds = credentials::read_backup(num, old_email)
-        .with_context(|| format!("Cannot read credentials backup for Account {num}"))?;
+// ── Notes ─────────────────────────────────────────────────────────────────────
 
-    // Write new backup under new label, then delete old one
-    credentials::write_backup(num, new_label, &creds)?;
-    credentials::delete_backup(num, old_email)?;
+/// Set the note for account `num`; an empty (or whitespace-only) `text` clears it.
+pub fn core_note(num: u32, text: &str) -> Result<String> {
+    let _lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load()?;
+    let entry = seq
+        .accounts
+        .get_mut(&num.to_string())
+        .with_context(|| format!("Account {num} does not exist"))?;
+    let text = text.trim();
+    entry.notes = (!text.is_empty()).then(|| text.to_string());
+    let email = entry.email.clone();
+    seq.last_updated = now_utc();
+    sequence::save(&seq)?;
 
-    // Migrate config backup (best-effort — missing backup is not fatal)
-    if let Ok(config_str) = read_config_backup(num, old_email) {
-        write_config_backup(num, new_label, &config_str)?;
-        let _ = std::fs::remove_file(config_backup_path(num, old_email));
+    if text.is_empty() {
+        Ok(format!("Cleared note for Account {} ({})", num, email))
+    } else {
+        Ok(format!("Note for Account {} ({}) set", num, email))
     }
+}
 
-    // Update sequence.json
+/// `s` as a canonical lower-case UUID if it is an RFC 4122 version 4 UUID.
+fn parse_v4_uuid(s: &str) -> Option<String> {
+    uuid::Uuid::parse_str(s)
+        .ok()
+        .filter(|u| u.get_version_num() == 4 && u.get_variant() == uuid::Variant::RFC4122)
+        .map(|u| u.hyphenated().to_string())
+}
+
+/// Record `uuid` as account `num`'s `oauthAccount.accountUuid`, for accounts
+/// added before ccswitch stored it.
+pub(crate) fn core_set_uuid(num: u32, uuid: &str) -> Result<String> {
+    let Some(uuid) = parse_v4_uuid(uuid.trim()) else {
+        bail!("'{}' is not a version 4 UUID (xxxxxxxx-xxxx-4xxx-yxxx-xxxxxxxxxxxx)", uuid.trim());
+    };
+    let _lock = sequence::acquire_lock()?;
     let mut seq = sequence::load()?;
-    if let Some(entry) = seq.accounts.get_mut(&num.to_string()) {
-        entry.email = new_label.to_string();
-    }
+    let entry = seq
+        .accounts
+        .get_mut(&num.to_string())
+        .with_context(|| format!("Account {num} does not exist"))?;
+    entry.uuid = uuid.clone();
+    let email = entry.email.clone();
     seq.last_updated = now_utc();
     sequence::save(&seq)?;
+    Ok(format!("Set UUID of Account {num} ({email}) to {uuid}"))
+}
 
-    Ok(format!(
-        "Renamed Account {num}: '{}' → '{}'",
-        old_email, new_label
-    ))
+pub fn set_uuid(account: &str, uuid: &str) -> Result<()> {
+    let msg = core_set_uuid(resolve_account(account)?, uuid)?;
+    outln!("\n  {} {}\n", "✓".green().bold(), msg);
+    Ok(())
 }
 
-pub fn edit_account(identifier: &str, new_label: &str) -> Result<()> {
+/// Fill in account `num`'s UUID from `oauth` (an `oauthAccount` object) when
+/// it was added before UUIDs were stored. Only for a matching email.
+fn backfill_uuid(seq: &mut SequenceFile, num: u32, oauth: &serde_json::Value) {
+    let Some(entry) = seq.accounts.get_mut(&num.to_string()) else { return };
+    if !entry.uuid.is_empty()
+        || oauth.get("emailAddress").and_then(|e| e.as_str()) != Some(entry.email.as_str())
+    {
+        return;
+    }
+    if let Some(uuid) = oauth.get("accountUuid").and_then(|u| u.as_str()).filter(|u| !u.is_empty()) {
+        entry.uuid = uuid.to_string();
+    }
+}
+
+/// Set the note when `text` is given, otherwise print the current one.
+pub fn note(account: &str, text: Option<&str>) -> Result<()> {
     let seq = sequence::load()?;
 
     if seq.accounts.is_empty() {
@@ -1079,1186 +1742,5894 @@ pub fn edit_account(identifier: &str, new_label: &str) -> Result<()> {
     }
 
     let num = seq
-        .resolve(identifier)
-        .with_context(|| format!("No account found matching '{identifier}'"))?;
-
-    let entry = seq
-        .accounts
-        .get(&num.to_string())
-        .cloned()
-        .with_context(|| format!("Account {num} does not exist"))?;
-
-    let old_email = entry.email.clone();
+        .resolve(account)
+        .with_context(|| format!("No account found matching '{account}'"))?;
 
-    if old_email == new_label {
-        println!(
-            "\n  {} Account {} already has label '{}'.\n",
-            "·".cyan(),
-            num,
-            new_label
-        );
+    if let Some(text) = text {
+        let msg = core_note(num, text)?;
+        outln!("\n  {} {}\n", "✓".green().bold(), msg);
         return Ok(());
     }
 
-    if let Some(existing_num) = seq.find_by_email(new_label) {
-        if existing_num != num {
-            bail!(
-                "Label '{}' is already used by Account {}",
-                new_label,
-                existing_num
-            );
-        }
+    match seq.accounts.get(&num.to_string()).and_then(|e| e.notes.as_deref()) {
+        Some(n) => outln!("\n  {}\n", n),
+        None => outln!("\n  {}\n", format!("No note for Account {num}.").dimmed()),
     }
-
-    let msg = core_edit_account(num, &old_email, new_label)?;
-    println!("\n  {} {}\n", "✓".green().bold(), msg);
     Ok(())
 }
 
-// ── Credential helpers ────────────────────────────────────────────────────────
+// ── List accounts ─────────────────────────────────────────────────────────────
 
-/// Check whether a token value is already stored in any managed account.
-/// Returns (account_num, email) if found.
-fn find_account_by_token(seq: &SequenceFile, token: &str) -> Option<(u32, String)> {
-    for &num in &seq.sequence {
-        let entry = match seq.accounts.get(&num.to_string()) {
-            Some(e) => e,
-            None => continue,
-        };
-        if entry.auth_kind != AuthKind::Token {
-            continue;
+/// `list --sort` orders; without one, accounts are listed in rotation order.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum SortOrder {
+    Number,
+    Email,
+    /// Most recently used first; never-used accounts last.
+    LastUsed,
+    /// Most switched-to first.
+    SwitchCount,
+    /// Oldest first.
+    Added,
+}
+
+impl SortOrder {
+    pub const ALL: [SortOrder; 5] = [
+        SortOrder::Number,
+        SortOrder::Email,
+        SortOrder::LastUsed,
+        SortOrder::SwitchCount,
+        SortOrder::Added,
+    ];
+}
+
+impl std::fmt::Display for SortOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SortOrder::Number => write!(f, "number"),
+            SortOrder::Email => write!(f, "email"),
+            SortOrder::LastUsed => write!(f, "last used"),
+            SortOrder::SwitchCount => write!(f, "switch count"),
+            SortOrder::Added => write!(f, "date added"),
         }
-        if let Ok(creds) = credentials::read_backup(num, &entry.email) {
-            if let Ok(stored) = extract_access_token(&creds) {
-                if stored == token {
-                    return Some((num, entry.email.clone()));
-                }
-            }
+    }
+}
+
+/// `seq.sequence` reordered by `order`. The sort is stable, so ties keep
+/// their rotation order.
+pub(crate) fn sorted_sequence(seq: &SequenceFile, order: SortOrder) -> Vec<u32> {
+    let mut nums = seq.sequence.clone();
+    let entry = |n: &u32| seq.accounts.get(&n.to_string());
+    match order {
+        SortOrder::Number => nums.sort(),
+        SortOrder::Email => {
+            nums.sort_by_key(|n| entry(n).map(|e| e.email.to_lowercase()).unwrap_or_default())
         }
+        SortOrder::LastUsed => nums.sort_by_key(|n| {
+            // RFC 3339 UTC timestamps sort chronologically as strings.
+            std::cmp::Reverse(entry(n).and_then(|e| e.last_used.clone()))
+        }),
+        SortOrder::SwitchCount => {
+            nums.sort_by_key(|n| std::cmp::Reverse(entry(n).map_or(0, |e| e.switch_count)))
+        }
+        SortOrder::Added => nums.sort_by_key(|n| entry(n).map(|e| e.added.clone())),
     }
-    None
+    nums
 }
 
-/// Extract the raw token value from a credentials backup.
-/// Token accounts store: {"token": "sk-ant-..."}
-/// OAuth accounts store the full credentials JSON (not used here).
-fn extract_access_token(creds_json: &str) -> Result<String> {
-    let v: serde_json::Value =
-        serde_json::from_str(creds_json).context("Invalid JSON in credentials backup")?;
-    v.get("token")
-        .and_then(|t| t.as_str())
-        .map(String::from)
-        .context(
-            "Cannot extract token from credentials backup. \
-             Was this account added with `ccswitch add`?",
-        )
+/// Load the sequence and collect display info for every managed account,
+/// in rotation order unless `sort` is given.
+pub fn core_list(sort: Option<SortOrder>) -> Result<Vec<AccountInfo>> {
+    let mut seq = sequence::load()?;
+    if let Some(order) = sort {
+        seq.sequence = sorted_sequence(&seq, order);
+    }
+    Ok(account_infos(&seq))
 }
 
-/// Resolve the currently-active account from sequence state, falling back to
-/// the live Claude config. This works for both OAuth and token accounts.
-fn resolve_current_account(seq: &SequenceFile) -> Result<(u32, String)> {
-    if let Some(num) = seq.active_account_number {
-        if let Some(entry) = seq.accounts.get(&num.to_string()) {
-            return Ok((num, entry.email.clone()));
+/// Width of the terminal on stdout, or `None` when it isn't one.
+fn terminal_width() -> Option<usize> {
+    let (terminal_size::Width(cols), _) = terminal_size::terminal_size_of(io::stdout())?;
+    Some(usize::from(cols))
+}
+
+/// Print the managed accounts. Text output is the compact list, which carries
+/// every badge, or a table of the main columns when `table` is set.
+/// `active_only` keeps just the active account (a single JSON object rather
+/// than an array) and exits 1 without printing anything when none is active.
+/// `no_active` renders the active account like any other, for piping to grep.
+pub fn list(
+    format: OutputFormat,
+    sort: Option<SortOrder>,
+    table: bool,
+    active_only: bool,
+    no_active: bool,
+) -> Result<()> {
+    let mut accounts = core_list(sort)?;
+    if no_active {
+        accounts.iter_mut().for_each(|a| a.active = false);
+    }
+
+    if active_only {
+        accounts.retain(|a| a.active);
+        let Some(active) = accounts.first() else {
+            std::process::exit(1);
+        };
+        if format == OutputFormat::Json {
+            outln!("{}", serde_json::to_string_pretty(active)?);
+            return Ok(());
         }
     }
-    let email = config::current_email()
-        .context("No active Claude account found. Run `ccswitch add` first.")?;
-    let num = seq
-        .find_by_email(&email)
-        .with_context(|| {
-            format!("Account for '{email}' is not managed. Run `ccswitch add` first.")
-        })?;
-    Ok((num, email))
-}
 
-// ── Config backup helpers ─────────────────────────────────────────────────────
+    if format == OutputFormat::Json {
+        outln!("{}", serde_json::to_string_pretty(&accounts)?);
+        return Ok(());
+    }
 
-pub(crate) fn config_backup_path(num: u32, email: &str) -> PathBuf {
-    sequence::backup_dir()
-        .join("configs")
-        .join(format!(".claude-config-{num}-{email}.json"))
-}
+    if accounts.is_empty() {
+        outln!("\n  {}\n", "No accounts managed yet.".dimmed());
+        outln!("  Run {} to add the current account.\n", "ccswitch add".cyan().bold());
+        return Ok(());
+    }
 
-fn write_config_backup(num: u32, email: &str, content: &str) -> Result<()> {
-    #[cfg(unix)]
-    use std::os::unix::fs::PermissionsExt;
+    if table {
+        print_table(&accounts, terminal_width());
+        return Ok(());
+    }
 
-    let path = config_backup_path(num, email);
-    std::fs::write(&path, content)
-        .with_context(|| format!("Cannot write config backup to {}", path.display()))?;
+    outln!("\n  {}", "Managed Accounts".bold());
+    outln!("  {}", "─".repeat(40).dimmed());
 
-    #[cfg(unix)]
-    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    let now = chrono::Utc::now();
+    for info in &accounts {
+        let num = info.number;
+        let expiry_badge = colored_expiry_badge(info.expires_at);
+        let last_used = info.last_used.as_deref().and_then(|ts| relative_time(ts, now));
 
-    Ok(())
-}
+        let kind_badge = if info.auth_kind == AuthKind::Token {
+            " [token]"
+        } else {
+            ""
+        };
 
-pub(crate) fn read_config_backup(num: u32, email: &str) -> Result<String> {
-    let path = config_backup_path(num, email);
-    std::fs::read_to_string(&path)
-        .with_context(|| format!("Cannot read config backup from {}", path.display()))
-}
+        let alias_badge = info
+            .alias
+            .as_ref()
+            .map(|a| format!(" [{}]", a))
+            .unwrap_or_default();
 
-// ── Session expiry helper ─────────────────────────────────────────────────────
+        let tag_badges: String = info.tags.iter().map(|t| format!(" [{}]", t)).collect();
+        let pin_badge = if info.pinned { " [pin]" } else { "" };
 
-/// Compare the fingerprint of `creds` against the stored one in `seq`.
-/// Prints a warning to stderr when they differ.
-fn warn_if_format_changed(seq: &SequenceFile, creds: &str) {
-    let Some(stored_fp) = seq.format_fingerprint.as_deref() else {
-        return;
-    };
-    let live_fp = credentials::credential_field_fingerprint(creds);
-    if !live_fp.is_empty() && live_fp != stored_fp {
-        eprintln!(
-            "\n  {} Claude Code may have changed its credential format. \
-             ccswitch may not work correctly. \
-             Please check https://github.com/vyshnavsdeepak/ccswitch/issues for updates.",
-            "Warning:".yellow().bold()
-        );
+        if info.active {
+            out!(
+                "  {}  {}{}{}{}{}",
+                format!("▶ {num:>2}").green().bold(),
+                info.email.green().bold(),
+                kind_badge.green().dimmed(),
+                pin_badge.blue().bold(),
+                alias_badge.magenta().bold(),
+                tag_badges.cyan(),
+            );
+            if !expiry_badge.is_empty() {
+                out!("  {}", expiry_badge);
+            }
+            out!("  {}", "(active)".green().dimmed());
+            if let Some(ago) = last_used {
+                out!("  {}", format!("used {ago}").dimmed());
+            }
+            outln!();
+        } else {
+            out!(
+                "  {}  {}{}{}{}{}",
+                format!("  {num:>2}").dimmed(),
+                info.email,
+                kind_badge.dimmed(),
+                pin_badge.blue(),
+                alias_badge.magenta(),
+                tag_badges.cyan().dimmed(),
+            );
+            if !expiry_badge.is_empty() {
+                out!("  {}", expiry_badge);
+            }
+            if let Some(ago) = last_used {
+                out!("  {}", format!("used {ago}").dimmed());
+            }
+            outln!();
+        }
+
+        if let Some(ref note) = info.notes {
+            outln!("        {}", note.dimmed());
+        }
     }
+
+    outln!("  {}\n", "─".repeat(40).dimmed());
+    Ok(())
 }
 
-/// Return a short badge string describing session expiry for display in lists.
-/// Empty string means the session is healthy and no badge is needed.
-fn session_expiry_badge(creds_json: &str) -> String {
-    match credentials::oauth_secs_remaining(creds_json) {
-        None => String::new(),
-        Some(secs) if secs <= 0 => "[expired]".to_string(),
-        Some(secs) if secs <= 3 * 24 * 3600 => {
-            let hours = secs / 3600;
-            format!("[~{}h]", hours)
-        }
-        Some(secs) if secs <= 7 * 24 * 3600 => {
-            let days = secs / 86400;
-            format!("[~{}d]", days)
+/// `list --table`: one row per account with the active one highlighted,
+/// emails shortened to fit `max_width` columns.
+fn print_table(accounts: &[AccountInfo], max_width: Option<usize>) {
+    let active_line = accounts.iter().position(|a| a.active).map(|row| row + 3);
+    outln!();
+    for (i, line) in table_lines(accounts, max_width.map(|w| w.saturating_sub(2)))
+        .iter()
+        .enumerate()
+    {
+        if Some(i) == active_line {
+            outln!("  {}", line.green().bold());
+        } else if line.starts_with('+') {
+            outln!("  {}", line.dimmed());
+        } else {
+            outln!("  {}", line);
         }
-        _ => String::new(),
     }
+    outln!();
 }
 
-// ── Doctor health check ───────────────────────────────────────────────────────
+/// The `list --table` lines: top border, header, separator, one line per
+/// account, bottom border. Only the email column gives way to `max_width`.
+fn table_lines(accounts: &[AccountInfo], max_width: Option<usize>) -> Vec<String> {
+    use comfy_table::{presets::ASCII_FULL_CONDENSED, ColumnConstraint, ContentArrangement, Row};
+
+    let now = chrono::Utc::now();
+    let mut table = comfy_table::Table::new();
+    table
+        .load_preset(ASCII_FULL_CONDENSED)
+        .set_truncation_indicator("…")
+        .set_header(["#", "Email", "Type", "Added", "Last Used", "Tags"]);
+    for info in accounts {
+        let mut row = Row::from([
+            if info.active { format!("▶ {}", info.number) } else { info.number.to_string() },
+            info.email.clone(),
+            porcelain_kind(&info.auth_kind).to_string(),
+            chrono::DateTime::parse_from_rfc3339(&info.added)
+                .map(|t| t.format("%Y-%m-%d").to_string())
+                .unwrap_or_else(|_| info.added.clone()),
+            info.last_used
+                .as_deref()
+                .and_then(|ts| relative_time(ts, now))
+                .unwrap_or_else(|| "never".to_string()),
+            info.tags.join(", "),
+        ]);
+        row.max_height(1);
+        table.add_row(row);
+    }
+    if let Some(width) = max_width {
+        table
+            .set_content_arrangement(ContentArrangement::Dynamic)
+            .set_width(u16::try_from(width).unwrap_or(u16::MAX));
+        for (i, column) in table.column_iter_mut().enumerate() {
+            if i != 1 {
+                column.set_constraint(ColumnConstraint::ContentWidth);
+            }
+        }
+    }
+    table.lines().collect()
+}
 
-/// Counts of issues and warnings found by a doctor run (used by tests).
-#[derive(Debug, Default, PartialEq)]
-pub(crate) struct DoctorReport {
-    pub issues: usize,
-    pub warnings: usize,
+/// Per-account data exposed to `list --template`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AccountInfo {
+    /// Account number, as used on the command line.
+    pub number: u32,
+    /// Email, or the label of a token account.
+    pub email: String,
+    /// `oauthAccount.accountUuid`; empty for token accounts.
+    pub uuid: String,
+    /// RFC 3339 timestamp of when the account was added.
+    pub added: String,
+    /// OAuth login or static token.
+    pub auth_kind: AuthKind,
+    /// An alias of this account, if it has any.
+    pub alias: Option<String>,
+    /// Switch priority; `switch --highest-priority` prefers larger values.
+    pub priority: u32,
+    /// Set with `ccswitch note`.
+    pub notes: Option<String>,
+    /// RFC 3339 timestamp of the last switch to this account.
+    pub last_used: Option<String>,
+    /// Number of times this account has been switched to.
+    pub switch_count: u32,
+    /// Set with `ccswitch tag`.
+    pub tags: Vec<String>,
+    /// Whether this is the account Claude Code is using.
+    pub active: bool,
+    /// Set with `ccswitch pin`.
+    pub pinned: bool,
+    /// OAuth session expiry in milliseconds since the Unix epoch.
+    pub expires_at: Option<i64>,
 }
 
-/// Run all health checks and return counts without printing anything.
-/// Takes the already-loaded `SequenceFile` so tests can pass an in-memory value.
-pub(crate) fn core_doctor(seq: &SequenceFile) -> DoctorReport {
-    let mut report = DoctorReport::default();
-    const EXPIRE_SOON_SECS: i64 = 24 * 3600;
+/// Collect display info for every account in rotation order.
+pub(crate) fn account_infos(seq: &SequenceFile) -> Vec<AccountInfo> {
+    let active_num = seq
+        .active_account_number
+        .or_else(|| config::current_email().as_deref().and_then(|e| seq.find_by_email(e)));
+
+    seq.sequence
+        .iter()
+        .filter_map(|&num| {
+            let entry = seq.accounts.get(&num.to_string())?;
+            let active = active_num == Some(num);
+            let expires_at = if entry.auth_kind == AuthKind::Oauth {
+                let creds = if active {
+                    credentials::read_live().ok()
+                } else {
+                    credentials::read_backup(num, &entry.email).ok()
+                };
+                creds
+                    .and_then(|c| credentials::oauth_expires_at(&c))
+                    .or_else(|| {
+                        let at = entry.expires_at.as_deref()?;
+                        chrono::DateTime::parse_from_rfc3339(at)
+                            .ok()
+                            .map(|t| t.timestamp_millis())
+                    })
+            } else {
+                None
+            };
+            let alias = seq
+                .aliases
+                .iter()
+                .find(|(_, &n)| n == num)
+                .map(|(name, _)| name.clone());
+            Some(AccountInfo {
+                number: num,
+                email: entry.email.clone(),
+                uuid: entry.uuid.clone(),
+                added: entry.added.clone(),
+                auth_kind: entry.auth_kind.clone(),
+                alias,
+                priority: entry.priority,
+                notes: entry.notes.clone(),
+                last_used: entry.last_used.clone(),
+                switch_count: entry.switch_count,
+                tags: entry.tags.clone(),
+                active,
+                pinned: seq.pinned_account == Some(num),
+                expires_at,
+            })
+        })
+        .collect()
+}
+
+/// `auth_kind` column of `--porcelain` output.
+fn porcelain_kind(kind: &AuthKind) -> &'static str {
+    match kind {
+        AuthKind::Oauth => "oauth",
+        AuthKind::Token => "token",
+    }
+}
+
+/// One `<num>\t<email>\t<auth_kind>\t<active>` line per account, `active`
+/// being `1` or `0`. Kept stable across patch releases for scripts.
+pub(crate) fn porcelain_lines(accounts: &[AccountInfo]) -> Vec<String> {
+    accounts
+        .iter()
+        .map(|a| {
+            format!("{}\t{}\t{}\t{}", a.number, a.email, porcelain_kind(&a.auth_kind), u8::from(a.active))
+        })
+        .collect()
+}
+
+pub fn list_porcelain(sort: Option<SortOrder>) -> Result<()> {
+    for line in porcelain_lines(&core_list(sort)?) {
+        outln!("{line}");
+    }
+    Ok(())
+}
+
+/// `<email>`, or `<email> [token]` for token accounts, as `list --oneline` prints it.
+fn oneline(entry: &AccountEntry) -> String {
+    match entry.auth_kind {
+        AuthKind::Oauth => entry.email.clone(),
+        AuthKind::Token => format!("{} [token]", entry.email),
+    }
+}
+
+/// The active account on one line without a newline, for shell prompts.
+/// Exits 1 without printing anything when no account is active.
+pub fn list_oneline() -> Result<()> {
+    let Some((_, entry)) = resolve_active_entry(&sequence::load()?) else {
+        std::process::exit(1);
+    };
+    out!("{}", oneline(&entry));
+    io::stdout().flush()?;
+    Ok(())
+}
+
+/// `list --count`: the number of managed accounts, or with `active_only`
+/// whether one is active (1 or 0). Always succeeds, even with no accounts.
+pub(crate) fn core_count(active_only: bool) -> Result<usize> {
+    let seq = sequence::load()?;
+    Ok(if active_only {
+        usize::from(resolve_active_entry(&seq).is_some())
+    } else {
+        seq.accounts.len()
+    })
+}
+
+pub fn list_count(active_only: bool) -> Result<()> {
+    outln!("{}", core_count(active_only)?);
+    Ok(())
+}
+
+/// Render the account list through a user-supplied Handlebars template.
+/// A template starting with `@` is read from the named file.
+pub fn list_template(template: &str, sort: Option<SortOrder>) -> Result<()> {
+    let template = match template.strip_prefix('@') {
+        Some(raw) => {
+            let path = match raw.strip_prefix("~/") {
+                Some(rest) => crate::paths::home_dir()
+                    .context("Cannot find home directory")?
+                    .join(rest),
+                None => PathBuf::from(raw),
+            };
+            std::fs::read_to_string(&path)
+                .with_context(|| format!("Cannot read template file {}", path.display()))?
+        }
+        None => crate::template::unescape(template),
+    };
+
+    let accounts = core_list(sort)?;
+    let active = accounts.iter().find(|a| a.active).map(|a| a.number);
+    let data = serde_json::json!({ "accounts": accounts, "active": active });
+
+    out!("{}", crate::template::render(&template, &data)?);
+    io::stdout().flush()?;
+    Ok(())
+}
+
+/// Format an RFC 3339 timestamp as "3d ago"-style relative time.
+pub(crate) fn relative_time(ts: &str, now: chrono::DateTime<chrono::Utc>) -> Option<String> {
+    let then = chrono::DateTime::parse_from_rfc3339(ts).ok()?;
+    let secs = (now - then.with_timezone(&chrono::Utc)).num_seconds().max(0);
+    Some(match secs {
+        s if s < 60 => "just now".to_string(),
+        s if s < 3600 => format!("{}m ago", s / 60),
+        s if s < 86400 => format!("{}h ago", s / 3600),
+        s => format!("{}d ago", s / 86400),
+    })
+}
+
+// ── Info ──────────────────────────────────────────────────────────────────────
+
+/// Everything ccswitch knows about one account, for `ccswitch info`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AccountDetails {
+    #[serde(flatten)]
+    pub info: AccountInfo,
+    /// Where the credential backup lives (file path or keyring entry).
+    pub credentials_location: String,
+    /// Whether the credential backup could be read.
+    pub credentials_readable: bool,
+    /// SHA-256 of the stored credentials, so backups can be compared without exposing them.
+    pub credentials_sha256: Option<String>,
+    /// Size of the credentials in the backup file; `None` when stored in a keyring or missing.
+    pub credentials_size: Option<u64>,
+    /// Where the Claude config backup lives.
+    pub config_backup_path: PathBuf,
+    /// Size of the config backup file; `None` when missing.
+    pub config_backup_size: Option<u64>,
+    /// `expiresAt` from the stored OAuth credentials, in milliseconds since the Unix epoch.
+    pub stored_expires_at: Option<i64>,
+    /// `user@hostname` that added the account, when recorded.
+    pub created_by: Option<String>,
+    /// Short ID of the machine that added the account, when recorded.
+    pub machine_id: Option<String>,
+}
+
+/// Everything stored about account `num`.
+pub fn core_info(num: u32) -> Result<AccountDetails> {
+    let seq = sequence::load()?;
+    let entry = seq
+        .accounts
+        .get(&num.to_string())
+        .with_context(|| format!("Account {num} does not exist"))?;
+    let info = account_infos(&seq)
+        .into_iter()
+        .find(|i| i.number == num)
+        .with_context(|| format!("Account {num} is not in the rotation sequence"))?;
+
+    let stored = credentials::read_backup(num, &entry.email).ok();
+    let file_size = |p: &std::path::Path| std::fs::metadata(p).ok().map(|m| m.len());
+    let config_backup_path = config_backup_path(num, &entry.email);
+
+    Ok(AccountDetails {
+        credentials_location: credentials::backup_location(num, &entry.email),
+        credentials_readable: stored.is_some(),
+        credentials_sha256: stored.as_deref().map(credentials::sha256_fingerprint),
+        credentials_size: credentials::backup_file_path(num, &entry.email)
+            .filter(|p| p.exists())
+            .and(stored.as_deref())
+            .map(|s| s.len() as u64),
+        config_backup_size: file_size(&config_backup_path),
+        config_backup_path,
+        stored_expires_at: stored.as_deref().and_then(credentials::oauth_expires_at),
+        created_by: entry.created_by.clone(),
+        machine_id: entry.machine_id.clone(),
+        info,
+    })
+}
+
+pub fn info(identifier: &str, format: OutputFormat) -> Result<()> {
+    let num = resolve_account(identifier)?;
+    let d = core_info(num)?;
+
+    if format == OutputFormat::Json {
+        outln!("{}", serde_json::to_string_pretty(&d)?);
+        return Ok(());
+    }
+
+    let now = chrono::Utc::now();
+    let none = || "—".dimmed().to_string();
+    let row = |label: &str, value: String| outln!("  {:<14} {}", label.dimmed(), value);
+
+    outln!(
+        "\n  {} {}{}",
+        format!("Account {}", d.info.number).bold(),
+        d.info.email.bold(),
+        if d.info.active { "  (active)".green().to_string() } else { String::new() }
+    );
+    outln!("  {}", "─".repeat(40).dimmed());
+    row("UUID", if d.info.uuid.is_empty() { none() } else { d.info.uuid.clone() });
+    row(
+        "Auth",
+        match d.info.auth_kind {
+            AuthKind::Oauth => "OAuth".to_string(),
+            AuthKind::Token => "token".to_string(),
+        },
+    );
+    row("Added", d.info.added.clone());
+    row(
+        "Created by",
+        match (&d.created_by, &d.machine_id) {
+            (Some(by), Some(id)) => format!("{by} {}", format!("(machine {id})").dimmed()),
+            (Some(by), None) => by.clone(),
+            (None, _) => none(),
+        },
+    );
+    row(
+        "Last used",
+        d.info
+            .last_used
+            .as_deref()
+            .map(|ts| match relative_time(ts, now) {
+                Some(ago) => format!("{ts} ({ago})"),
+                None => ts.to_string(),
+            })
+            .unwrap_or_else(|| "never".to_string()),
+    );
+    row("Switches", d.info.switch_count.to_string());
+    row("Priority", d.info.priority.to_string());
+    row("Alias", d.info.alias.clone().unwrap_or_else(none));
+    row(
+        "Tags",
+        if d.info.tags.is_empty() {
+            none()
+        } else {
+            d.info.tags.iter().map(|t| format!("[{t}]")).collect::<Vec<_>>().join(" ")
+        },
+    );
+    row("Notes", d.info.notes.clone().unwrap_or_else(none));
+    if let Some(ms) = d.stored_expires_at {
+        let badge = expiry_badge(Some(ms));
+        let at = chrono::DateTime::from_timestamp_millis(ms)
+            .map(|t| t.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+            .unwrap_or_else(|| ms.to_string());
+        row("Expires", format!("{at} {}", badge.yellow()).trim_end().to_string());
+    }
+
+    outln!();
+    let size = |s: Option<u64>| s.map(|b| format!(" ({b} bytes)")).unwrap_or_default();
+    row(
+        "Credentials",
+        if d.credentials_readable {
+            format!("{}{}", d.credentials_location, size(d.credentials_size))
+        } else {
+            format!("{} {}", d.credentials_location, "(not accessible)".red())
+        },
+    );
+    row(
+        "SHA-256",
+        d.credentials_sha256.clone().unwrap_or_else(none),
+    );
+    row(
+        "Config",
+        match d.config_backup_size {
+            Some(_) => format!("{}{}", d.config_backup_path.display(), size(d.config_backup_size)),
+            None => format!("{} {}", d.config_backup_path.display(), "(missing)".yellow()),
+        },
+    );
+    outln!();
+    Ok(())
+}
+
+// ── Stats ─────────────────────────────────────────────────────────────────────
+
+/// Accounts sorted by switch count, most used first (ties keep rotation order).
+pub(crate) fn core_stats() -> Result<Vec<AccountInfo>> {
+    core_list(Some(SortOrder::SwitchCount))
+}
+
+pub fn stats(format: OutputFormat) -> Result<()> {
+    let accounts = core_stats()?;
+
+    if format == OutputFormat::Json {
+        outln!("{}", serde_json::to_string_pretty(&accounts)?);
+        return Ok(());
+    }
+
+    if accounts.is_empty() {
+        outln!("\n  {}\n", "No accounts managed yet.".dimmed());
+        return Ok(());
+    }
+
+    let width = accounts.iter().map(|a| a.email.len()).max().unwrap_or(0).max(5);
+    let now = chrono::Utc::now();
+
+    outln!("\n  {}", "Usage".bold());
+    outln!(
+        "  {}",
+        format!("{:>3}  {:<width$}  {:>8}  {}", "#", "Email", "Switches", "Last used").dimmed()
+    );
+    for info in &accounts {
+        let last_used = info
+            .last_used
+            .as_deref()
+            .and_then(|ts| relative_time(ts, now))
+            .unwrap_or_else(|| "never".to_string());
+        outln!(
+            "  {:>3}  {:<width$}  {:>8}  {}",
+            info.number, info.email, info.switch_count, last_used
+        );
+    }
+    outln!();
+    Ok(())
+}
+
+// ── History ───────────────────────────────────────────────────────────────────
+
+/// Print the last `lines` entries of the switch log, oldest first.
+pub fn history(lines: usize, format: OutputFormat) -> Result<()> {
+    let entries = sequence::read_switch_log(lines)?;
+
+    if format == OutputFormat::Json {
+        outln!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+
+    if entries.is_empty() {
+        outln!("\n  {}\n", "No switches recorded yet.".dimmed());
+        return Ok(());
+    }
+
+    let seq = sequence::load()?;
+    let label = |num: u32| match seq.accounts.get(&num.to_string()) {
+        Some(e) => format!("{} ({})", num, e.email),
+        None => num.to_string(),
+    };
+
+    outln!("\n  {}", "Switch history".bold());
+    for e in &entries {
+        let line = format!("{}  {} → {}", e.timestamp, label(e.from), label(e.to));
+        if e.ok {
+            outln!("  {} {}", "✓".green().bold(), line.green());
+        } else {
+            outln!("  {} {}", "✗".red().bold(), line.red());
+        }
+    }
+    outln!();
+    Ok(())
+}
+
+// ── Audit log ─────────────────────────────────────────────────────────────────
+
+/// Record a credential access in `audit.log`. Like the switch log, a write
+/// failure never fails the operation itself.
+pub(crate) fn record_audit(action: AuditAction, num: u32, email: &str) {
+    let _ = sequence::append_audit_log(AuditEvent::new(action, num, email));
+}
+
+/// Print the audit log (the last `lines` entries when given), or copy it as
+/// JSON lines to `output`.
+pub fn audit_log(lines: Option<usize>, output: Option<&Path>, format: OutputFormat) -> Result<()> {
+    let events = sequence::read_audit_log(lines)?;
+
+    if let Some(path) = output {
+        let mut content = String::new();
+        for e in &events {
+            content.push_str(&serde_json::to_string(e)?);
+            content.push('\n');
+        }
+        std::fs::write(path, content)
+            .with_context(|| format!("Cannot write to {}", path.display()))?;
+        outln!(
+            "\n  {} Wrote {} audit event(s) to {}\n",
+            "✓".green().bold(),
+            events.len(),
+            path.display()
+        );
+        return Ok(());
+    }
+
+    if format == OutputFormat::Json {
+        outln!("{}", serde_json::to_string_pretty(&events)?);
+        return Ok(());
+    }
+
+    if events.is_empty() {
+        outln!("\n  {}\n", "No credential access recorded yet.".dimmed());
+        return Ok(());
+    }
+
+    outln!("\n  {}", "Audit log".bold());
+    for e in &events {
+        let action = if e.ok { e.action.to_string().cyan() } else { e.action.to_string().red() };
+        let failed = if e.ok { String::new() } else { format!("  {}", "failed".red()) };
+        let profile = if e.profile.is_empty() {
+            String::new()
+        } else {
+            format!("[{}] ", e.profile)
+        };
+        outln!(
+            "  {}  {:<6}  {}Account {} ({}){}  {}",
+            e.timestamp,
+            action,
+            profile,
+            e.account_num,
+            e.account_email,
+            failed,
+            format!("{} pid {}", e.user, e.pid).dimmed()
+        );
+    }
+    outln!();
+    Ok(())
+}
+
+/// `audit --verify`: check the log's hash chain and fail at the first
+/// broken line.
+pub fn audit_verify() -> Result<()> {
+    match sequence::verify_audit_log()? {
+        None => {
+            outln!(
+                "\n  {} The audit log's hash chain is intact\n",
+                "✓".green().bold()
+            );
+            Ok(())
+        }
+        Some(line) => bail!(
+            "audit.log line {line} does not follow the line before it; \
+             the log was edited or lines were removed"
+        ),
+    }
+}
+
+// ── Status ────────────────────────────────────────────────────────────────────
+
+/// Structured `status` output for `--format json`.
+#[derive(Debug, serde::Serialize)]
+pub struct StatusReport {
+    /// The active account; `None` when Claude Code's account isn't managed.
+    pub active: Option<ActiveStatus>,
+    /// True when CLAUDE_CODE_OAUTH_TOKEN overrides the keychain credentials.
+    pub env_token_override: bool,
+}
+
+/// The active account, as reported by `status`.
+#[derive(Debug, serde::Serialize)]
+pub struct ActiveStatus {
+    /// Account number.
+    pub number: u32,
+    /// Email, or the label of a token account.
+    pub email: String,
+    /// OAuth login or static token.
+    pub auth_kind: AuthKind,
+    /// OAuth session expiry in milliseconds since the Unix epoch.
+    pub expires_at: Option<i64>,
+    /// RFC 3339 timestamp of the last switch to this account.
+    pub last_used: Option<String>,
+}
+
+/// Resolve the active account — prefers seq state so token accounts show correctly.
+fn resolve_active_entry(seq: &SequenceFile) -> Option<(u32, AccountEntry)> {
+    seq.active_account_number
+        .and_then(|num| seq.accounts.get(&num.to_string()).map(|e| (num, e.clone())))
+        .or_else(|| {
+            config::current_email().and_then(|email| {
+                seq.find_by_email(&email)
+                    .and_then(|num| seq.accounts.get(&num.to_string()).map(|e| (num, e.clone())))
+            })
+        })
+}
+
+pub(crate) fn status_report(seq: &SequenceFile) -> StatusReport {
+    let active = resolve_active_entry(seq).map(|(num, entry)| {
+        let expires_at = if entry.auth_kind == AuthKind::Oauth {
+            credentials::read_live()
+                .ok()
+                .and_then(|c| credentials::oauth_expires_at(&c))
+        } else {
+            None
+        };
+        ActiveStatus {
+            number: num,
+            email: entry.email,
+            auth_kind: entry.auth_kind,
+            expires_at,
+            last_used: entry.last_used,
+        }
+    });
+    StatusReport {
+        active,
+        env_token_override: config::has_env_token(),
+    }
+}
+
+/// Load the sequence and build the structured status report.
+pub fn core_status() -> Result<StatusReport> {
+    let seq = sequence::load()?;
+    Ok(status_report(&seq))
+}
+
+/// `status --porcelain`: `<num>\t<email>\t<auth_kind>` for the active
+/// account, or an empty line when none is active.
+pub fn status_porcelain() -> Result<()> {
+    match resolve_active_entry(&sequence::load()?) {
+        Some((num, entry)) => outln!("{num}\t{}\t{}", entry.email, porcelain_kind(&entry.auth_kind)),
+        None => outln!(),
+    }
+    Ok(())
+}
+
+pub fn status(format: OutputFormat, verbose: bool) -> Result<()> {
+    if format == OutputFormat::Json {
+        outln!("{}", serde_json::to_string_pretty(&core_status()?)?);
+        return Ok(());
+    }
+
+    let seq = sequence::load()?;
+
+    match resolve_active_entry(&seq) {
+        None => {
+            if config::has_env_token() {
+                outln!(
+                    "\n  {} {} {}\n",
+                    "·".yellow().bold(),
+                    "Token active".bold(),
+                    "(not managed — run `ccswitch add`)".dimmed()
+                );
+            } else {
+                outln!("\n  {} Not logged in to Claude Code.\n", "✗".red().bold());
+            }
+        }
+        Some((num, entry)) => {
+            let kind_badge = if entry.auth_kind == AuthKind::Token {
+                " [token]"
+            } else {
+                ""
+            };
+
+            let expiry_str = if entry.auth_kind == AuthKind::Oauth {
+                credentials::read_live().ok().map(|c| {
+                    match credentials::oauth_secs_remaining(&c) {
+                        None => String::new(),
+                        Some(secs) if secs <= 0 => " — session expired".red().bold().to_string(),
+                        Some(secs) => {
+                            let days = secs / 86400;
+                            let hours = (secs % 86400) / 3600;
+                            if days > 7 {
+                                format!(" — expires in {}d", days).dimmed().to_string()
+                            } else if days >= 1 {
+                                format!(" — expires in {}d {}h", days, hours).yellow().to_string()
+                            } else {
+                                format!(" — expires in {}h", hours).yellow().bold().to_string()
+                            }
+                        }
+                    }
+                })
+            } else {
+                None
+            };
+
+            outln!(
+                "\n  {} {}{}{} {}\n",
+                "▶".green().bold(),
+                entry.email.bold(),
+                kind_badge.dimmed(),
+                expiry_str.as_deref().unwrap_or(""),
+                format!("(Account {num})").dimmed()
+            );
+        }
+    }
+    if verbose {
+        print_status_details(resolve_active_entry(&seq).map(|(num, _)| num))?;
+    }
+    Ok(())
+}
+
+/// `status --verbose`: where everything lives, for bug reports. Sizes and
+/// lengths only; never credential contents.
+fn print_status_details(active: Option<u32>) -> Result<()> {
+    let none = || "—".dimmed().to_string();
+    let row = |label: &str, value: String| outln!("  {:<14} {}", label.dimmed(), value);
+    let size = |s: Option<u64>| s.map(|b| format!(" ({b} bytes)")).unwrap_or_default();
+
+    row("Claude config", config::path().display().to_string());
+    row("Live creds", credentials::live_location());
+    if let Some(keychain) = credentials::live_keychain() {
+        row("Keychain", keychain);
+    }
+    row(
+        "Env token",
+        match std::env::var("CLAUDE_CODE_OAUTH_TOKEN") {
+            Ok(token) => format!("CLAUDE_CODE_OAUTH_TOKEN set ({} chars)", token.len()),
+            Err(_) => "CLAUDE_CODE_OAUTH_TOKEN not set".to_string(),
+        },
+    );
+
+    if let Some(num) = active {
+        let d = core_info(num)?;
+        outln!();
+        row("UUID", if d.info.uuid.is_empty() { none() } else { d.info.uuid.clone() });
+        row("Added", d.info.added.clone());
+        row("Last used", d.info.last_used.clone().unwrap_or_else(|| "never".to_string()));
+        row(
+            "Credentials",
+            if d.credentials_readable {
+                format!("{}{}", d.credentials_location, size(d.credentials_size))
+            } else {
+                format!("{} {}", d.credentials_location, "(not accessible)".red())
+            },
+        );
+        row(
+            "Config backup",
+            match d.config_backup_size {
+                Some(_) => format!("{}{}", d.config_backup_path.display(), size(d.config_backup_size)),
+                None => format!("{} {}", d.config_backup_path.display(), "(missing)".yellow()),
+            },
+        );
+    }
+    outln!();
+    Ok(())
+}
+
+// ── Project-local account ─────────────────────────────────────────────────────
+
+/// The account chosen by a `.ccswitch` file in the working directory or a parent.
+fn project_override(opts: &SwitchOptions) -> Option<u32> {
+    let num = config::find_project_account()?;
+    if opts.format == OutputFormat::Text {
+        outln!(
+            "\n  {} Using project-local account {num} (from {})",
+            "·".cyan(),
+            config::PROJECT_FILE
+        );
+    }
+    Some(num)
+}
+
+/// Write a `.ccswitch` in the current directory selecting the account
+/// `identifier` resolves to, by number and email.
+pub fn init_project(identifier: &str) -> Result<()> {
+    let seq = sequence::load()?;
+    let num = seq
+        .resolve(identifier)
+        .with_context(|| format!("No account found matching '{identifier}'"))?;
+    let email = seq.accounts[&num.to_string()].email.clone();
+    let path = std::env::current_dir()?.join(config::PROJECT_FILE);
+    std::fs::write(&path, format!("account={num}\nemail={email}\n"))
+        .with_context(|| format!("Cannot write {}", path.display()))?;
+    outln!(
+        "\n  {} Wrote {} — switches in this directory now use Account {num} ({email}).\n",
+        "✓".green().bold(),
+        path.display()
+    );
+    Ok(())
+}
+
+// ── Switch (rotate to next) ───────────────────────────────────────────────────
+
+/// The account after `current` in rotation order, or before it when
+/// `backwards`. Counts from the first account when `current` is not in the
+/// rotation. `seq.sequence` must not be empty.
+pub(crate) fn rotation_neighbor(seq: &SequenceFile, current: u32, backwards: bool) -> u32 {
+    let len = seq.sequence.len();
+    let idx = seq.sequence.iter().position(|&n| n == current).unwrap_or(0);
+    let step = if backwards { len - 1 } else { 1 };
+    seq.sequence[(idx + step) % len]
+}
+
+/// How `ccswitch switch` with no account picks the next one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum RotationStrategy {
+    /// The account after the current one in rotation order.
+    #[default]
+    RoundRobin,
+    /// The least recently used account; never-used accounts first.
+    Lru,
+    /// Any other account, chosen uniformly at random.
+    Random,
+}
+
+/// The account to rotate to from `current` under `strategy`. Only
+/// round-robin counts `current` in when it is not in the rotation; the others
+/// choose among the remaining accounts, breaking ties in rotation order.
+pub(crate) fn next_by_strategy(
+    seq: &SequenceFile,
+    current: u32,
+    strategy: RotationStrategy,
+) -> Result<u32> {
+    let others: Vec<u32> = seq.sequence.iter().copied().filter(|&n| n != current).collect();
+    if others.is_empty() {
+        bail!("No other account to switch to. Add another with `ccswitch add`.");
+    }
+    Ok(match strategy {
+        RotationStrategy::RoundRobin => rotation_neighbor(seq, current, false),
+        RotationStrategy::Lru => *others
+            .iter()
+            .min_by_key(|&&n| {
+                seq.accounts
+                    .get(&n.to_string())
+                    .and_then(|e| e.last_used.as_deref())
+                    .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+            })
+            .expect("others is not empty"),
+        RotationStrategy::Random => {
+            use rand::seq::SliceRandom;
+            *others.choose(&mut rand::thread_rng()).expect("others is not empty")
+        }
+    })
+}
+
+/// `ccswitch switch` with no account: the account picked by a `.ccswitch`
+/// file, or else the next account by `strategy`.
+pub fn switch_next(opts: &SwitchOptions, strategy: RotationStrategy) -> Result<()> {
+    if let Some(num) = project_override(opts) {
+        return do_switch(num, opts);
+    }
+    switch_rotate(opts, false, strategy)
+}
+
+/// `ccswitch switch --cycle-back`: rotate to the previous account.
+pub fn switch_prev(opts: &SwitchOptions) -> Result<()> {
+    switch_rotate(opts, true, RotationStrategy::RoundRobin)
+}
+
+fn switch_rotate(opts: &SwitchOptions, backwards: bool, strategy: RotationStrategy) -> Result<()> {
+    let seq = sequence::load()?;
+
+    if seq.accounts.is_empty() {
+        bail!("No accounts managed yet. Run `ccswitch add` first.");
+    }
+
+    if seq.sequence.len() < 2 {
+        bail!("Only one account managed. Add another with `ccswitch add`.");
+    }
+
+    // For token accounts, active_account_number is the source of truth
+    let active_num = if let Some(num) = seq.active_account_number {
+        num
+    } else if let Some(num) = seq.pinned_account.filter(|n| seq.sequence.contains(n)) {
+        // Nothing is known to be active (e.g. a fresh machine): start at the pin.
+        return do_switch(num, opts);
+    } else {
+        let current_email = config::current_email()
+            .context("No active Claude account found")?;
+
+        if !seq.account_exists(&current_email) {
+            outln!(
+                "\n  {} Active account '{}' is not managed — adding it...",
+                "·".yellow(),
+                current_email
+            );
+            add(&AddOptions::default())?;
+            outln!(
+                "\n  Run {} again to switch to the next account.\n",
+                "ccswitch switch".cyan().bold()
+            );
+            return Ok(());
+        }
+
+        seq.find_by_email(&current_email)
+            .context("Cannot find account number for current email")?
+    };
+
+    let target = if backwards {
+        rotation_neighbor(&seq, active_num, true)
+    } else {
+        next_by_strategy(&seq, active_num, strategy)?
+    };
+    do_switch(target, opts)
+}
+
+// ── Switch by tag ─────────────────────────────────────────────────────────────
+
+/// The next account in rotation after the active one that carries `tag`.
+fn tag_target(seq: &SequenceFile, tag: &str) -> Result<u32> {
+    let tag = normalize_tag(tag)?;
+    seq.next_with_tag(&tag, seq.active_account_number)
+        .with_context(|| format!("No other account is tagged [{tag}]"))
+}
+
+/// Switch to the next account in rotation tagged `tag`.
+pub fn core_switch_tag(tag: &str) -> Result<SwitchResult> {
+    let target = tag_target(&sequence::load()?, tag)?;
+    core_switch(target).map_err(anyhow::Error::from)
+}
+
+pub fn switch_tag(tag: &str, opts: &SwitchOptions) -> Result<()> {
+    let target = tag_target(&sequence::load()?, tag)?;
+    do_switch(target, opts)
+}
+
+// ── Switch to highest-priority account ────────────────────────────────────────
+
+pub fn switch_highest_priority(opts: &SwitchOptions) -> Result<()> {
+    let seq = sequence::load()?;
+
+    if seq.accounts.is_empty() {
+        bail!("No accounts managed yet. Run `ccswitch add` first.");
+    }
+
+    let target_num = seq
+        .highest_priority_excluding(seq.active_account_number)
+        .context("No other account to switch to. Add another with `ccswitch add`.")?;
+
+    do_switch(target_num, opts)
+}
+
+// ── Interactive switch ────────────────────────────────────────────────────────
+
+/// `switch --interactive`: pick the target from an inline, filterable list.
+#[cfg(feature = "tui")]
+pub fn switch_interactive(opts: &SwitchOptions, no_color: bool) -> Result<()> {
+    use std::io::IsTerminal;
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        bail!("--interactive needs a terminal; pass an account instead.");
+    }
+    let seq = sequence::load()?;
+    if seq.accounts.is_empty() {
+        bail!("No accounts managed yet. Run `ccswitch add` first.");
+    }
+
+    let choices: Vec<crate::picker::Choice> = account_infos(&seq)
+        .into_iter()
+        .map(|info| crate::picker::Choice {
+            number: info.number,
+            email: info.email,
+            active: info.active,
+        })
+        .collect();
+    match crate::picker::pick(&choices, no_color)? {
+        Some(num) => do_switch(num, opts),
+        None => Ok(()),
+    }
+}
+
+// ── Switch to specific account ────────────────────────────────────────────────
+
+pub fn switch_to(identifier: &str, opts: &SwitchOptions) -> Result<()> {
+    let seq = sequence::load()?;
+
+    if seq.accounts.is_empty() {
+        bail!("No accounts managed yet. Run `ccswitch add` first.");
+    }
+
+    let target_num = seq
+        .resolve(identifier)
+        .ok_or_else(|| no_match_error(&seq, identifier))?;
+
+    do_switch(target_num, opts)
+}
+
+// ── Undo last switch ──────────────────────────────────────────────────────────
+
+/// The account `undo` would return to, checked to still exist.
+pub(crate) fn undo_target(seq: &SequenceFile) -> Result<u32> {
+    let num = seq
+        .previous_account_number
+        .context("Nothing to undo — no switch has been made since the last undo.")?;
+    if !seq.accounts.contains_key(&num.to_string()) {
+        bail!("Cannot undo: the previous account ({num}) no longer exists.");
+    }
+    Ok(num)
+}
+
+/// Switch back to the account that was active before the last switch.
+pub fn core_undo() -> Result<SwitchResult> {
+    let target = undo_target(&sequence::load()?)?;
+    Ok(switch_account(target, true)?)
+}
+
+pub fn undo(opts: &SwitchOptions) -> Result<()> {
+    let target = undo_target(&sequence::load()?)?;
+    report_switch(target, opts, true)
+}
+
+// ── Switch preview (--dry-run) ────────────────────────────────────────────────
+
+/// What `core_switch` would change, computed without writing anything.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SwitchPreview {
+    pub from_email: String,
+    pub to_email: String,
+    /// Line diff of the `oauthAccount` config section; each line starts with `-`, `+` or ` `.
+    pub config_diff: Vec<String>,
+    /// Whether the live credentials would be replaced with different ones.
+    pub creds_changed: bool,
+    /// Every file or keychain entry the switch would write, in order.
+    pub writes: Vec<String>,
+}
+
+pub fn preview_switch(target_num: u32) -> Result<SwitchPreview> {
+    let seq = sequence::load()?;
+    let target = seq
+        .accounts
+        .get(&target_num.to_string())
+        .cloned()
+        .with_context(|| format!("Account {target_num} does not exist"))?;
+    let (current_num, from_email) = resolve_current_account(&seq)?;
+
+    let mut preview = SwitchPreview {
+        from_email,
+        to_email: target.email.clone(),
+        config_diff: Vec::new(),
+        creds_changed: false,
+        writes: Vec::new(),
+    };
+    if target_num == current_num {
+        return Ok(preview);
+    }
+
+    let current_kind = seq
+        .accounts
+        .get(&current_num.to_string())
+        .map(|e| e.auth_kind.clone())
+        .unwrap_or_default();
+    if current_kind == AuthKind::Oauth {
+        preview.writes.push(credentials::backup_location(current_num, &preview.from_email));
+        preview
+            .writes
+            .push(config_backup_path(current_num, &preview.from_email).display().to_string());
+    }
+
+    let target_creds = credentials::read_backup(target_num, &target.email)
+        .with_context(|| format!("Missing credentials backup for Account {target_num}"))?;
+    let live_creds = credentials::read_live().ok();
+    let live_config = config::load().context("Cannot read current Claude config")?;
+    let old_oauth = live_config.get("oauthAccount").cloned();
+
+    let new_oauth = match target.auth_kind {
+        AuthKind::Oauth => {
+            let backup: serde_json::Value =
+                serde_json::from_str(&read_config_backup(target_num, &target.email)?)
+                    .context("Invalid JSON in config backup")?;
+            preview.creds_changed = live_creds.as_deref() != Some(target_creds.as_str());
+            Some(
+                backup
+                    .get("oauthAccount")
+                    .cloned()
+                    .context("Missing oauthAccount in config backup")?,
+            )
+        }
+        AuthKind::Token => {
+            let token = extract_access_token(&target_creds)?;
+            let live_token = live_creds.as_deref().and_then(|c| {
+                let v: serde_json::Value = serde_json::from_str(c).ok()?;
+                v.get("claudeAiOauth")?.get("accessToken")?.as_str().map(String::from)
+            });
+            preview.creds_changed = live_token.as_deref() != Some(token.as_str());
+            if crate::platform::detect() != crate::platform::Platform::MacOS {
+                preview.writes.push(credentials::active_token_file_path().display().to_string());
+            } else {
+                preview.writes.push("keychain entry \"ccswitch-active-token\"".to_string());
+            }
+            None
+        }
+    };
+
+    preview.writes.push(credentials::live_location());
+    preview.writes.push(config::path().display().to_string());
+    preview.writes.push(sequence::sequence_path().display().to_string());
+
+    preview.config_diff = line_diff(&render_oauth(old_oauth), &render_oauth(new_oauth));
+
+    Ok(preview)
+}
+
+fn render_oauth(v: Option<serde_json::Value>) -> String {
+    match v {
+        Some(v) => serde_json::to_string_pretty(&serde_json::json!({ "oauthAccount": v }))
+            .unwrap_or_default(),
+        None => String::new(),
+    }
+}
+
+/// Minimal LCS line diff. Every line of both inputs appears once, prefixed
+/// with `-` (only in `old`), `+` (only in `new`) or ` ` (in both).
+fn line_diff(old: &str, new: &str) -> Vec<String> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j, mut out) = (0, 0, Vec::new());
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            out.push(format!(" {}", a[i]));
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            out.push(format!("-{}", a[i]));
+            i += 1;
+        } else {
+            out.push(format!("+{}", b[j]));
+            j += 1;
+        }
+    }
+    out
+}
+
+fn print_switch_preview(preview: &SwitchPreview) {
+    outln!(
+        "\n  {} {}  {}  {}  {}",
+        "→".cyan().bold(),
+        preview.from_email.dimmed(),
+        "→".dimmed(),
+        preview.to_email.cyan().bold(),
+        "(dry run)".yellow()
+    );
+
+    outln!("\n  {}", "Would write:".bold());
+    for w in &preview.writes {
+        outln!("    {}", w);
+    }
+
+    outln!(
+        "\n  {} {}",
+        "Credentials:".bold(),
+        if preview.creds_changed { "replaced" } else { "unchanged" }
+    );
+
+    outln!("\n  {}", "Config (oauthAccount):".bold());
+    print_diff_lines(&preview.config_diff);
+    outln!("\n  {} Nothing was written.\n", "·".dimmed());
+}
+
+fn print_diff_lines(lines: &[String]) {
+    for line in lines {
+        match line.chars().next() {
+            Some('-') => outln!("    {}", line.red()),
+            Some('+') => outln!("    {}", line.green()),
+            _ => outln!("    {}", line.dimmed()),
+        }
+    }
+}
+
+// ── Diff two accounts ─────────────────────────────────────────────────────────
+
+/// How two accounts' backups differ. Credentials are only reported as
+/// present or missing; their contents never leave the store.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AccountDiff {
+    /// Email of the first account.
+    pub from_email: String,
+    /// Email of the second account.
+    pub to_email: String,
+    /// Whether the first account's credential backup is readable.
+    pub from_has_credentials: bool,
+    /// Whether the second account's credential backup is readable.
+    pub to_has_credentials: bool,
+    /// Line diff of the `oauthAccount` sections of the two config backups.
+    pub config_diff: Vec<String>,
+}
+
+/// The `oauthAccount` section of an account's config backup, if it has one.
+fn backup_oauth(num: u32, email: &str) -> Option<serde_json::Value> {
+    let raw = read_config_backup(num, email).ok()?;
+    serde_json::from_str::<serde_json::Value>(&raw).ok()?.get("oauthAccount").cloned()
+}
+
+/// Compare the backups of accounts `from_num` and `to_num`.
+pub fn core_diff(from_num: u32, to_num: u32) -> Result<AccountDiff> {
+    let seq = sequence::load()?;
+    let entry = |num: u32| {
+        seq.accounts
+            .get(&num.to_string())
+            .with_context(|| format!("Account {num} does not exist"))
+    };
+    let (from, to) = (entry(from_num)?, entry(to_num)?);
+    Ok(AccountDiff {
+        from_email: from.email.clone(),
+        to_email: to.email.clone(),
+        from_has_credentials: credentials::read_backup(from_num, &from.email).is_ok(),
+        to_has_credentials: credentials::read_backup(to_num, &to.email).is_ok(),
+        config_diff: line_diff(
+            &render_oauth(backup_oauth(from_num, &from.email)),
+            &render_oauth(backup_oauth(to_num, &to.email)),
+        ),
+    })
+}
+
+pub fn diff(from_num: u32, to_num: u32) -> Result<()> {
+    let d = core_diff(from_num, to_num)?;
+    let present = |ok: bool| if ok { "present".green() } else { "missing".red() };
+
+    outln!(
+        "\n  {} Account {from_num} ({})  {}  Account {to_num} ({})",
+        "±".cyan().bold(),
+        d.from_email.dimmed(),
+        "→".dimmed(),
+        d.to_email.cyan().bold()
+    );
+    outln!("\n  {}", "Credentials backup:".bold());
+    outln!("    {:<8} {}", from_num, present(d.from_has_credentials));
+    outln!("    {:<8} {}", to_num, present(d.to_has_credentials));
+
+    outln!("\n  {}", "Config backup (oauthAccount):".bold());
+    if d.config_diff.is_empty() {
+        outln!("    {}", "neither account has a config backup".dimmed());
+    } else if d.config_diff.iter().all(|l| l.starts_with(' ')) {
+        outln!("    {}", "identical".dimmed());
+    } else {
+        print_diff_lines(&d.config_diff);
+    }
+    outln!();
+    Ok(())
+}
+
+// ── CLI switch wrapper ────────────────────────────────────────────────────────
+
+fn do_switch(target_num: u32, opts: &SwitchOptions) -> Result<()> {
+    report_switch(target_num, opts, false)
+}
+
+/// `do_switch`, or with `undo` the switch back that `undo` performs.
+fn report_switch(target_num: u32, opts: &SwitchOptions, undo: bool) -> Result<()> {
+    let seq = sequence::load()?;
+    let json = opts.format == OutputFormat::Json;
+
+    let target_entry = seq
+        .accounts
+        .get(&target_num.to_string())
+        .cloned()
+        .with_context(|| format!("Account {target_num} does not exist"))?;
+    let target_email = target_entry.email.clone();
+    // Determine display name for current account — handles both OAuth and token
+    let current_slot_email = if let Some(num) = seq.active_account_number {
+        seq.accounts
+            .get(&num.to_string())
+            .map(|e| e.email.clone())
+            .unwrap_or_else(|| "unknown".to_string())
+    } else {
+        config::current_email().unwrap_or_else(|| "unknown".to_string())
+    };
+
+    // Already on the target?
+    if seq.active_account_number == Some(target_num) {
+        if json {
+            let result = SwitchResult {
+                message: format!("Already using {} (Account {}).", target_email, target_num),
+                files_written: Vec::new(),
+                active_account: target_num,
+                post_hook_error: None,
+                env_changed: false,
+            };
+            outln!("{}", serde_json::to_string_pretty(&result)?);
+        } else {
+            outln!(
+                "\n  {} Already using {} (Account {target_num}).\n",
+                "·".cyan(),
+                target_email.bold()
+            );
+        }
+        return Ok(());
+    }
+
+    if opts.dry_run {
+        let preview = preview_switch(target_num)?;
+        if json {
+            outln!("{}", serde_json::to_string_pretty(&preview)?);
+        } else {
+            print_switch_preview(&preview);
+        }
+        return Ok(());
+    }
+
+    // If the target is an OAuth account with an expired session, warn and
+    // optionally refresh before switching. JSON output is never interactive.
+    if !json && target_entry.auth_kind == AuthKind::Oauth {
+        if let Ok(backup_creds) = credentials::read_backup(target_num, &target_email) {
+            if !credentials::is_oauth_active(&backup_creds) {
+                outln!(
+                    "\n  {} Account {} ({}) has an expired session.",
+                    "!".yellow().bold(),
+                    target_num,
+                    target_email
+                );
+
+                use std::io::IsTerminal;
+                if io::stdin().is_terminal() {
+                    print!("  Refresh now? [y/N] ");
+                    io::stdout().flush()?;
+                    let mut input = String::new();
+                    io::stdin().read_line(&mut input)?;
+                    if matches!(input.trim(), "y" | "Y") {
+                        outln!();
+                        match core_refresh(target_num) {
+                            Ok(msg) => outln!("  {} {}\n", "✓".green().bold(), msg),
+                            Err(e) => {
+                                outln!("  {} Refresh failed: {e}", "✗".red().bold());
+                                outln!(
+                                    "  {} Switching anyway — Claude Code may reject the expired session.\n",
+                                    "!".yellow().bold()
+                                );
+                            }
+                        }
+                    } else {
+                        outln!(
+                            "  {} Switching with expired session — Claude Code may reject it.\n",
+                            "!".yellow().bold()
+                        );
+                    }
+                } else {
+                    outln!(
+                        "  {} Switching with expired session — Claude Code may reject it.\n",
+                        "!".yellow().bold()
+                    );
+                }
+            }
+        }
+    }
+
+    if !json {
+        outln!(
+            "\n  {} {}  {}  {}",
+            "→".cyan().bold(),
+            current_slot_email.dimmed(),
+            "→".dimmed(),
+            target_email.cyan().bold()
+        );
+    }
+
+    let started = std::time::Instant::now();
+    let outcome = switch_account(target_num, undo);
+    emit_switch_metrics(opts, &target_entry, target_num, outcome.is_ok(), started.elapsed())?;
+    let result = outcome?;
+
+    if json {
+        let _ = credentials::ensure_ccswitchrc();
+        outln!("{}", serde_json::to_string_pretty(&result)?);
+        return Ok(());
+    }
+
+    // Upgrade ~/.ccswitchrc to the new keychain-only format if needed.
+    let _ = credentials::ensure_ccswitchrc();
+
+    list(OutputFormat::Text, None, false, false, false)?;
+
+    if let Some(err) = &result.post_hook_error {
+        outln!("  {} Post-switch hook failed: {}\n", "!".yellow().bold(), err);
+    }
+
+    if opts.verbose {
+        outln!("  {}", "Files written:".dimmed());
+        for path in &result.files_written {
+            outln!("    {}", path.display());
+        }
+        outln!();
+    }
+
+    if !opts.no_restart_hint {
+        outln!(
+            "  {} Restart Claude Code to apply.\n",
+            "✓".green().bold()
+        );
+    }
+
+    if result.env_changed {
+        outln!(
+            "  {} Account env vars changed: open a new shell or run {}.\n",
+            "!".yellow().bold(),
+            format!("source {}", credentials::ccswitchrc_path().display()).cyan(),
+        );
+    }
+
+    // Warn if CLAUDE_CODE_OAUTH_TOKEN is set — it overrides the keychain and
+    // will cause Claude Code to ignore the switch until it is cleared.
+    if std::env::var("CLAUDE_CODE_OAUTH_TOKEN").is_ok() {
+        let rc = credentials::ccswitchrc_path();
+        outln!(
+            "  {} {} is set in this shell.",
+            "!".yellow().bold(),
+            "CLAUDE_CODE_OAUTH_TOKEN".yellow().bold(),
+        );
+        outln!(
+            "  {} Run {} or {} to clear it before restarting Claude Code.\n",
+            " ".normal(),
+            "unset CLAUDE_CODE_OAUTH_TOKEN".cyan().bold(),
+            format!("source {}", rc.display()).cyan(),
+        );
+    }
+
+    Ok(())
+}
+
+/// Emit switch metrics to `--metrics-file`, `$CCSWITCH_METRICS_FILE`, or
+/// stderr (with `--metrics`), leaving stdout to the switch's own output.
+/// Does nothing when no destination is configured. Switch totals are counted
+/// from `switch.log`, so they keep growing across runs.
+fn emit_switch_metrics(
+    opts: &SwitchOptions,
+    entry: &AccountEntry,
+    num: u32,
+    ok: bool,
+    duration: std::time::Duration,
+) -> Result<()> {
+    use crate::metrics::SwitchLabels;
+    let file = opts.metrics_file.clone().or_else(|| {
+        std::env::var_os(crate::metrics::METRICS_FILE_ENV).map(PathBuf::from)
+    });
+    if file.is_none() && !opts.metrics {
+        return Ok(());
+    }
+
+    let seq = sequence::load()?;
+    let mut counts: std::collections::BTreeMap<(u32, bool), u64> = Default::default();
+    for logged in sequence::read_switch_log(usize::MAX)? {
+        *counts.entry((logged.to, logged.ok)).or_default() += 1;
+    }
+    let hostname = crate::platform::hostname();
+    let totals: Vec<(SwitchLabels, u64)> = counts
+        .into_iter()
+        .filter_map(|((to, ok), total)| {
+            let e = seq.accounts.get(&to.to_string())?;
+            let labels = SwitchLabels {
+                hostname: &hostname,
+                account_number: to,
+                account: &e.email,
+                auth_kind: &e.auth_kind,
+                ok,
+            };
+            Some((labels, total))
+        })
+        .collect();
+    let last = SwitchLabels {
+        hostname: &hostname,
+        account_number: num,
+        account: &entry.email,
+        auth_kind: &entry.auth_kind,
+        ok,
+    };
+    let text = crate::metrics::render_switch_metrics(&last, duration, &totals);
+
+    match file {
+        Some(path) => crate::metrics::write_metrics_file(&path, &text),
+        None => {
+            eprint!("{text}");
+            Ok(())
+        }
+    }
+}
+
+// ── Run a command as an account ───────────────────────────────────────────────
+
+/// Private `CLAUDE_CONFIG_DIR` for `ccswitch run`; deleted on drop.
+struct TempConfigDir {
+    path: PathBuf,
+}
+
+impl TempConfigDir {
+    fn create() -> Result<Self> {
+        #[cfg_attr(not(unix), allow(unused_mut))]
+        let mut builder = std::fs::DirBuilder::new();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::DirBuilderExt;
+            builder.mode(0o700);
+        }
+        loop {
+            let path = std::env::temp_dir().join(format!(
+                "ccswitch-run-{}-{:08x}",
+                std::process::id(),
+                rand::random::<u32>()
+            ));
+            match builder.create(&path) {
+                Ok(()) => return Ok(TempConfigDir { path }),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Cannot create {}", path.display()))
+                }
+            }
+        }
+    }
+
+    fn write(&self, name: &str, content: &str) -> Result<()> {
+        let path = self.path.join(name);
+        std::fs::write(&path, content)
+            .with_context(|| format!("Cannot write to {}", path.display()))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for TempConfigDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Environment that makes Claude Code use one account without touching the
+/// live credentials. Holds the temp config dir alive for OAuth accounts.
+struct RunEnv {
+    vars: Vec<(&'static str, String)>,
+    /// Inherited variables that would override `vars`.
+    unset: Vec<&'static str>,
+    _config_dir: Option<TempConfigDir>,
+}
+
+fn run_env(num: u32) -> Result<RunEnv> {
+    let seq = sequence::load()?;
+    let entry = seq
+        .accounts
+        .get(&num.to_string())
+        .with_context(|| format!("Account {num} does not exist"))?;
+    let creds = credentials::read_backup(num, &entry.email)
+        .with_context(|| format!("Missing credentials backup for Account {num}"))?;
+    record_audit(AuditAction::Read, num, &entry.email);
+
+    match entry.auth_kind {
+        AuthKind::Token => Ok(RunEnv {
+            vars: vec![("CLAUDE_CODE_OAUTH_TOKEN", extract_access_token(&creds)?)],
+            unset: vec!["CLAUDE_CONFIG_DIR"],
+            _config_dir: None,
+        }),
+        AuthKind::Oauth => {
+            let dir = TempConfigDir::create()?;
+            dir.write(".credentials.json", &creds)?;
+            if let Ok(config) = read_config_backup(num, &entry.email) {
+                dir.write(".claude.json", &config)?;
+            }
+            let mut vars = vec![("CLAUDE_CONFIG_DIR", dir.path.to_string_lossy().into_owned())];
+            let mut unset = vec![];
+            // Claude Code on macOS reads OAuth credentials from the keychain, not
+            // CLAUDE_CONFIG_DIR, so hand it the access token directly there.
+            match crate::platform::detect() {
+                crate::platform::Platform::MacOS => {
+                    let token = serde_json::from_str::<serde_json::Value>(&creds)
+                        .ok()
+                        .and_then(|v| v["claudeAiOauth"]["accessToken"].as_str().map(String::from))
+                        .context("Cannot extract accessToken from credentials backup")?;
+                    vars.push(("CLAUDE_CODE_OAUTH_TOKEN", token));
+                }
+                _ => unset.push("CLAUDE_CODE_OAUTH_TOKEN"),
+            }
+            Ok(RunEnv { vars, unset, _config_dir: Some(dir) })
+        }
+    }
+}
+
+/// Run `cmd` as `account_num` without switching the active account. Exits
+/// with the command's status once temp files are cleaned up.
+pub fn run_as(account_num: u32, cmd: &[&str]) -> Result<()> {
+    let (program, args) = cmd.split_first().context("No command given")?;
+    let env = run_env(account_num)?;
+
+    // Ctrl+C reaches the child through the terminal; keep ccswitch alive so
+    // the temp config dir is removed once the child exits.
+    #[cfg(unix)]
+    let signals = [signal_hook::consts::SIGINT, signal_hook::consts::SIGQUIT];
+    #[cfg(not(unix))]
+    let signals = [signal_hook::consts::SIGINT];
+    let handlers = signals
+        .into_iter()
+        .map(|sig| {
+            signal_hook::flag::register(
+                sig,
+                std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            )
+        })
+        .collect::<std::io::Result<Vec<_>>>()?;
+
+    let mut command = std::process::Command::new(program);
+    command.args(args).envs(env.vars.iter().map(|(k, v)| (k, v)));
+    for key in &env.unset {
+        command.env_remove(key);
+    }
+    let status = command
+        .status()
+        .with_context(|| format!("Failed to run `{program}`"));
+
+    for id in handlers {
+        signal_hook::low_level::unregister(id);
+    }
+    drop(env);
+
+    let status = status?;
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
+    Ok(())
+}
+
+// ── Shell env export ──────────────────────────────────────────────────────────
+
+/// Shell syntax for `ccswitch env`.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ShellKind {
+    Bash,
+    Fish,
+    Zsh,
+    Powershell,
+}
+
+impl ShellKind {
+    /// Guess the user's shell from `$SHELL`, defaulting to bash.
+    fn detect() -> Self {
+        let shell = std::env::var("SHELL").unwrap_or_default();
+        match shell.rsplit('/').next().unwrap_or_default() {
+            "fish" => ShellKind::Fish,
+            "zsh" => ShellKind::Zsh,
+            "pwsh" | "powershell" => ShellKind::Powershell,
+            _ if crate::platform::detect() == crate::platform::Platform::Windows => {
+                ShellKind::Powershell
+            }
+            _ => ShellKind::Bash,
+        }
+    }
+
+    /// Set an exported variable in this shell's syntax.
+    fn export(self, var: &str, value: &str) -> String {
+        match self {
+            ShellKind::Bash | ShellKind::Zsh => format!("export {var}={}", posix_quote(value)),
+            ShellKind::Fish => format!("set -x {var} {}", posix_quote(value)),
+            ShellKind::Powershell => format!(
+                "$env:{var} = \"{}\"",
+                value.replace('`', "``").replace('"', "`\"").replace('$', "`$")
+            ),
+        }
+    }
+
+    /// Load a script file into the current shell, then delete it.
+    fn source(self, path: &Path) -> String {
+        let path = path.display().to_string();
+        match self {
+            ShellKind::Powershell => format!(". '{}'", path.replace('\'', "''")),
+            _ => format!("source {}", posix_quote(&path)),
+        }
+    }
+
+    fn remove_self(self, path: &Path) -> String {
+        let path = path.display().to_string();
+        match self {
+            ShellKind::Powershell => {
+                format!("Remove-Item -Force -LiteralPath '{}'", path.replace('\'', "''"))
+            }
+            _ => format!("rm -f -- {}", posix_quote(&path)),
+        }
+    }
+
+    fn script_extension(self) -> &'static str {
+        match self {
+            ShellKind::Bash | ShellKind::Zsh => "sh",
+            ShellKind::Fish => "fish",
+            ShellKind::Powershell => "ps1",
+        }
+    }
+}
+
+/// Single-quote `s` for POSIX shells and fish.
+fn posix_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Export statements that make Claude Code use the active account's token.
+fn env_script(shell: ShellKind) -> Result<String> {
+    let seq = sequence::load()?;
+    let num = seq.active_account_number.context("No active account")?;
+    let entry = seq
+        .accounts
+        .get(&num.to_string())
+        .with_context(|| format!("Account {num} does not exist"))?;
+    let token = match entry.auth_kind {
+        AuthKind::Token => {
+            let creds = credentials::read_backup(num, &entry.email)
+                .with_context(|| format!("Missing credentials backup for Account {num}"))?;
+            extract_access_token(&creds)?
+        }
+        AuthKind::Oauth => {
+            let creds = credentials::read_live().context("Cannot read live credentials")?;
+            serde_json::from_str::<serde_json::Value>(&creds)
+                .ok()
+                .and_then(|v| v["claudeAiOauth"]["accessToken"].as_str().map(String::from))
+                .context("Cannot extract accessToken from live credentials")?
+        }
+    };
+    record_audit(AuditAction::Read, num, &entry.email);
+    Ok(format!("{}\n", shell.export("CLAUDE_CODE_OAUTH_TOKEN", &token)))
+}
+
+/// Write `script` to a 0600 temp file that deletes itself once sourced. The
+/// file is also removed if the returned guard is dropped before `keep`.
+fn write_env_file(shell: ShellKind, script: &str) -> Result<tempfile::TempPath> {
+    let mut file = tempfile::Builder::new()
+        .prefix("ccswitch-env-")
+        .suffix(&format!(".{}", shell.script_extension()))
+        .tempfile()
+        .context("Cannot create a temp file for the env script")?;
+    let remove = shell.remove_self(file.path());
+    writeln!(file, "{script}{remove}")
+        .with_context(|| format!("Cannot write to {}", file.path().display()))?;
+    Ok(file.into_temp_path())
+}
+
+/// Print shell statements exporting the active account's token, for
+/// `eval "$(ccswitch env)"`. Unless `print` is set, a non-terminal stdout gets
+/// a `source` line for a temp file instead of the token itself.
+pub fn env(shell: Option<ShellKind>, print: bool) -> Result<()> {
+    use std::io::IsTerminal;
+    let shell = shell.unwrap_or_else(ShellKind::detect);
+    let script = env_script(shell)?;
+    if print || io::stdout().is_terminal() {
+        print!("{script}");
+    } else {
+        let path = write_env_file(shell, &script)?;
+        writeln!(io::stdout(), "{}", shell.source(&path))?;
+        // From here the shell removes the file once it has sourced it.
+        path.keep().context("Cannot keep the env script")?;
+    }
+    Ok(())
+}
+
+// ── Shell setup ───────────────────────────────────────────────────────────────
+
+/// The startup file `shell` reads: ~/.bashrc, ~/.zshrc (under `$ZDOTDIR` if
+/// set) or fish's config.fish.
+fn shell_rc_path(shell: ShellKind) -> Result<PathBuf> {
+    let home = crate::paths::home_dir().context("Cannot find home directory")?;
+    Ok(match shell {
+        ShellKind::Bash => home.join(".bashrc"),
+        ShellKind::Zsh => std::env::var_os("ZDOTDIR")
+            .filter(|_| !crate::paths::home_overridden())
+            .map(PathBuf::from)
+            .unwrap_or(home)
+            .join(".zshrc"),
+        ShellKind::Fish => crate::platform::xdg_config_home()
+            .context("Cannot find the config directory")?
+            .join("fish")
+            .join("config.fish"),
+        ShellKind::Powershell => bail!("No default startup file for PowerShell"),
+    })
+}
+
+/// Add a line sourcing ccswitch's env file to the shell's startup file.
+/// Returns the startup file and the line added, or `None` when it already
+/// sources the env file.
+pub(crate) fn core_shell_setup(
+    shell: ShellKind,
+    rc_path: Option<PathBuf>,
+) -> Result<(PathBuf, Option<String>)> {
+    let env_file = match shell {
+        ShellKind::Fish => credentials::ensure_fish_env()?,
+        ShellKind::Powershell => {
+            bail!("shell-setup supports bash, zsh and fish; PowerShell can't source ~/.ccswitchrc")
+        }
+        ShellKind::Bash | ShellKind::Zsh => {
+            credentials::ensure_ccswitchrc()?;
+            credentials::ccswitchrc_path()
+        }
+    };
+    let rc_path = match rc_path {
+        Some(path) => path,
+        None => shell_rc_path(shell)?,
+    };
+    let file_name = env_file
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let existing = match std::fs::read_to_string(&rc_path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Cannot read {}", rc_path.display())),
+    };
+    let already = existing.lines().any(|l| {
+        let l = l.trim_start();
+        !l.starts_with('#')
+            && (l.contains(&env_file.display().to_string())
+                || (l.contains(&file_name) && l.contains("ccswitch")))
+    });
+    if already {
+        return Ok((rc_path, None));
+    }
+
+    let line = format!("source {}", posix_quote(&env_file.display().to_string()));
+    let mut updated = existing;
+    if !updated.is_empty() && !updated.ends_with('\n') {
+        updated.push('\n');
+    }
+    updated.push_str(&format!("\n# Added by `ccswitch shell-setup`\n{line}\n"));
+    if let Some(parent) = rc_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&rc_path, updated)
+        .with_context(|| format!("Cannot write {}", rc_path.display()))?;
+    Ok((rc_path, Some(line)))
+}
+
+pub fn shell_setup(shell: Option<ShellKind>, rc_path: Option<PathBuf>) -> Result<()> {
+    let (rc_path, added) = core_shell_setup(shell.unwrap_or_else(ShellKind::detect), rc_path)?;
+    match added {
+        Some(line) => {
+            outln!("\n  {} Added to {}:\n", "✓".green().bold(), rc_path.display());
+            outln!("      {}\n", line.cyan().bold());
+            outln!("  Open a new shell to pick it up.\n");
+        }
+        None => outln!(
+            "\n  {} {} already sources ccswitch's env file.\n",
+            "✓".green().bold(),
+            rc_path.display()
+        ),
+    }
+    Ok(())
+}
+
+// ── Per-account environment ───────────────────────────────────────────────────
+
+const RC_ENV_BEGIN: &str = "# >>> ccswitch account env >>>";
+const RC_ENV_END: &str = "# <<< ccswitch account env <<<";
+
+/// Variables ccswitch sets itself to pick the account and its data; an
+/// account's env must not override them.
+const RESERVED_ENV_KEYS: &[&str] = &["CLAUDE_CODE_OAUTH_TOKEN", "CLAUDE_CONFIG_DIR"];
+
+/// Environment variable names a POSIX shell accepts, other than the ones
+/// ccswitch reserves.
+fn validate_env_key(key: &str) -> Result<()> {
+    let mut chars = key.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        bail!("'{key}' is not a valid environment variable name");
+    }
+    if RESERVED_ENV_KEYS.contains(&key) || key.starts_with("CCSWITCH_") {
+        bail!("'{key}' is set by ccswitch itself and cannot be stored per account");
+    }
+    Ok(())
+}
+
+/// `rc` with its managed env section replaced by exports of `vars`. Keys the
+/// old section exported but `vars` lacks are unset, so re-sourcing the file
+/// in an open shell drops the previous account's values.
+fn with_rc_env(rc: &str, email: &str, vars: &std::collections::HashMap<String, String>) -> String {
+    let mut out = String::new();
+    let mut previous = Vec::new();
+    let mut inside = false;
+    for line in rc.lines() {
+        if line == RC_ENV_BEGIN {
+            inside = true;
+        } else if inside {
+            if line == RC_ENV_END {
+                inside = false;
+            } else if let Some((key, _)) = line.strip_prefix("export ").and_then(|l| l.split_once('=')) {
+                previous.push(key.to_string());
+            }
+        } else {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+
+    let stale: Vec<&String> = previous.iter().filter(|k| !vars.contains_key(*k)).collect();
+    if vars.is_empty() && stale.is_empty() {
+        return out;
+    }
+    out.push_str(RC_ENV_BEGIN);
+    out.push('\n');
+    out.push_str(&format!("# Set with `ccswitch env-set` for {email}; rewritten on every switch.\n"));
+    for key in stale {
+        out.push_str(&format!("unset {key}\n"));
+    }
+    let mut vars: Vec<_> = vars.iter().collect();
+    vars.sort();
+    for (key, value) in vars {
+        out.push_str(&format!("export {key}={}\n", posix_quote(value)));
+    }
+    out.push_str(RC_ENV_END);
+    out.push('\n');
+    out
+}
+
+/// Point the env section of `~/.ccswitchrc` at the newly active account.
+/// Returns true when the file changed.
+fn write_rc_env(email: &str, vars: &std::collections::HashMap<String, String>) -> Result<bool> {
+    let path = credentials::ccswitchrc_path();
+    let existing = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            if vars.is_empty() {
+                return Ok(false);
+            }
+            credentials::ensure_ccswitchrc()?;
+            std::fs::read_to_string(&path)?
+        }
+        Err(e) => return Err(e).with_context(|| format!("Cannot read {}", path.display())),
+    };
+    let updated = with_rc_env(&existing, email, vars);
+    if updated == existing {
+        return Ok(false);
+    }
+    credentials::write_ccswitchrc(&updated)?;
+    Ok(true)
+}
+
+/// Set or clear one env var on account `num`; the rc file is rewritten
+/// straight away when `num` is active.
+fn update_env_var(num: u32, key: &str, value: Option<&str>) -> Result<(String, bool)> {
+    let _lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load()?;
+    let active = seq.active_account_number == Some(num);
+    let entry = seq
+        .accounts
+        .get_mut(&num.to_string())
+        .with_context(|| format!("Account {num} does not exist"))?;
+    let msg = match value {
+        Some(value) => {
+            entry.env_vars.insert(key.to_string(), value.to_string());
+            format!("Set {key} for Account {num} ({})", entry.email)
+        }
+        None => {
+            if entry.env_vars.remove(key).is_none() {
+                bail!("Account {num} ({}) has no {key} set", entry.email);
+            }
+            format!("Removed {key} from Account {num} ({})", entry.email)
+        }
+    };
+    let (email, vars) = (entry.email.clone(), entry.env_vars.clone());
+    seq.last_updated = now_utc();
+    sequence::save(&seq)?;
+    let rc_changed = active && write_rc_env(&email, &vars)?;
+    Ok((msg, rc_changed))
+}
+
+/// `env-set`: `var` is `KEY=VALUE`.
+pub(crate) fn core_env_set(num: u32, var: &str) -> Result<(String, bool)> {
+    let (key, value) = var
+        .split_once('=')
+        .with_context(|| format!("Expected KEY=VALUE, got '{var}'"))?;
+    validate_env_key(key)?;
+    update_env_var(num, key, Some(value))
+}
+
+pub(crate) fn core_env_unset(num: u32, key: &str) -> Result<(String, bool)> {
+    validate_env_key(key)?;
+    update_env_var(num, key, None)
+}
+
+fn print_env_update(msg: &str, rc_changed: bool) {
+    outln!("\n  {} {}", "✓".green().bold(), msg);
+    if rc_changed {
+        outln!(
+            "  {} Open a new shell or run {} to apply.",
+            "·".cyan(),
+            format!("source {}", credentials::ccswitchrc_path().display()).cyan()
+        );
+    } else {
+        outln!("  {} Exported from ~/.ccswitchrc whenever this account is active.", "·".cyan());
+    }
+    outln!();
+}
+
+pub fn env_set(account: &str, var: &str) -> Result<()> {
+    let num = resolve_account(account)?;
+    let (msg, rc_changed) = core_env_set(num, var)?;
+    print_env_update(&msg, rc_changed);
+    Ok(())
+}
+
+pub fn env_unset(account: &str, key: &str) -> Result<()> {
+    let num = resolve_account(account)?;
+    let (msg, rc_changed) = core_env_unset(num, key)?;
+    print_env_update(&msg, rc_changed);
+    Ok(())
+}
+
+pub fn env_list(account: &str) -> Result<()> {
+    let num = resolve_account(account)?;
+    let seq = sequence::load()?;
+    let entry = seq
+        .accounts
+        .get(&num.to_string())
+        .with_context(|| format!("Account {num} does not exist"))?;
+    if entry.env_vars.is_empty() {
+        outln!("\n  {} Account {num} ({}) has no env vars.\n", "·".cyan(), entry.email);
+        return Ok(());
+    }
+    let mut vars: Vec<_> = entry.env_vars.iter().collect();
+    vars.sort();
+    for (key, value) in vars {
+        outln!("{key}={value}");
+    }
+    Ok(())
+}
+
+// ── Edit account label ────────────────────────────────────────────────────────
+
+/// Relabel account `num`. New credential/config copies are written first and
+/// the old ones are only deleted once sequence.json points at the new label;
+/// if anything fails before that, the new copies are removed again.
+pub fn core_rename(num: u32, new_label: &str) -> Result<String> {
+    let new_label = new_label.trim();
+    if new_label.is_empty() {
+        bail!("New label must not be empty");
+    }
+
+    let _lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load()?;
+    refuse_if_linked(&seq, Some(num), "Relabelling this account")?;
+
+    let old_email = seq
+        .accounts
+        .get(&num.to_string())
+        .map(|e| e.email.clone())
+        .with_context(|| format!("Account {num} does not exist"))?;
+
+    if old_email == new_label {
+        return Ok(format!("Account {num} already has label '{new_label}'."));
+    }
+    if let Some(existing_num) = seq.find_by_email(new_label) {
+        if existing_num != num {
+            bail!("Label '{}' is already used by Account {}", new_label, existing_num);
+        }
+    }
+
+    // Read existing backups before touching anything
+    let creds = credentials::read_backup(num, &old_email)
+        .with_context(|| format!("Cannot read credentials backup for Account {num}"))?;
+    let config_str = read_config_backup(num, &old_email).ok();
+
+    credentials::write_backup(num, new_label, &creds)?;
+    let staged = (|| -> Result<()> {
+        if let Some(ref config_str) = config_str {
+            write_config_backup(num, new_label, config_str)?;
+        }
+        if let Some(entry) = seq.accounts.get_mut(&num.to_string()) {
+            entry.email = new_label.to_string();
+        }
+        seq.last_updated = now_utc();
+        sequence::save(&seq)
+    })();
+
+    if let Err(e) = staged {
+        let _ = credentials::delete_backup(num, new_label);
+        let _ = std::fs::remove_file(config_backup_path(num, new_label));
+        return Err(e.context("Rename failed; no changes were kept"));
+    }
+
+    // Committed — drop the old copies (best-effort)
+    let _ = credentials::delete_backup(num, &old_email);
+    let _ = std::fs::remove_file(config_backup_path(num, &old_email));
+
+    Ok(format!(
+        "Renamed Account {num}: '{}' → '{}'",
+        old_email, new_label
+    ))
+}
+
+pub fn edit_account(identifier: &str, new_label: &str) -> Result<()> {
+    let seq = sequence::load()?;
+
+    if seq.accounts.is_empty() {
+        bail!("No accounts managed yet. Run `ccswitch add` first.");
+    }
+
+    let num = seq
+        .resolve(identifier)
+        .with_context(|| format!("No account found matching '{identifier}'"))?;
+
+    let msg = core_rename(num, new_label)?;
+    outln!("\n  {} {}\n", "✓".green().bold(), msg);
+    Ok(())
+}
+
+// ── Clone account ─────────────────────────────────────────────────────────────
+
+/// Copy account `num`'s backups to a new account labelled `new_label`
+/// (default `<email>-copy`). The copy keeps the auth kind, uuid, priority,
+/// note and tags, starts with no usage history and is not made active.
+pub(crate) fn core_clone(num: u32, new_label: Option<&str>) -> Result<String> {
+    let _lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load()?;
+
+    let source = seq
+        .accounts
+        .get(&num.to_string())
+        .cloned()
+        .with_context(|| format!("Account {num} does not exist"))?;
+    let label = match new_label.map(str::trim) {
+        Some("") => bail!("New label must not be empty"),
+        Some(label) => label.to_string(),
+        None => format!("{}-copy", source.email),
+    };
+    if let Some(existing_num) = seq.find_by_email(&label) {
+        bail!("Label '{}' is already used by Account {}", label, existing_num);
+    }
+
+    let creds = credentials::read_backup(num, &source.email)
+        .with_context(|| format!("Cannot read credentials backup for Account {num}"))?;
+    let config_str = read_config_backup(num, &source.email).ok();
+
+    let new_num = seq.next_account_number();
+    credentials::write_backup(new_num, &label, &creds)?;
+    let staged = (|| -> Result<()> {
+        if let Some(ref config_str) = config_str {
+            write_config_backup(new_num, &label, config_str)?;
+        }
+        let now = now_utc();
+        seq.accounts.insert(
+            new_num.to_string(),
+            AccountEntry {
+                email: label.clone(),
+                added: now.clone(),
+                has_config_backup: config_str.is_some(),
+                last_used: None,
+                switch_count: 0,
+                created_by: Some(created_by()),
+                machine_id: platform::machine_id(),
+                ..source.clone()
+            },
+        );
+        seq.sequence.push(new_num);
+        seq.last_updated = now;
+        sequence::save(&seq)
+    })();
+
+    if let Err(e) = staged {
+        let _ = credentials::delete_backup(new_num, &label);
+        let _ = std::fs::remove_file(config_backup_path(new_num, &label));
+        return Err(e.context("Clone failed; no changes were kept"));
+    }
+    record_audit(AuditAction::Add, new_num, &label);
+
+    Ok(format!(
+        "Cloned Account {num} ({}) as Account {new_num} ({label})",
+        source.email
+    ))
+}
+
+pub fn clone_account(identifier: &str, new_label: Option<&str>) -> Result<()> {
+    let num = resolve_account(identifier)?;
+    let msg = core_clone(num, new_label)?;
+    outln!("\n  {} {}\n", "✓".green().bold(), msg);
+    Ok(())
+}
+
+// ── Renumber ──────────────────────────────────────────────────────────────────
+
+/// An account whose number changes during `renumber`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Renumbering {
+    pub old: u32,
+    pub new: u32,
+    pub email: String,
+}
+
+/// `seq` with every account number replaced through `map`.
+fn renumbered(seq: &SequenceFile, map: &std::collections::HashMap<u32, u32>) -> SequenceFile {
+    let remap = |n: u32| map.get(&n).copied().unwrap_or(n);
+    let mut out = seq.clone();
+    out.accounts = seq
+        .accounts
+        .iter()
+        .map(|(k, v)| {
+            let key = k.parse().map(|n: u32| remap(n).to_string()).unwrap_or_else(|_| k.clone());
+            (key, v.clone())
+        })
+        .collect();
+    out.sequence = seq.sequence.iter().map(|&n| remap(n)).collect();
+    out.active_account_number = seq.active_account_number.map(remap);
+    out.previous_account_number = seq.previous_account_number.map(remap);
+    out.pinned_account = seq.pinned_account.map(remap);
+    for n in out.aliases.values_mut() {
+        *n = remap(*n);
+    }
+    for s in &mut out.schedules {
+        s.account_num = remap(s.account_num);
+    }
+    out.last_updated = now_utc();
+    out
+}
+
+/// Number accounts 1, 2, 3… in rotation order. Backups are copied to their new
+/// names first and the old copies removed only after `sequence.json` is saved,
+/// so a failure part-way leaves the previous state intact. Backup names include
+/// the email, so a new name never collides with another account's old one.
+pub(crate) fn core_renumber(dry_run: bool) -> Result<Vec<Renumbering>> {
+    let _lock = sequence::acquire_lock()?;
+    let seq = sequence::load()?;
+
+    let changes: Vec<Renumbering> = seq
+        .sequence
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &old)| {
+            let email = seq.accounts.get(&old.to_string())?.email.clone();
+            Some(Renumbering { old, new: i as u32 + 1, email })
+        })
+        .filter(|r| r.old != r.new)
+        .collect();
+    if dry_run || changes.is_empty() {
+        return Ok(changes);
+    }
+    for r in &changes {
+        refuse_if_linked(&seq, Some(r.old), "Renumbering")?;
+    }
+
+    let mut written: Vec<(u32, String)> = Vec::new();
+    let staged = (|| -> Result<()> {
+        for r in &changes {
+            let creds = credentials::read_backup(r.old, &r.email)
+                .with_context(|| format!("Cannot read credentials backup for Account {}", r.old))?;
+            let config_str = read_config_backup(r.old, &r.email).ok();
+            written.push((r.new, r.email.clone()));
+            credentials::write_backup(r.new, &r.email, &creds)?;
+            if let Some(config_str) = config_str {
+                write_config_backup(r.new, &r.email, &config_str)?;
+            }
+        }
+        let map = changes.iter().map(|r| (r.old, r.new)).collect();
+        sequence::save(&renumbered(&seq, &map))
+    })();
+
+    if let Err(e) = staged {
+        for (num, email) in &written {
+            let _ = credentials::delete_backup(*num, email);
+            let _ = std::fs::remove_file(config_backup_path(*num, email));
+        }
+        return Err(e.context("Renumber failed; no changes were kept"));
+    }
+
+    // Committed — drop the old copies (best-effort)
+    for r in &changes {
+        let _ = credentials::delete_backup(r.old, &r.email);
+        let _ = std::fs::remove_file(config_backup_path(r.old, &r.email));
+    }
+    Ok(changes)
+}
+
+pub fn renumber(dry_run: bool) -> Result<()> {
+    let changes = core_renumber(dry_run)?;
+    if changes.is_empty() {
+        outln!("\n  {} Account numbers are already contiguous.\n", "·".dimmed());
+        return Ok(());
+    }
+    outln!();
+    for r in &changes {
+        outln!("  {:>3} → {:<3} {}", r.old, r.new.to_string().cyan().bold(), r.email.dimmed());
+    }
+    if dry_run {
+        outln!("\n  {} Nothing was changed (dry run).\n", "·".dimmed());
+    } else {
+        outln!("\n  {} Renumbered {} account(s).\n", "✓".green().bold(), changes.len());
+    }
+    Ok(())
+}
+
+// ── Credential backend migration ──────────────────────────────────────────────
+
+/// Move every account's credential backup from `from` to `to`. `progress` is called after
+/// each account with whether it had a backup to move. Stops at the first
+/// failure; accounts already moved stay moved and the failed one is untouched.
+/// Returns the numbers of the accounts that were migrated.
+pub(crate) fn core_migrate_backend(
+    from: credentials::CredentialBackend,
+    to: credentials::CredentialBackend,
+    mut progress: impl FnMut(u32, &str, bool),
+) -> Result<Vec<u32>> {
+    if from == to {
+        bail!("Source and destination are both the {from} backend");
+    }
+    let _lock = sequence::acquire_lock()?;
+    let seq = sequence::load()?;
+    refuse_if_linked(&seq, None, "Moving backups to another backend")?;
+    let mut migrated = Vec::new();
+    for &num in &seq.sequence {
+        let Some(entry) = seq.accounts.get(&num.to_string()) else {
+            continue;
+        };
+        let moved = credentials::migrate_backup(num, &entry.email, from, to)
+            .with_context(|| format!("Failed to migrate Account {num}"))?;
+        progress(num, &entry.email, moved);
+        if moved {
+            migrated.push(num);
+        }
+    }
+    Ok(migrated)
+}
+
+pub fn migrate_backend(
+    from: credentials::CredentialBackend,
+    to: Option<credentials::CredentialBackend>,
+) -> Result<()> {
+    let to = to.unwrap_or_else(credentials::preferred_backend);
+    if from == to {
+        outln!(
+            "\n  {}  The {} backend is both source and destination — nothing to migrate.\n",
+            "·".dimmed(),
+            to
+        );
+        return Ok(());
+    }
+
+    outln!();
+    let migrated = core_migrate_backend(from, to, |num, email, moved| {
+        if moved {
+            outln!("  {} Account {} ({}) → {}", "✓".green().bold(), num, email, to);
+        } else {
+            outln!("  {}  Account {} ({}): no {} backup", "·".dimmed(), num, email, from);
+        }
+    })?;
+    if migrated.is_empty() {
+        outln!("\n  {}  No {} backups left to migrate to {}.\n", "·".dimmed(), from, to);
+    } else {
+        outln!(
+            "\n  {} Migrated {} account(s) from {} to {}.",
+            "✓".green().bold(),
+            migrated.len(),
+            from,
+            to
+        );
+        if to != credentials::backend() {
+            outln!(
+                "  Run `ccswitch config set credential_backend {}` so ccswitch reads from it.",
+                to
+            );
+        }
+        outln!();
+    }
+    Ok(())
+}
+
+// ── Credential helpers ────────────────────────────────────────────────────────
+
+/// Check whether a token value is already stored in any managed account.
+/// Returns (account_num, email) if found.
+fn find_account_by_token(seq: &SequenceFile, token: &str) -> Option<(u32, String)> {
+    for &num in &seq.sequence {
+        let entry = match seq.accounts.get(&num.to_string()) {
+            Some(e) => e,
+            None => continue,
+        };
+        if entry.auth_kind != AuthKind::Token {
+            continue;
+        }
+        if let Ok(creds) = credentials::read_backup(num, &entry.email) {
+            if let Ok(stored) = extract_access_token(&creds) {
+                if stored == token {
+                    return Some((num, entry.email.clone()));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Extract the raw token value from a credentials backup.
+/// Token accounts store: {"token": "sk-ant-..."}
+/// OAuth accounts store the full credentials JSON (not used here).
+pub(crate) fn extract_access_token(creds_json: &str) -> Result<String> {
+    let v: serde_json::Value =
+        serde_json::from_str(creds_json).context("Invalid JSON in credentials backup")?;
+    v.get("token")
+        .and_then(|t| t.as_str())
+        .map(String::from)
+        .context(
+            "Cannot extract token from credentials backup. \
+             Was this account added with `ccswitch add`?",
+        )
+}
+
+/// Resolve the currently-active account from sequence state, falling back to
+/// the live Claude config. This works for both OAuth and token accounts.
+fn resolve_current_account(seq: &SequenceFile) -> Result<(u32, String)> {
+    if let Some(num) = seq.active_account_number {
+        if let Some(entry) = seq.accounts.get(&num.to_string()) {
+            return Ok((num, entry.email.clone()));
+        }
+    }
+    let email = config::current_email()
+        .context("No active Claude account found. Run `ccswitch add` first.")?;
+    let num = seq
+        .find_by_email(&email)
+        .with_context(|| {
+            format!("Account for '{email}' is not managed. Run `ccswitch add` first.")
+        })?;
+    Ok((num, email))
+}
+
+// ── Config backup helpers ─────────────────────────────────────────────────────
+
+pub(crate) fn config_backup_path(num: u32, email: &str) -> PathBuf {
+    sequence::backup_dir()
+        .join("configs")
+        .join(format!(".claude-config-{num}-{email}.json"))
+}
+
+fn write_config_backup(num: u32, email: &str, content: &str) -> Result<()> {
+    #[cfg(unix)]
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = config_backup_path(num, email);
+    std::fs::write(&path, content)
+        .with_context(|| format!("Cannot write config backup to {}", path.display()))?;
+
+    #[cfg(unix)]
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+
+    Ok(())
+}
+
+pub(crate) fn read_config_backup(num: u32, email: &str) -> Result<String> {
+    let path = config_backup_path(num, email);
+    std::fs::read_to_string(&path)
+        .with_context(|| format!("Cannot read config backup from {}", path.display()))
+}
+
+// ── Session expiry helper ─────────────────────────────────────────────────────
+
+/// Compare the fingerprint of `creds` against the stored one in `seq`.
+/// Prints a warning to stderr when they differ.
+fn warn_if_format_changed(seq: &SequenceFile, creds: &str) {
+    let Some(stored_fp) = seq.format_fingerprint.as_deref() else {
+        return;
+    };
+    let live_fp = credentials::credential_field_fingerprint(creds);
+    if !live_fp.is_empty() && live_fp != stored_fp {
+        eprintln!(
+            "\n  {} Claude Code may have changed its credential format. \
+             ccswitch may not work correctly. \
+             Please check https://github.com/vyshnavsdeepak/ccswitch/issues for updates.",
+            "Warning:".yellow().bold()
+        );
+    }
+}
+
+/// `AccountEntry::expires_at` for a credentials blob: RFC 3339, or `None`
+/// when the blob carries no expiry (token accounts).
+pub(crate) fn stored_expiry(creds_json: &str) -> Option<String> {
+    credentials::parse_expiry(creds_json)
+        .map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+}
+
+/// Seconds until an expiry timestamp (ms since epoch); negative once past.
+fn expiry_secs(expires_at_ms: Option<i64>) -> Option<i64> {
+    expires_at_ms.map(|ms| (ms - chrono::Utc::now().timestamp_millis()) / 1000)
+}
+
+/// Badge for an OAuth expiry timestamp (ms since epoch); empty when more than
+/// a week away or unknown.
+fn expiry_badge(expires_at_ms: Option<i64>) -> String {
+    match expiry_secs(expires_at_ms) {
+        None => String::new(),
+        Some(secs) if secs <= 0 => "(expired)".to_string(),
+        Some(secs) if secs < 3600 => format!("(expires in {}m)", secs / 60),
+        Some(secs) if secs <= 24 * 3600 => format!("(expires in {}h)", secs / 3600),
+        Some(secs) if secs <= 7 * 24 * 3600 => format!("(expires in {}d)", secs / 86400),
+        _ => String::new(),
+    }
+}
+
+/// `expiry_badge` colored for `list`: red once expired, yellow within a day.
+fn colored_expiry_badge(expires_at_ms: Option<i64>) -> colored::ColoredString {
+    let badge = expiry_badge(expires_at_ms);
+    match expiry_secs(expires_at_ms) {
+        Some(secs) if secs <= 0 => badge.red().bold(),
+        Some(secs) if secs <= 24 * 3600 => badge.yellow(),
+        _ => badge.dimmed(),
+    }
+}
+
+// ── Verify backups ────────────────────────────────────────────────────────────
+
+/// Result of checking one account's backups; `problems` is empty when all pass.
+#[derive(Debug, Clone, serde::Serialize)]
+pub(crate) struct AccountVerification {
+    pub number: u32,
+    pub email: String,
+    pub problems: Vec<String>,
+}
+
+/// Read and validate every account's credential and config backups without switching.
+pub(crate) fn core_verify(seq: &SequenceFile) -> Vec<AccountVerification> {
+    seq.sequence
+        .iter()
+        .filter_map(|&num| seq.accounts.get(&num.to_string()).map(|e| (num, e)))
+        .map(|(num, entry)| {
+            let mut problems = Vec::new();
+            match credentials::read_backup_unverified(num, &entry.email) {
+                Err(e) => problems.push(format!(
+                    "cannot read credentials from {}: {e}",
+                    credentials::backup_location(num, &entry.email)
+                )),
+                Ok(raw) => {
+                    if credentials::verify_backup_checksum(num, &entry.email, &raw).is_err() {
+                        problems.push("credentials do not match their checksum".to_string());
+                    } else if let Err(e) =
+                        credentials::validate_credentials_shape(&raw, &entry.auth_kind)
+                    {
+                        problems.push(format!("credentials are malformed: {e}"));
+                    }
+                }
+            }
+            if entry.has_config_backup {
+                match read_config_backup(num, &entry.email)
+                    .and_then(|raw| Ok(serde_json::from_str::<serde_json::Value>(&raw)?))
+                {
+                    Err(e) => problems.push(format!("config backup: {e}")),
+                    Ok(cfg) if entry.auth_kind == AuthKind::Oauth && cfg.get("oauthAccount").is_none() => {
+                        problems.push("config backup has no oauthAccount".to_string())
+                    }
+                    Ok(_) => {}
+                }
+            }
+            AccountVerification { number: num, email: entry.email.clone(), problems }
+        })
+        .collect()
+}
+
+pub fn verify(format: OutputFormat) -> Result<()> {
+    let seq = sequence::load()?;
+    if seq.accounts.is_empty() {
+        bail!("No accounts managed yet. Run `ccswitch add` first.");
+    }
+    let results = core_verify(&seq);
+    let failed = results.iter().filter(|r| !r.problems.is_empty()).count();
+
+    if format == OutputFormat::Json {
+        outln!("{}", serde_json::to_string_pretty(&results)?);
+    } else {
+        outln!();
+        for r in &results {
+            if r.problems.is_empty() {
+                outln!("  {} Account {} ({})", "✓".green().bold(), r.number, r.email);
+            } else {
+                outln!("  {} Account {} ({})", "✗".red().bold(), r.number, r.email);
+                for p in &r.problems {
+                    outln!("      {}", p.red());
+                }
+            }
+        }
+        outln!();
+    }
+
+    if failed > 0 {
+        bail!(
+            "{failed} of {} account{} failed verification. Re-add them with `ccswitch add`.",
+            results.len(),
+            if results.len() == 1 { "" } else { "s" }
+        );
+    }
+    Ok(())
+}
+
+// ── Prune ─────────────────────────────────────────────────────────────────────
+
+/// A backup that no managed account refers to.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Orphan {
+    /// Human-readable location (file path or keychain service).
+    pub location: String,
+    pub reason: String,
+    keychain: bool,
+}
+
+fn orphan_reason(seq: &SequenceFile, num: u32, email: &str) -> Option<String> {
+    match seq.accounts.get(&num.to_string()) {
+        None => Some(format!("account {num} is not managed")),
+        Some(entry) if entry.email != email => {
+            Some(format!("account {num} is now {}", entry.email))
+        }
+        Some(_) => None,
+    }
+}
+
+/// Backup files named `<prefix><num>-<email>.json` in `dir`, sorted by path.
+fn backup_files(dir: &std::path::Path, prefix: &str) -> Result<Vec<(PathBuf, u32, String)>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Cannot read {}", dir.display())),
+    };
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else { continue };
+        let Some((num, email)) = credentials::parse_backup_name(name, prefix, ".json") else {
+            continue;
+        };
+        files.push((path, num, email));
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn orphaned_files(seq: &SequenceFile, dir: &std::path::Path, prefix: &str) -> Result<Vec<Orphan>> {
+    let mut orphans = Vec::new();
+    for (path, num, email) in backup_files(dir, prefix)? {
+        if let Some(reason) = orphan_reason(seq, num, &email) {
+            orphans.push(Orphan { location: path.display().to_string(), reason, keychain: false });
+        }
+    }
+    Ok(orphans)
+}
+
+/// Find (and unless `dry_run`, delete) credential and config backups that no
+/// longer belong to a managed account.
+pub(crate) fn core_prune(dry_run: bool) -> Result<Vec<Orphan>> {
+    let _lock = sequence::acquire_lock()?;
+    let seq = sequence::load()?;
+    let mut orphans = orphaned_files(&seq, &credentials::backup_files_dir(), ".claude-credentials-")?;
+    orphans.extend(orphaned_files(&seq, &sequence::backup_dir().join("configs"), ".claude-config-")?);
+    for service in credentials::keychain_account_services()? {
+        let Some((num, email)) = credentials::parse_account_service(&service) else { continue };
+        if let Some(reason) = orphan_reason(&seq, num, &email) {
+            orphans.push(Orphan { location: format!("keychain: {service}"), reason, keychain: true });
+        }
+    }
+
+    if !dry_run {
+        for orphan in &orphans {
+            if orphan.keychain {
+                let service = orphan.location.trim_start_matches("keychain: ");
+                credentials::delete_keychain_service(service)?;
+            } else {
+                std::fs::remove_file(&orphan.location)
+                    .with_context(|| format!("Cannot remove {}", orphan.location))?;
+                let _ = std::fs::remove_file(credentials::checksum_file_path(Path::new(
+                    &orphan.location,
+                )));
+            }
+        }
+    }
+    Ok(orphans)
+}
+
+pub fn prune(dry_run: bool) -> Result<()> {
+    let orphans = core_prune(dry_run)?;
+    if orphans.is_empty() {
+        outln!("{}", "No orphaned backups found.".green());
+        return Ok(());
+    }
+    outln!();
+    for o in &orphans {
+        outln!("  {} {}", o.location, format!("({})", o.reason).dimmed());
+    }
+    outln!();
+    let count = format!("{} orphaned backup{}", orphans.len(), if orphans.len() == 1 { "" } else { "s" });
+    if dry_run {
+        outln!("Would remove {count}. Run without --dry-run to delete.");
+    } else {
+        outln!("{} Removed {count}.", "✓".green().bold());
+    }
+    Ok(())
+}
+
+// ── Dedup ─────────────────────────────────────────────────────────────────────
+
+/// Accounts whose stored credentials are byte-for-byte identical, grouped by
+/// SHA-256. Each group is sorted by account number and has two or more
+/// entries; accounts whose backup can't be read are skipped.
+pub(crate) fn core_find_duplicates() -> Result<Vec<Vec<(u32, String)>>> {
+    let seq = sequence::load()?;
+    let mut by_hash: std::collections::BTreeMap<String, Vec<(u32, String)>> = Default::default();
+    for (key, entry) in &seq.accounts {
+        let Ok(num) = key.parse::<u32>() else { continue };
+        let Ok(creds) = credentials::read_backup(num, &entry.email) else { continue };
+        by_hash
+            .entry(credentials::sha256_fingerprint(&creds))
+            .or_default()
+            .push((num, entry.email.clone()));
+    }
+    let mut groups: Vec<Vec<(u32, String)>> = by_hash
+        .into_values()
+        .filter(|g| g.len() > 1)
+        .map(|mut g| {
+            g.sort();
+            g
+        })
+        .collect();
+    groups.sort();
+    Ok(groups)
+}
+
+/// Remove `duplicates` in favour of `keep`, which inherits what was set on
+/// them: tags, notes, env vars the kept account lacks, the highest priority,
+/// aliases, a schedule if it has none, and active or pinned status since the
+/// credentials are the same.
+pub(crate) fn core_merge_duplicates(keep: u32, duplicates: &[u32]) -> Result<String> {
+    let seq = sequence::load()?;
+    let mut removed = Vec::new();
+    let mut merged: Vec<AccountEntry> = Vec::new();
+    for &num in duplicates {
+        let entry = seq
+            .accounts
+            .get(&num.to_string())
+            .with_context(|| format!("Account {num} does not exist"))?;
+        merged.push(entry.clone());
+        removed.push((num, entry.email.clone()));
+    }
+    for &num in duplicates {
+        refuse_if_linked(&seq, Some(num), "Merging away this account")?;
+    }
+    let was_active = duplicates.iter().any(|&n| seq.active_account_number == Some(n));
+    let was_pinned = duplicates.iter().any(|&n| seq.pinned_account == Some(n));
+    let aliases: Vec<String> = seq
+        .aliases
+        .iter()
+        .filter(|(_, n)| duplicates.contains(n))
+        .map(|(alias, _)| alias.clone())
+        .collect();
+    let schedule = seq.schedules.iter().find(|s| duplicates.contains(&s.account_num)).cloned();
+
+    for (num, email) in &removed {
+        core_remove(*num, email)?;
+    }
+
+    let _lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load()?;
+    let entry = seq
+        .accounts
+        .get_mut(&keep.to_string())
+        .with_context(|| format!("Account {keep} does not exist"))?;
+    for dup in merged {
+        for tag in dup.tags {
+            if !entry.tags.contains(&tag) {
+                entry.tags.push(tag);
+            }
+        }
+        for (key, value) in dup.env_vars {
+            entry.env_vars.entry(key).or_insert(value);
+        }
+        entry.priority = entry.priority.max(dup.priority);
+        entry.notes = match (entry.notes.take(), dup.notes) {
+            (Some(a), Some(b)) if a != b => Some(format!("{a}\n{b}")),
+            (a, b) => a.or(b),
+        };
+    }
+    let email = entry.email.clone();
+    for alias in aliases {
+        seq.aliases.insert(alias, keep);
+    }
+    let keep_scheduled = seq.schedules.iter().any(|s| s.account_num == keep);
+    if let Some(schedule) = schedule.filter(|_| !keep_scheduled) {
+        seq.schedules.push(sequence::ScheduleEntry { account_num: keep, ..schedule });
+    }
+    if was_active {
+        seq.active_account_number = Some(keep);
+    }
+    if was_pinned {
+        seq.pinned_account = Some(keep);
+    }
+    seq.last_updated = now_utc();
+    sequence::save(&seq)?;
+
+    let removed: Vec<String> = removed.iter().map(|(n, e)| format!("{n} ({e})")).collect();
+    Ok(format!("Kept Account {keep} ({email}); removed {}", removed.join(", ")))
+}
+
+pub fn dedup(dry_run: bool) -> Result<()> {
+    let groups = core_find_duplicates()?;
+    if groups.is_empty() {
+        outln!("\n  {} No duplicate credentials found.\n", "✓".green().bold());
+        return Ok(());
+    }
+
+    outln!("\n  {}", "Accounts sharing the same credentials".bold());
+    outln!("  {}", "─".repeat(40).dimmed());
+    for group in &groups {
+        for (i, (num, email)) in group.iter().enumerate() {
+            if i == 0 {
+                outln!("  {}  {}  {}", format!("{num:>2}").green().bold(), email, "(keep)".green());
+            } else {
+                outln!("  {}  {}  {}", format!("{num:>2}").dimmed(), email, "(remove)".yellow());
+            }
+        }
+        outln!();
+    }
+
+    if dry_run {
+        outln!("  {} Dry run: nothing removed.\n", "·".cyan());
+        return Ok(());
+    }
+
+    let count: usize = groups.iter().map(|g| g.len() - 1).sum();
+    outln!(
+        "  {} Copies made with {} share their source's credentials and are merged too.",
+        "·".cyan(),
+        "ccswitch clone".cyan()
+    );
+    print!(
+        "  Remove {} duplicate account{}, keeping the lowest-numbered of each? [y/N] ",
+        count,
+        if count == 1 { "" } else { "s" }
+    );
+    io::stdout().flush()?;
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    if !matches!(input.trim(), "y" | "Y") {
+        outln!("  Cancelled.");
+        return Ok(());
+    }
+
+    outln!();
+    for group in &groups {
+        let duplicates: Vec<u32> = group[1..].iter().map(|(n, _)| *n).collect();
+        let msg = core_merge_duplicates(group[0].0, &duplicates)?;
+        outln!("  {} {}", "✓".green().bold(), msg);
+    }
+    outln!();
+    Ok(())
+}
+
+// ── Repair ────────────────────────────────────────────────────────────────────
+
+/// An account `repair` can write back into sequence.json.
+#[derive(Debug)]
+pub(crate) struct RepairCandidate {
+    pub number: u32,
+    pub entry: AccountEntry,
+    /// Where the account was found: "sequence.json", "credentials", "configs"
+    /// or "keychain".
+    pub sources: Vec<&'static str>,
+}
+
+/// Entry for an account known only from its backups: auth kind from the
+/// credentials, uuid from the config's `oauthAccount`.
+fn inferred_entry(num: u32, email: &str) -> AccountEntry {
+    let creds = credentials::read_backup(num, email).ok();
+    let is_token = creds
+        .as_deref()
+        .and_then(|c| serde_json::from_str::<serde_json::Value>(c).ok())
+        .is_some_and(|v| v.get("token").is_some());
+    let config = read_config_backup(num, email).ok();
+    let uuid = config
+        .as_deref()
+        .and_then(|c| serde_json::from_str::<serde_json::Value>(c).ok())
+        .and_then(|v| v["oauthAccount"]["accountUuid"].as_str().map(String::from))
+        .unwrap_or_default();
+    AccountEntry {
+        email: email.to_string(),
+        uuid,
+        added: now_utc(),
+        auth_kind: if is_token { AuthKind::Token } else { AuthKind::Oauth },
+        has_config_backup: config.is_some(),
+        expires_at: creds.as_deref().and_then(stored_expiry),
+        ..Default::default()
+    }
+}
+
+/// Accounts recoverable from a salvaged sequence.json plus the credential and
+/// config backups on disk (and the keychain on macOS). Salvaged entries win
+/// over backups that claim the same number.
+pub(crate) fn repair_candidates(salvaged: &SequenceFile) -> Result<Vec<RepairCandidate>> {
+    let mut found: std::collections::BTreeMap<u32, RepairCandidate> = salvaged
+        .accounts
+        .iter()
+        .filter_map(|(num, entry)| {
+            let number = num.parse().ok()?;
+            Some((number, RepairCandidate { number, entry: entry.clone(), sources: vec!["sequence.json"] }))
+        })
+        .collect();
+
+    let mut discovered: Vec<(u32, String, &'static str)> = Vec::new();
+    for (_, num, email) in backup_files(&credentials::backup_files_dir(), ".claude-credentials-")? {
+        discovered.push((num, email, "credentials"));
+    }
+    for (_, num, email) in backup_files(&sequence::backup_dir().join("configs"), ".claude-config-")? {
+        discovered.push((num, email, "configs"));
+    }
+    for service in credentials::keychain_account_services()? {
+        if let Some((num, email)) = credentials::parse_account_service(&service) {
+            discovered.push((num, email, "keychain"));
+        }
+    }
+
+    for (num, email, source) in discovered {
+        let candidate = found.entry(num).or_insert_with(|| RepairCandidate {
+            number: num,
+            entry: inferred_entry(num, &email),
+            sources: Vec::new(),
+        });
+        if candidate.entry.email == email && !candidate.sources.contains(&source) {
+            candidate.sources.push(source);
+        }
+    }
+    Ok(found.into_values().collect())
+}
+
+/// Write a fresh sequence.json holding the `selected` candidates, keeping the
+/// salvaged rotation order, active, previous and pinned accounts, aliases and
+/// schedules where they still apply. The damaged file is kept as `sequence.json.corrupt`.
+pub(crate) fn core_repair(salvaged: &SequenceFile, selected: Vec<RepairCandidate>) -> Result<String> {
+    let path = sequence::sequence_path();
+    if path.exists() {
+        let corrupt = path.with_extension("json.corrupt");
+        std::fs::copy(&path, &corrupt)
+            .with_context(|| format!("Cannot copy {} to {}", path.display(), corrupt.display()))?;
+    }
+
+    let mut seq = SequenceFile::default();
+    for c in selected {
+        seq.accounts.insert(c.number.to_string(), c.entry);
+    }
+    let kept = |num: &u32| seq.accounts.contains_key(&num.to_string());
+    let mut order: Vec<u32> = salvaged.sequence.iter().copied().filter(kept).collect();
+    order.dedup();
+    let mut rest: Vec<u32> = seq
+        .accounts
+        .keys()
+        .filter_map(|k| k.parse().ok())
+        .filter(|n| !order.contains(n))
+        .collect();
+    rest.sort();
+    order.extend(rest);
+    seq.active_account_number = salvaged.active_account_number.filter(kept);
+    seq.previous_account_number = salvaged.previous_account_number.filter(kept);
+    seq.pinned_account = salvaged.pinned_account.filter(kept);
+    seq.aliases = salvaged.aliases.iter().filter(|(_, n)| kept(n)).map(|(a, n)| (a.clone(), *n)).collect();
+    seq.schedules = salvaged.schedules.iter().filter(|s| kept(&s.account_num)).cloned().collect();
+    seq.schedule_last_run = salvaged.schedule_last_run.clone();
+    seq.sequence = order;
+    seq.last_updated = now_utc();
+    sequence::save(&seq)?;
+
+    let count = seq.accounts.len();
+    Ok(format!("Rebuilt sequence.json with {count} account{}", if count == 1 { "" } else { "s" }))
+}
+
+/// Rebuild a corrupted or missing sequence.json from what is left of it and
+/// the account backups, asking which accounts to keep.
+pub fn repair() -> Result<()> {
+    if crate::store::configured_kind()? != crate::store::StoreKind::Json {
+        bail!("`repair` rebuilds sequence.json, but accounts are kept in the sqlite store");
+    }
+    let _lock = sequence::acquire_lock()?;
+    let path = sequence::sequence_path();
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => {
+            if serde_json::from_str::<SequenceFile>(&content).is_ok() {
+                outln!("{} {} is valid; nothing to repair.", "✓".green().bold(), path.display());
+                return Ok(());
+            }
+            content
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+
+    let salvaged = sequence::salvage(&content);
+    let candidates = repair_candidates(&salvaged)?;
+    if candidates.is_empty() {
+        bail!("No accounts found in {} or in the backups. Re-add them with `ccswitch add`.", path.display());
+    }
+
+    outln!();
+    outln!("  Found {} account{}:", candidates.len(), if candidates.len() == 1 { "" } else { "s" });
+    for c in &candidates {
+        outln!(
+            "    {} ({}) {}",
+            format!("Account {}", c.number).bold(),
+            c.entry.email,
+            format!("[{}]", c.sources.join(", ")).dimmed()
+        );
+    }
+    outln!();
+
+    let mut selected = Vec::new();
+    for c in candidates {
+        print!("  Include Account {} ({})? [Y/n] ", c.number, c.entry.email);
+        io::stdout().flush()?;
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        if !matches!(input.trim(), "n" | "N") {
+            selected.push(c);
+        }
+    }
+
+    print!(
+        "\n  Overwrite {} with {} account{}? [y/N] ",
+        path.display(),
+        selected.len(),
+        if selected.len() == 1 { "" } else { "s" }
+    );
+    io::stdout().flush()?;
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    if !matches!(input.trim(), "y" | "Y") {
+        outln!("  Cancelled.");
+        return Ok(());
+    }
+
+    let msg = core_repair(&salvaged, selected)?;
+    outln!("\n  {} {}", "✓".green().bold(), msg);
+    Ok(())
+}
+
+// ── Doctor health check ───────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// One line of doctor output, with an optional hint on how to fix it.
+#[derive(Debug)]
+struct DoctorCheck {
+    status: CheckStatus,
+    message: String,
+    hint: Option<String>,
+}
+
+impl DoctorCheck {
+    fn pass(message: impl Into<String>) -> Self {
+        DoctorCheck { status: CheckStatus::Pass, message: message.into(), hint: None }
+    }
+
+    fn warn(message: impl Into<String>, hint: impl Into<String>) -> Self {
+        DoctorCheck { status: CheckStatus::Warn, message: message.into(), hint: Some(hint.into()) }
+    }
+
+    fn fail(message: impl Into<String>, hint: impl Into<String>) -> Self {
+        DoctorCheck { status: CheckStatus::Fail, message: message.into(), hint: Some(hint.into()) }
+    }
+
+    fn print(&self) {
+        let mark = match self.status {
+            CheckStatus::Pass => "✓".green().bold(),
+            CheckStatus::Warn => "⚠".yellow().bold(),
+            CheckStatus::Fail => "✗".red().bold(),
+        };
+        outln!("  {} {}", mark, self.message);
+        if let Some(hint) = &self.hint {
+            outln!("    {} {}", "Fix:".dimmed(), hint);
+        }
+    }
+}
+
+/// The checks from a doctor run, with counts of issues (✗) and warnings (⚠).
+#[derive(Debug)]
+pub(crate) struct DoctorReport {
+    pub issues: usize,
+    pub warnings: usize,
+    checks: Vec<DoctorCheck>,
+}
+
+impl DoctorReport {
+    fn from_checks(checks: Vec<DoctorCheck>) -> Self {
+        let count = |s| checks.iter().filter(|c| c.status == s).count();
+        DoctorReport {
+            issues: count(CheckStatus::Fail),
+            warnings: count(CheckStatus::Warn),
+            checks,
+        }
+    }
+}
+
+/// Run all health checks without printing anything.
+/// Takes the already-loaded `SequenceFile` so tests can pass an in-memory value.
+pub(crate) fn core_doctor(seq: &SequenceFile) -> DoctorReport {
+    let mut checks = vec![env_token_check()];
+    if !seq.accounts.is_empty() {
+        checks.extend(doctor_checks(seq));
+    }
+    DoctorReport::from_checks(checks)
+}
+
+fn env_token_check() -> DoctorCheck {
+    if config::has_env_token() {
+        DoctorCheck::warn(
+            "CLAUDE_CODE_OAUTH_TOKEN is set — it shadows the credentials ccswitch switches",
+            "Unset it or add `source ~/.ccswitchrc` to your shell profile.",
+        )
+    } else {
+        DoctorCheck::pass("CLAUDE_CODE_OAUTH_TOKEN not set")
+    }
+}
+
+fn readd_hint(num: u32) -> String {
+    format!("Run `ccswitch remove {num}`, log in to that account with `claude`, then `ccswitch add`.")
+}
+
+/// Every check that needs `sequence.json`, in display order.
+fn doctor_checks(seq: &SequenceFile) -> Vec<DoctorCheck> {
+    const EXPIRE_SOON_SECS: i64 = 24 * 3600;
+    let mut checks = Vec::new();
+    let seq_path = sequence::sequence_path();
+
+    // ── sequence.json integrity ──────────────────────────────────────────────
+    let before = checks.len();
+    for &num in &seq.sequence {
+        if !seq.accounts.contains_key(&num.to_string()) {
+            checks.push(DoctorCheck::fail(
+                format!("sequence.json references account {num} but no account entry exists"),
+                format!("Remove {num} from the \"sequence\" list in {}.", seq_path.display()),
+            ));
+        }
+    }
+    let mut orphans: Vec<(u32, &AccountEntry)> = seq
+        .accounts
+        .iter()
+        .filter_map(|(k, e)| k.parse::<u32>().ok().map(|n| (n, e)))
+        .filter(|(n, _)| !seq.sequence.contains(n))
+        .collect();
+    orphans.sort_by_key(|(n, _)| *n);
+    for (num, entry) in orphans {
+        checks.push(DoctorCheck::warn(
+            format!(
+                "Account {} ({}) is in accounts map but missing from sequence list",
+                num, entry.email
+            ),
+            format!(
+                "Add {num} to the \"sequence\" list in {} or run `ccswitch remove {num}`.",
+                seq_path.display()
+            ),
+        ));
+    }
+    if checks.len() == before {
+        checks.push(DoctorCheck::pass("sequence.json integrity OK"));
+    }
+
+    // ── Active account ───────────────────────────────────────────────────────
+    match seq.active_account_number {
+        Some(num) => match seq.accounts.get(&num.to_string()) {
+            Some(entry) => checks.push(DoctorCheck::pass(format!(
+                "Active account is Account {} ({})",
+                num, entry.email
+            ))),
+            None => checks.push(DoctorCheck::fail(
+                format!("Active account {num} does not exist"),
+                "Run `ccswitch switch <account>` to select a managed account.",
+            )),
+        },
+        None => checks.push(DoctorCheck::warn(
+            "No active account recorded",
+            "Run `ccswitch switch <account>` to select one.",
+        )),
+    }
+
+    // ── Credential backups readable, well-formed and unexpired ───────────────
+    for &num in &seq.sequence {
+        let Some(entry) = seq.accounts.get(&num.to_string()) else {
+            continue;
+        };
+        let label = format!("Account {} ({})", num, entry.email);
+
+        let backup = match credentials::read_backup(num, &entry.email) {
+            Ok(b) => b,
+            Err(e) => {
+                checks.push(DoctorCheck::fail(
+                    format!("{label} — cannot read credential backup: {e}"),
+                    readd_hint(num),
+                ));
+                continue;
+            }
+        };
+        if let Err(e) = credentials::validate_credentials_shape(&backup, &entry.auth_kind) {
+            checks.push(DoctorCheck::fail(
+                format!("{label} — credential backup is malformed: {e}"),
+                readd_hint(num),
+            ));
+            continue;
+        }
+        if entry.auth_kind != AuthKind::Oauth {
+            checks.push(DoctorCheck::pass(format!("{label} — credentials OK (static token)")));
+            continue;
+        }
+
+        // The live credentials are fresher than the backup for the active account.
+        let creds = if seq.active_account_number == Some(num) {
+            credentials::read_live().unwrap_or(backup)
+        } else {
+            backup
+        };
+        match credentials::oauth_secs_remaining(&creds) {
+            Some(secs) if secs <= 0 => {
+                let ago = if -secs >= 3600 {
+                    format!("{}h", -secs / 3600)
+                } else {
+                    format!("{}m", -secs / 60)
+                };
+                checks.push(DoctorCheck::fail(
+                    format!("{label} — token expired {ago} ago"),
+                    format!("Run `ccswitch refresh {num}`."),
+                ));
+            }
+            Some(secs) if secs <= EXPIRE_SOON_SECS => checks.push(DoctorCheck::warn(
+                format!("{label} — token expires in {}h", secs / 3600),
+                format!("Run `ccswitch refresh {num}`."),
+            )),
+            _ => checks.push(DoctorCheck::pass(format!("{label} — credentials OK"))),
+        }
+    }
+
+    // ── Config backups readable where expected ───────────────────────────────
+    let before = checks.len();
+    for &num in &seq.sequence {
+        let Some(entry) = seq.accounts.get(&num.to_string()) else {
+            continue;
+        };
+        if !entry.has_config_backup {
+            continue;
+        }
+        match read_config_backup(num, &entry.email) {
+            Err(_) => checks.push(DoctorCheck::warn(
+                format!("Account {} ({}) — config backup missing", num, entry.email),
+                readd_hint(num),
+            )),
+            Ok(raw) if serde_json::from_str::<serde_json::Value>(&raw).is_err() => {
+                checks.push(DoctorCheck::fail(
+                    format!("Account {} ({}) — config backup is not valid JSON", num, entry.email),
+                    readd_hint(num),
+                ))
+            }
+            Ok(_) => {}
+        }
+    }
+    if checks.len() == before {
+        checks.push(DoctorCheck::pass("Config backups OK"));
+    }
+
+    // ── File permissions ─────────────────────────────────────────────────────
+    let before = checks.len();
+    for check in permission_checks() {
+        match check.mode {
+            Err(e) => checks.push(DoctorCheck::fail(
+                format!("Cannot stat {}: {}", check.path.display(), e),
+                format!("Check that {} is readable by you.", check.path.display()),
+            )),
+            Ok(mode) if mode != check.expected => checks.push(DoctorCheck::fail(
+                format!(
+                    "{} has permissions {:04o}, expected {:04o}",
+                    check.path.display(),
+                    mode,
+                    check.expected
+                ),
+                "ccswitch check-permissions --fix",
+            )),
+            Ok(_) => {}
+        }
+    }
+    if cfg!(unix) && checks.len() == before {
+        checks.push(DoctorCheck::pass("File permissions OK"));
+    }
+
+    // ── Leftover temp files ──────────────────────────────────────────────────
+    let stale = sequence::stale_temp_files(std::time::Duration::from_secs(60 * 60));
+    if !stale.is_empty() {
+        checks.push(DoctorCheck::warn(
+            format!("{} temp file(s) left by interrupted writes, e.g. {}", stale.len(), stale[0].display()),
+            "ccswitch gc",
+        ));
+    }
+
+    checks
+}
+
+// ── Garbage collection ────────────────────────────────────────────────────────
+
+/// Remove temp files older than `older_than` and empty backups, listing each.
+pub fn gc(older_than: std::time::Duration) -> Result<()> {
+    let removed = sequence::gc(older_than)?;
+    if removed.is_empty() {
+        outln!("{}", "Nothing to clean up.".green());
+        return Ok(());
+    }
+    outln!();
+    for path in &removed {
+        outln!("  {}", path.display());
+    }
+    outln!();
+    let plural = if removed.len() == 1 { "" } else { "s" };
+    outln!("{} Removed {} stale file{plural}.", "✓".green().bold(), removed.len());
+    Ok(())
+}
+
+// ── File permissions ──────────────────────────────────────────────────────────
+
+/// A file or directory that only its owner should be able to read.
+struct PermissionCheck {
+    path: PathBuf,
+    /// Current permission bits, or why they could not be read.
+    mode: io::Result<u32>,
+    expected: u32,
+}
+
+/// Everything under the profile directory (directories 0700, files 0600) plus
+/// `~/.ccswitchrc`. The lock file holds no data and is skipped; `.active`,
+/// which shell scripts read, is expected at 0644. Empty on
+/// non-Unix platforms, where modes do not apply.
+fn permission_checks() -> Vec<PermissionCheck> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let check = |path: PathBuf, expected: u32| {
+            let mode = std::fs::metadata(&path).map(|m| m.permissions().mode() & 0o777);
+            PermissionCheck { path, mode, expected }
+        };
+        let mut checks = Vec::new();
+        let mut pending = vec![sequence::backup_dir()];
+        while let Some(dir) = pending.pop() {
+            if !dir.exists() {
+                continue;
+            }
+            checks.push(check(dir.clone(), 0o700));
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            let mut paths: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+            paths.sort();
+            for path in paths.into_iter().rev() {
+                if path == sequence::lock_path() {
+                    continue;
+                }
+                if path.is_dir() {
+                    pending.push(path);
+                } else if path == sequence::active_account_path() {
+                    // Read by shell scripts; holds only an email.
+                    checks.push(check(path, 0o644));
+                } else {
+                    checks.push(check(path, 0o600));
+                }
+            }
+        }
+        // Directories are pushed before their contents; keep a stable, readable order.
+        checks.sort_by(|a, b| a.path.cmp(&b.path));
+        let rc = credentials::ccswitchrc_path();
+        if rc.exists() {
+            checks.push(check(rc, 0o600));
+        }
+        checks
+    }
+    #[cfg(not(unix))]
+    Vec::new()
+}
+
+/// List each ccswitch file with its mode. With `fix`, tighten the ones that are
+/// too open. Fails while any violation remains.
+pub fn check_permissions(fix: bool) -> Result<()> {
+    let checks = permission_checks();
+    if checks.is_empty() {
+        outln!("\n  {} No file permissions to check on this platform.\n", "·".dimmed());
+        return Ok(());
+    }
+
+    let mut remaining = 0;
+    outln!();
+    for c in &checks {
+        let shown = c.path.display();
+        match &c.mode {
+            Ok(mode) if *mode == c.expected => {
+                outln!("  {} {:04o}  {}", "✓".green().bold(), mode, shown);
+            }
+            Ok(mode) if fix => match set_mode(&c.path, c.expected) {
+                Ok(()) => outln!(
+                    "  {} {:04o}  {}  {}",
+                    "✓".green().bold(),
+                    c.expected,
+                    shown,
+                    format!("(was {mode:04o})").dimmed()
+                ),
+                Err(e) => {
+                    remaining += 1;
+                    let why = format!("({e})");
+                    outln!("  {} {:04o}  {}  {}", "✗".red().bold(), mode, shown, why.red());
+                }
+            },
+            Ok(mode) => {
+                remaining += 1;
+                outln!(
+                    "  {} {:04o}  {}  {}",
+                    "✗".red().bold(),
+                    mode,
+                    shown,
+                    format!("(expected {:04o})", c.expected).red()
+                );
+            }
+            Err(e) => {
+                remaining += 1;
+                outln!("  {} ????  {}  {}", "✗".red().bold(), shown, format!("({e})").red());
+            }
+        }
+    }
+    outln!();
+
+    match remaining {
+        0 => Ok(()),
+        n if fix => bail!("Could not fix permissions on {n} path(s)"),
+        n => bail!("{n} path(s) have unsafe permissions. Run `ccswitch check-permissions --fix`."),
+    }
+}
+
+#[cfg(unix)]
+fn set_mode(path: &std::path::Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &std::path::Path, _mode: u32) -> io::Result<()> {
+    Ok(())
+}
+
+/// Print a health-check summary for all managed accounts and configuration.
+/// Fails (non-zero exit) unless every check passes.
+pub fn doctor() -> Result<()> {
+    outln!();
+
+    let (report, no_accounts) = match sequence::load() {
+        Err(e) => {
+            let load_failed = DoctorCheck::fail(
+                format!("Cannot load sequence.json: {e:#}"),
+                format!(
+                    "Fix or restore {} (ccswitch cannot switch until it loads).",
+                    sequence::sequence_path().display()
+                ),
+            );
+            (DoctorReport::from_checks(vec![env_token_check(), load_failed]), false)
+        }
+        Ok(seq) => (core_doctor(&seq), seq.accounts.is_empty()),
+    };
+    report.checks.iter().for_each(DoctorCheck::print);
+
+    if no_accounts {
+        outln!(
+            "  {} No accounts managed yet — run `ccswitch add` first",
+            "·".dimmed()
+        );
+        outln!();
+        return match report {
+            DoctorReport { issues: 0, warnings: 0, .. } => Ok(()),
+            report => doctor_failed(&report),
+        };
+    }
+
+    outln!();
+    if report.issues == 0 && report.warnings == 0 {
+        outln!("  {} All checks passed.\n", "✓".green().bold());
+        return Ok(());
+    }
+    doctor_failed(&report)
+}
+
+fn doctor_failed(report: &DoctorReport) -> Result<()> {
+    let plural = |n: usize| if n == 1 { "" } else { "s" };
+    let mut parts = Vec::new();
+    if report.issues > 0 {
+        parts.push(format!("{} error{}", report.issues, plural(report.issues)));
+    }
+    if report.warnings > 0 {
+        parts.push(format!("{} warning{}", report.warnings, plural(report.warnings)));
+    }
+    bail!("Doctor found {}", parts.join(", "))
+}
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        sequence::{AccountEntry, AuthKind, SequenceFile},
+        test_utils::TestEnv,
+    };
+    use std::fs;
+
+    // ── is_invalid_grant_error ────────────────────────────────────────────────
+
+    #[test]
+    fn test_is_invalid_grant_error_http_response() {
+        let e = anyhow::anyhow!("Token refresh failed (HTTP 400): invalid_grant: Refresh token not found");
+        assert!(is_invalid_grant_error(&e));
+    }
+
+    #[test]
+    fn test_is_invalid_grant_error_transformed_message() {
+        let e = anyhow::anyhow!("Refresh token expired for Account 2 (user@test.com).");
+        assert!(is_invalid_grant_error(&e));
+    }
+
+    #[test]
+    fn test_is_invalid_grant_error_network_error() {
+        let e = anyhow::anyhow!("Token refresh request failed: connection refused");
+        assert!(!is_invalid_grant_error(&e));
+    }
+
+    #[test]
+    fn test_is_invalid_grant_error_http_500() {
+        let e = anyhow::anyhow!("Token refresh failed (HTTP 500): internal server error");
+        assert!(!is_invalid_grant_error(&e));
+    }
+
+    // ── Helpers ───────────────────────────────────────────────────────────────
+
+    fn make_oauth_creds(label: &str) -> String {
+        let expires = chrono::Utc::now().timestamp_millis() + 30 * 24 * 3600 * 1000_i64;
+        serde_json::json!({
+            "claudeAiOauth": {
+                "accessToken": format!("sk-ant-oat01-{}", label),
+                "refreshToken": format!("sk-ant-ort01-{}", label),
+                "expiresAt": expires
+            }
+        })
+        .to_string()
+    }
+
+    fn make_token_backup(token: &str) -> String {
+        serde_json::json!({ "token": token }).to_string()
+    }
+
+    fn make_oauth_config(email: &str, uuid: &str) -> serde_json::Value {
+        serde_json::json!({
+            "oauthAccount": { "emailAddress": email, "accountUuid": uuid },
+            "numStartups": 1
+        })
+    }
+
+    fn write_live_file(env: &TestEnv, creds: &str) {
+        fs::write(env.dir.path().join(".credentials.json"), creds).unwrap();
+    }
+
+    fn write_config_file(env: &TestEnv, config: &serde_json::Value) {
+        fs::write(
+            env.dir.path().join(".claude.json"),
+            serde_json::to_string_pretty(config).unwrap(),
+        )
+        .unwrap();
+    }
+
+    fn read_live_json(env: &TestEnv) -> serde_json::Value {
+        let raw = fs::read_to_string(env.dir.path().join(".credentials.json")).unwrap();
+        serde_json::from_str(&raw).unwrap()
+    }
+
+    fn read_config_json(env: &TestEnv) -> serde_json::Value {
+        let raw = fs::read_to_string(env.dir.path().join(".claude.json")).unwrap();
+        serde_json::from_str(&raw).unwrap()
+    }
+
+    fn entry(email: &str, kind: AuthKind) -> AccountEntry {
+        AccountEntry {
+            email: email.to_string(),
+            uuid: format!("uuid-{email}"),
+            added: sequence::now_utc(),
+            auth_kind: kind,
+            ..Default::default()
+        }
+    }
+
+    /// Two OAuth accounts, account 1 active.
+    fn setup_two_oauth(env: &TestEnv) {
+        let creds1 = make_oauth_creds("acct1");
+        let creds2 = make_oauth_creds("acct2");
+        let cfg1 = make_oauth_config("acct1@test.com", "uuid1");
+        let cfg2 = make_oauth_config("acct2@test.com", "uuid2");
+
+        let mut seq = SequenceFile::default();
+        seq.accounts.insert("1".into(), entry("acct1@test.com", AuthKind::Oauth));
+        seq.accounts.insert("2".into(), entry("acct2@test.com", AuthKind::Oauth));
+        seq.sequence = vec![1, 2];
+        seq.active_account_number = Some(1);
+        seq.last_updated = sequence::now_utc();
+        sequence::save(&seq).unwrap();
+
+        write_live_file(env, &creds1);
+        write_config_file(env, &cfg1);
+
+        // Backup for account 2
+        credentials::write_backup(2, "acct2@test.com", &creds2).unwrap();
+        fs::write(
+            config_backup_path(2, "acct2@test.com"),
+            serde_json::to_string_pretty(&cfg2).unwrap(),
+        )
+        .unwrap();
+    }
+
+    // ── Unit tests ────────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_extract_access_token_ok() {
+        let creds = r#"{"token": "sk-ant-oat01-mytoken"}"#;
+        assert_eq!(extract_access_token(creds).unwrap(), "sk-ant-oat01-mytoken");
+    }
+
+    #[test]
+    fn test_extract_access_token_missing() {
+        let creds = r#"{"claudeAiOauth": {"accessToken": "tok"}}"#;
+        assert!(extract_access_token(creds).is_err());
+    }
+
+    #[test]
+    fn test_expiry_badge_expired() {
+        let ms = chrono::Utc::now().timestamp_millis() - 1_000;
+        assert_eq!(expiry_badge(Some(ms)), "(expired)");
+    }
+
+    #[test]
+    fn test_expiry_badge_hours() {
+        // Add a 60s buffer so integer division still yields 2h when the test runs.
+        let ms = chrono::Utc::now().timestamp_millis() + (2 * 3600 + 60) * 1000_i64;
+        assert_eq!(expiry_badge(Some(ms)), "(expires in 2h)");
+    }
+
+    #[test]
+    fn test_expiry_badge_days() {
+        // Add a 1h buffer so integer division still yields 4d when the test runs.
+        let ms = chrono::Utc::now().timestamp_millis() + (4 * 86400 + 3600) * 1000_i64;
+        assert_eq!(expiry_badge(Some(ms)), "(expires in 4d)");
+    }
+
+    #[test]
+    fn test_expiry_badge_healthy() {
+        let ms = chrono::Utc::now().timestamp_millis() + 30 * 86400 * 1000_i64;
+        assert_eq!(expiry_badge(Some(ms)), "");
+    }
+
+    #[test]
+    fn test_expiry_badge_unknown() {
+        assert_eq!(expiry_badge(None), "");
+    }
+
+    // ── Integration tests: core_switch ────────────────────────────────────────
+
+    #[test]
+    fn test_switch_already_active() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+
+        let result = core_switch(1).unwrap();
+        assert!(result.message.contains("Already using"), "unexpected: {}", result.message);
+        assert!(result.files_written.is_empty());
+        // Sequence unchanged
+        assert_eq!(sequence::load().unwrap().active_account_number, Some(1));
+    }
+
+    #[test]
+    fn test_switch_records_usage() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+
+        core_switch(2).unwrap();
+        core_switch(1).unwrap();
+        core_switch(2).unwrap();
+        // Already active — not counted
+        core_switch(2).unwrap();
+
+        let seq = sequence::load().unwrap();
+        assert_eq!(seq.accounts["2"].switch_count, 2);
+        assert_eq!(seq.accounts["1"].switch_count, 1);
+        assert!(seq.accounts["2"].last_used.is_some());
+
+        let stats = core_stats().unwrap();
+        assert_eq!(stats[0].number, 2);
+        assert_eq!(stats[1].number, 1);
+    }
+
+    #[test]
+    fn test_switch_writes_active_account_file() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let active = sequence::active_account_path();
+        assert!(!active.exists());
+
+        core_switch(2).unwrap();
+        assert_eq!(fs::read_to_string(&active).unwrap(), "acct2@test.com\n");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&active).unwrap().permissions().mode() & 0o777, 0o644);
+        }
+        core_switch(1).unwrap();
+        assert_eq!(fs::read_to_string(&active).unwrap(), "acct1@test.com\n");
+    }
+
+    #[test]
+    fn test_active_account_file_written_on_first_add_only() {
+        let env = TestEnv::new();
+        write_live_file(&env, &make_oauth_creds("acct1"));
+        write_config_file(&env, &make_oauth_config("acct1@test.com", "uuid1"));
+        let active = sequence::active_account_path();
+
+        core_add(&AddOptions::default()).unwrap();
+        assert_eq!(fs::read_to_string(&active).unwrap(), "acct1@test.com\n");
+
+        // Bookkeeping saves leave it alone; only a switch or first add writes it.
+        fs::write(&active, "other@test.com\n").unwrap();
+        core_note(1, "work laptop").unwrap();
+        core_rename(1, "work").unwrap();
+        assert_eq!(fs::read_to_string(&active).unwrap(), "other@test.com\n");
+    }
+
+    #[test]
+    fn test_read_piped_token_takes_first_non_blank_line() {
+        let input = "\n  sk-ant-oat01-abc  \nignored\n";
+        assert_eq!(read_piped_token(input.as_bytes()).unwrap(), "sk-ant-oat01-abc");
+        assert!(read_piped_token(" \n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_count_accounts_and_active() {
+        let env = TestEnv::new();
+        assert_eq!(core_count(false).unwrap(), 0);
+        assert_eq!(core_count(true).unwrap(), 0);
+        setup_two_oauth(&env);
+        assert_eq!(core_count(false).unwrap(), 2);
+        assert_eq!(core_count(true).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_set_uuid_validates_and_switch_backfills() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let mut seq = sequence::load().unwrap();
+        for entry in seq.accounts.values_mut() {
+            entry.uuid.clear();
+        }
+        sequence::save(&seq).unwrap();
+
+        assert!(core_set_uuid(1, "not-a-uuid").is_err());
+        // Version 1 UUIDs are refused.
+        assert!(core_set_uuid(1, "6ba7b810-9dad-11d1-80b4-00c04fd430c8").is_err());
+        // Wrong variant bits (`c` instead of 8, 9, a or b).
+        assert!(core_set_uuid(1, "3f2504e0-4f89-41d3-ca0c-0305e82c3301").is_err());
+        core_set_uuid(1, "3F2504E0-4F89-41D3-9A0C-0305E82C3301").unwrap();
+        assert_eq!(
+            sequence::load().unwrap().accounts["1"].uuid,
+            "3f2504e0-4f89-41d3-9a0c-0305e82c3301"
+        );
+
+        // The switch reads account 2's UUID from its config backup.
+        core_switch(2).unwrap();
+        assert_eq!(sequence::load().unwrap().accounts["2"].uuid, "uuid2");
+    }
+
+    #[test]
+    fn test_switch_with_memory_backend_writes_no_backup_files() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        credentials::delete_backup(2, "acct2@test.com").unwrap();
+
+        let creds1 = credentials::read_live().unwrap();
+        let creds2 = make_oauth_creds("acct2");
+        crate::test_utils::with_memory_backend(|store| {
+            credentials::write_backup(2, "acct2@test.com", &creds2).unwrap();
+            core_switch(2).unwrap();
+            assert_eq!(credentials::read_live().unwrap(), creds2);
+            // Account 1's snapshot went to memory along with account 2's backup.
+            assert_eq!(store.len(), 2);
+            assert_eq!(credentials::read_backup(1, "acct1@test.com").unwrap(), creds1);
+        });
+        let files = fs::read_dir(credentials::backup_files_dir()).unwrap().count();
+        assert_eq!(files, 0);
+    }
+
+    #[test]
+    fn test_next_by_strategy() {
+        let mut seq = SequenceFile::default();
+        let used = [(1, Some("2026-03-02T00:00:00Z")), (2, Some("2026-03-01T00:00:00Z")), (3, None)];
+        for (num, last_used) in used {
+            let mut e = entry(&format!("a{num}@test.com"), AuthKind::Oauth);
+            e.last_used = last_used.map(str::to_string);
+            seq.accounts.insert(num.to_string(), e);
+        }
+        seq.sequence = vec![1, 2, 3];
+
+        assert_eq!(next_by_strategy(&seq, 1, RotationStrategy::RoundRobin).unwrap(), 2);
+        // Never-used accounts count as least recently used.
+        assert_eq!(next_by_strategy(&seq, 1, RotationStrategy::Lru).unwrap(), 3);
+        assert_eq!(next_by_strategy(&seq, 3, RotationStrategy::Lru).unwrap(), 2);
+        for _ in 0..20 {
+            assert_ne!(next_by_strategy(&seq, 2, RotationStrategy::Random).unwrap(), 2);
+        }
+        seq.sequence = vec![1];
+        assert!(next_by_strategy(&seq, 1, RotationStrategy::Random).is_err());
+    }
+
+    #[test]
+    fn test_relative_time_buckets() {
+        let now = chrono::DateTime::parse_from_rfc3339("2026-03-10T12:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let ago = |ts: &str| relative_time(ts, now).unwrap();
+        assert_eq!(ago("2026-03-10T11:59:30Z"), "just now");
+        assert_eq!(ago("2026-03-10T11:15:00Z"), "45m ago");
+        assert_eq!(ago("2026-03-10T07:00:00Z"), "5h ago");
+        assert_eq!(ago("2026-03-07T12:00:00Z"), "3d ago");
+        assert!(relative_time("garbage", now).is_none());
+    }
+
+    #[test]
+    fn test_switch_nonexistent_account() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+
+        let err = core_switch(99).unwrap_err();
+        assert!(err.to_string().contains("does not exist"), "unexpected: {err}");
+        assert!(matches!(err, CcswitchError::AccountNotFound(ref id) if id == "99"));
+    }
+
+    #[test]
+    fn test_core_errors_are_typed() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+
+        credentials::delete_backup(2, "acct2@test.com").unwrap();
+        let err = core_switch(2).unwrap_err();
+        assert!(
+            matches!(err, CcswitchError::CredentialReadFailed { account_num: Some(2), .. }),
+            "unexpected: {err:?}"
+        );
+
+        fs::write(sequence::sequence_path(), "{ not json").unwrap();
+        assert!(matches!(core_remove(1, "acct1@test.com"), Err(CcswitchError::SequenceCorrupted(_))));
+        // The CLI wrappers still see an ordinary anyhow error.
+        let err = anyhow::Error::from(core_switch(1).unwrap_err());
+        assert!(err.to_string().contains("corrupted"), "unexpected: {err}");
+    }
+
+    #[test]
+    fn test_switch_oauth_to_oauth() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+
+        let msg = core_switch(2).unwrap().message;
+        assert!(msg.contains("acct2@test.com"), "unexpected: {msg}");
+
+        // Sequence points at account 2
+        assert_eq!(sequence::load().unwrap().active_account_number, Some(2));
+
+        // Live credentials are account 2's token
+        let live = read_live_json(&env);
+        assert_eq!(
+            live["claudeAiOauth"]["accessToken"].as_str().unwrap(),
+            "sk-ant-oat01-acct2"
+        );
+
+        // Live config oauthAccount is account 2
+        let cfg = read_config_json(&env);
+        assert_eq!(
+            cfg["oauthAccount"]["emailAddress"].as_str().unwrap(),
+            "acct2@test.com"
+        );
+    }
+
+    #[test]
+    fn test_switch_oauth_to_token() {
+        let env = TestEnv::new();
+
+        let creds1 = make_oauth_creds("acct1");
+        let cfg1 = make_oauth_config("acct1@test.com", "uuid1");
+        let token2 = "sk-ant-oat01-tokenacct2";
+
+        let mut seq = SequenceFile::default();
+        seq.accounts.insert("1".into(), entry("acct1@test.com", AuthKind::Oauth));
+        seq.accounts.insert("2".into(), entry("tokenuser", AuthKind::Token));
+        seq.sequence = vec![1, 2];
+        seq.active_account_number = Some(1);
+        seq.last_updated = sequence::now_utc();
+        sequence::save(&seq).unwrap();
+
+        write_live_file(&env, &creds1);
+        write_config_file(&env, &cfg1);
+        credentials::write_backup(2, "tokenuser", &make_token_backup(token2)).unwrap();
+
+        let msg = core_switch(2).unwrap().message;
+        assert!(msg.contains("tokenuser"), "unexpected: {msg}");
+
+        // Sequence points at account 2
+        assert_eq!(sequence::load().unwrap().active_account_number, Some(2));
+
+        // Live credentials contain the raw token as the OAuth accessToken
+        let live = read_live_json(&env);
+        assert_eq!(live["claudeAiOauth"]["accessToken"].as_str().unwrap(), token2);
+
+        // oauthAccount is removed from config
+        let cfg = read_config_json(&env);
+        assert!(cfg.get("oauthAccount").is_none(), "oauthAccount should be absent");
+    }
+
+    #[test]
+    fn test_core_refresh_token_keeps_metadata_and_updates_live() {
+        let env = TestEnv::new();
+        let mut seq = SequenceFile::default();
+        let mut tok = entry("tokenuser", AuthKind::Token);
+        tok.notes = Some("ci".into());
+        tok.tags = vec!["work".into()];
+        seq.accounts.insert("1".into(), entry("acct1@test.com", AuthKind::Oauth));
+        seq.accounts.insert("2".into(), tok.clone());
+        seq.sequence = vec![2, 1];
+        seq.active_account_number = Some(2);
+        sequence::save(&seq).unwrap();
+        credentials::write_backup(2, "tokenuser", &make_token_backup("sk-ant-oat01-old")).unwrap();
+
+        let err = core_refresh_token(2, "not-a-token").unwrap_err();
+        assert!(err.to_string().contains("sk-ant-"), "unexpected: {err}");
+        assert!(core_refresh_token(1, "sk-ant-oat01-new").is_err());
+
+        core_refresh_token(2, " sk-ant-oat01-new\n").unwrap();
+        let creds = credentials::read_backup(2, "tokenuser").unwrap();
+        assert_eq!(extract_access_token(&creds).unwrap(), "sk-ant-oat01-new");
+        let live = read_live_json(&env);
+        assert_eq!(live["claudeAiOauth"]["accessToken"].as_str().unwrap(), "sk-ant-oat01-new");
+
+        let seq = sequence::load().unwrap();
+        assert_eq!(seq.sequence, [2, 1]);
+        assert_eq!(seq.accounts["2"].notes, tok.notes);
+        assert_eq!(seq.accounts["2"].tags, tok.tags);
+        assert_eq!(seq.accounts["2"].added, tok.added);
+    }
+
+    #[test]
+    fn test_env_script_per_shell() {
+        let _env = TestEnv::new();
+        let mut seq = SequenceFile::default();
+        seq.accounts.insert("1".into(), entry("tokenuser", AuthKind::Token));
+        seq.sequence = vec![1];
+        seq.active_account_number = Some(1);
+        sequence::save(&seq).unwrap();
+        credentials::write_backup(1, "tokenuser", &make_token_backup("sk-ant-oat01-x")).unwrap();
+
+        assert_eq!(
+            env_script(ShellKind::Bash).unwrap(),
+            "export CLAUDE_CODE_OAUTH_TOKEN='sk-ant-oat01-x'\n"
+        );
+        assert_eq!(
+            env_script(ShellKind::Fish).unwrap(),
+            "set -x CLAUDE_CODE_OAUTH_TOKEN 'sk-ant-oat01-x'\n"
+        );
+        assert_eq!(
+            env_script(ShellKind::Powershell).unwrap(),
+            "$env:CLAUDE_CODE_OAUTH_TOKEN = \"sk-ant-oat01-x\"\n"
+        );
+        assert_eq!(posix_quote("a'b"), "'a'\\''b'");
+    }
+
+    #[test]
+    fn test_env_file_removes_itself() {
+        let path = write_env_file(ShellKind::Bash, "export A='1'\n").unwrap();
+        let script = fs::read_to_string(&path).unwrap();
+        assert!(script.starts_with("export A='1'\nrm -f -- '"), "{}", script);
+        assert_eq!(ShellKind::Bash.source(&path), format!("source '{}'", path.display()));
+
+        // Dropped without `keep`, as on an error path, the file is removed.
+        let file = path.to_path_buf();
+        drop(path);
+        assert!(!file.exists());
+    }
+
+    #[test]
+    fn test_switch_token_to_oauth() {
+        let env = TestEnv::new();
+
+        let token1 = "sk-ant-oat01-mytokenacct";
+        let creds2 = make_oauth_creds("acct2");
+        let cfg2 = make_oauth_config("acct2@test.com", "uuid2");
+
+        let mut seq = SequenceFile::default();
+        seq.accounts.insert("1".into(), entry("tokenuser", AuthKind::Token));
+        seq.accounts.insert("2".into(), entry("acct2@test.com", AuthKind::Oauth));
+        seq.sequence = vec![1, 2];
+        seq.active_account_number = Some(1);
+        seq.last_updated = sequence::now_utc();
+        sequence::save(&seq).unwrap();
+
+        // Live credentials: token stored in OAuth format (as write_live_token would do)
+        let live_token_creds = serde_json::json!({
+            "claudeAiOauth": {
+                "accessToken": token1,
+                "refreshToken": "",
+                "expiresAt": chrono::Utc::now().timestamp_millis() + 10 * 365 * 24 * 3600 * 1000_i64
+            }
+        })
+        .to_string();
+        write_live_file(&env, &live_token_creds);
+        write_config_file(&env, &serde_json::json!({ "numStartups": 1 }));
+
+        // Backup for account 2
+        credentials::write_backup(2, "acct2@test.com", &creds2).unwrap();
+        fs::write(
+            config_backup_path(2, "acct2@test.com"),
+            serde_json::to_string_pretty(&cfg2).unwrap(),
+        )
+        .unwrap();
+
+        let msg = core_switch(2).unwrap().message;
+        assert!(msg.contains("acct2@test.com"), "unexpected: {msg}");
+
+        // Sequence points at account 2
+        assert_eq!(sequence::load().unwrap().active_account_number, Some(2));
+
+        // Live credentials are account 2's OAuth token
+        let live = read_live_json(&env);
+        assert_eq!(
+            live["claudeAiOauth"]["accessToken"].as_str().unwrap(),
+            "sk-ant-oat01-acct2"
+        );
+
+        // Live config now has account 2's oauthAccount
+        let cfg = read_config_json(&env);
+        assert_eq!(
+            cfg["oauthAccount"]["emailAddress"].as_str().unwrap(),
+            "acct2@test.com"
+        );
+    }
+
+    #[test]
+    fn test_switch_reports_files_written() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+
+        let result = core_switch(2).unwrap();
+        assert_eq!(result.active_account, 2);
+        let written = &result.files_written;
+        assert!(written.contains(&config_backup_path(1, "acct1@test.com")));
+        assert!(written.contains(&env.dir.path().join(".credentials.json")));
+        assert!(written.contains(&env.dir.path().join(".claude.json")));
+        assert!(written.contains(&sequence::sequence_path()));
+    }
+
+    #[test]
+    fn test_do_switch_json_format_switches() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+
+        let opts = SwitchOptions {
+            format: OutputFormat::Json,
+            ..Default::default()
+        };
+        do_switch(2, &opts).unwrap();
+        assert_eq!(sequence::load().unwrap().active_account_number, Some(2));
+    }
+
+    #[test]
+    fn test_do_switch_writes_metrics_file() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+
+        let path = env.dir.path().join("ccswitch.prom");
+        let opts = SwitchOptions {
+            metrics_file: Some(path.clone()),
+            ..Default::default()
+        };
+        do_switch(2, &opts).unwrap();
+        do_switch(1, &opts).unwrap();
+        do_switch(2, &opts).unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.contains(
+            "ccswitch_switch_duration_seconds{account_number=\"2\",account=\"acct2@test.com\""
+        ), "{text}");
+        let total = |num: u32| {
+            text.lines()
+                .find(|l| l.starts_with(&format!("ccswitch_switch_total{{account_number=\"{num}\"")))
+                .and_then(|l| l.rsplit(' ').next())
+                .map(str::to_string)
+        };
+        assert_eq!(total(2).as_deref(), Some("2"), "{text}");
+        assert_eq!(total(1).as_deref(), Some("1"), "{text}");
+    }
+
+    #[test]
+    fn test_switch_oauth_to_oauth_snapshots_current() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+
+        // Switch to account 2
+        core_switch(2).unwrap();
+
+        // Now switch back to account 1 — its backup should have been written
+        // when we switched away from it
+        core_switch(1).unwrap();
+
+        // Live creds should be back to account 1's token
+        let live = read_live_json(&env);
+        assert_eq!(
+            live["claudeAiOauth"]["accessToken"].as_str().unwrap(),
+            "sk-ant-oat01-acct1"
+        );
+        assert_eq!(sequence::load().unwrap().active_account_number, Some(1));
+    }
+
+    fn make_expired_oauth_creds(label: &str) -> String {
+        let expires = chrono::Utc::now().timestamp_millis() - 3_600_000_i64; // 1h ago
+        serde_json::json!({
+            "claudeAiOauth": {
+                "accessToken": format!("sk-ant-oat01-{}", label),
+                "refreshToken": format!("sk-ant-ort01-{}", label),
+                "expiresAt": expires
+            }
+        })
+        .to_string()
+    }
+
+    /// In a non-interactive (non-tty) environment the expiry prompt is skipped
+    /// and do_switch proceeds with the expired session, completing successfully.
+    #[test]
+    fn test_do_switch_expired_oauth_skips_prompt_and_switches() {
+        let env = TestEnv::new();
+
+        let creds1 = make_oauth_creds("acct1");
+        let creds2_expired = make_expired_oauth_creds("acct2");
+        let cfg1 = make_oauth_config("acct1@test.com", "uuid1");
+        let cfg2 = make_oauth_config("acct2@test.com", "uuid2");
+
+        let mut seq = SequenceFile::default();
+        seq.accounts.insert("1".into(), entry("acct1@test.com", AuthKind::Oauth));
+        seq.accounts.insert("2".into(), entry("acct2@test.com", AuthKind::Oauth));
+        seq.sequence = vec![1, 2];
+        seq.active_account_number = Some(1);
+        seq.last_updated = sequence::now_utc();
+        sequence::save(&seq).unwrap();
+
+        write_live_file(&env, &creds1);
+        write_config_file(&env, &cfg1);
+
+        credentials::write_backup(2, "acct2@test.com", &creds2_expired).unwrap();
+        fs::write(
+            config_backup_path(2, "acct2@test.com"),
+            serde_json::to_string_pretty(&cfg2).unwrap(),
+        )
+        .unwrap();
+
+        // Non-tty: do_switch should warn but still switch successfully.
+        do_switch(2, &SwitchOptions::default()).unwrap();
+
+        assert_eq!(sequence::load().unwrap().active_account_number, Some(2));
+        let live = read_live_json(&env);
+        assert_eq!(
+            live["claudeAiOauth"]["accessToken"].as_str().unwrap(),
+            "sk-ant-oat01-acct2"
+        );
+    }
+
+    /// Switching to a healthy (non-expired) OAuth account takes the normal path
+    /// with no expiry warning.
+    #[test]
+    fn test_do_switch_healthy_oauth_no_expiry_warning() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env); // both accounts have fresh creds
+
+        // Should succeed without any expiry-related branching.
+        do_switch(2, &SwitchOptions::default()).unwrap();
+
+        assert_eq!(sequence::load().unwrap().active_account_number, Some(2));
+    }
+
+    // ── Tests: refresh --all ──────────────────────────────────────────────────
+
+    fn make_oauth_creds_with_expiry(label: &str, expires_at_ms: i64) -> String {
+        serde_json::json!({
+            "claudeAiOauth": {
+                "accessToken": format!("sk-ant-oat01-{}", label),
+                "refreshToken": format!("sk-ant-ort01-{}", label),
+                "expiresAt": expires_at_ms
+            }
+        })
+        .to_string()
+    }
+
+    /// Build a SequenceFile with one or more accounts (does not persist to disk).
+    fn seq_with_accounts(entries: &[(u32, &str, AuthKind)]) -> SequenceFile {
+        let mut seq = SequenceFile::default();
+        for &(num, email, ref kind) in entries {
+            seq.accounts.insert(
+                num.to_string(),
+                AccountEntry {
+                    email: email.to_string(),
+                    uuid: format!("uuid-{num}"),
+                    added: sequence::now_utc(),
+                    auth_kind: kind.clone(),
+                    ..Default::default()
+                },
+            );
+            seq.sequence.push(num);
+        }
+        seq.active_account_number = entries.first().map(|&(num, _, _)| num);
+        seq.last_updated = sequence::now_utc();
+        seq
+    }
+
+    #[test]
+    fn test_refresh_all_skips_token_accounts() {
+        let _env = TestEnv::new();
+
+        let seq = seq_with_accounts(&[(1, "token@test.com", AuthKind::Token)]);
+        sequence::save(&seq).unwrap();
+
+        // No credentials written — token accounts should be skipped without error.
+        let result = refresh_all(&seq);
+        assert!(result.is_ok(), "expected Ok for all-token seq, got: {:?}", result);
+    }
+
+    #[test]
+    fn test_refresh_all_skips_healthy_oauth() {
+        let _env = TestEnv::new();
+
+        let expires_ms = chrono::Utc::now().timestamp_millis() + 30 * 86400 * 1000_i64; // 30 days
+        let creds = make_oauth_creds_with_expiry("healthy", expires_ms);
+
+        let mut seq = seq_with_accounts(&[(1, "healthy@test.com", AuthKind::Oauth)]);
+        seq.active_account_number = Some(1);
+        sequence::save(&seq).unwrap();
+
+        // Write as live credentials (account 1 is active)
+        fs::write(
+            _env.dir.path().join(".credentials.json"),
+            &creds,
+        )
+        .unwrap();
+
+        let result = refresh_all(&seq);
+        assert!(result.is_ok(), "healthy account should be skipped, got: {:?}", result);
+    }
+
+    #[test]
+    fn test_refresh_all_attempts_expired_oauth() {
+        let _env = TestEnv::new();
+
+        let expires_ms = chrono::Utc::now().timestamp_millis() - 3600 * 1000_i64; // 1h ago
+        let creds = make_oauth_creds_with_expiry("expired", expires_ms);
+
+        let mut seq = seq_with_accounts(&[(1, "expired@test.com", AuthKind::Oauth)]);
+        seq.active_account_number = Some(1);
+        sequence::save(&seq).unwrap();
+
+        fs::write(
+            _env.dir.path().join(".credentials.json"),
+            &creds,
+        )
+        .unwrap();
+
+        // core_refresh will attempt a network call that fails in the test env.
+        // We verify that refresh_all returns an error (account was attempted, not skipped).
+        let result = refresh_all(&seq);
+        assert!(result.is_err(), "expired account should be attempted and fail in test env");
+    }
+
+    #[test]
+    fn test_refresh_all_attempts_expiring_soon_oauth() {
+        let _env = TestEnv::new();
+
+        let expires_ms = chrono::Utc::now().timestamp_millis() + 12 * 3600 * 1000_i64; // 12h
+        let creds = make_oauth_creds_with_expiry("expiring", expires_ms);
+
+        let mut seq = seq_with_accounts(&[(1, "expiring@test.com", AuthKind::Oauth)]);
+        seq.active_account_number = Some(1);
+        sequence::save(&seq).unwrap();
+
+        fs::write(
+            _env.dir.path().join(".credentials.json"),
+            &creds,
+        )
+        .unwrap();
+
+        // Token expires within 24h → should be attempted.
+        let result = refresh_all(&seq);
+        assert!(result.is_err(), "expiring-soon account should be attempted and fail in test env");
+    }
+
+    #[test]
+    fn test_refresh_all_mixed_accounts() {
+        let _env = TestEnv::new();
+
+        let healthy_ms = chrono::Utc::now().timestamp_millis() + 30 * 86400 * 1000_i64;
+        let expired_ms = chrono::Utc::now().timestamp_millis() - 3600 * 1000_i64;
+
+        let creds_active = make_oauth_creds_with_expiry("active-healthy", healthy_ms);
+        let creds_inactive = make_oauth_creds_with_expiry("inactive-expired", expired_ms);
+
+        let mut seq = seq_with_accounts(&[
+            (1, "active@test.com", AuthKind::Oauth),
+            (2, "expired@test.com", AuthKind::Oauth),
+            (3, "token@test.com", AuthKind::Token),
+        ]);
+        seq.active_account_number = Some(1);
+        sequence::save(&seq).unwrap();
+
+        // Account 1 active (healthy)
+        fs::write(_env.dir.path().join(".credentials.json"), &creds_active).unwrap();
+        // Account 2 backup (expired)
+        credentials::write_backup(2, "expired@test.com", &creds_inactive).unwrap();
+
+        // Account 1 is healthy → skipped. Account 3 is token → skipped.
+        // Account 2 is expired → attempted → fails in test env.
+        let result = refresh_all(&seq);
+        assert!(result.is_err(), "one expired account should cause overall failure");
+    }
+
+    #[test]
+    fn test_refresh_flag_all_with_identifier_is_error() {
+        let _env = TestEnv::new();
+
+        // Set up a minimal valid sequence so we get past the empty-check
+        let seq = seq_with_accounts(&[(1, "user@test.com", AuthKind::Oauth)]);
+        sequence::save(&seq).unwrap();
+
+        let result = refresh(Some("1"), true);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("--all"));
+    }
+
+    #[test]
+    fn test_remove_force_deletes_without_prompt() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let creds_path = credentials::backup_file_path(2, "acct2@test.com").unwrap();
+        assert!(creds_path.exists());
+
+        remove("acct2@test.com", true).unwrap();
 
-    // 1. CLAUDE_CODE_OAUTH_TOKEN env var
-    if std::env::var("CLAUDE_CODE_OAUTH_TOKEN").is_ok() {
-        report.warnings += 1;
+        assert!(!creds_path.exists());
+        assert!(!config_backup_path(2, "acct2@test.com").exists());
+        let seq = sequence::load().unwrap();
+        assert!(!seq.accounts.contains_key("2"));
+        assert_eq!(seq.sequence, vec![1]);
     }
 
-    // 2. sequence.json integrity
-    for &num in &seq.sequence {
-        if !seq.accounts.contains_key(&num.to_string()) {
-            report.issues += 1;
-        }
-    }
-    for num_str in seq.accounts.keys() {
-        let num: u32 = num_str.parse().unwrap_or(0);
-        if !seq.sequence.contains(&num) {
-            report.warnings += 1;
-        }
-    }
+    // ── Tests: tags ───────────────────────────────────────────────────────────
 
-    // 3 & 4. Credentials readable + token expiry
-    for &num in &seq.sequence {
-        let Some(entry) = seq.accounts.get(&num.to_string()) else {
-            continue;
-        };
-        let is_active = seq.active_account_number == Some(num);
-        let creds_result = if is_active {
-            credentials::read_live()
-        } else {
-            credentials::read_backup(num, &entry.email)
-        };
+    #[test]
+    fn test_core_tag_and_untag() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
 
-        match creds_result {
-            Err(_) => report.issues += 1,
-            Ok(creds) if entry.auth_kind == AuthKind::Oauth => {
-                match credentials::oauth_secs_remaining(&creds) {
-                    Some(secs) if secs <= 0 => report.issues += 1,
-                    Some(secs) if secs <= EXPIRE_SOON_SECS => report.warnings += 1,
-                    _ => {}
-                }
-            }
-            Ok(_) => {}
-        }
+        core_tag(2, "#Work").unwrap();
+        assert!(core_tag(2, "work").unwrap().contains("already tagged"));
+        assert!(core_tag(2, "two words").is_err());
+        let seq = sequence::load().unwrap();
+        assert_eq!(seq.accounts["2"].tags, vec!["work"]);
+        assert_eq!(account_infos(&seq)[1].tags, vec!["work"]);
+
+        core_untag(2, "work").unwrap();
+        assert!(sequence::load().unwrap().accounts["2"].tags.is_empty());
+        assert!(core_untag(2, "work").is_err());
     }
 
-    // 5. File permissions (Linux / WSL only)
-    #[cfg(unix)]
-    {
-        use crate::platform::{detect, Platform};
-        use std::os::unix::fs::PermissionsExt;
+    #[test]
+    fn test_core_switch_tag_picks_next_tagged_account() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
 
-        let platform = detect();
-        if platform == Platform::Linux || platform == Platform::Wsl {
-            let base = sequence::backup_dir();
-            let mut checks: Vec<(std::path::PathBuf, u32)> = vec![
-                (base.clone(), 0o700),
-                (base.join("configs"), 0o700),
-                (base.join("credentials"), 0o700),
-                (sequence::sequence_path(), 0o600),
-            ];
-            if let Ok(entries) = std::fs::read_dir(base.join("credentials")) {
-                for e in entries.flatten() {
-                    checks.push((e.path(), 0o600));
-                }
-            }
-            for (path, expected) in &checks {
-                if !path.exists() {
-                    continue;
-                }
-                if let Ok(meta) = std::fs::metadata(path) {
-                    if meta.permissions().mode() & 0o777 != *expected {
-                        report.issues += 1;
-                    }
-                }
-            }
-        }
+        assert!(core_switch_tag("prod").is_err());
+        core_tag(2, "prod").unwrap();
+        assert_eq!(core_switch_tag("prod").unwrap().active_account, 2);
+        // Account 2 is now active and is the only one tagged.
+        assert!(core_switch_tag("prod").is_err());
     }
 
-    report
-}
+    // ── Tests: verify ─────────────────────────────────────────────────────────
 
-/// Print a health-check summary for all managed accounts and configuration.
-pub fn doctor() -> Result<()> {
-    #[cfg(unix)]
-    use std::os::unix::fs::PermissionsExt;
+    #[test]
+    fn test_core_verify_reports_each_account() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        credentials::write_backup(1, "acct1@test.com", &make_oauth_creds("acct1")).unwrap();
+        fs::write(config_backup_path(1, "acct1@test.com"), "{}").unwrap();
 
-    const EXPIRE_SOON_SECS: i64 = 24 * 3600;
-    let mut issues: usize = 0;
-    let mut warnings: usize = 0;
+        let seq = sequence::load().unwrap();
+        let results = core_verify(&seq);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].problems, vec!["config backup has no oauthAccount"]);
+        assert!(results[1].problems.is_empty(), "{:?}", results[1].problems);
+
+        credentials::write_backup(2, "acct2@test.com", &make_token_backup("sk-ant-oat01-x")).unwrap();
+        let results = core_verify(&seq);
+        assert!(results[1].problems[0].starts_with("credentials are malformed"));
+        assert!(verify(OutputFormat::Json).is_err());
+    }
 
-    println!();
+    #[test]
+    fn test_core_verify_reports_checksum_mismatch() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let path = credentials::backup_file_path(2, "acct2@test.com").unwrap();
+        fs::write(&path, make_oauth_creds("tampered")).unwrap();
 
-    // ── 1. CLAUDE_CODE_OAUTH_TOKEN env var ────────────────────────────────────
-    if std::env::var("CLAUDE_CODE_OAUTH_TOKEN").is_ok() {
-        println!(
-            "  {} CLAUDE_CODE_OAUTH_TOKEN is set — this overrides ccswitch and may break account switching",
-            "⚠".yellow().bold()
-        );
-        println!("    Unset it or add `source ~/.ccswitchrc` to your shell profile.");
-        warnings += 1;
-    } else {
-        println!("  {} CLAUDE_CODE_OAUTH_TOKEN not set", "✓".green().bold());
+        let results = core_verify(&sequence::load().unwrap());
+        assert_eq!(results[1].problems, vec!["credentials do not match their checksum"]);
     }
 
-    // ── Load sequence ─────────────────────────────────────────────────────────
-    let seq = sequence::load()?;
+    // ── Tests: prune ──────────────────────────────────────────────────────────
 
-    if seq.accounts.is_empty() {
-        println!(
-            "  {} No accounts managed yet — run `ccswitch add` first",
-            "⚠".yellow().bold()
-        );
-        println!();
-        return Ok(());
+    #[test]
+    fn test_core_prune_removes_orphaned_backups() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        credentials::write_backup(1, "acct1@test.com", &make_oauth_creds("acct1")).unwrap();
+        fs::write(config_backup_path(1, "acct1@test.com"), "{}").unwrap();
+        credentials::write_backup(3, "gone@test.com", &make_oauth_creds("gone")).unwrap();
+        fs::write(config_backup_path(2, "old@test.com"), "{}").unwrap();
+        fs::write(sequence::backup_dir().join("configs").join("notes.txt"), "").unwrap();
+
+        let found = core_prune(true).unwrap();
+        assert_eq!(found.len(), 2);
+        assert!(found.iter().any(|o| o.reason == "account 3 is not managed"));
+        assert!(found.iter().any(|o| o.reason == "account 2 is now acct2@test.com"));
+        assert!(credentials::read_backup(3, "gone@test.com").is_ok(), "dry run must not delete");
+
+        assert_eq!(core_prune(false).unwrap(), found);
+        assert!(credentials::read_backup(3, "gone@test.com").is_err());
+        assert!(!config_backup_path(2, "old@test.com").exists());
+        assert!(credentials::read_backup(1, "acct1@test.com").is_ok());
+        assert!(config_backup_path(1, "acct1@test.com").exists());
+        assert!(core_prune(false).unwrap().is_empty());
     }
 
-    // ── 2. sequence.json integrity ────────────────────────────────────────────
-    let mut seq_ok = true;
-    for &num in &seq.sequence {
-        if !seq.accounts.contains_key(&num.to_string()) {
-            println!(
-                "  {} sequence.json references account {} but no account entry exists",
-                "✗".red().bold(),
-                num
-            );
-            issues += 1;
-            seq_ok = false;
-        }
-    }
-    for (num_str, entry) in &seq.accounts {
-        let num: u32 = num_str.parse().unwrap_or(0);
-        if !seq.sequence.contains(&num) {
-            println!(
-                "  {} Account {} ({}) is in accounts map but missing from sequence list",
-                "⚠".yellow().bold(),
-                num,
-                entry.email
-            );
-            warnings += 1;
-            seq_ok = false;
-        }
-    }
-    if seq_ok {
-        println!("  {} sequence.json integrity OK", "✓".green().bold());
-    }
+    // ── Tests: repair ─────────────────────────────────────────────────────────
 
-    // ── 3 & 4. Credentials readable + token expiry ────────────────────────────
-    for &num in &seq.sequence {
-        let Some(entry) = seq.accounts.get(&num.to_string()) else {
-            continue;
-        };
-        let is_active = seq.active_account_number == Some(num);
-        let creds_result = if is_active {
-            credentials::read_live()
-        } else {
-            credentials::read_backup(num, &entry.email)
-        };
+    #[test]
+    fn test_repair_rebuilds_from_salvage_and_backups() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        credentials::write_backup(3, "tok@test.com", &make_token_backup("sk-ant-oat01-x")).unwrap();
+        let full = fs::read_to_string(sequence::sequence_path()).unwrap();
+        let damaged = &full[..full.find("\"accounts\"").unwrap()];
+        fs::write(sequence::sequence_path(), damaged).unwrap();
+        assert!(sequence::load().is_err());
+
+        let salvaged = sequence::salvage(damaged);
+        let candidates = repair_candidates(&salvaged).unwrap();
+        let found: Vec<_> = candidates
+            .iter()
+            .map(|c| (c.number, c.entry.email.as_str(), c.sources.clone()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (2, "acct2@test.com", vec!["credentials", "configs"]),
+                (3, "tok@test.com", vec!["credentials"]),
+            ]
+        );
+        assert_eq!(candidates[1].entry.auth_kind, AuthKind::Token);
 
-        match creds_result {
-            Err(e) => {
-                println!(
-                    "  {} Account {} ({}) — cannot read credentials: {}",
-                    "✗".red().bold(),
-                    num,
-                    entry.email,
-                    e
-                );
-                issues += 1;
-            }
-            Ok(creds) if entry.auth_kind == AuthKind::Oauth => {
-                match credentials::oauth_secs_remaining(&creds) {
-                    Some(secs) if secs <= 0 => {
-                        let hours = (-secs) / 3600;
-                        if hours > 0 {
-                            println!(
-                                "  {} Account {} ({}) — token expired {}h ago",
-                                "✗".red().bold(),
-                                num,
-                                entry.email,
-                                hours
-                            );
-                        } else {
-                            println!(
-                                "  {} Account {} ({}) — token expired {}m ago",
-                                "✗".red().bold(),
-                                num,
-                                entry.email,
-                                (-secs) / 60
-                            );
-                        }
-                        issues += 1;
-                    }
-                    Some(secs) if secs <= EXPIRE_SOON_SECS => {
-                        println!(
-                            "  {} Account {} ({}) — token expires in {}h (run `ccswitch refresh {}`)",
-                            "⚠".yellow().bold(),
-                            num,
-                            entry.email,
-                            secs / 3600,
-                            num
-                        );
-                        warnings += 1;
-                    }
-                    _ => {
-                        println!(
-                            "  {} Account {} ({}) — credentials OK",
-                            "✓".green().bold(),
-                            num,
-                            entry.email
-                        );
-                    }
-                }
-            }
-            Ok(_) => {
-                println!(
-                    "  {} Account {} ({}) — credentials OK (static token)",
-                    "✓".green().bold(),
-                    num,
-                    entry.email
-                );
-            }
-        }
+        let msg = core_repair(&salvaged, candidates).unwrap();
+        assert_eq!(msg, "Rebuilt sequence.json with 2 accounts");
+        let seq = sequence::load().unwrap();
+        assert_eq!(seq.sequence, [2, 3]);
+        assert_eq!(seq.active_account_number, None);
+        assert_eq!(seq.accounts["2"].uuid, "uuid2");
+        let corrupt = sequence::sequence_path().with_extension("json.corrupt");
+        assert_eq!(fs::read_to_string(corrupt).unwrap(), damaged);
     }
 
-    // ── 5. File permissions (Linux / WSL only) ────────────────────────────────
-    #[cfg(unix)]
-    {
-        use crate::platform::{detect, Platform};
-
-        let platform = detect();
-        if platform == Platform::Linux || platform == Platform::Wsl {
-            let base = sequence::backup_dir();
-            let mut checks: Vec<(std::path::PathBuf, u32)> = vec![
-                (base.clone(), 0o700),
-                (base.join("configs"), 0o700),
-                (base.join("credentials"), 0o700),
-                (sequence::sequence_path(), 0o600),
-            ];
-            if let Ok(entries) = std::fs::read_dir(base.join("credentials")) {
-                for e in entries.flatten() {
-                    checks.push((e.path(), 0o600));
-                }
-            }
-            let mut perm_ok = true;
-            for (path, expected) in &checks {
-                if !path.exists() {
-                    continue;
-                }
-                match std::fs::metadata(path) {
-                    Err(e) => {
-                        println!(
-                            "  {} Cannot stat {}: {}",
-                            "✗".red().bold(),
-                            path.display(),
-                            e
-                        );
-                        issues += 1;
-                        perm_ok = false;
-                    }
-                    Ok(meta) => {
-                        let mode = meta.permissions().mode() & 0o777;
-                        if mode != *expected {
-                            println!(
-                                "  {} {} has permissions {:04o}, expected {:04o}",
-                                "✗".red().bold(),
-                                path.display(),
-                                mode,
-                                expected
-                            );
-                            issues += 1;
-                            perm_ok = false;
-                        }
-                    }
-                }
-            }
-            if perm_ok {
-                println!("  {} File permissions OK", "✓".green().bold());
-            }
-        }
-    }
+    #[test]
+    fn test_repair_keeps_pin_and_schedules_of_kept_accounts() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let mut salvaged = sequence::load().unwrap();
+        salvaged.pinned_account = Some(2);
+        salvaged.schedules = vec![
+            sequence::ScheduleEntry { account_num: 1, cron_expr: "0 18 * * *".into() },
+            sequence::ScheduleEntry { account_num: 2, cron_expr: "0 9 * * *".into() },
+        ];
+        salvaged.schedule_last_run = Some("2026-01-01T09:00:00Z".into());
+
+        let candidates = repair_candidates(&salvaged).unwrap();
+        let kept: Vec<_> = candidates.into_iter().filter(|c| c.number == 2).collect();
+        core_repair(&salvaged, kept).unwrap();
 
-    // ── Summary ───────────────────────────────────────────────────────────────
-    println!();
-    if issues == 0 && warnings == 0 {
-        println!("  {} All checks passed.\n", "✓".green().bold());
-    } else {
-        let mut parts = Vec::new();
-        if issues > 0 {
-            parts.push(format!("{} error{}", issues, if issues == 1 { "" } else { "s" }));
-        }
-        if warnings > 0 {
-            parts.push(format!(
-                "{} warning{}",
-                warnings,
-                if warnings == 1 { "" } else { "s" }
-            ));
-        }
-        println!("  {} {}\n", "⚠".yellow().bold(), parts.join(", "));
+        let seq = sequence::load().unwrap();
+        assert_eq!(seq.pinned_account, Some(2));
+        assert_eq!(seq.schedules, [salvaged.schedules[1].clone()]);
+        assert_eq!(seq.schedule_last_run, salvaged.schedule_last_run);
     }
 
-    Ok(())
-}
-
-// ── Tests ─────────────────────────────────────────────────────────────────────
+    // ── Tests: run ────────────────────────────────────────────────────────────
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{
-        sequence::{AccountEntry, AuthKind, SequenceFile},
-        test_utils::TestEnv,
-    };
-    use std::fs;
+    #[test]
+    fn test_run_env_oauth_uses_private_config_dir() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
 
-    // ── is_invalid_grant_error ────────────────────────────────────────────────
+        let run = run_env(2).unwrap();
+        let dir = PathBuf::from(&run.vars[0].1);
+        assert_eq!(run.vars[0].0, "CLAUDE_CONFIG_DIR");
+        assert_eq!(
+            fs::read_to_string(dir.join(".credentials.json")).unwrap(),
+            credentials::read_backup(2, "acct2@test.com").unwrap()
+        );
+        assert!(fs::read_to_string(dir.join(".claude.json")).unwrap().contains("acct2@test.com"));
+        assert!(run.unset.contains(&"CLAUDE_CODE_OAUTH_TOKEN"));
 
-    #[test]
-    fn test_is_invalid_grant_error_http_response() {
-        let e = anyhow::anyhow!("Token refresh failed (HTTP 400): invalid_grant: Refresh token not found");
-        assert!(is_invalid_grant_error(&e));
+        drop(run);
+        assert!(!dir.exists());
+        // The live account is untouched.
+        assert_eq!(sequence::load().unwrap().active_account_number, Some(1));
     }
 
     #[test]
-    fn test_is_invalid_grant_error_transformed_message() {
-        let e = anyhow::anyhow!("Refresh token expired for Account 2 (user@test.com).");
-        assert!(is_invalid_grant_error(&e));
-    }
+    fn test_run_env_token_sets_oauth_token() {
+        let _env = TestEnv::new();
+        let mut seq = SequenceFile::default();
+        seq.accounts.insert("1".into(), entry("tok@test.com", AuthKind::Token));
+        seq.sequence = vec![1];
+        sequence::save(&seq).unwrap();
+        credentials::write_backup(1, "tok@test.com", &make_token_backup("sk-ant-oat01-tok")).unwrap();
 
-    #[test]
-    fn test_is_invalid_grant_error_network_error() {
-        let e = anyhow::anyhow!("Token refresh request failed: connection refused");
-        assert!(!is_invalid_grant_error(&e));
+        let run = run_env(1).unwrap();
+        assert_eq!(run.vars, vec![("CLAUDE_CODE_OAUTH_TOKEN", "sk-ant-oat01-tok".to_string())]);
+        assert!(run._config_dir.is_none());
     }
 
+    #[cfg(unix)]
     #[test]
-    fn test_is_invalid_grant_error_http_500() {
-        let e = anyhow::anyhow!("Token refresh failed (HTTP 500): internal server error");
-        assert!(!is_invalid_grant_error(&e));
-    }
+    fn test_run_as_runs_command_and_cleans_up() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let out = env.dir.path().join("run.out");
+        let script = format!(
+            "echo \"$CLAUDE_CONFIG_DIR\" > {0}; cat \"$CLAUDE_CONFIG_DIR/.credentials.json\" >> {0}",
+            out.display()
+        );
 
-    // ── Helpers ───────────────────────────────────────────────────────────────
+        run_as(2, &["sh", "-c", &script]).unwrap();
 
-    fn make_oauth_creds(label: &str) -> String {
-        let expires = chrono::Utc::now().timestamp_millis() + 30 * 24 * 3600 * 1000_i64;
-        serde_json::json!({
-            "claudeAiOauth": {
-                "accessToken": format!("sk-ant-oat01-{}", label),
-                "refreshToken": format!("sk-ant-ort01-{}", label),
-                "expiresAt": expires
-            }
-        })
-        .to_string()
+        let output = fs::read_to_string(&out).unwrap();
+        let (dir, creds) = output.split_once('\n').unwrap();
+        assert!(creds.contains("sk-ant-oat01-acct2"));
+        assert!(!PathBuf::from(dir).exists());
     }
 
-    fn make_token_backup(token: &str) -> String {
-        serde_json::json!({ "token": token }).to_string()
-    }
+    // ── Tests: hooks ──────────────────────────────────────────────────────────
 
-    fn make_oauth_config(email: &str, uuid: &str) -> serde_json::Value {
-        serde_json::json!({
-            "oauthAccount": { "emailAddress": email, "accountUuid": uuid },
-            "numStartups": 1
-        })
+    #[cfg(unix)]
+    fn install_hook(env: &TestEnv, key: &str, body: &str) {
+        use std::os::unix::fs::PermissionsExt;
+        let script = env.dir.path().join(format!("{key}.sh"));
+        fs::write(&script, format!("#!/bin/sh\n{body}\n")).unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        let mut config = fs::read_to_string(crate::config_user::path()).unwrap_or_else(|_| "[hooks]\n".into());
+        config.push_str(&format!("{key} = \"{}\"\n", script.display()));
+        fs::write(crate::config_user::path(), config).unwrap();
     }
 
-    fn write_live_file(env: &TestEnv, creds: &str) {
-        fs::write(env.dir.path().join(".credentials.json"), creds).unwrap();
-    }
+    #[cfg(unix)]
+    #[test]
+    fn test_pre_switch_hook_failure_aborts_switch() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        install_hook(&env, "pre_switch", "echo busy >&2; exit 1");
 
-    fn write_config_file(env: &TestEnv, config: &serde_json::Value) {
-        fs::write(
-            env.dir.path().join(".claude.json"),
-            serde_json::to_string_pretty(config).unwrap(),
-        )
-        .unwrap();
+        let err = core_switch(2).unwrap_err();
+        assert!(format!("{err:#}").contains("busy"), "{err:#}");
+        assert_eq!(sequence::load().unwrap().active_account_number, Some(1));
     }
 
-    fn read_live_json(env: &TestEnv) -> serde_json::Value {
-        let raw = fs::read_to_string(env.dir.path().join(".credentials.json")).unwrap();
-        serde_json::from_str(&raw).unwrap()
+    #[cfg(unix)]
+    #[test]
+    fn test_pre_switch_hook_abort_keeps_source_error() {
+        use std::os::unix::fs::PermissionsExt;
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        install_hook(&env, "pre_switch", "exit 0");
+        let script = env.dir.path().join("pre_switch.sh");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let err = core_switch(2).unwrap_err();
+        assert!(matches!(err, CcswitchError::HookAborted(_)), "unexpected: {err:?}");
+        let source = std::error::Error::source(&err).expect("hook error keeps its source");
+        assert!(source.downcast_ref::<std::io::Error>().is_some(), "unexpected: {source:?}");
     }
 
-    fn read_config_json(env: &TestEnv) -> serde_json::Value {
-        let raw = fs::read_to_string(env.dir.path().join(".claude.json")).unwrap();
-        serde_json::from_str(&raw).unwrap()
-    }
+    #[cfg(unix)]
+    #[test]
+    fn test_pre_switch_hook_runs_without_the_lock() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        // flock -n fails at once if ccswitch still held the sequence lock.
+        let lock = sequence::lock_path();
+        install_hook(&env, "pre_switch", &format!("flock -n {} true", lock.display()));
 
-    fn entry(email: &str, kind: AuthKind) -> AccountEntry {
-        AccountEntry {
-            email: email.to_string(),
-            uuid: format!("uuid-{email}"),
-            added: sequence::now_utc(),
-            auth_kind: kind,
-        }
+        core_switch(2).unwrap();
+        assert_eq!(sequence::load().unwrap().active_account_number, Some(2));
     }
 
-    /// Two OAuth accounts, account 1 active.
-    fn setup_two_oauth(env: &TestEnv) {
-        let creds1 = make_oauth_creds("acct1");
-        let creds2 = make_oauth_creds("acct2");
-        let cfg1 = make_oauth_config("acct1@test.com", "uuid1");
-        let cfg2 = make_oauth_config("acct2@test.com", "uuid2");
-
-        let mut seq = SequenceFile::default();
-        seq.accounts.insert("1".into(), entry("acct1@test.com", AuthKind::Oauth));
-        seq.accounts.insert("2".into(), entry("acct2@test.com", AuthKind::Oauth));
-        seq.sequence = vec![1, 2];
-        seq.active_account_number = Some(1);
-        seq.last_updated = sequence::now_utc();
-        sequence::save(&seq).unwrap();
-
-        write_live_file(env, &creds1);
-        write_config_file(env, &cfg1);
+    #[cfg(unix)]
+    #[test]
+    fn test_hooks_receive_accounts_and_post_failure_is_reported() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let log = env.dir.path().join("hook.log");
+        install_hook(
+            &env,
+            "pre_switch",
+            &format!("echo \"pre $CCSWITCH_FROM_ACCOUNT $CCSWITCH_TO_ACCOUNT\" >> {}", log.display()),
+        );
+        install_hook(
+            &env,
+            "post_switch",
+            &format!("echo \"post $CCSWITCH_TO_EMAIL\" >> {}; exit 2", log.display()),
+        );
 
-        // Backup for account 2
-        credentials::write_backup(2, "acct2@test.com", &creds2).unwrap();
-        fs::write(
-            config_backup_path(2, "acct2@test.com"),
-            serde_json::to_string_pretty(&cfg2).unwrap(),
-        )
-        .unwrap();
+        let result = core_switch(2).unwrap();
+        assert_eq!(sequence::load().unwrap().active_account_number, Some(2));
+        assert_eq!(fs::read_to_string(&log).unwrap(), "pre 1 2\npost acct2@test.com\n");
+        assert!(result.post_hook_error.unwrap().contains("exit status 2"));
     }
 
-    // ── Unit tests ────────────────────────────────────────────────────────────
+    // ── Tests: info ───────────────────────────────────────────────────────────
 
     #[test]
-    fn test_extract_access_token_ok() {
-        let creds = r#"{"token": "sk-ant-oat01-mytoken"}"#;
-        assert_eq!(extract_access_token(creds).unwrap(), "sk-ant-oat01-mytoken");
+    fn test_core_info_reports_backups_without_secrets() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        core_tag(2, "work").unwrap();
+
+        let d = core_info(2).unwrap();
+        let creds = credentials::read_backup(2, "acct2@test.com").unwrap();
+        assert_eq!(d.info.tags, vec!["work"]);
+        assert!(d.credentials_readable);
+        assert_eq!(d.credentials_sha256, Some(credentials::sha256_fingerprint(&creds)));
+        assert_eq!(d.credentials_size, Some(creds.len() as u64));
+        assert!(d.config_backup_size.is_some());
+        assert_eq!(d.stored_expires_at, credentials::oauth_expires_at(&creds));
+        assert!(!serde_json::to_string(&d).unwrap().contains("sk-ant-"));
+
+        // Account 1 is active and has no backups in this fixture.
+        let d = core_info(1).unwrap();
+        assert!(!d.credentials_readable);
+        assert_eq!(d.credentials_sha256, None);
+        assert_eq!(d.config_backup_size, None);
+        assert_eq!(d.created_by, None);
     }
 
     #[test]
-    fn test_extract_access_token_missing() {
-        let creds = r#"{"claudeAiOauth": {"accessToken": "tok"}}"#;
-        assert!(extract_access_token(creds).is_err());
+    fn test_core_info_reports_creator() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let mut seq = sequence::load().unwrap();
+        let entry = seq.accounts.get_mut("2").unwrap();
+        entry.created_by = Some("alice@laptop".into());
+        entry.machine_id = Some("5a3c1d2e".into());
+        sequence::save(&seq).unwrap();
+
+        let d = core_info(2).unwrap();
+        assert_eq!(d.created_by.as_deref(), Some("alice@laptop"));
+        assert_eq!(d.machine_id.as_deref(), Some("5a3c1d2e"));
+        let json = serde_json::to_value(&d).unwrap();
+        assert_eq!(json["created_by"], "alice@laptop");
     }
 
+    // ── Tests: dry run ────────────────────────────────────────────────────────
+
     #[test]
-    fn test_session_expiry_badge_expired() {
-        let ms = chrono::Utc::now().timestamp_millis() - 1_000;
-        let creds = serde_json::json!({ "claudeAiOauth": { "expiresAt": ms } }).to_string();
-        assert_eq!(session_expiry_badge(&creds), "[expired]");
+    fn test_line_diff_marks_changes() {
+        let diff = line_diff("a\nb\nc", "a\nx\nc");
+        assert_eq!(diff, vec![" a", "-b", "+x", " c"]);
     }
 
     #[test]
-    fn test_session_expiry_badge_hours() {
-        // Add a 60s buffer so integer division still yields 2h when the test runs.
-        let ms = chrono::Utc::now().timestamp_millis() + (2 * 3600 + 60) * 1000_i64;
-        let creds = serde_json::json!({ "claudeAiOauth": { "expiresAt": ms } }).to_string();
-        assert_eq!(session_expiry_badge(&creds), "[~2h]");
+    fn test_core_diff_compares_backups_without_secrets() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        fs::write(
+            config_backup_path(1, "acct1@test.com"),
+            serde_json::to_string_pretty(&make_oauth_config("acct1@test.com", "uuid1")).unwrap(),
+        )
+        .unwrap();
+
+        let d = core_diff(1, 2).unwrap();
+        assert!(!d.from_has_credentials);
+        assert!(d.to_has_credentials);
+        assert!(d.config_diff.iter().any(|l| l.starts_with('-') && l.contains("uuid1")));
+        assert!(d.config_diff.iter().any(|l| l.starts_with('+') && l.contains("uuid2")));
+        assert!(!d.config_diff.iter().any(|l| l.contains("sk-ant-oat01-acct2")));
+        assert!(core_diff(1, 9).is_err());
     }
 
     #[test]
-    fn test_session_expiry_badge_days() {
-        // Add a 1h buffer so integer division still yields 4d when the test runs.
-        let ms = chrono::Utc::now().timestamp_millis() + (4 * 86400 + 3600) * 1000_i64;
-        let creds = serde_json::json!({ "claudeAiOauth": { "expiresAt": ms } }).to_string();
-        assert_eq!(session_expiry_badge(&creds), "[~4d]");
+    fn test_preview_switch_writes_nothing() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let seq_before = fs::read_to_string(sequence::sequence_path()).unwrap();
+        let live_before = read_live_json(&env);
+
+        let preview = preview_switch(2).unwrap();
+        assert_eq!(preview.from_email, "acct1@test.com");
+        assert_eq!(preview.to_email, "acct2@test.com");
+        assert!(preview.creds_changed);
+        assert!(preview.config_diff.iter().any(|l| l.starts_with('-') && l.contains("acct1@test.com")));
+        assert!(preview.config_diff.iter().any(|l| l.starts_with('+') && l.contains("acct2@test.com")));
+        assert!(preview.writes.iter().any(|w| w.ends_with("sequence.json")));
+
+        assert_eq!(fs::read_to_string(sequence::sequence_path()).unwrap(), seq_before);
+        assert_eq!(read_live_json(&env), live_before);
     }
 
+    // ── Tests: undo ───────────────────────────────────────────────────────────
+
     #[test]
-    fn test_session_expiry_badge_healthy() {
-        let ms = chrono::Utc::now().timestamp_millis() + 30 * 86400 * 1000_i64;
-        let creds = serde_json::json!({ "claudeAiOauth": { "expiresAt": ms } }).to_string();
-        assert_eq!(session_expiry_badge(&creds), "");
-    }
+    fn test_core_undo_returns_to_previous_once() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
 
-    // ── Integration tests: core_switch ────────────────────────────────────────
+        assert!(core_undo().unwrap_err().to_string().contains("Nothing to undo"));
+
+        core_switch(2).unwrap();
+        assert_eq!(sequence::load().unwrap().previous_account_number, Some(1));
+
+        let result = core_undo().unwrap();
+        assert_eq!(result.active_account, 1);
+        let seq = sequence::load().unwrap();
+        assert_eq!(seq.active_account_number, Some(1));
+        assert_eq!(seq.previous_account_number, None);
+
+        assert!(core_undo().is_err(), "a second undo should have nothing to revert");
+    }
 
     #[test]
-    fn test_switch_already_active() {
+    fn test_undo_refuses_when_previous_changed_before_the_lock() {
         let env = TestEnv::new();
         setup_two_oauth(&env);
+        core_switch(2).unwrap();
+        let target = undo_target(&sequence::load().unwrap()).unwrap();
 
-        let msg = core_switch(1).unwrap();
-        assert!(msg.contains("Already using"), "unexpected: {msg}");
-        // Sequence unchanged
-        assert_eq!(sequence::load().unwrap().active_account_number, Some(1));
+        // Another process switches between the read and the locked undo.
+        core_switch(1).unwrap();
+        let err = switch_account(target, true).unwrap_err();
+        assert!(err.to_string().contains("another switch happened"), "unexpected: {err}");
+        let seq = sequence::load().unwrap();
+        assert_eq!(seq.active_account_number, Some(1));
+        assert_eq!(seq.previous_account_number, Some(2));
     }
 
     #[test]
-    fn test_switch_nonexistent_account() {
+    fn test_core_remove_clears_previous_account() {
         let env = TestEnv::new();
         setup_two_oauth(&env);
+        core_switch(2).unwrap();
 
-        let err = core_switch(99).unwrap_err();
-        assert!(err.to_string().contains("does not exist"), "unexpected: {err}");
+        core_remove(1, "acct1@test.com").unwrap();
+        assert_eq!(sequence::load().unwrap().previous_account_number, None);
     }
 
+    // ── Tests: history ────────────────────────────────────────────────────────
+
     #[test]
-    fn test_switch_oauth_to_oauth() {
+    fn test_core_switch_appends_switch_log() {
         let env = TestEnv::new();
         setup_two_oauth(&env);
 
-        let msg = core_switch(2).unwrap();
-        assert!(msg.contains("acct2@test.com"), "unexpected: {msg}");
+        core_switch(2).unwrap();
+        // With the live credentials gone, the snapshot step of the next switch fails.
+        fs::remove_file(env.dir.path().join(".credentials.json")).unwrap();
+        assert!(core_switch(1).is_err());
+
+        let log = sequence::read_switch_log(20).unwrap();
+        assert_eq!(log.len(), 2);
+        assert_eq!((log[0].from, log[0].to, log[0].ok), (1, 2, true));
+        assert_eq!((log[1].from, log[1].to, log[1].ok), (2, 1, false));
+    }
 
-        // Sequence points at account 2
-        assert_eq!(sequence::load().unwrap().active_account_number, Some(2));
+    #[test]
+    fn test_audit_log_records_switch_run_and_remove() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
 
-        // Live credentials are account 2's token
-        let live = read_live_json(&env);
+        core_switch(2).unwrap();
+        run_env(1).unwrap();
+        // With the live credentials gone, the next switch fails.
+        let live = env.dir.path().join(".credentials.json");
+        let creds = fs::read_to_string(&live).unwrap();
+        fs::remove_file(&live).unwrap();
+        assert!(core_switch(1).is_err());
+        fs::write(&live, creds).unwrap();
+        core_remove(1, "acct1@test.com").unwrap();
+
+        let events = sequence::read_audit_log(None).unwrap();
+        let summary: Vec<_> = events.iter().map(|e| (e.action, e.account_num, e.ok)).collect();
         assert_eq!(
-            live["claudeAiOauth"]["accessToken"].as_str().unwrap(),
-            "sk-ant-oat01-acct2"
+            summary,
+            [
+                (AuditAction::Switch, 2, true),
+                (AuditAction::Read, 1, true),
+                (AuditAction::Switch, 1, false),
+                (AuditAction::Remove, 1, true),
+            ]
         );
+        assert_eq!(events[0].account_email, "acct2@test.com");
+        assert_eq!(events[0].pid, std::process::id());
+        assert!(audit_verify().is_ok());
 
-        // Live config oauthAccount is account 2
-        let cfg = read_config_json(&env);
-        assert_eq!(
-            cfg["oauthAccount"]["emailAddress"].as_str().unwrap(),
-            "acct2@test.com"
-        );
+        let out = env.dir.path().join("audit-copy.log");
+        audit_log(Some(2), Some(&out), OutputFormat::Text).unwrap();
+        assert_eq!(fs::read_to_string(&out).unwrap().lines().count(), 2);
     }
 
+    // ── Tests: doctor ─────────────────────────────────────────────────────────
+
     #[test]
-    fn test_switch_oauth_to_token() {
-        let env = TestEnv::new();
+    fn test_doctor_no_accounts_returns_zero_counts() {
+        let _env = TestEnv::new();
+        let seq = SequenceFile::default();
+        let report = core_doctor(&seq);
+        assert_eq!(report.issues, 0);
+        assert_eq!(report.warnings, 0);
+    }
 
-        let creds1 = make_oauth_creds("acct1");
-        let cfg1 = make_oauth_config("acct1@test.com", "uuid1");
-        let token2 = "sk-ant-oat01-tokenacct2";
+    #[test]
+    fn test_doctor_env_var_set_warns() {
+        let _env = TestEnv::new();
+        std::env::set_var("CLAUDE_CODE_OAUTH_TOKEN", "test-tok");
+        let seq = SequenceFile::default();
+        let report = core_doctor(&seq);
+        std::env::remove_var("CLAUDE_CODE_OAUTH_TOKEN");
+        assert_eq!(report.warnings, 1, "CLAUDE_CODE_OAUTH_TOKEN should produce 1 warning");
+        assert_eq!(report.issues, 0);
+    }
 
-        let mut seq = SequenceFile::default();
-        seq.accounts.insert("1".into(), entry("acct1@test.com", AuthKind::Oauth));
-        seq.accounts.insert("2".into(), entry("tokenuser", AuthKind::Token));
-        seq.sequence = vec![1, 2];
+    #[test]
+    fn test_doctor_healthy_oauth_account() {
+        let _env = TestEnv::new();
+        let expires_ms = chrono::Utc::now().timestamp_millis() + 7 * 86400 * 1000_i64;
+        let creds = make_oauth_creds_with_expiry("healthy", expires_ms);
+
+        let mut seq = seq_with_accounts(&[(1, "user@test.com", AuthKind::Oauth)]);
         seq.active_account_number = Some(1);
-        seq.last_updated = sequence::now_utc();
         sequence::save(&seq).unwrap();
+        fs::write(_env.dir.path().join(".credentials.json"), &creds).unwrap();
+        credentials::write_backup(1, "user@test.com", &creds).unwrap();
 
-        write_live_file(&env, &creds1);
-        write_config_file(&env, &cfg1);
-        credentials::write_backup(2, "tokenuser", &make_token_backup(token2)).unwrap();
+        let report = core_doctor(&seq);
+        assert_eq!(report.issues, 0, "healthy account should produce no issues");
+        // warnings may include perm issues in test env; just check no cred issues
+    }
 
-        let msg = core_switch(2).unwrap();
-        assert!(msg.contains("tokenuser"), "unexpected: {msg}");
+    #[cfg(unix)]
+    #[test]
+    fn test_check_permissions_reports_and_fixes() {
+        use std::os::unix::fs::PermissionsExt;
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let config = config_backup_path(2, "acct2@test.com");
+        fs::set_permissions(&config, fs::Permissions::from_mode(0o644)).unwrap();
+        fs::write(credentials::ccswitchrc_path(), "unset X\n").unwrap();
+        fs::set_permissions(credentials::ccswitchrc_path(), fs::Permissions::from_mode(0o644))
+            .unwrap();
+
+        assert!(check_permissions(false).is_err());
+        let checks = doctor_checks(&sequence::load().unwrap());
+        assert_eq!(checks.iter().filter(|c| c.message.contains("has permissions")).count(), 2);
+
+        check_permissions(true).unwrap();
+        assert_eq!(fs::metadata(&config).unwrap().permissions().mode() & 0o777, 0o600);
+        check_permissions(false).unwrap();
+    }
 
-        // Sequence points at account 2
-        assert_eq!(sequence::load().unwrap().active_account_number, Some(2));
+    #[test]
+    fn test_doctor_missing_credentials_is_issue() {
+        let _env = TestEnv::new();
 
-        // Live credentials contain the raw token as the OAuth accessToken
-        let live = read_live_json(&env);
-        assert_eq!(live["claudeAiOauth"]["accessToken"].as_str().unwrap(), token2);
+        let mut seq = seq_with_accounts(&[(1, "ghost@test.com", AuthKind::Oauth)]);
+        seq.active_account_number = Some(1);
+        // No credentials file written → read_live() will fail
 
-        // oauthAccount is removed from config
-        let cfg = read_config_json(&env);
-        assert!(cfg.get("oauthAccount").is_none(), "oauthAccount should be absent");
+        let report = core_doctor(&seq);
+        assert!(report.issues >= 1, "missing credentials should produce at least 1 issue");
     }
 
     #[test]
-    fn test_switch_token_to_oauth() {
-        let env = TestEnv::new();
+    fn test_doctor_expired_oauth_is_issue() {
+        let _env = TestEnv::new();
+        let expired_ms = chrono::Utc::now().timestamp_millis() - 3600 * 1000_i64;
+        let creds = make_oauth_creds_with_expiry("expired", expired_ms);
 
-        let token1 = "sk-ant-oat01-mytokenacct";
-        let creds2 = make_oauth_creds("acct2");
-        let cfg2 = make_oauth_config("acct2@test.com", "uuid2");
+        let mut seq = seq_with_accounts(&[(1, "expired@test.com", AuthKind::Oauth)]);
+        seq.active_account_number = Some(1);
+        sequence::save(&seq).unwrap();
+        fs::write(_env.dir.path().join(".credentials.json"), &creds).unwrap();
 
-        let mut seq = SequenceFile::default();
-        seq.accounts.insert("1".into(), entry("tokenuser", AuthKind::Token));
-        seq.accounts.insert("2".into(), entry("acct2@test.com", AuthKind::Oauth));
-        seq.sequence = vec![1, 2];
+        let report = core_doctor(&seq);
+        assert!(report.issues >= 1, "expired token should produce at least 1 issue");
+    }
+
+    #[test]
+    fn test_doctor_expiring_soon_is_warning() {
+        let _env = TestEnv::new();
+        let soon_ms = chrono::Utc::now().timestamp_millis() + 12 * 3600 * 1000_i64; // 12 h
+        let creds = make_oauth_creds_with_expiry("soon", soon_ms);
+
+        let mut seq = seq_with_accounts(&[(1, "soon@test.com", AuthKind::Oauth)]);
         seq.active_account_number = Some(1);
-        seq.last_updated = sequence::now_utc();
         sequence::save(&seq).unwrap();
+        fs::write(_env.dir.path().join(".credentials.json"), &creds).unwrap();
+        credentials::write_backup(1, "soon@test.com", &creds).unwrap();
 
-        // Live credentials: token stored in OAuth format (as write_live_token would do)
-        let live_token_creds = serde_json::json!({
-            "claudeAiOauth": {
-                "accessToken": token1,
-                "refreshToken": "",
-                "expiresAt": chrono::Utc::now().timestamp_millis() + 10 * 365 * 24 * 3600 * 1000_i64
-            }
-        })
-        .to_string();
-        write_live_file(&env, &live_token_creds);
-        write_config_file(&env, &serde_json::json!({ "numStartups": 1 }));
+        let report = core_doctor(&seq);
+        assert_eq!(report.issues, 0, "expiring-soon token should not be an issue");
+        // The warning count may include perm issues; verify at least 1 warning
+        assert!(report.warnings >= 1, "expiring-soon token should produce at least 1 warning");
+    }
 
-        // Backup for account 2
-        credentials::write_backup(2, "acct2@test.com", &creds2).unwrap();
-        fs::write(
-            config_backup_path(2, "acct2@test.com"),
-            serde_json::to_string_pretty(&cfg2).unwrap(),
-        )
-        .unwrap();
+    #[test]
+    fn test_doctor_token_account_no_expiry_issue() {
+        let _env = TestEnv::new();
+        let token_creds = make_token_backup("sk-ant-tok01-test");
 
-        let msg = core_switch(2).unwrap();
-        assert!(msg.contains("acct2@test.com"), "unexpected: {msg}");
+        let mut seq = seq_with_accounts(&[(1, "tok@test.com", AuthKind::Token)]);
+        seq.active_account_number = Some(1);
+        sequence::save(&seq).unwrap();
+        // Write as live credentials (active account)
+        fs::write(_env.dir.path().join(".credentials.json"), &token_creds).unwrap();
+        credentials::write_backup(1, "tok@test.com", &token_creds).unwrap();
 
-        // Sequence points at account 2
-        assert_eq!(sequence::load().unwrap().active_account_number, Some(2));
+        let report = core_doctor(&seq);
+        // Token accounts never produce expiry issues
+        assert_eq!(report.issues, 0, "token account with readable creds should not be an issue");
+    }
 
-        // Live credentials are account 2's OAuth token
-        let live = read_live_json(&env);
-        assert_eq!(
-            live["claudeAiOauth"]["accessToken"].as_str().unwrap(),
-            "sk-ant-oat01-acct2"
-        );
+    #[test]
+    fn test_core_move_reorders_and_clamps() {
+        let _env = TestEnv::new();
+        let seq = seq_with_accounts(&[
+            (1, "a@test.com", AuthKind::Token),
+            (2, "b@test.com", AuthKind::Token),
+            (3, "c@test.com", AuthKind::Token),
+        ]);
+        sequence::save(&seq).unwrap();
 
-        // Live config now has account 2's oauthAccount
-        let cfg = read_config_json(&env);
-        assert_eq!(
-            cfg["oauthAccount"]["emailAddress"].as_str().unwrap(),
-            "acct2@test.com"
-        );
+        let msg = core_move(3, 1).unwrap();
+        assert!(msg.contains("position 1"), "unexpected: {msg}");
+        assert_eq!(sequence::load().unwrap().sequence, vec![3, 1, 2]);
+
+        core_move(3, 99).unwrap();
+        assert_eq!(sequence::load().unwrap().sequence, vec![1, 2, 3]);
+
+        core_move(2, 0).unwrap();
+        assert_eq!(sequence::load().unwrap().sequence, vec![2, 1, 3]);
+    }
+
+    #[test]
+    fn test_core_set_priority_persists() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+
+        let msg = core_set_priority(2, 7).unwrap();
+        assert!(msg.contains("set to 7"), "unexpected: {msg}");
+        assert_eq!(sequence::load().unwrap().accounts["2"].priority, 7);
+    }
+
+    #[test]
+    fn test_switch_highest_priority_skips_current() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        // Account 1 (active) has the highest priority but is skipped.
+        core_set_priority(1, 10).unwrap();
+        core_set_priority(2, 1).unwrap();
+
+        switch_highest_priority(&SwitchOptions::default()).unwrap();
+        assert_eq!(sequence::load().unwrap().active_account_number, Some(2));
+    }
+
+    #[test]
+    fn test_doctor_missing_config_backup_is_warning() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let seq = sequence::load().unwrap();
+        let before = core_doctor(&seq).warnings;
+
+        fs::remove_file(config_backup_path(2, "acct2@test.com")).unwrap();
+        assert_eq!(core_doctor(&seq).warnings, before + 1);
     }
 
     #[test]
-    fn test_switch_oauth_to_oauth_snapshots_current() {
-        let env = TestEnv::new();
-        setup_two_oauth(&env);
+    fn test_doctor_no_config_backup_expected_for_token() {
+        let _env = TestEnv::new();
+        let mut seq = seq_with_accounts(&[(1, "tok@test.com", AuthKind::Token)]);
+        seq.accounts.get_mut("1").unwrap().has_config_backup = false;
+        let before = core_doctor(&seq).warnings;
 
-        // Switch to account 2
-        core_switch(2).unwrap();
+        seq.accounts.get_mut("1").unwrap().has_config_backup = true;
+        assert_eq!(core_doctor(&seq).warnings, before + 1);
+    }
 
-        // Now switch back to account 1 — its backup should have been written
-        // when we switched away from it
-        core_switch(1).unwrap();
+    #[test]
+    fn test_doctor_sequence_integrity_missing_entry() {
+        let _env = TestEnv::new();
 
-        // Live creds should be back to account 1's token
-        let live = read_live_json(&env);
-        assert_eq!(
-            live["claudeAiOauth"]["accessToken"].as_str().unwrap(),
-            "sk-ant-oat01-acct1"
+        // Sequence lists account 99 which has no entry in accounts map
+        let mut seq = SequenceFile::default();
+        seq.accounts.insert(
+            "1".into(),
+            AccountEntry {
+                email: "user@test.com".to_string(),
+                uuid: "uuid-1".to_string(),
+                added: sequence::now_utc(),
+                auth_kind: AuthKind::Oauth,
+                ..Default::default()
+            },
         );
-        assert_eq!(sequence::load().unwrap().active_account_number, Some(1));
-    }
+        seq.sequence = vec![1, 99]; // 99 has no entry
+        seq.active_account_number = Some(1);
+        seq.last_updated = sequence::now_utc();
 
-    fn make_expired_oauth_creds(label: &str) -> String {
-        let expires = chrono::Utc::now().timestamp_millis() - 3_600_000_i64; // 1h ago
-        serde_json::json!({
-            "claudeAiOauth": {
-                "accessToken": format!("sk-ant-oat01-{}", label),
-                "refreshToken": format!("sk-ant-ort01-{}", label),
-                "expiresAt": expires
-            }
-        })
-        .to_string()
+        let report = core_doctor(&seq);
+        assert!(report.issues >= 1, "dangling sequence entry should be an issue");
     }
 
-    /// In a non-interactive (non-tty) environment the expiry prompt is skipped
-    /// and do_switch proceeds with the expired session, completing successfully.
     #[test]
-    fn test_do_switch_expired_oauth_skips_prompt_and_switches() {
-        let env = TestEnv::new();
-
-        let creds1 = make_oauth_creds("acct1");
-        let creds2_expired = make_expired_oauth_creds("acct2");
-        let cfg1 = make_oauth_config("acct1@test.com", "uuid1");
-        let cfg2 = make_oauth_config("acct2@test.com", "uuid2");
+    fn test_doctor_account_not_in_sequence_is_warning() {
+        let _env = TestEnv::new();
 
+        // Account 2 is in accounts map but not in sequence list
         let mut seq = SequenceFile::default();
-        seq.accounts.insert("1".into(), entry("acct1@test.com", AuthKind::Oauth));
-        seq.accounts.insert("2".into(), entry("acct2@test.com", AuthKind::Oauth));
-        seq.sequence = vec![1, 2];
+        seq.accounts.insert(
+            "1".into(),
+            AccountEntry {
+                email: "a@test.com".to_string(),
+                uuid: "uuid-1".to_string(),
+                added: sequence::now_utc(),
+                auth_kind: AuthKind::Oauth,
+                ..Default::default()
+            },
+        );
+        seq.accounts.insert(
+            "2".into(),
+            AccountEntry {
+                email: "b@test.com".to_string(),
+                uuid: "uuid-2".to_string(),
+                added: sequence::now_utc(),
+                auth_kind: AuthKind::Token,
+                ..Default::default()
+            },
+        );
+        seq.sequence = vec![1]; // 2 is missing from sequence
         seq.active_account_number = Some(1);
         seq.last_updated = sequence::now_utc();
-        sequence::save(&seq).unwrap();
-
-        write_live_file(&env, &creds1);
-        write_config_file(&env, &cfg1);
 
-        credentials::write_backup(2, "acct2@test.com", &creds2_expired).unwrap();
-        fs::write(
-            config_backup_path(2, "acct2@test.com"),
-            serde_json::to_string_pretty(&cfg2).unwrap(),
-        )
-        .unwrap();
+        let report = core_doctor(&seq);
+        assert!(report.warnings >= 1, "account missing from sequence should be a warning");
+    }
 
-        // Non-tty: do_switch should warn but still switch successfully.
-        do_switch(2).unwrap();
+    #[test]
+    fn test_doctor_dangling_active_account_is_issue() {
+        let _env = TestEnv::new();
+        let mut seq = seq_with_accounts(&[(1, "tok@test.com", AuthKind::Token)]);
+        credentials::write_backup(1, "tok@test.com", &make_token_backup("sk-ant-x")).unwrap();
+        seq.active_account_number = Some(1);
+        let before = core_doctor(&seq).issues;
 
-        assert_eq!(sequence::load().unwrap().active_account_number, Some(2));
-        let live = read_live_json(&env);
-        assert_eq!(
-            live["claudeAiOauth"]["accessToken"].as_str().unwrap(),
-            "sk-ant-oat01-acct2"
-        );
+        seq.active_account_number = Some(7);
+        assert_eq!(core_doctor(&seq).issues, before + 1);
     }
 
-    /// Switching to a healthy (non-expired) OAuth account takes the normal path
-    /// with no expiry warning.
     #[test]
-    fn test_do_switch_healthy_oauth_no_expiry_warning() {
-        let env = TestEnv::new();
-        setup_two_oauth(&env); // both accounts have fresh creds
-
-        // Should succeed without any expiry-related branching.
-        do_switch(2).unwrap();
+    fn test_doctor_malformed_backup_is_issue() {
+        let _env = TestEnv::new();
+        let mut seq = seq_with_accounts(&[(1, "tok@test.com", AuthKind::Token)]);
+        seq.active_account_number = Some(1);
+        credentials::write_backup(1, "tok@test.com", r#"{"unexpected":true}"#).unwrap();
 
-        assert_eq!(sequence::load().unwrap().active_account_number, Some(2));
+        assert!(core_doctor(&seq).issues >= 1, "malformed backup should be an issue");
     }
 
-    // ── Tests: refresh --all ──────────────────────────────────────────────────
+    #[test]
+    fn test_doctor_fails_when_checks_fail() {
+        let _env = TestEnv::new();
+        let mut seq = seq_with_accounts(&[(1, "ghost@test.com", AuthKind::Oauth)]);
+        seq.active_account_number = Some(1);
+        sequence::save(&seq).unwrap();
 
-    fn make_oauth_creds_with_expiry(label: &str, expires_at_ms: i64) -> String {
-        serde_json::json!({
-            "claudeAiOauth": {
-                "accessToken": format!("sk-ant-oat01-{}", label),
-                "refreshToken": format!("sk-ant-ort01-{}", label),
-                "expiresAt": expires_at_ms
-            }
-        })
-        .to_string()
+        let err = doctor().unwrap_err();
+        assert!(err.to_string().contains("error"), "unexpected: {err}");
     }
 
-    /// Build a SequenceFile with one or more accounts (does not persist to disk).
-    fn seq_with_accounts(entries: &[(u32, &str, AuthKind)]) -> SequenceFile {
-        let mut seq = SequenceFile::default();
-        for &(num, email, ref kind) in entries {
-            seq.accounts.insert(
-                num.to_string(),
-                AccountEntry {
-                    email: email.to_string(),
-                    uuid: format!("uuid-{num}"),
-                    added: sequence::now_utc(),
-                    auth_kind: kind.clone(),
-                },
-            );
-            seq.sequence.push(num);
-        }
-        seq.active_account_number = entries.first().map(|&(num, _, _)| num);
-        seq.last_updated = sequence::now_utc();
-        seq
+    #[test]
+    fn test_doctor_function_returns_ok() {
+        let _env = TestEnv::new();
+        // doctor() should always return Ok regardless of issues found
+        let result = doctor();
+        assert!(result.is_ok(), "doctor() should return Ok even with no accounts");
     }
 
+    // ── Tests: notes ──────────────────────────────────────────────────────────
+
     #[test]
-    fn test_refresh_all_skips_token_accounts() {
-        let _env = TestEnv::new();
+    fn test_core_note_sets_and_clears() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
 
-        let seq = seq_with_accounts(&[(1, "token@test.com", AuthKind::Token)]);
-        sequence::save(&seq).unwrap();
+        core_note(2, "  personal hobby project ").unwrap();
+        let seq = sequence::load().unwrap();
+        assert_eq!(seq.accounts["2"].notes.as_deref(), Some("personal hobby project"));
+        assert_eq!(account_infos(&seq)[1].notes.as_deref(), Some("personal hobby project"));
 
-        // No credentials written — token accounts should be skipped without error.
-        let result = refresh_all(&seq);
-        assert!(result.is_ok(), "expected Ok for all-token seq, got: {:?}", result);
+        let msg = core_note(2, "").unwrap();
+        assert!(msg.starts_with("Cleared"), "unexpected: {msg}");
+        assert_eq!(sequence::load().unwrap().accounts["2"].notes, None);
     }
 
     #[test]
-    fn test_refresh_all_skips_healthy_oauth() {
-        let _env = TestEnv::new();
+    fn test_core_note_unknown_account_is_error() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        assert!(core_note(9, "x").is_err());
+    }
 
-        let expires_ms = chrono::Utc::now().timestamp_millis() + 30 * 86400 * 1000_i64; // 30 days
-        let creds = make_oauth_creds_with_expiry("healthy", expires_ms);
+    // ── Tests: renumber ───────────────────────────────────────────────────────
 
-        let mut seq = seq_with_accounts(&[(1, "healthy@test.com", AuthKind::Oauth)]);
-        seq.active_account_number = Some(1);
+    #[test]
+    fn test_core_renumber_compacts_numbers_and_moves_backups() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        // Account 2 becomes 5, leaving a gap; rotation order is 5, 1.
+        let mut seq = sequence::load().unwrap();
+        let entry = seq.accounts.remove("2").unwrap();
+        seq.accounts.insert("5".into(), entry);
+        seq.sequence = vec![5, 1];
+        seq.previous_account_number = Some(5);
+        seq.aliases.insert("work".into(), 5);
         sequence::save(&seq).unwrap();
+        let creds2 = credentials::read_backup(2, "acct2@test.com").unwrap();
+        credentials::write_backup(5, "acct2@test.com", &creds2).unwrap();
+        fs::rename(config_backup_path(2, "acct2@test.com"), config_backup_path(5, "acct2@test.com")).unwrap();
+        credentials::delete_backup(2, "acct2@test.com").unwrap();
+        credentials::write_backup(1, "acct1@test.com", &make_oauth_creds("acct1")).unwrap();
+
+        let preview = core_renumber(true).unwrap();
+        assert_eq!(preview.len(), 2);
+        assert!(credentials::read_backup(5, "acct2@test.com").is_ok(), "dry run must not move");
+
+        let changes = core_renumber(false).unwrap();
+        assert_eq!(changes, preview);
+        let seq = sequence::load().unwrap();
+        assert_eq!(seq.sequence, vec![1, 2]);
+        assert_eq!(seq.accounts["1"].email, "acct2@test.com");
+        assert_eq!(seq.accounts["2"].email, "acct1@test.com");
+        assert_eq!(seq.active_account_number, Some(2));
+        assert_eq!(seq.previous_account_number, Some(1));
+        assert_eq!(seq.aliases["work"], 1);
+        assert_eq!(credentials::read_backup(1, "acct2@test.com").unwrap(), creds2);
+        assert!(config_backup_path(1, "acct2@test.com").exists());
+        assert!(credentials::read_backup(5, "acct2@test.com").is_err());
+        assert!(!config_backup_path(5, "acct2@test.com").exists());
+        assert!(credentials::read_backup(2, "acct1@test.com").is_ok());
+        assert!(credentials::read_backup(1, "acct1@test.com").is_err());
+
+        assert!(core_renumber(false).unwrap().is_empty());
+    }
 
-        // Write as live credentials (account 1 is active)
+    // ── Tests: migrate ────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_parse_legacy_accounts_by_version() {
+        let v1 = serde_json::json!({ "accounts": [{ "email": "a@x.com", "token": "t1" }, { "email": "b@x.com" }] });
+        let parsed = parse_legacy_accounts(&v1).unwrap();
+        assert_eq!(parsed[0], Ok(LegacyAccount { email: "a@x.com".into(), token: "t1".into() }));
+        assert!(parsed[1].is_err());
+
+        let v2 = serde_json::json!({ "version": 2, "accounts": { "1": { "label": "c@x.com", "accessToken": "t3" } } });
+        assert_eq!(parse_legacy_accounts(&v2).unwrap()[0].as_ref().unwrap().email, "c@x.com");
+
+        assert!(parse_legacy_accounts(&serde_json::json!({ "version": 9, "accounts": [] })).is_err());
+        assert!(parse_legacy_accounts(&serde_json::json!({ "users": [] })).is_err());
+    }
+
+    #[test]
+    fn test_core_migrate_backend_stops_without_losing_backups() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let file = credentials::CredentialBackend::File;
+        assert!(core_migrate_backend(file, file, |_, _, _| {}).is_err());
+
+        std::env::set_var("CCSWITCH_TEST_BIN_SECRET_TOOL", env.dir.path().join("missing"));
+        let mut seen = Vec::new();
+        let keyring = credentials::CredentialBackend::SecretService;
+        let err = core_migrate_backend(file, keyring, |n, _, moved| seen.push((n, moved))).unwrap_err();
+        // Account 1 has no backup yet; account 2's write fails and it stays put.
+        assert_eq!(seen, [(1, false)]);
+        assert!(err.to_string().contains("Account 2"), "{err}");
+        assert!(credentials::read_backup(2, "acct2@test.com").is_ok());
+    }
+
+    #[test]
+    fn test_core_migrate_imports_skips_and_fails() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let source = env.dir.path().join(".ccswitch.json");
         fs::write(
-            _env.dir.path().join(".credentials.json"),
-            &creds,
+            &source,
+            serde_json::json!({ "version": 1, "accounts": [
+                { "email": "acct1@test.com", "token": "sk-ant-oat01-dup" },
+                { "email": "new@test.com", "token": "sk-ant-oat01-new" },
+                { "email": "broken@test.com" }
+            ] })
+            .to_string(),
         )
         .unwrap();
 
-        let result = refresh_all(&seq);
-        assert!(result.is_ok(), "healthy account should be skipped, got: {:?}", result);
+        let summary = core_migrate(&source).unwrap();
+        assert_eq!(summary.imported, vec!["new@test.com"]);
+        assert_eq!(summary.skipped, vec!["acct1@test.com"]);
+        assert_eq!(summary.failed.len(), 1);
+
+        let seq = sequence::load().unwrap();
+        assert_eq!(seq.sequence, vec![1, 2, 3]);
+        assert_eq!(seq.accounts["3"].auth_kind, AuthKind::Token);
+        assert_eq!(seq.active_account_number, Some(1));
+        let backup = credentials::read_backup(3, "new@test.com").unwrap();
+        assert_eq!(extract_access_token(&backup).unwrap(), "sk-ant-oat01-new");
     }
 
+    // ── Tests: aliases ────────────────────────────────────────────────────────
+
     #[test]
-    fn test_refresh_all_attempts_expired_oauth() {
-        let _env = TestEnv::new();
+    fn test_alias_set_resolve_and_unalias() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
 
-        let expires_ms = chrono::Utc::now().timestamp_millis() - 3600 * 1000_i64; // 1h ago
-        let creds = make_oauth_creds_with_expiry("expired", expires_ms);
+        core_set_alias("acct2@test.com", "work").unwrap();
+        assert_eq!(resolve_account("work").unwrap(), 2);
+        assert!(core_set_alias("1", "work").unwrap_err().to_string().contains("already used"));
+        assert!(core_set_alias("1", "42").is_err());
+        assert!(core_set_alias("1", "acct2@test.com").is_err());
+        assert!(core_set_alias("1", "two words").is_err());
 
-        let mut seq = seq_with_accounts(&[(1, "expired@test.com", AuthKind::Oauth)]);
-        seq.active_account_number = Some(1);
-        sequence::save(&seq).unwrap();
+        core_unalias("work").unwrap();
+        assert!(resolve_account("work").is_err());
+        assert!(core_unalias("work").is_err());
+    }
 
-        fs::write(
-            _env.dir.path().join(".credentials.json"),
-            &creds,
-        )
-        .unwrap();
+    #[test]
+    fn test_remove_drops_aliases() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        core_set_alias("2", "work").unwrap();
+        core_remove(2, "acct2@test.com").unwrap();
+        assert!(sequence::load().unwrap().aliases.is_empty());
+    }
 
-        // core_refresh will attempt a network call that fails in the test env.
-        // We verify that refresh_all returns an error (account was attempted, not skipped).
-        let result = refresh_all(&seq);
-        assert!(result.is_err(), "expired account should be attempted and fail in test env");
+    #[test]
+    fn test_pin_unpin_and_remove_clears_pin() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+
+        core_pin(2).unwrap();
+        assert_eq!(sequence::load().unwrap().pinned_account, Some(2));
+        assert!(account_infos(&sequence::load().unwrap())[1].pinned);
+        core_unpin().unwrap();
+        assert_eq!(sequence::load().unwrap().pinned_account, None);
+        assert!(core_unpin().is_err());
+        assert!(core_pin(9).is_err());
+
+        core_pin(2).unwrap();
+        core_remove(2, "acct2@test.com").unwrap();
+        assert_eq!(sequence::load().unwrap().pinned_account, None);
     }
 
+    #[cfg(unix)]
     #[test]
-    fn test_refresh_all_attempts_expiring_soon_oauth() {
-        let _env = TestEnv::new();
+    fn test_link_switch_relinks_and_unlink_restores_file() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let live = env.dir.path().join(".credentials.json");
 
-        let expires_ms = chrono::Utc::now().timestamp_millis() + 12 * 3600 * 1000_i64; // 12h
-        let creds = make_oauth_creds_with_expiry("expiring", expires_ms);
+        core_link(1).unwrap();
+        assert!(live.is_symlink());
+        assert!(sequence::load().unwrap().live_is_symlink);
+        // Claude Code refreshing the token writes straight into the backup.
+        let refreshed = make_oauth_creds("acct1-refreshed");
+        fs::write(&live, &refreshed).unwrap();
+        assert_eq!(credentials::read_backup_unverified(1, "acct1@test.com").unwrap(), refreshed);
 
-        let mut seq = seq_with_accounts(&[(1, "expiring@test.com", AuthKind::Oauth)]);
-        seq.active_account_number = Some(1);
-        sequence::save(&seq).unwrap();
+        core_switch(2).unwrap();
+        assert_eq!(
+            fs::read_link(&live).unwrap(),
+            credentials::backup_file_path(2, "acct2@test.com").unwrap()
+        );
+        // The refreshed backup passes its checksum again after the switch.
+        assert_eq!(credentials::read_backup(1, "acct1@test.com").unwrap(), refreshed);
 
-        fs::write(
-            _env.dir.path().join(".credentials.json"),
-            &creds,
-        )
-        .unwrap();
+        core_unlink().unwrap();
+        assert!(!live.is_symlink());
+        assert_eq!(
+            fs::read_to_string(&live).unwrap(),
+            credentials::read_backup(2, "acct2@test.com").unwrap()
+        );
+        assert!(!sequence::load().unwrap().live_is_symlink);
+        assert!(core_unlink().is_err());
+    }
 
-        // Token expires within 24h → should be attempted.
-        let result = refresh_all(&seq);
-        assert!(result.is_err(), "expiring-soon account should be attempted and fail in test env");
+    #[test]
+    fn test_link_refuses_backup_moves_and_survives_checksums() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let live = env.dir.path().join(".credentials.json");
+        core_link(1).unwrap();
+
+        // Claude Code's refresh bypasses the checksum, which link dropped.
+        let refreshed = make_oauth_creds("acct1-refreshed");
+        fs::write(&live, &refreshed).unwrap();
+        assert_eq!(credentials::read_backup(1, "acct1@test.com").unwrap(), refreshed);
+
+        for err in [
+            core_rename(1, "new@test.com").unwrap_err().to_string(),
+            core_remove(1, "acct1@test.com").unwrap_err().to_string(),
+            core_merge_duplicates(2, &[1]).unwrap_err().to_string(),
+        ] {
+            assert!(err.contains("ccswitch unlink"), "{err}");
+        }
+        // Other accounts' backups are not linked.
+        core_rename(2, "two@test.com").unwrap();
+        assert!(live.is_symlink());
+
+        core_unlink().unwrap();
+        assert_eq!(credentials::read_backup(1, "acct1@test.com").unwrap(), refreshed);
+        let backup = credentials::backup_file_path(1, "acct1@test.com").unwrap();
+        assert!(credentials::checksum_file_path(&backup).exists());
     }
 
     #[test]
-    fn test_refresh_all_mixed_accounts() {
-        let _env = TestEnv::new();
+    fn test_with_rc_env_replaces_section_and_unsets_stale_keys() {
+        let base = "# Managed by ccswitch\nunset CLAUDE_CODE_OAUTH_TOKEN\n";
+        let vars = std::collections::HashMap::from([
+            ("B".to_string(), "it's".to_string()),
+            ("A".to_string(), "1".to_string()),
+        ]);
+        let rc = with_rc_env(base, "a@test.com", &vars);
+        assert!(rc.starts_with(base));
+        assert!(rc.contains("export A='1'\nexport B='it'\\''s'\n"), "{rc}");
+
+        let vars = std::collections::HashMap::from([("A".to_string(), "2".to_string())]);
+        let rc = with_rc_env(&rc, "b@test.com", &vars);
+        assert!(rc.contains("unset B\nexport A='2'\n"), "{rc}");
+        assert_eq!(rc.matches(RC_ENV_BEGIN).count(), 1);
+
+        let rc = with_rc_env(&rc, "c@test.com", &Default::default());
+        assert!(rc.contains("unset A\n") && !rc.contains("export"), "{rc}");
+        assert_eq!(with_rc_env(&rc, "d@test.com", &Default::default()), base);
+    }
 
-        let healthy_ms = chrono::Utc::now().timestamp_millis() + 30 * 86400 * 1000_i64;
-        let expired_ms = chrono::Utc::now().timestamp_millis() - 3600 * 1000_i64;
+    #[test]
+    fn test_env_set_writes_rc_for_active_account_and_on_switch() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let rc = env.dir.path().join(".ccswitchrc");
 
-        let creds_active = make_oauth_creds_with_expiry("active-healthy", healthy_ms);
-        let creds_inactive = make_oauth_creds_with_expiry("inactive-expired", expired_ms);
+        assert!(core_env_set(1, "not a key=1").is_err());
+        assert!(core_env_set(1, "NOEQUALS").is_err());
+        for reserved in ["CLAUDE_CODE_OAUTH_TOKEN", "CLAUDE_CONFIG_DIR", "CCSWITCH_BACKUP_DIR"] {
+            let err = core_env_set(1, &format!("{reserved}=x")).unwrap_err();
+            assert!(err.to_string().contains("set by ccswitch"), "{err}");
+        }
+        let (_, changed) = core_env_set(2, "ANTHROPIC_BASE_URL=https://b.example").unwrap();
+        assert!(!changed && !rc.exists());
+        let (_, changed) = core_env_set(1, "ANTHROPIC_BASE_URL=https://a.example").unwrap();
+        assert!(changed);
+        assert!(fs::read_to_string(&rc).unwrap().contains("export ANTHROPIC_BASE_URL='https://a.example'"));
+
+        assert!(core_switch(2).unwrap().env_changed);
+        assert!(fs::read_to_string(&rc).unwrap().contains("export ANTHROPIC_BASE_URL='https://b.example'"));
+
+        core_env_unset(2, "ANTHROPIC_BASE_URL").unwrap();
+        assert!(sequence::load().unwrap().accounts["2"].env_vars.is_empty());
+        assert!(fs::read_to_string(&rc).unwrap().contains("unset ANTHROPIC_BASE_URL"));
+        assert!(core_env_unset(2, "ANTHROPIC_BASE_URL").is_err());
+    }
 
-        let mut seq = seq_with_accounts(&[
-            (1, "active@test.com", AuthKind::Oauth),
-            (2, "expired@test.com", AuthKind::Oauth),
-            (3, "token@test.com", AuthKind::Token),
-        ]);
-        seq.active_account_number = Some(1);
+    #[test]
+    fn test_dedup_merges_identical_credentials() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let creds2 = credentials::read_backup(2, "acct2@test.com").unwrap();
+        let mut seq = sequence::load().unwrap();
+        let mut dup = entry("copy@test.com", AuthKind::Oauth);
+        dup.tags = vec!["work".to_string()];
+        dup.notes = Some("shared laptop".to_string());
+        dup.priority = 5;
+        dup.env_vars.insert("ANTHROPIC_BASE_URL".into(), "https://dup.example".into());
+        dup.env_vars.insert("HTTPS_PROXY".into(), "http://proxy".into());
+        seq.accounts.insert("3".into(), dup);
+        seq.sequence.push(3);
+        seq.pinned_account = Some(3);
+        let kept = seq.accounts.get_mut("2").unwrap();
+        kept.notes = Some("main".to_string());
+        kept.env_vars.insert("ANTHROPIC_BASE_URL".into(), "https://kept.example".into());
+        seq.aliases.insert("laptop".into(), 3);
+        let schedule = sequence::ScheduleEntry { account_num: 3, cron_expr: "0 9 * * *".into() };
+        seq.schedules.push(schedule);
         sequence::save(&seq).unwrap();
+        credentials::write_backup(3, "copy@test.com", &creds2).unwrap();
 
-        // Account 1 active (healthy)
-        fs::write(_env.dir.path().join(".credentials.json"), &creds_active).unwrap();
-        // Account 2 backup (expired)
-        credentials::write_backup(2, "expired@test.com", &creds_inactive).unwrap();
+        let groups = core_find_duplicates().unwrap();
+        assert_eq!(groups, [[(2, "acct2@test.com".to_string()), (3, "copy@test.com".to_string())]]);
 
-        // Account 1 is healthy → skipped. Account 3 is token → skipped.
-        // Account 2 is expired → attempted → fails in test env.
-        let result = refresh_all(&seq);
-        assert!(result.is_err(), "one expired account should cause overall failure");
+        core_merge_duplicates(2, &[3]).unwrap();
+        let seq = sequence::load().unwrap();
+        assert_eq!(seq.sequence, [1, 2]);
+        let kept = &seq.accounts["2"];
+        assert_eq!(kept.tags, ["work"]);
+        assert_eq!(kept.notes.as_deref(), Some("main\nshared laptop"));
+        assert_eq!(kept.priority, 5);
+        assert_eq!(kept.env_vars["ANTHROPIC_BASE_URL"], "https://kept.example");
+        assert_eq!(kept.env_vars["HTTPS_PROXY"], "http://proxy");
+        assert_eq!(seq.aliases["laptop"], 2);
+        assert_eq!(seq.schedules.len(), 1);
+        assert_eq!(seq.schedules[0].account_num, 2);
+        assert_eq!(seq.pinned_account, Some(2));
+        assert!(core_find_duplicates().unwrap().is_empty());
     }
 
     #[test]
-    fn test_refresh_flag_all_with_identifier_is_error() {
-        let _env = TestEnv::new();
+    fn test_shell_setup_appends_source_line_once() {
+        let env = TestEnv::new();
+        let bashrc = env.dir.path().join(".bashrc");
+        fs::write(&bashrc, "alias ll='ls -l'").unwrap();
+
+        let (_, added) = core_shell_setup(ShellKind::Bash, Some(bashrc.clone())).unwrap();
+        let line = added.unwrap();
+        assert!(line.contains(".ccswitchrc"), "{line}");
+        let content = fs::read_to_string(&bashrc).unwrap();
+        assert!(content.starts_with("alias ll='ls -l'\n\n# Added by"), "{content}");
+        assert!(content.ends_with(&format!("{line}\n")));
+        assert!(core_shell_setup(ShellKind::Bash, Some(bashrc.clone())).unwrap().1.is_none());
+
+        let fish = env.dir.path().join("config.fish");
+        let (_, added) = core_shell_setup(ShellKind::Fish, Some(fish)).unwrap();
+        assert!(added.unwrap().contains("env.fish"));
+        let fish_env = fs::read_to_string(credentials::fish_env_path()).unwrap();
+        assert!(fish_env.contains("set -e CLAUDE_CODE_OAUTH_TOKEN"), "{fish_env}");
+        assert!(core_shell_setup(ShellKind::Powershell, Some(bashrc)).is_err());
+    }
 
-        // Set up a minimal valid sequence so we get past the empty-check
-        let seq = seq_with_accounts(&[(1, "user@test.com", AuthKind::Oauth)]);
+    #[test]
+    fn test_core_swap_exchanges_positions_only() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let mut seq = sequence::load().unwrap();
+        seq.accounts.insert("3".into(), entry("acct3@test.com", AuthKind::Oauth));
+        seq.sequence.push(3);
         sequence::save(&seq).unwrap();
 
-        let result = refresh(Some("1"), true);
-        assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("--all"));
-    }
+        let msg = core_swap(1, 3).unwrap();
+        assert!(msg.contains("Account 1 (now position 3)"), "{msg}");
+        let seq = sequence::load().unwrap();
+        assert_eq!(seq.sequence, [3, 2, 1]);
+        assert_eq!(seq.accounts["1"].email, "acct1@test.com");
+        assert_eq!(seq.active_account_number, Some(1));
 
-    // ── Tests: doctor ─────────────────────────────────────────────────────────
+        assert!(core_swap(2, 2).unwrap().contains("already at position 2"));
+        assert!(core_swap(1, 9).is_err());
+        assert_eq!(sequence::load().unwrap().sequence, [3, 2, 1]);
+    }
 
     #[test]
-    fn test_doctor_no_accounts_returns_zero_counts() {
-        let _env = TestEnv::new();
-        let seq = SequenceFile::default();
-        let report = core_doctor(&seq);
-        assert_eq!(report.issues, 0);
-        assert_eq!(report.warnings, 0);
+    fn test_core_set_active_touches_only_sequence() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let live = read_live_json(&env);
+
+        assert_eq!(core_set_active(2).unwrap(), "Marked Account 2 (acct2@test.com) as active");
+        assert_eq!(sequence::load().unwrap().active_account_number, Some(2));
+        assert_eq!(read_live_json(&env), live);
+        assert_eq!(config::current_email().as_deref(), Some("acct1@test.com"));
+        assert!(core_set_active(9).is_err());
     }
 
     #[test]
-    fn test_doctor_env_var_set_warns() {
-        let _env = TestEnv::new();
-        std::env::set_var("CLAUDE_CODE_OAUTH_TOKEN", "test-tok");
-        let seq = SequenceFile::default();
-        let report = core_doctor(&seq);
-        std::env::remove_var("CLAUDE_CODE_OAUTH_TOKEN");
-        assert_eq!(report.warnings, 1, "CLAUDE_CODE_OAUTH_TOKEN should produce 1 warning");
-        assert_eq!(report.issues, 0);
+    fn test_switch_next_starts_at_pin_without_active_account() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let mut seq = sequence::load().unwrap();
+        seq.active_account_number = None;
+        seq.pinned_account = Some(1);
+        sequence::save(&seq).unwrap();
+
+        // Without the pin, rotation would move on from the live account (1) to 2.
+        switch_next(&SwitchOptions::default(), RotationStrategy::RoundRobin).unwrap();
+        assert_eq!(config::current_email().as_deref(), Some("acct1@test.com"));
+        assert_ne!(sequence::load().unwrap().active_account_number, Some(2));
     }
 
     #[test]
-    fn test_doctor_healthy_oauth_account() {
-        let _env = TestEnv::new();
-        let expires_ms = chrono::Utc::now().timestamp_millis() + 7 * 86400 * 1000_i64;
-        let creds = make_oauth_creds_with_expiry("healthy", expires_ms);
-
-        let mut seq = seq_with_accounts(&[(1, "user@test.com", AuthKind::Oauth)]);
-        seq.active_account_number = Some(1);
-        sequence::save(&seq).unwrap();
-        fs::write(_env.dir.path().join(".credentials.json"), &creds).unwrap();
+    fn test_ambiguous_identifier_lists_candidates() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let err = switch_to("acct", &SwitchOptions::default()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "'acct' matches 2 accounts. Did you mean: 1 (acct1@test.com), 2 (acct2@test.com)?"
+        );
+        let err = remove("nobody", true).unwrap_err();
+        assert_eq!(err.to_string(), "No account found matching 'nobody'");
 
-        let report = core_doctor(&seq);
-        assert_eq!(report.issues, 0, "healthy account should produce no issues");
-        // warnings may include perm issues in test env; just check no cred issues
+        switch_to("ACCT2", &SwitchOptions::default()).unwrap();
+        assert_eq!(sequence::load().unwrap().active_account_number, Some(2));
     }
 
     #[test]
-    fn test_doctor_missing_credentials_is_issue() {
-        let _env = TestEnv::new();
+    fn test_rotation_neighbor_wraps_both_ways() {
+        let mut seq = seq_with_accounts(&[
+            (1, "a@test.com", AuthKind::Oauth),
+            (2, "b@test.com", AuthKind::Oauth),
+            (3, "c@test.com", AuthKind::Oauth),
+        ]);
+        seq.sequence = vec![3, 1, 2];
+        assert_eq!(rotation_neighbor(&seq, 1, false), 2);
+        assert_eq!(rotation_neighbor(&seq, 2, false), 3);
+        assert_eq!(rotation_neighbor(&seq, 1, true), 3);
+        assert_eq!(rotation_neighbor(&seq, 3, true), 2);
+    }
 
-        let mut seq = seq_with_accounts(&[(1, "ghost@test.com", AuthKind::Oauth)]);
-        seq.active_account_number = Some(1);
-        // No credentials file written → read_live() will fail
+    // ── Tests: account_infos ──────────────────────────────────────────────────
 
-        let report = core_doctor(&seq);
-        assert!(report.issues >= 1, "missing credentials should produce at least 1 issue");
+    #[test]
+    fn test_sorted_sequence_orders_and_keeps_ties_stable() {
+        let mut seq = seq_with_accounts(&[
+            (3, "carol@test.com", AuthKind::Oauth),
+            (1, "Bob@test.com", AuthKind::Oauth),
+            (2, "alice@test.com", AuthKind::Token),
+        ]);
+        let mut set = |num: u32, count: u32, used: Option<&str>, added: &str| {
+            let e = seq.accounts.get_mut(&num.to_string()).unwrap();
+            e.switch_count = count;
+            e.last_used = used.map(String::from);
+            e.added = added.to_string();
+        };
+        set(3, 2, Some("2026-01-02T00:00:00Z"), "2025-03-01T00:00:00Z");
+        set(1, 5, None, "2025-01-01T00:00:00Z");
+        set(2, 2, Some("2026-01-05T00:00:00Z"), "2025-02-01T00:00:00Z");
+
+        assert_eq!(sorted_sequence(&seq, SortOrder::Number), [1, 2, 3]);
+        assert_eq!(sorted_sequence(&seq, SortOrder::Email), [2, 1, 3]);
+        assert_eq!(sorted_sequence(&seq, SortOrder::LastUsed), [2, 3, 1]);
+        // 3 and 2 tie on switch count and keep their rotation order.
+        assert_eq!(sorted_sequence(&seq, SortOrder::SwitchCount), [1, 3, 2]);
+        assert_eq!(sorted_sequence(&seq, SortOrder::Added), [1, 2, 3]);
+        assert_eq!(seq.sequence, [3, 1, 2]);
     }
 
     #[test]
-    fn test_doctor_expired_oauth_is_issue() {
-        let _env = TestEnv::new();
-        let expired_ms = chrono::Utc::now().timestamp_millis() - 3600 * 1000_i64;
-        let creds = make_oauth_creds_with_expiry("expired", expired_ms);
+    fn test_switch_records_expiry_in_sequence() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        core_switch(2).unwrap();
+        let seq = sequence::load().unwrap();
+        for num in ["1", "2"] {
+            let at = seq.accounts[num].expires_at.as_deref().unwrap();
+            assert!(chrono::DateTime::parse_from_rfc3339(at).is_ok(), "{}", at);
+        }
+    }
 
-        let mut seq = seq_with_accounts(&[(1, "expired@test.com", AuthKind::Oauth)]);
-        seq.active_account_number = Some(1);
-        sequence::save(&seq).unwrap();
-        fs::write(_env.dir.path().join(".credentials.json"), &creds).unwrap();
+    #[test]
+    fn test_account_infos_order_active_and_alias() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let mut seq = sequence::load().unwrap();
+        seq.aliases.insert("work".into(), 2);
+
+        let infos = account_infos(&seq);
+        assert_eq!(infos.len(), 2);
+        assert_eq!(infos[0].number, 1);
+        assert!(infos[0].active);
+        assert!(infos[0].expires_at.is_some());
+        assert_eq!(infos[1].email, "acct2@test.com");
+        assert_eq!(infos[1].alias.as_deref(), Some("work"));
+        assert!(!infos[1].active);
+    }
 
-        let report = core_doctor(&seq);
-        assert!(report.issues >= 1, "expired token should produce at least 1 issue");
+    #[test]
+    fn test_porcelain_lines_are_tab_separated() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let infos = account_infos(&sequence::load().unwrap());
+        assert_eq!(
+            porcelain_lines(&infos),
+            ["1\tacct1@test.com\toauth\t1", "2\tacct2@test.com\toauth\t0"]
+        );
     }
 
     #[test]
-    fn test_doctor_expiring_soon_is_warning() {
-        let _env = TestEnv::new();
-        let soon_ms = chrono::Utc::now().timestamp_millis() + 12 * 3600 * 1000_i64; // 12 h
-        let creds = make_oauth_creds_with_expiry("soon", soon_ms);
+    fn test_table_lines_shorten_only_the_email() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let infos = account_infos(&sequence::load().unwrap());
 
-        let mut seq = seq_with_accounts(&[(1, "soon@test.com", AuthKind::Oauth)]);
-        seq.active_account_number = Some(1);
-        sequence::save(&seq).unwrap();
-        fs::write(_env.dir.path().join(".credentials.json"), &creds).unwrap();
+        let lines = table_lines(&infos, None);
+        assert_eq!(lines.len(), 6);
+        assert!(lines[1].contains("Email") && lines[3].contains("▶ 1 | acct1@test.com |"));
 
-        let report = core_doctor(&seq);
-        assert_eq!(report.issues, 0, "expiring-soon token should not be an issue");
-        // The warning count may include perm issues; verify at least 1 warning
-        assert!(report.warnings >= 1, "expiring-soon token should produce at least 1 warning");
+        let narrow = table_lines(&infos, Some(lines[0].chars().count() - 5));
+        assert!(narrow.iter().all(|l| l.chars().count() <= lines[0].chars().count() - 5));
+        assert!(narrow[4].contains("acct2@te") && narrow[4].contains('…'), "{narrow:?}");
+        assert!(narrow[4].contains("| oauth |"), "{narrow:?}");
     }
 
     #[test]
-    fn test_doctor_token_account_no_expiry_issue() {
-        let _env = TestEnv::new();
-        let token_creds = make_token_backup("sk-ant-tok01-test");
-
-        let mut seq = seq_with_accounts(&[(1, "tok@test.com", AuthKind::Token)]);
-        seq.active_account_number = Some(1);
-        sequence::save(&seq).unwrap();
-        // Write as live credentials (active account)
-        fs::write(_env.dir.path().join(".credentials.json"), &token_creds).unwrap();
+    fn test_account_infos_render_through_template() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let seq = sequence::load().unwrap();
 
-        let report = core_doctor(&seq);
-        // Token accounts never produce expiry issues
-        assert_eq!(report.issues, 0, "token account with readable creds should not be an issue");
+        let data = serde_json::json!({ "accounts": account_infos(&seq) });
+        let out = crate::template::render(
+            "{{#each accounts}}{{number}}:{{email}}:{{auth_kind}}\n{{/each}}",
+            &data,
+        )
+        .unwrap();
+        assert_eq!(out, "1:acct1@test.com:oauth\n2:acct2@test.com:oauth\n");
     }
 
     #[test]
-    fn test_doctor_sequence_integrity_missing_entry() {
-        let _env = TestEnv::new();
+    fn test_core_list_json_fields() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
 
-        // Sequence lists account 99 which has no entry in accounts map
-        let mut seq = SequenceFile::default();
-        seq.accounts.insert(
-            "1".into(),
-            AccountEntry {
-                email: "user@test.com".to_string(),
-                uuid: "uuid-1".to_string(),
-                added: sequence::now_utc(),
-                auth_kind: AuthKind::Oauth,
-            },
-        );
-        seq.sequence = vec![1, 99]; // 99 has no entry
-        seq.active_account_number = Some(1);
-        seq.last_updated = sequence::now_utc();
+        let json = serde_json::to_value(core_list(None).unwrap()).unwrap();
+        let arr = json.as_array().unwrap();
+        assert_eq!(arr.len(), 2);
+        assert_eq!(arr[0]["number"], 1);
+        assert_eq!(arr[0]["email"], "acct1@test.com");
+        assert_eq!(arr[0]["auth_kind"], "oauth");
+        assert_eq!(arr[0]["active"], true);
+        assert!(arr[0]["added"].is_string());
+        assert_eq!(arr[1]["active"], false);
+    }
 
-        let report = core_doctor(&seq);
-        assert!(report.issues >= 1, "dangling sequence entry should be an issue");
+    // ── Tests: status ─────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_core_status_matches_status_report() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+
+        let report = core_status().unwrap();
+        assert_eq!(report.active.map(|a| a.number), Some(1));
     }
 
     #[test]
-    fn test_doctor_account_not_in_sequence_is_warning() {
-        let _env = TestEnv::new();
+    fn test_status_report_active_oauth() {
+        let env = TestEnv::new();
+        setup_two_oauth(&env);
+        let seq = sequence::load().unwrap();
 
-        // Account 2 is in accounts map but not in sequence list
-        let mut seq = SequenceFile::default();
-        seq.accounts.insert(
-            "1".into(),
-            AccountEntry {
-                email: "a@test.com".to_string(),
-                uuid: "uuid-1".to_string(),
-                added: sequence::now_utc(),
-                auth_kind: AuthKind::Oauth,
-            },
-        );
-        seq.accounts.insert(
-            "2".into(),
-            AccountEntry {
-                email: "b@test.com".to_string(),
-                uuid: "uuid-2".to_string(),
-                added: sequence::now_utc(),
-                auth_kind: AuthKind::Token,
-            },
-        );
-        seq.sequence = vec![1]; // 2 is missing from sequence
-        seq.active_account_number = Some(1);
-        seq.last_updated = sequence::now_utc();
+        let report = status_report(&seq);
+        let active = report.active.expect("active account");
+        assert_eq!(active.number, 1);
+        assert_eq!(active.email, "acct1@test.com");
+        assert!(active.expires_at.is_some());
 
-        let report = core_doctor(&seq);
-        assert!(report.warnings >= 1, "account missing from sequence should be a warning");
+        let json = serde_json::to_value(status_report(&seq)).unwrap();
+        assert_eq!(json["active"]["auth_kind"], "oauth");
+        assert_eq!(json["env_token_override"], false);
     }
 
     #[test]
-    fn test_doctor_function_returns_ok() {
+    fn test_status_report_not_logged_in() {
         let _env = TestEnv::new();
-        // doctor() should always return Ok regardless of issues found
-        let result = doctor();
-        assert!(result.is_ok(), "doctor() should return Ok even with no accounts");
+        let report = status_report(&SequenceFile::default());
+        assert!(report.active.is_none());
+        let json = serde_json::to_value(&report).unwrap();
+        assert!(json["active"].is_null());
     }
 
-    // ── Tests: core_edit_account ──────────────────────────────────────────────
+    // ── Tests: core_rename ────────────────────────────────────────────────────
 
     fn setup_single_oauth(env: &TestEnv, num: u32, email: &str) {
         let creds = make_oauth_creds(email);
@@ -2287,7 +7658,7 @@ mod tests {
         let env = TestEnv::new();
         setup_single_oauth(&env, 1, "old@test.com");
 
-        let msg = core_edit_account(1, "old@test.com", "new@test.com").unwrap();
+        let msg = core_rename(1, "new@test.com").unwrap();
         assert!(msg.contains("old@test.com"), "unexpected: {msg}");
         assert!(msg.contains("new@test.com"), "unexpected: {msg}");
 
@@ -2321,7 +7692,7 @@ mod tests {
         let env = TestEnv::new();
         setup_single_oauth(&env, 1, "active@test.com");
 
-        core_edit_account(1, "active@test.com", "renamed@test.com").unwrap();
+        core_rename(1, "renamed@test.com").unwrap();
 
         let seq = sequence::load().unwrap();
         assert_eq!(seq.active_account_number, Some(1));
@@ -2340,13 +7711,83 @@ mod tests {
         seq.last_updated = sequence::now_utc();
         sequence::save(&seq).unwrap();
 
-        let err = core_edit_account(1, "ghost@test.com", "new@test.com").unwrap_err();
+        let err = core_rename(1, "new@test.com").unwrap_err();
         assert!(
             err.to_string().contains("Cannot read credentials backup"),
             "unexpected: {err}"
         );
     }
 
+    #[test]
+    fn test_core_rename_rejects_empty_label() {
+        let env = TestEnv::new();
+        setup_single_oauth(&env, 1, "old@test.com");
+
+        let err = core_rename(1, "   ").unwrap_err();
+        assert!(err.to_string().contains("must not be empty"), "unexpected: {err}");
+    }
+
+    #[test]
+    fn test_core_rename_rolls_back_on_failure() {
+        let env = TestEnv::new();
+        setup_single_oauth(&env, 1, "old@test.com");
+
+        // A directory in the way makes the config backup write fail mid-rename.
+        fs::create_dir_all(config_backup_path(1, "new@test.com")).unwrap();
+
+        let err = core_rename(1, "new@test.com").unwrap_err();
+        assert!(err.to_string().contains("no changes were kept"), "unexpected: {err}");
+
+        let seq = sequence::load().unwrap();
+        assert_eq!(seq.accounts["1"].email, "old@test.com");
+        assert!(credentials::read_backup(1, "old@test.com").is_ok());
+        assert!(credentials::read_backup(1, "new@test.com").is_err());
+        assert!(read_config_backup(1, "old@test.com").is_ok());
+    }
+
+    // ── Tests: clone ──────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_core_clone_copies_backups_under_new_number() {
+        let env = TestEnv::new();
+        setup_single_oauth(&env, 1, "me@test.com");
+
+        let msg = core_clone(1, None).unwrap();
+        assert_eq!(msg, "Cloned Account 1 (me@test.com) as Account 2 (me@test.com-copy)");
+        let seq = sequence::load().unwrap();
+        assert_eq!(seq.sequence, [1, 2]);
+        assert_eq!(seq.active_account_number, Some(1));
+        let copy = &seq.accounts["2"];
+        assert_eq!(copy.auth_kind, AuthKind::Oauth);
+        assert_eq!(copy.switch_count, 0);
+        assert_eq!(copy.created_by, Some(created_by()));
+        assert_eq!(
+            credentials::read_backup(2, "me@test.com-copy").unwrap(),
+            credentials::read_backup(1, "me@test.com").unwrap()
+        );
+        assert!(read_config_backup(2, "me@test.com-copy").is_ok());
+
+        let err = core_clone(1, None).unwrap_err();
+        assert!(err.to_string().contains("already used by Account 2"), "unexpected: {err}");
+    }
+
+    #[test]
+    fn test_core_clone_token_account() {
+        let _env = TestEnv::new();
+        let mut seq = SequenceFile::default();
+        seq.accounts.insert("1".into(), entry("tokenuser", AuthKind::Token));
+        seq.sequence = vec![1];
+        sequence::save(&seq).unwrap();
+        credentials::write_backup(1, "tokenuser", &make_token_backup("sk-ant-oat01-x")).unwrap();
+
+        core_clone(1, Some("project-b")).unwrap();
+        let seq = sequence::load().unwrap();
+        assert_eq!(seq.accounts["2"].auth_kind, AuthKind::Token);
+        assert!(!seq.accounts["2"].has_config_backup);
+        let creds = credentials::read_backup(2, "project-b").unwrap();
+        assert_eq!(extract_access_token(&creds).unwrap(), "sk-ant-oat01-x");
+    }
+
     #[test]
     fn test_edit_account_conflict_with_existing() {
         let env = TestEnv::new();
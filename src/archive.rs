@@ -0,0 +1,632 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{Read, Write},
+    path::{Component, Path},
+};
+
+use crate::{
+    accounts, config, credentials, platform, profiles,
+    sequence::{self, AuthKind, SequenceFile},
+    transfer,
+};
+
+// ── Portable backup archives (`ccswitch backup` / `ccswitch restore`) ─────────
+//
+// A gzip-compressed tar holding a plaintext `manifest.json`, every file of the
+// profile directory as `data/<path>.enc`, and `credentials.json.enc` with each
+// account's credential blob. Credentials are read through the configured
+// backend, so keychain and keyring entries are included and an archive made on
+// one machine restores on another whatever their backends. Every `.enc` entry
+// is encrypted on its own with the export passphrase scheme.
+
+const MANIFEST: &str = "manifest.json";
+const CREDENTIALS_SIDECAR: &str = "credentials.json.enc";
+const DATA_PREFIX: &str = "data/";
+const ENC_SUFFIX: &str = ".enc";
+const ARCHIVE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct Manifest {
+    pub version: u32,
+    pub created_at: String,
+    pub profile: String,
+    pub platform: String,
+    pub credential_backend: String,
+    pub accounts: usize,
+    /// Files from the profile directory, relative to it.
+    pub files: Vec<String>,
+}
+
+/// A decrypted archive.
+pub(crate) struct Archive {
+    pub manifest: Manifest,
+    /// Profile directory files keyed by relative path.
+    pub files: BTreeMap<String, Vec<u8>>,
+    /// Credential blobs keyed by account number.
+    pub credentials: BTreeMap<u32, String>,
+}
+
+/// Relative paths of files under `dir`, skipping the credentials directory
+/// (covered by the sidecar) and the lock file.
+fn collect_files(base: &Path, dir: &Path, out: &mut Vec<String>) -> Result<()> {
+    for entry in fs::read_dir(dir).with_context(|| format!("Cannot read {}", dir.display()))? {
+        let path = entry?.path();
+        let rel = path
+            .strip_prefix(base)?
+            .to_string_lossy()
+            .replace(std::path::MAIN_SEPARATOR, "/");
+        if rel == "credentials" || rel == ".ccswitch.lock" {
+            continue;
+        }
+        if path.is_dir() {
+            collect_files(base, &path, out)?;
+        } else {
+            out.push(rel);
+        }
+    }
+    Ok(())
+}
+
+/// The credential blob to archive for one account. The live credentials are
+/// fresher than the backup for the active OAuth account.
+fn account_credentials(seq: &SequenceFile, num: u32) -> Result<String> {
+    let entry = &seq.accounts[&num.to_string()];
+    if seq.active_account_number == Some(num) && entry.auth_kind == AuthKind::Oauth {
+        if let Ok(live) = credentials::read_live() {
+            return Ok(live);
+        }
+    }
+    credentials::read_backup(num, &entry.email).with_context(|| {
+        format!(
+            "Cannot read credentials for Account {num} ({})",
+            entry.email
+        )
+    })
+}
+
+/// One file in the tar archive.
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    path: String,
+    data: Vec<u8>,
+}
+
+/// Write `entries` as regular files with mode 0600. Long paths get GNU
+/// long-name headers from `tar`.
+fn write_tar<W: Write>(w: W, entries: &[Entry]) -> Result<W> {
+    let mtime = chrono::Utc::now().timestamp().max(0) as u64;
+    let mut builder = tar::Builder::new(w);
+    for entry in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_mode(0o600);
+        header.set_mtime(mtime);
+        header.set_size(entry.data.len() as u64);
+        builder
+            .append_data(&mut header, &entry.path, entry.data.as_slice())
+            .with_context(|| format!("Cannot add {} to the archive", entry.path))?;
+    }
+    Ok(builder.into_inner()?)
+}
+
+/// Read every regular file in the archive; directories are skipped. Links,
+/// devices and paths that are absolute or contain `..` are refused.
+fn read_tar<R: Read>(r: R) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let mut archive = tar::Archive::new(r);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+        check_relative(&path)?;
+        match entry.header().entry_type() {
+            tar::EntryType::Regular => {
+                let mut data = Vec::new();
+                entry.read_to_end(&mut data)?;
+                entries.push(Entry { path, data });
+            }
+            tar::EntryType::Directory => {}
+            other => bail!("Unsupported tar entry type {other:?} for {path}"),
+        }
+    }
+    Ok(entries)
+}
+
+fn encrypt_entry(path: String, plaintext: &[u8], passphrase: &str) -> Result<Entry> {
+    Ok(Entry {
+        path,
+        data: transfer::encrypt(plaintext, passphrase)?.into_bytes(),
+    })
+}
+
+pub(crate) fn core_backup(output: &Path, passphrase: &str) -> Result<Manifest> {
+    let seq = sequence::load()?;
+    if seq.accounts.is_empty() {
+        bail!("No accounts managed yet. Run `ccswitch add` first.");
+    }
+
+    let base = sequence::backup_dir();
+    let mut files = Vec::new();
+    collect_files(&base, &base, &mut files)?;
+    files.sort();
+
+    let mut creds = BTreeMap::new();
+    for num in seq.accounts.keys().filter_map(|k| k.parse::<u32>().ok()) {
+        creds.insert(num, account_credentials(&seq, num)?);
+    }
+
+    let manifest = Manifest {
+        version: ARCHIVE_VERSION,
+        created_at: sequence::now_utc(),
+        profile: profiles::current(),
+        platform: platform::detect().to_string(),
+        credential_backend: credentials::backend().to_string(),
+        accounts: seq.accounts.len(),
+        files: files.clone(),
+    };
+
+    let mut entries = vec![Entry {
+        path: MANIFEST.to_string(),
+        data: serde_json::to_vec_pretty(&manifest)?,
+    }];
+    for rel in &files {
+        let data = fs::read(base.join(rel)).with_context(|| format!("Cannot read {rel}"))?;
+        entries.push(encrypt_entry(
+            format!("{DATA_PREFIX}{rel}{ENC_SUFFIX}"),
+            &data,
+            passphrase,
+        )?);
+    }
+    entries.push(encrypt_entry(
+        CREDENTIALS_SIDECAR.to_string(),
+        &serde_json::to_vec(&creds)?,
+        passphrase,
+    )?);
+
+    let mut opts = fs::OpenOptions::new();
+    opts.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
+    }
+    let file = opts
+        .open(output)
+        .with_context(|| format!("Cannot create {}", output.display()))?;
+    let gz = write_tar(GzEncoder::new(file, Compression::default()), &entries)?;
+    gz.finish()?.flush()?;
+
+    Ok(manifest)
+}
+
+fn decrypt_entry(entry: &Entry, passphrase: &str) -> Result<Vec<u8>> {
+    let encoded = std::str::from_utf8(&entry.data)
+        .with_context(|| format!("{} is not an encrypted entry", entry.path))?;
+    transfer::decrypt(encoded, passphrase)
+}
+
+/// Archive paths become filesystem paths on restore, so only plain relative
+/// components are allowed.
+fn check_relative(rel: &str) -> Result<()> {
+    let path = Path::new(rel);
+    if rel.is_empty() || !path.components().all(|c| matches!(c, Component::Normal(_))) {
+        bail!("Refusing to restore unsafe path '{rel}' from archive");
+    }
+    Ok(())
+}
+
+pub(crate) fn read_archive(input: &Path, passphrase: &str) -> Result<Archive> {
+    let file = fs::File::open(input).with_context(|| format!("Cannot open {}", input.display()))?;
+    let entries = read_tar(GzDecoder::new(file))
+        .with_context(|| format!("{} is not a ccswitch backup archive", input.display()))?;
+
+    let manifest: Manifest = entries
+        .iter()
+        .find(|e| e.path == MANIFEST)
+        .map(|e| serde_json::from_slice(&e.data))
+        .context("Archive has no manifest.json")?
+        .context("Invalid manifest.json in archive")?;
+    if manifest.version > ARCHIVE_VERSION {
+        bail!(
+            "Archive format version {} is newer than this ccswitch supports ({ARCHIVE_VERSION}). Update ccswitch.",
+            manifest.version
+        );
+    }
+
+    let mut files = BTreeMap::new();
+    let mut creds = None;
+    for entry in &entries {
+        if entry.path == MANIFEST {
+            continue;
+        }
+        if entry.path == CREDENTIALS_SIDECAR {
+            creds = Some(
+                serde_json::from_slice(&decrypt_entry(entry, passphrase)?)
+                    .context("Invalid credentials in archive")?,
+            );
+            continue;
+        }
+        let rel = entry
+            .path
+            .strip_prefix(DATA_PREFIX)
+            .and_then(|p| p.strip_suffix(ENC_SUFFIX))
+            .with_context(|| format!("Unexpected entry '{}' in archive", entry.path))?;
+        check_relative(rel)?;
+        files.insert(rel.to_string(), decrypt_entry(entry, passphrase)?);
+    }
+
+    if !files.contains_key("sequence.json") {
+        bail!("Archive has no sequence.json");
+    }
+    Ok(Archive {
+        manifest,
+        files,
+        credentials: creds.context("Archive has no credentials")?,
+    })
+}
+
+fn archived_sequence(archive: &Archive) -> Result<SequenceFile> {
+    serde_json::from_slice(&archive.files["sequence.json"])
+        .context("Invalid sequence.json in archive")
+}
+
+/// The archive's credentials paired with the accounts they belong to, failing
+/// unless every archived account has credentials and every credential has an
+/// account.
+fn archived_credentials<'a>(
+    archive: &'a Archive,
+    seq: &SequenceFile,
+) -> Result<Vec<(u32, String, &'a str)>> {
+    let mut out = Vec::new();
+    for (num, blob) in &archive.credentials {
+        let entry = seq
+            .accounts
+            .get(&num.to_string())
+            .with_context(|| format!("Archive has credentials for unknown Account {num}"))?;
+        out.push((*num, entry.email.clone(), blob.as_str()));
+    }
+    for num in seq.accounts.keys().filter_map(|k| k.parse::<u32>().ok()) {
+        if !archive.credentials.contains_key(&num) {
+            bail!("Archive has no credentials for Account {num}");
+        }
+    }
+    Ok(out)
+}
+
+/// Replace the current profile with the archive's accounts. The active account
+/// follows whoever is logged in now, so the next switch snapshots the right slot.
+/// The whole archive is checked before any current backup is deleted.
+pub(crate) fn core_replace(archive: &Archive) -> Result<usize> {
+    let mut seq = archived_sequence(archive)?;
+    let creds = archived_credentials(archive, &seq)?;
+    sequence::setup_dirs()?;
+    let _lock = sequence::acquire_lock()?;
+    let current = sequence::load()?;
+
+    for (num, entry) in &current.accounts {
+        let num: u32 = num.parse().unwrap_or_default();
+        credentials::delete_backup(num, &entry.email)?;
+        let _ = fs::remove_file(accounts::config_backup_path(num, &entry.email));
+    }
+
+    let base = sequence::backup_dir();
+    for (rel, data) in archive
+        .files
+        .iter()
+        .filter(|(rel, _)| *rel != "sequence.json")
+    {
+        let path = base.join(rel);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, data).with_context(|| format!("Cannot write {}", path.display()))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+        }
+    }
+
+    for (num, email, blob) in creds {
+        credentials::write_backup(num, &email, blob)?;
+    }
+
+    let previous_active_email = current
+        .active_account_number
+        .and_then(|n| current.accounts.get(&n.to_string()))
+        .map(|e| e.email.clone());
+    seq.active_account_number = config::current_email()
+        .or(previous_active_email)
+        .and_then(|email| seq.find_by_email(&email));
+    seq.previous_account_number = None;
+    seq.last_updated = sequence::now_utc();
+    sequence::save(&seq)?;
+    Ok(seq.accounts.len())
+}
+
+/// Accounts from the archive in the shape `import` understands.
+fn archived_exports(archive: &Archive) -> Result<Vec<transfer::AccountExport>> {
+    let seq = archived_sequence(archive)?;
+    let mut exports = Vec::new();
+    for &num in &seq.sequence {
+        let Some(entry) = seq.accounts.get(&num.to_string()) else {
+            continue;
+        };
+        let credentials = archive
+            .credentials
+            .get(&num)
+            .cloned()
+            .with_context(|| format!("Archive has no credentials for Account {num}"))?;
+        let config_rel = accounts::config_backup_path(num, &entry.email)
+            .strip_prefix(sequence::backup_dir())?
+            .to_string_lossy()
+            .replace(std::path::MAIN_SEPARATOR, "/");
+        let config = archive
+            .files
+            .get(&config_rel)
+            .map(|b| String::from_utf8_lossy(b).into_owned())
+            .unwrap_or_else(|| "{}".to_string());
+        exports.push(transfer::AccountExport {
+            num,
+            email: entry.email.clone(),
+            uuid: entry.uuid.clone(),
+            added: entry.added.clone(),
+            auth_kind: entry.auth_kind.clone(),
+            priority: entry.priority,
+            notes: entry.notes.clone(),
+            tags: entry.tags.clone(),
+            credentials,
+            config,
+            has_config_backup: entry.has_config_backup,
+            created_by: entry.created_by.clone(),
+        });
+    }
+    Ok(exports)
+}
+
+// ── Commands ──────────────────────────────────────────────────────────────────
+
+fn passphrase_from_stdin() -> Result<String> {
+    let mut line = String::new();
+    std::io::stdin()
+        .read_line(&mut line)
+        .context("Failed to read passphrase from stdin")?;
+    let passphrase = line.trim_end_matches(['\r', '\n']).to_string();
+    if passphrase.is_empty() {
+        bail!("Passphrase must not be empty");
+    }
+    Ok(passphrase)
+}
+
+pub fn backup(output: &Path, passphrase_stdin: bool) -> Result<()> {
+    let passphrase = if passphrase_stdin {
+        passphrase_from_stdin()?
+    } else {
+        println!();
+        transfer::prompt_new_passphrase()?
+    };
+    let manifest = core_backup(output, &passphrase)?;
+    println!(
+        "\n  {} Backed up {} account{} ({} files) to {}\n",
+        "✓".green().bold(),
+        manifest.accounts,
+        if manifest.accounts == 1 { "" } else { "s" },
+        manifest.files.len() + 1,
+        output.display()
+    );
+    Ok(())
+}
+
+pub fn restore(input: &Path, passphrase_stdin: bool) -> Result<()> {
+    let passphrase = if passphrase_stdin {
+        passphrase_from_stdin()?
+    } else {
+        println!();
+        rpassword::prompt_password("  Passphrase (to decrypt): ")
+            .context("Failed to read passphrase")?
+    };
+    let archive = read_archive(input, &passphrase)?;
+    let m = &archive.manifest;
+    println!(
+        "\n  {} Backup of profile '{}' from {} ({}, {} accounts)",
+        "·".cyan(),
+        m.profile,
+        m.created_at,
+        m.platform,
+        m.accounts
+    );
+
+    let current = sequence::load()?;
+    let merge = if current.accounts.is_empty() {
+        false
+    } else {
+        print!(
+            "  {} This profile already has {} account{}. [m]erge / [r]eplace / [C]ancel: ",
+            "!".yellow().bold(),
+            current.accounts.len(),
+            if current.accounts.len() == 1 { "" } else { "s" }
+        );
+        std::io::stdout().flush()?;
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input)?;
+        match input.trim().to_lowercase().as_str() {
+            "m" | "merge" => true,
+            "r" | "replace" => false,
+            _ => {
+                println!("\n  {} Restore cancelled.\n", "·".dimmed());
+                return Ok(());
+            }
+        }
+    };
+
+    if merge {
+        return transfer::import_accounts(archived_exports(&archive)?);
+    }
+    let n = core_replace(&archive)?;
+    println!(
+        "\n  {} Restored {} account{}. Run `ccswitch list` to check the active account.\n",
+        "✓".green().bold(),
+        n,
+        if n == 1 { "" } else { "s" }
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequence::AccountEntry;
+    use crate::test_utils::TestEnv;
+
+    fn seed(num: u32, email: &str) {
+        let _lock = sequence::acquire_lock().unwrap();
+        let mut seq = sequence::load().unwrap();
+        seq.accounts.insert(
+            num.to_string(),
+            AccountEntry {
+                email: email.to_string(),
+                auth_kind: AuthKind::Token,
+                tags: vec!["work".into()],
+                ..Default::default()
+            },
+        );
+        seq.sequence.push(num);
+        seq.last_updated = sequence::now_utc();
+        sequence::save(&seq).unwrap();
+        credentials::write_backup(num, email, &format!(r#"{{"token":"sk-{num}"}}"#)).unwrap();
+        fs::write(
+            accounts::config_backup_path(num, email),
+            format!(r#"{{"n":{num}}}"#),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_backup_then_replace_restores_everything() {
+        let env = TestEnv::new();
+        seed(1, "a@test.com");
+        seed(2, "b@test.com");
+        let out = env.dir.path().join("backup.tar.gz");
+
+        let manifest = core_backup(&out, "pw").unwrap();
+        assert_eq!(manifest.accounts, 2);
+        assert!(manifest.files.contains(&"sequence.json".to_string()));
+        assert!(!manifest.files.iter().any(|f| f.starts_with("credentials")));
+        // Nothing secret in the clear.
+        let raw = fs::read(&out).unwrap();
+        let mut plain = Vec::new();
+        std::io::Read::read_to_end(&mut GzDecoder::new(raw.as_slice()), &mut plain).unwrap();
+        assert!(!String::from_utf8_lossy(&plain).contains("sk-1"));
+
+        // Wipe and replace.
+        accounts::core_remove(2, "b@test.com").unwrap();
+        seed(3, "c@test.com");
+        let archive = read_archive(&out, "pw").unwrap();
+        assert_eq!(core_replace(&archive).unwrap(), 2);
+
+        let seq = sequence::load().unwrap();
+        assert_eq!(seq.sequence, vec![1, 2]);
+        assert_eq!(seq.accounts["2"].tags, vec!["work"]);
+        assert_eq!(
+            credentials::read_backup(2, "b@test.com").unwrap(),
+            r#"{"token":"sk-2"}"#
+        );
+        assert_eq!(
+            accounts::read_config_backup(2, "b@test.com").unwrap(),
+            r#"{"n":2}"#
+        );
+        assert!(credentials::read_backup(3, "c@test.com").is_err());
+        assert!(!accounts::config_backup_path(3, "c@test.com").exists());
+    }
+
+    #[test]
+    fn test_replace_checks_the_archive_before_deleting() {
+        let env = TestEnv::new();
+        seed(1, "a@test.com");
+        let out = env.dir.path().join("backup.tar.gz");
+        core_backup(&out, "pw").unwrap();
+        seed(2, "b@test.com");
+
+        let mut archive = read_archive(&out, "pw").unwrap();
+        archive.credentials.insert(7, r#"{"token":"sk-7"}"#.to_string());
+        assert!(core_replace(&archive).unwrap_err().to_string().contains("Account 7"));
+        archive.credentials.clear();
+        assert!(core_replace(&archive).unwrap_err().to_string().contains("Account 1"));
+        archive.files.insert("sequence.json".into(), b"{".to_vec());
+        assert!(core_replace(&archive).is_err());
+
+        assert_eq!(sequence::load().unwrap().sequence, vec![1, 2]);
+        assert!(credentials::read_backup(2, "b@test.com").is_ok());
+        assert!(accounts::config_backup_path(2, "b@test.com").exists());
+    }
+
+    #[test]
+    fn test_read_archive_rejects_wrong_passphrase() {
+        let env = TestEnv::new();
+        seed(1, "a@test.com");
+        let out = env.dir.path().join("backup.tar.gz");
+        core_backup(&out, "pw").unwrap();
+        let err = read_archive(&out, "nope").err().unwrap();
+        assert!(err.to_string().contains("wrong passphrase"), "{err}");
+    }
+
+    #[test]
+    fn test_archived_exports_carry_config_and_credentials() {
+        let env = TestEnv::new();
+        seed(1, "a@test.com");
+        let out = env.dir.path().join("backup.tar.gz");
+        core_backup(&out, "pw").unwrap();
+
+        let exports = archived_exports(&read_archive(&out, "pw").unwrap()).unwrap();
+        assert_eq!(exports.len(), 1);
+        assert_eq!(exports[0].credentials, r#"{"token":"sk-1"}"#);
+        assert_eq!(exports[0].config, r#"{"n":1}"#);
+    }
+
+    #[test]
+    fn test_check_relative_rejects_traversal() {
+        assert!(check_relative("configs/a.json").is_ok());
+        assert!(check_relative("../x").is_err());
+        assert!(check_relative("/etc/passwd").is_err());
+        assert!(check_relative("").is_err());
+    }
+
+    #[test]
+    fn test_tar_roundtrip_including_long_paths() {
+        let entries = vec![
+            Entry { path: MANIFEST.into(), data: b"{}".to_vec() },
+            Entry {
+                path: format!("data/configs/{}.json.enc", "x".repeat(200)),
+                data: vec![7; 1300],
+            },
+            Entry { path: "empty".into(), data: Vec::new() },
+        ];
+        let buf = write_tar(Vec::new(), &entries).unwrap();
+        assert_eq!(read_tar(buf.as_slice()).unwrap(), entries);
+        assert!(read_tar(&buf[..100]).is_err());
+    }
+
+    #[test]
+    fn test_read_tar_rejects_traversal_and_links() {
+        let tar_with = |path: &str, kind: tar::EntryType| {
+            let mut header = tar::Header::new_gnu();
+            // Written straight into the name field: `set_path` refuses `..` itself.
+            header.as_gnu_mut().unwrap().name[..path.len()].copy_from_slice(path.as_bytes());
+            header.set_entry_type(kind);
+            header.set_size(0);
+            header.set_cksum();
+            let mut builder = tar::Builder::new(Vec::new());
+            builder.append(&header, std::io::empty()).unwrap();
+            builder.into_inner().unwrap()
+        };
+        assert!(read_tar(tar_with("../evil", tar::EntryType::Regular).as_slice()).is_err());
+        assert!(read_tar(tar_with("/etc/evil", tar::EntryType::Regular).as_slice()).is_err());
+        assert!(read_tar(tar_with("link", tar::EntryType::Symlink).as_slice()).is_err());
+        let dir = tar_with("dir", tar::EntryType::Directory);
+        assert!(read_tar(dir.as_slice()).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,244 @@
+use std::io::Write;
+
+use anyhow::Result;
+use clap_complete::Shell;
+
+use crate::sequence::{self, SequenceFile};
+
+/// Hidden subcommand the generated scripts call to list account identifiers.
+pub const ACCOUNTS_COMMAND: &str = "complete-accounts";
+
+/// Subcommands whose first positional argument is an account identifier.
+pub const ACCOUNT_SUBCOMMANDS: &[&str] = &[
+    "switch",
+    "remove",
+    "refresh",
+    "edit",
+    "alias",
+    "export",
+    "note",
+    "move",
+    "swap",
+    "set-uuid",
+    "tag",
+    "untag",
+    "info",
+    "run",
+    "init",
+    "diff",
+    "pin",
+    "clone",
+    "set-active",
+    "link",
+    "env-set",
+    "env-unset",
+    "env-list",
+];
+
+// ── Candidates ────────────────────────────────────────────────────────────────
+
+/// Account numbers, aliases, and emails — everything `SequenceFile::resolve` accepts.
+pub fn account_candidates(seq: &SequenceFile) -> Vec<String> {
+    let mut out = Vec::new();
+    for num in &seq.sequence {
+        out.push(num.to_string());
+        let mut aliases: Vec<&String> = seq
+            .aliases
+            .iter()
+            .filter(|(_, n)| *n == num)
+            .map(|(name, _)| name)
+            .collect();
+        aliases.sort();
+        out.extend(aliases.into_iter().cloned());
+        if let Some(entry) = seq.accounts.get(&num.to_string()) {
+            out.push(entry.email.clone());
+        }
+    }
+    out
+}
+
+/// Print account candidates one per line. Prints nothing if no accounts exist,
+/// so a completion request never surfaces an error.
+pub fn print_accounts() -> Result<()> {
+    if let Ok(seq) = sequence::load() {
+        for candidate in account_candidates(&seq) {
+            println!("{candidate}");
+        }
+    }
+    Ok(())
+}
+
+// ── Script generation ─────────────────────────────────────────────────────────
+
+/// Generate the static clap script for `shell`, then hook in dynamic account
+/// completion for the subcommands in `ACCOUNT_SUBCOMMANDS`.
+pub fn generate(shell: Shell, cmd: &mut clap::Command, out: &mut dyn Write) -> Result<()> {
+    let bin = cmd.get_name().to_string();
+    let mut buf = Vec::new();
+    clap_complete::generate(shell, cmd, &bin, &mut buf);
+    let script = String::from_utf8(buf)?;
+
+    let value_flags = global_value_flags(cmd).join("|");
+    let script = match shell {
+        Shell::Bash => script + &bash_hook(&bin, &value_flags),
+        Shell::Zsh => script + &zsh_hook(&bin, &value_flags),
+        Shell::Fish => script + &fish_hook(&bin),
+        Shell::PowerShell => powershell_hook(&script, &bin),
+        // Elvish keeps the static script only.
+        _ => script,
+    };
+    out.write_all(script.as_bytes())?;
+    Ok(())
+}
+
+/// Global options that take a separate value, e.g. `--profile NAME`, so the
+/// hooks can skip that value while looking for the subcommand.
+fn global_value_flags(cmd: &clap::Command) -> Vec<String> {
+    let mut flags = Vec::new();
+    for arg in cmd.get_arguments() {
+        if !arg.is_global_set() || !arg.get_action().takes_values() {
+            continue;
+        }
+        flags.extend(arg.get_short().map(|s| format!("-{s}")));
+        flags.extend(arg.get_long().map(|l| format!("--{l}")));
+        for alias in arg.get_all_aliases().unwrap_or_default() {
+            flags.push(format!("--{alias}"));
+        }
+    }
+    flags
+}
+
+/// The bash and zsh hooks find the subcommand as the first word that is not
+/// an option (or an option's value), and complete accounts only for the first
+/// positional word after it.
+fn bash_hook(bin: &str, value_flags: &str) -> String {
+    let subs = ACCOUNT_SUBCOMMANDS.join("|");
+    format!(
+        r#"
+_{bin}_dynamic() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}" sub="" i
+    for (( i = 1; i < COMP_CWORD; i++ )); do
+        case "${{COMP_WORDS[i]}}" in
+            {value_flags})
+                # bash splits `--flag=value` into three words.
+                [[ ${{COMP_WORDS[i+1]}} == = ]] && (( i++ ))
+                (( i++ ))
+                ;;
+            -*) ;;
+            *)
+                if [[ -n ${{sub}} ]]; then
+                    sub=""
+                    break
+                fi
+                sub="${{COMP_WORDS[i]}}"
+                ;;
+        esac
+    done
+    if [[ -n ${{sub}} && ${{cur}} != -* ]]; then
+        case "${{sub}}" in
+            {subs})
+                COMPREPLY=( $(compgen -W "$({bin} {ACCOUNTS_COMMAND} 2>/dev/null)" -- "${{cur}}") )
+                return 0
+                ;;
+        esac
+    fi
+    _{bin} "$@"
+}}
+
+if [[ "${{BASH_VERSINFO[0]}}" -eq 4 && "${{BASH_VERSINFO[1]}}" -ge 4 || "${{BASH_VERSINFO[0]}}" -gt 4 ]]; then
+    complete -F _{bin}_dynamic -o nosort -o bashdefault -o default {bin}
+else
+    complete -F _{bin}_dynamic -o bashdefault -o default {bin}
+fi
+"#
+    )
+}
+
+fn zsh_hook(bin: &str, value_flags: &str) -> String {
+    let subs = ACCOUNT_SUBCOMMANDS.join("|");
+    format!(
+        r#"
+_{bin}_dynamic() {{
+    local sub="" i
+    for (( i = 2; i < CURRENT; i++ )); do
+        case ${{words[i]}} in
+            ({value_flags}) (( i++ )) ;;
+            (-*) ;;
+            (*)
+                if [[ -n ${{sub}} ]]; then
+                    sub=""
+                    break
+                fi
+                sub=${{words[i]}}
+                ;;
+        esac
+    done
+    if [[ ${{sub}} == ({subs}) && ${{words[CURRENT]}} != -* ]]; then
+        local -a accounts
+        accounts=(${{(f)"$({bin} {ACCOUNTS_COMMAND} 2>/dev/null)"}})
+        compadd -a accounts
+    else
+        _{bin} "$@"
+    fi
+}}
+
+compdef _{bin}_dynamic {bin}
+"#
+    )
+}
+
+fn fish_hook(bin: &str) -> String {
+    format!(
+        "\ncomplete -c {bin} -n \"__fish_seen_subcommand_from {}\" -f -a \"({bin} {ACCOUNTS_COMMAND} 2>/dev/null)\"\n",
+        ACCOUNT_SUBCOMMANDS.join(" ")
+    )
+}
+
+/// PowerShell registers a single completer, so account results are injected
+/// into the generated per-subcommand blocks instead of wrapping them.
+fn powershell_hook(script: &str, bin: &str) -> String {
+    let mut out = script.to_string();
+    for sub in ACCOUNT_SUBCOMMANDS {
+        let marker = format!("'{bin};{sub}' {{\n");
+        let injected = format!(
+            "{marker}            & {bin} {ACCOUNTS_COMMAND} 2>$null | ForEach-Object {{ [CompletionResult]::new($_, $_, [CompletionResultType]::ParameterValue, $_) }}\n"
+        );
+        out = out.replacen(&marker, &injected, 1);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequence::AccountEntry;
+
+    fn sample_seq() -> SequenceFile {
+        let mut seq = SequenceFile::default();
+        for (num, email) in [(1u32, "a@test.com"), (2, "b@test.com")] {
+            seq.accounts.insert(
+                num.to_string(),
+                AccountEntry {
+                    email: email.into(),
+                    ..Default::default()
+                },
+            );
+            seq.sequence.push(num);
+        }
+        seq.aliases.insert("work".into(), 2);
+        seq
+    }
+
+    #[test]
+    fn test_account_candidates_include_numbers_aliases_and_emails() {
+        assert_eq!(
+            account_candidates(&sample_seq()),
+            vec!["1", "a@test.com", "2", "work", "b@test.com"]
+        );
+    }
+
+    #[test]
+    fn test_account_candidates_empty_sequence() {
+        assert!(account_candidates(&SequenceFile::default()).is_empty());
+    }
+}
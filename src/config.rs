@@ -1,6 +1,9 @@
 use anyhow::{Context, Result};
 use serde_json::Value;
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
 /// Find the active Claude config file: prefers ~/.claude/.claude.json if it has
 /// an oauthAccount, falls back to ~/.claude.json.
@@ -9,7 +12,7 @@ pub fn path() -> PathBuf {
     if let Ok(dir) = std::env::var("CCSWITCH_TEST_DIR") {
         return std::path::PathBuf::from(dir).join(".claude.json");
     }
-    let home = dirs::home_dir().expect("Cannot find home directory");
+    let home = crate::paths::home_dir().expect("Cannot find home directory");
     let primary = home.join(".claude").join(".claude.json");
     let fallback = home.join(".claude.json");
 
@@ -25,18 +28,31 @@ pub fn path() -> PathBuf {
     fallback
 }
 
-pub fn load() -> Result<Value> {
+/// A Claude config together with the file it was read from.
+pub struct LoadedConfig {
+    pub path: PathBuf,
+    pub value: Value,
+}
+
+/// Load the active Claude config, remembering its path so a later `save`
+/// writes the same file even if `path()` would now pick the other one.
+pub fn load_with_path() -> Result<LoadedConfig> {
     let p = path();
     let content = fs::read_to_string(&p)
         .with_context(|| format!("Cannot read Claude config at {}", p.display()))?;
-    serde_json::from_str(&content)
-        .with_context(|| format!("Invalid JSON in {}", p.display()))
+    let value = serde_json::from_str(&content)
+        .with_context(|| format!("Invalid JSON in {}", p.display()))?;
+    Ok(LoadedConfig { path: p, value })
 }
 
-pub fn save(config: &Value) -> Result<()> {
-    let p = path();
+pub fn load() -> Result<Value> {
+    load_with_path().map(|loaded| loaded.value)
+}
+
+/// Atomically write `config` to `path`, normally `LoadedConfig::path`.
+pub fn save(path: &Path, config: &Value) -> Result<()> {
     let content = serde_json::to_string_pretty(config)?;
-    crate::sequence::write_atomic(&p, &content)
+    crate::sequence::write_atomic(path, &content)
 }
 
 pub fn current_email() -> Option<String> {
@@ -68,3 +84,116 @@ pub fn has_env_token() -> bool {
 pub fn email_from_token(_token: &str) -> Option<String> {
     None
 }
+
+// ── Project-local account ─────────────────────────────────────────────────────
+
+/// Name of the per-directory file selecting an account: `account=<number-or-email>`,
+/// optionally followed by `email=<email>` (as `ccswitch init` writes it).
+pub const PROJECT_FILE: &str = ".ccswitch";
+
+/// The nearest `.ccswitch` in `start` or its parents, not looking above `stop`.
+fn find_project_file(start: &Path, stop: Option<&Path>) -> Option<PathBuf> {
+    for dir in start.ancestors() {
+        let candidate = dir.join(PROJECT_FILE);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        if Some(dir) == stop {
+            break;
+        }
+    }
+    None
+}
+
+/// The value of the first `<key>=` line of a `.ccswitch` file.
+fn project_value(content: &str, key: &str) -> Option<String> {
+    content
+        .lines()
+        .find_map(|line| line.trim().strip_prefix(key)?.strip_prefix('='))
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// The account a `.ccswitch` file selects. An `email=` line wins while that
+/// email is managed, so a renumbered account is still found; otherwise the
+/// `account=` line is resolved as a number or email.
+fn resolve_project_file(content: &str, seq: &crate::sequence::SequenceFile) -> Option<u32> {
+    project_value(content, "email")
+        .and_then(|email| seq.find_by_email(&email))
+        .or_else(|| seq.resolve(&project_value(content, "account")?))
+}
+
+/// The account selected by the nearest `.ccswitch` between `$PWD` and `$HOME`,
+/// if it names a managed account.
+pub fn find_project_account() -> Option<u32> {
+    let cwd = std::env::current_dir().ok()?;
+    let home = crate::paths::home_dir();
+    let file = find_project_file(&cwd, home.as_deref())?;
+    let content = fs::read_to_string(file).ok()?;
+    resolve_project_file(&content, &crate::sequence::load().ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestEnv;
+
+    #[test]
+    fn test_save_writes_loaded_path() {
+        let _env = TestEnv::new();
+        let p = path();
+        fs::write(&p, r#"{"oauthAccount":{"emailAddress":"a@test.com"}}"#).unwrap();
+        let mut loaded = load_with_path().unwrap();
+        assert_eq!(loaded.path, p);
+        loaded.value.as_object_mut().unwrap().remove("oauthAccount");
+        save(&loaded.path, &loaded.value).unwrap();
+        assert_eq!(load().unwrap(), serde_json::json!({}));
+    }
+
+    #[test]
+    fn test_parse_project_file() {
+        assert_eq!(project_value("account=2\n", "account"), Some("2".to_string()));
+        assert_eq!(
+            project_value("# work repo\n  account=x@y.com \n", "account"),
+            Some("x@y.com".to_string())
+        );
+        assert_eq!(project_value("account=\n", "account"), None);
+        assert_eq!(project_value("accounts=2\n", "account"), None);
+        assert_eq!(project_value("2", "account"), None);
+    }
+
+    #[test]
+    fn test_resolve_project_file_prefers_the_email() {
+        use crate::sequence::{AccountEntry, SequenceFile};
+        let mut seq = SequenceFile::default();
+        for (num, email) in [(1, "a@x.com"), (2, "b@x.com")] {
+            let entry = AccountEntry { email: email.to_string(), ..Default::default() };
+            seq.accounts.insert(num.to_string(), entry);
+            seq.sequence.push(num);
+        }
+        assert_eq!(resolve_project_file("account=2\n", &seq), Some(2));
+        // Account 1 was renumbered since `init` wrote the file.
+        assert_eq!(resolve_project_file("account=2\nemail=a@x.com\n", &seq), Some(1));
+        assert_eq!(resolve_project_file("account=2\nemail=gone@x.com\n", &seq), Some(2));
+        assert_eq!(resolve_project_file("account=9\n", &seq), None);
+    }
+
+    #[test]
+    fn test_find_project_file_walks_up_to_stop() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let home = dir.path().join("home");
+        let nested = home.join("src").join("app");
+        fs::create_dir_all(&nested).unwrap();
+        assert_eq!(find_project_file(&nested, Some(&home)), None);
+
+        // A file above the stop directory is not picked up.
+        fs::write(dir.path().join(PROJECT_FILE), "account=1").unwrap();
+        assert_eq!(find_project_file(&nested, Some(&home)), None);
+
+        fs::write(home.join(PROJECT_FILE), "account=1").unwrap();
+        assert_eq!(find_project_file(&nested, Some(&home)), Some(home.join(PROJECT_FILE)));
+
+        fs::write(nested.join(PROJECT_FILE), "account=2").unwrap();
+        assert_eq!(find_project_file(&nested, Some(&home)), Some(nested.join(PROJECT_FILE)));
+    }
+}
@@ -0,0 +1,376 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+
+use clap::ValueEnum;
+
+use crate::{
+    accounts::{OutputFormat, RotationStrategy},
+    paths,
+};
+
+// ── ccswitch's own preferences (not Claude Code's config) ─────────────────────
+//
+// Stored in `config.toml` next to the hook settings. Nothing is cached: each
+// caller loads the file when it needs a value, so `config set` takes effect on
+// the next command.
+
+/// Preferences from `config.toml`; missing keys take their defaults.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct UserConfig {
+    /// Profile used when neither `--profile` nor `profile use` picked one.
+    pub default_profile: Option<String>,
+    /// Forced credential backend; `None` means auto-detect.
+    pub credential_backend: Option<String>,
+    /// Colored output (`--no-color` and `$NO_COLOR` still win).
+    pub color: bool,
+    /// Ask before switching from the TUI.
+    pub confirm_switch: bool,
+    /// Show a desktop notification after each successful switch.
+    #[serde(rename = "notifications")]
+    pub notify: bool,
+    /// Leave out the "Restart Claude Code" reminder after switches (CLI and TUI).
+    pub suppress_restart_hint: bool,
+    pub pre_switch_hook: Option<String>,
+    pub post_switch_hook: Option<String>,
+    /// `debug` and `trace` turn on `--verbose` output.
+    pub log_level: Option<String>,
+    /// How `switch` with no account picks the next one; `None` means round-robin.
+    pub rotation_strategy: Option<String>,
+    /// Where accounts are stored (see `store::StoreKind`); `None` means `json`.
+    pub store: Option<String>,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            default_profile: None,
+            credential_backend: None,
+            color: true,
+            confirm_switch: true,
+            notify: false,
+            suppress_restart_hint: false,
+            pre_switch_hook: None,
+            post_switch_hook: None,
+            log_level: None,
+            rotation_strategy: None,
+            store: None,
+        }
+    }
+}
+
+impl UserConfig {
+    pub fn verbose(&self) -> bool {
+        matches!(self.log_level.as_deref(), Some("debug" | "trace"))
+    }
+
+    /// The configured rotation strategy; unknown values are ignored.
+    pub fn rotation_strategy(&self) -> Option<RotationStrategy> {
+        RotationStrategy::from_str(self.rotation_strategy.as_deref()?, true).ok()
+    }
+}
+
+pub const LOG_LEVELS: &[&str] = &["error", "warn", "info", "debug", "trace"];
+
+/// A `config` key and where it lives in `config.toml`.
+struct Key {
+    name: &'static str,
+    table: &'static str,
+    toml_key: &'static str,
+}
+
+const fn top(name: &'static str) -> Key {
+    Key { name, table: "", toml_key: name }
+}
+
+/// Keys accepted by `ccswitch config`. The hooks keep their `[hooks]` table.
+const KEYS: &[Key] = &[
+    top("default_profile"),
+    top("credential_backend"),
+    top("color"),
+    top("confirm_switch"),
+    top("notifications"),
+    top("suppress_restart_hint"),
+    Key { name: "pre_switch_hook", table: "hooks", toml_key: "pre_switch" },
+    Key { name: "post_switch_hook", table: "hooks", toml_key: "post_switch" },
+    top("log_level"),
+    top("rotation_strategy"),
+    top("store"),
+];
+
+pub fn path() -> PathBuf {
+    paths::config_dir().join("config.toml")
+}
+
+fn read_file() -> Result<String> {
+    let p = path();
+    match fs::read_to_string(&p) {
+        Ok(content) => Ok(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", p.display())),
+    }
+}
+
+/// `config.toml` as written: the settings sit at the top level, the hook
+/// scripts in `[hooks]`.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ConfigFile {
+    #[serde(flatten)]
+    settings: UserConfig,
+    hooks: HooksTable,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct HooksTable {
+    pre_switch: Option<String>,
+    post_switch: Option<String>,
+}
+
+pub fn load_user_config() -> Result<UserConfig> {
+    let p = path();
+    let file: ConfigFile = toml::from_str(&read_file()?)
+        .with_context(|| format!("Invalid setting in {}", p.display()))?;
+    let mut config = file.settings;
+    config.pre_switch_hook = file.hooks.pre_switch;
+    config.post_switch_hook = file.hooks.post_switch;
+    Ok(config)
+}
+
+/// Accepts `credential-backend` as well as `credential_backend`.
+fn find_key(name: &str) -> Result<&'static Key> {
+    let name = name.replace('-', "_");
+    KEYS.iter().find(|k| k.name == name).with_context(|| {
+        let names: Vec<_> = KEYS.iter().map(|k| k.name).collect();
+        format!("Unknown setting '{name}'. Known settings: {}", names.join(", "))
+    })
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<toml_edit::Value> {
+    match value {
+        "true" | "on" | "yes" => Ok(toml_edit::Value::from(true)),
+        "false" | "off" | "no" => Ok(toml_edit::Value::from(false)),
+        _ => bail!("{key} must be true or false, not '{value}'"),
+    }
+}
+
+/// Check `value` for `key` and convert it to what gets written. `None` means
+/// the key should be removed (`credential_backend auto`).
+fn parse_value(key: &Key, value: &str) -> Result<Option<toml_edit::Value>> {
+    let string = |s: String| Ok(Some(toml_edit::Value::from(s)));
+    match key.name {
+        "default_profile" => {
+            crate::profiles::validate_name(value)?;
+            string(value.to_string())
+        }
+        "credential_backend" if value == "auto" => Ok(None),
+        "credential_backend" => {
+            let backend: crate::credentials::CredentialBackend = value.parse()?;
+            string(backend.to_string())
+        }
+        "color" | "confirm_switch" | "notifications" | "suppress_restart_hint" => {
+            parse_bool(key.name, value).map(Some)
+        }
+        "log_level" if LOG_LEVELS.contains(&value) => string(value.to_string()),
+        "log_level" => bail!("log_level must be one of: {}", LOG_LEVELS.join(", ")),
+        "rotation_strategy" => match RotationStrategy::from_str(value, true) {
+            Ok(strategy) => string(strategy.to_possible_value().unwrap().get_name().to_string()),
+            Err(_) => bail!("rotation_strategy must be one of: round-robin, lru, random"),
+        },
+        "store" => {
+            let kind: crate::store::StoreKind = value.parse()?;
+            crate::store::check_available(kind)?;
+            string(kind.to_string())
+        }
+        _ if value.is_empty() => bail!("{} cannot be empty; use `config reset`", key.name),
+        _ => string(value.to_string()),
+    }
+}
+
+fn write_key(key: &Key, value: Option<toml_edit::Value>) -> Result<()> {
+    if key.name == "store" {
+        // Move the accounts over first, so a failed copy leaves the old store in use.
+        let kind = match value.as_ref().and_then(|v| v.as_str()) {
+            Some(name) => name.parse()?,
+            None => crate::store::StoreKind::default(),
+        };
+        crate::store::convert(kind)?;
+    }
+    // Edit the parsed document rather than re-serializing the settings, so
+    // comments and layout in the user's file survive.
+    let p = path();
+    let mut doc: toml_edit::DocumentMut = read_file()?
+        .parse()
+        .with_context(|| format!("Invalid TOML in {}", p.display()))?;
+    let table = if key.table.is_empty() {
+        Some(doc.as_table_mut() as &mut dyn toml_edit::TableLike)
+    } else if value.is_some() {
+        doc.entry(key.table)
+            .or_insert_with(toml_edit::table)
+            .as_table_like_mut()
+    } else {
+        doc.get_mut(key.table).and_then(toml_edit::Item::as_table_like_mut)
+    };
+    match (table, value) {
+        (Some(table), Some(value)) => match table.get_mut(key.toml_key) {
+            // Assign through the existing item to keep the comments above the key.
+            Some(item) => *item = toml_edit::value(value),
+            None => {
+                table.insert(key.toml_key, toml_edit::value(value));
+            }
+        },
+        (Some(table), None) => {
+            table.remove(key.toml_key);
+        }
+        (None, Some(_)) => bail!("[{}] in {} is not a table", key.table, p.display()),
+        (None, None) => {}
+    }
+    fs::create_dir_all(paths::config_dir())?;
+    fs::write(&p, doc.to_string()).with_context(|| format!("Failed to write {}", p.display()))
+}
+
+/// Validate and store `value` for `key`.
+pub fn set(key: &str, value: &str) -> Result<()> {
+    let key = find_key(key)?;
+    let value = parse_value(key, value)?;
+    write_key(key, value)
+}
+
+/// Remove `key` from `config.toml` so its default applies again.
+pub fn reset(key: &str) -> Result<()> {
+    write_key(find_key(key)?, None)
+}
+
+fn display(name: &str, value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null if name == "credential_backend" => "auto".to_string(),
+        serde_json::Value::Null if name == "rotation_strategy" => "round-robin".to_string(),
+        serde_json::Value::Null if name == "store" => "json".to_string(),
+        serde_json::Value::Null => "(not set)".to_string(),
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// The effective value of `key`, defaults included.
+pub fn get(key: &str) -> Result<String> {
+    let key = find_key(key)?;
+    let values = serde_json::to_value(load_user_config()?)?;
+    Ok(display(key.name, &values[key.name]))
+}
+
+pub fn list(format: OutputFormat) -> Result<()> {
+    let config = load_user_config()?;
+    if format == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&config)?);
+        return Ok(());
+    }
+    let values = serde_json::to_value(&config)?;
+    println!();
+    for key in KEYS {
+        println!("  {:<20} {}", key.name, display(key.name, &values[key.name]));
+    }
+    println!();
+    Ok(())
+}
+
+/// Move `credential-backend` from the old `settings.json` into `config.toml`.
+pub fn migrate_settings_json() -> Result<()> {
+    let old = crate::profiles::root_dir().join("settings.json");
+    let Ok(content) = fs::read_to_string(&old) else {
+        return Ok(());
+    };
+    let settings: serde_json::Value = serde_json::from_str(&content)
+        .with_context(|| format!("Invalid JSON in {}", old.display()))?;
+    if let Some(backend) = settings.get("credentialBackend").and_then(|b| b.as_str()) {
+        if load_user_config()?.credential_backend.is_none() {
+            set("credential_backend", backend)?;
+        }
+    }
+    fs::remove_file(&old).with_context(|| format!("Failed to remove {}", old.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestEnv;
+
+    #[test]
+    fn test_load_missing_is_default() {
+        let _env = TestEnv::new();
+        let config = load_user_config().unwrap();
+        assert_eq!(config, UserConfig::default());
+        assert!(config.color && config.confirm_switch);
+    }
+
+    #[test]
+    fn test_set_get_reset() {
+        let _env = TestEnv::new();
+        set("credential-backend", "kwallet").unwrap();
+        set("confirm_switch", "false").unwrap();
+        set("pre_switch_hook", "~/bin/pre.sh").unwrap();
+        assert_eq!(get("credential_backend").unwrap(), "kwallet");
+        assert_eq!(get("confirm_switch").unwrap(), "false");
+
+        // Hooks stay in the [hooks] table that hooks::load reads.
+        let doc: toml::Table = toml::from_str(&fs::read_to_string(path()).unwrap()).unwrap();
+        assert_eq!(doc["hooks"]["pre_switch"].as_str(), Some("~/bin/pre.sh"));
+
+        set("credential_backend", "auto").unwrap();
+        assert_eq!(get("credential_backend").unwrap(), "auto");
+        reset("confirm_switch").unwrap();
+        assert!(load_user_config().unwrap().confirm_switch);
+
+        set("suppress_restart_hint", "true").unwrap();
+        assert!(load_user_config().unwrap().suppress_restart_hint);
+    }
+
+    #[test]
+    fn test_set_keeps_comments_and_other_keys() {
+        let _env = TestEnv::new();
+        fs::create_dir_all(paths::config_dir()).unwrap();
+        fs::write(path(), "# mine\ncolor = true\n\n[hooks]\npre_switch = \"/a.sh\" # keep\n")
+            .unwrap();
+        set("color", "false").unwrap();
+        set("post_switch_hook", r#"C:\x "y""#).unwrap();
+        reset("pre_switch_hook").unwrap();
+
+        let content = fs::read_to_string(path()).unwrap();
+        assert!(content.starts_with("# mine\ncolor = false\n"), "{content}");
+        let config = load_user_config().unwrap();
+        assert!(!config.color);
+        assert_eq!(config.pre_switch_hook, None);
+        assert_eq!(config.post_switch_hook.as_deref(), Some(r#"C:\x "y""#));
+    }
+
+    #[test]
+    fn test_set_rejects_unknown_key_and_bad_values() {
+        let _env = TestEnv::new();
+        assert!(set("nope", "file").is_err());
+        assert!(set("credential_backend", "floppy").is_err());
+        assert!(set("color", "maybe").is_err());
+        assert!(set("log_level", "loud").is_err());
+        assert!(set("default_profile", "../x").is_err());
+        assert!(!path().exists());
+    }
+
+    #[test]
+    fn test_load_rejects_wrong_types() {
+        let _env = TestEnv::new();
+        fs::write(path(), "color = \"yes\"\n").unwrap();
+        assert!(load_user_config().is_err());
+    }
+
+    #[test]
+    fn test_migrate_settings_json() {
+        let _env = TestEnv::new();
+        let old = crate::profiles::root_dir().join("settings.json");
+        fs::write(&old, r#"{"credentialBackend":"pass"}"#).unwrap();
+        migrate_settings_json().unwrap();
+        assert!(!old.exists());
+        assert_eq!(load_user_config().unwrap().credential_backend.as_deref(), Some("pass"));
+        migrate_settings_json().unwrap();
+    }
+}
@@ -1,27 +1,199 @@
 use anyhow::{Context, Result};
-use std::{fs, path::PathBuf, process::Command};
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
 
 const OAUTH_TOKEN_URL: &str = "https://platform.claude.com/v1/oauth/token";
 const OAUTH_CLIENT_ID: &str = "9d1c250a-e61b-44d9-88ed-5944d1962f5e";
 const OAUTH_BETA_HEADER: &str = "oauth-2025-04-20";
 
+/// Overrides the Claude API base URL used by `token validate`, e.g. for a mock server.
+pub const API_BASE_ENV: &str = "CCSWITCH_API_BASE";
+const DEFAULT_API_BASE: &str = "https://api.anthropic.com";
+const VALIDATE_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 
+use wait_timeout::ChildExt;
+
 use crate::{
     platform::{detect, Platform},
-    sequence::backup_dir,
+    sequence::{backup_dir, AuthKind},
 };
 
 /// Keychain service name for the currently-active token (read by ~/.ccswitchrc).
 const ACTIVE_TOKEN_SERVICE: &str = "ccswitch-active-token";
 
+/// Default time to wait for a keychain or keyring command before giving up.
+pub const DEFAULT_KEYCHAIN_TIMEOUT_MS: u64 = 30_000;
+
+/// Timeout applied to every keychain and keyring command. `0` disables the timeout.
+static KEYCHAIN_TIMEOUT_MS: AtomicU64 = AtomicU64::new(DEFAULT_KEYCHAIN_TIMEOUT_MS);
+
+/// Set the timeout (in milliseconds) for keychain and keyring commands.
+/// Passing `0` waits indefinitely.
+pub fn set_keychain_timeout_ms(ms: u64) {
+    KEYCHAIN_TIMEOUT_MS.store(ms, Ordering::Relaxed);
+}
+
+// ── Backend selection ─────────────────────────────────────────────────────────
+
+/// Where per-account credential backups are stored.
+///
+/// Live credentials always stay where Claude Code reads them (keychain on
+/// macOS, `~/.claude/.credentials.json` elsewhere, Windows included); only
+/// backups move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredentialBackend {
+    /// macOS keychain via `security`.
+    Keychain,
+    /// The standard Unix password manager, under `ccswitch/` in the store.
+    Pass,
+    /// KDE Wallet via `kwallet-query` (reads/writes) and `dbus-send` (deletes).
+    KWallet,
+    /// GNOME Keyring / any Secret Service provider via libsecret's `secret-tool`.
+    SecretService,
+    /// Windows Credential Manager (generic credentials) via CredRead/CredWrite.
+    WindowsCredentialManager,
+    /// Mode-0600 files under `<data dir>/<profile>/credentials/`.
+    File,
+}
+
+impl std::fmt::Display for CredentialBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CredentialBackend::Keychain => write!(f, "keychain"),
+            CredentialBackend::Pass => write!(f, "pass"),
+            CredentialBackend::KWallet => write!(f, "kwallet"),
+            CredentialBackend::SecretService => write!(f, "secret-service"),
+            CredentialBackend::WindowsCredentialManager => write!(f, "wincred"),
+            CredentialBackend::File => write!(f, "file"),
+        }
+    }
+}
+
+impl std::str::FromStr for CredentialBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "keychain" => Ok(CredentialBackend::Keychain),
+            "pass" => Ok(CredentialBackend::Pass),
+            "kwallet" => Ok(CredentialBackend::KWallet),
+            "secret-service" | "libsecret" => Ok(CredentialBackend::SecretService),
+            "wincred" | "windows" => Ok(CredentialBackend::WindowsCredentialManager),
+            "file" => Ok(CredentialBackend::File),
+            other => anyhow::bail!(
+                "Unknown credential backend '{other}'. \
+                 Expected one of: auto, keychain, pass, kwallet, secret-service, wincred, file"
+            ),
+        }
+    }
+}
+
+/// Pick the backup backend: `config set credential_backend` wins. Otherwise
+/// Linux/WSL users who already have file backups stay on files, so a keyring
+/// installed later is only used once they opt in with `migrate-backend`; everyone
+/// else gets `preferred_backend`.
+pub fn backend() -> CredentialBackend {
+    if let Some(forced) = configured_backend() {
+        return forced;
+    }
+    match detect() {
+        Platform::Linux | Platform::Wsl => keep_file_backups(detected_backend()),
+        Platform::MacOS | Platform::Windows => detected_backend(),
+    }
+}
+
+/// The backend a fresh install uses: the configured one, else auto-detected
+/// (Linux/WSL order: pass → KWallet → Secret Service → file).
+pub fn preferred_backend() -> CredentialBackend {
+    configured_backend().unwrap_or_else(detected_backend)
+}
+
+fn configured_backend() -> Option<CredentialBackend> {
+    #[cfg(test)]
+    if let Ok(name) = std::env::var("CCSWITCH_TEST_BACKEND") {
+        return Some(name.parse().unwrap_or(CredentialBackend::File));
+    }
+    crate::config_user::load_user_config()
+        .ok()
+        .and_then(|c| c.credential_backend)
+        .and_then(|b| b.parse().ok())
+}
+
+/// The platform's store. The Linux keyring probe looks for binaries and
+/// scans `/proc`, so it runs once per process.
+fn detected_backend() -> CredentialBackend {
+    static LINUX_KEYRING: std::sync::OnceLock<CredentialBackend> = std::sync::OnceLock::new();
+    match detect() {
+        Platform::MacOS => CredentialBackend::Keychain,
+        Platform::Windows => CredentialBackend::WindowsCredentialManager,
+        Platform::Linux | Platform::Wsl => {
+            #[cfg(test)]
+            if std::env::var("CCSWITCH_TEST_PLATFORM").is_ok() {
+                return CredentialBackend::File;
+            }
+            *LINUX_KEYRING.get_or_init(|| {
+                if pass_available() {
+                    CredentialBackend::Pass
+                } else if kwallet_available() {
+                    CredentialBackend::KWallet
+                } else if secret_service_available() {
+                    CredentialBackend::SecretService
+                } else {
+                    CredentialBackend::File
+                }
+            })
+        }
+    }
+}
+
+/// `detected`, unless file backups already exist, in which case the file backend.
+fn keep_file_backups(detected: CredentialBackend) -> CredentialBackend {
+    let has_file_backups = fs::read_dir(backup_files_dir()).is_ok_and(|entries| {
+        entries.flatten().any(|e| {
+            let name = e.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with(".claude-credentials-") && name.ends_with(".json")
+        })
+    });
+    if has_file_backups {
+        CredentialBackend::File
+    } else {
+        detected
+    }
+}
+
+/// `pass` is opt-in: only picked when `$PASSWORD_STORE_DIR` is set explicitly.
+fn pass_available() -> bool {
+    std::env::var_os("PASSWORD_STORE_DIR").is_some() && crate::platform::command_exists("pass")
+}
+
+fn kwallet_available() -> bool {
+    (std::env::var_os("KDE_FULL_SESSION").is_some() || running_kwalletd().is_some())
+        && crate::platform::command_exists("kwallet-query")
+}
+
+fn secret_service_available() -> bool {
+    (std::env::var_os("GNOME_KEYRING_CONTROL").is_some()
+        || std::env::var_os("DBUS_SESSION_BUS_ADDRESS").is_some())
+        && crate::platform::command_exists("secret-tool")
+}
+
 // ── Live credentials (currently active account) ───────────────────────────────
 
+/// Read the credentials Claude Code is currently using.
 pub fn read_live() -> Result<String> {
     match detect() {
         Platform::MacOS => keychain_read("Claude Code-credentials"),
-        Platform::Linux | Platform::Wsl => {
+        Platform::Linux | Platform::Wsl | Platform::Windows => {
             let path = creds_file_path();
             fs::read_to_string(&path)
                 .with_context(|| format!("Cannot read credentials from {}", path.display()))
@@ -29,70 +201,533 @@ pub fn read_live() -> Result<String> {
     }
 }
 
+/// Replace the credentials Claude Code uses (the keychain on macOS, the
+/// credentials file elsewhere).
 pub fn write_live(credentials: &str) -> Result<()> {
     match detect() {
         Platform::MacOS => keychain_write("Claude Code-credentials", credentials),
-        Platform::Linux | Platform::Wsl => {
+        Platform::Linux | Platform::Wsl | Platform::Windows => {
             let path = creds_file_path();
             if let Some(parent) = path.parent() {
                 fs::create_dir_all(parent)?;
             }
+            // Never write through a `ccswitch link` symlink into an account's backup.
+            if path.is_symlink() {
+                fs::remove_file(&path)
+                    .with_context(|| format!("Cannot remove symlink {}", path.display()))?;
+            }
             write_file_600(&path, credentials)
         }
     }
 }
 
+/// Path of the live credentials file, or `None` when stored in the keychain.
+pub fn live_file_path() -> Option<PathBuf> {
+    match detect() {
+        Platform::MacOS => None,
+        Platform::Linux | Platform::Wsl | Platform::Windows => Some(creds_file_path()),
+    }
+}
+
+/// The live credentials file and an account's file backup, or an error
+/// explaining why `ccswitch link` cannot join them on this machine.
+pub fn link_paths(num: u32, email: &str) -> Result<(PathBuf, PathBuf)> {
+    let live = live_file_path().context(
+        "`ccswitch link` is not supported on macOS, where Claude Code keeps credentials in the keychain",
+    )?;
+    let backup = backup_file_path(num, email).with_context(|| {
+        format!(
+            "`ccswitch link` needs file backups, but they are stored in {}. \
+             Run `ccswitch config set credential_backend file` first.",
+            backend()
+        )
+    })?;
+    if cfg!(not(unix)) {
+        anyhow::bail!("`ccswitch link` is only supported on Linux and WSL");
+    }
+    Ok((live, backup))
+}
+
+/// Replace the live credentials file with a symlink to an account's file
+/// backup. Returns the backup path the link points at.
+pub fn link_live(num: u32, email: &str) -> Result<PathBuf> {
+    let (live, backup) = link_paths(num, email)?;
+    if !backup.exists() {
+        anyhow::bail!("No credentials backup at {}", backup.display());
+    }
+    // Claude Code reads the linked file directly, so it must hold the bare
+    // credentials rather than a versioned envelope.
+    let creds = read_backup_file(num, email)?;
+    if fs::read_to_string(&backup)? != creds {
+        write_file_600(&backup, &creds)?;
+    }
+    // Claude Code rewrites the linked file on every token refresh, so a stored
+    // checksum would go stale. The next write after the link goes away adds one.
+    let checksum = checksum_file_path(&backup);
+    if checksum.exists() {
+        fs::remove_file(&checksum).with_context(|| format!("Cannot remove {}", checksum.display()))?;
+    }
+    if live.exists() || live.is_symlink() {
+        fs::remove_file(&live).with_context(|| format!("Cannot remove {}", live.display()))?;
+    }
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&backup, &live)
+        .with_context(|| format!("Cannot link {} → {}", live.display(), backup.display()))?;
+    Ok(backup)
+}
+
+/// Whether the live credentials file is a `ccswitch link` symlink to `backup`.
+fn is_linked(backup: &Path) -> bool {
+    live_file_path()
+        .and_then(|live| fs::read_link(live).ok())
+        .is_some_and(|target| target == backup)
+}
+
+/// Turn a symlinked live credentials file back into a regular copy of
+/// whatever it points at. Returns false when it was not a symlink.
+pub fn unlink_live() -> Result<bool> {
+    let Some(live) = live_file_path().filter(|p| p.is_symlink()) else {
+        return Ok(false);
+    };
+    let creds = fs::read_to_string(&live)
+        .with_context(|| format!("Cannot read credentials through {}", live.display()))?;
+    write_live(&creds)?;
+    Ok(true)
+}
+
 // ── Per-account backup credentials ───────────────────────────────────────────
 
+/// Path of an account's backup credentials file, or `None` when stored in a keyring.
+pub fn backup_file_path(num: u32, email: &str) -> Option<PathBuf> {
+    match backend() {
+        CredentialBackend::File => Some(cred_backup_path(num, email)),
+        _ => None,
+    }
+}
+
+/// Human-readable location of an account's backup, for previews and diagnostics.
+pub fn backup_location(num: u32, email: &str) -> String {
+    match backup_file_path(num, email) {
+        Some(path) => path.display().to_string(),
+        None => format!("{} entry \"{}\"", backend(), account_service(num, email)),
+    }
+}
+
+/// On macOS, which keychain holds the live credentials, e.g.
+/// `user keychain (/Users/me/Library/Keychains/login.keychain-db)`.
+/// `None` on other platforms or when there is no entry.
+pub fn live_keychain() -> Option<String> {
+    if detect() != Platform::MacOS {
+        return None;
+    }
+    let output = run_security(&["find-generic-password", "-s", "Claude Code-credentials"]).ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    describe_keychain(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Classify the `keychain: "<path>"` line of `security find-generic-password`.
+fn describe_keychain(output: &str) -> Option<String> {
+    let path = output
+        .lines()
+        .find_map(|l| l.strip_prefix("keychain: "))?
+        .trim()
+        .trim_matches('"');
+    let kind = if path.starts_with("/Library/") || path.starts_with("/System/") {
+        "system"
+    } else {
+        "user"
+    };
+    Some(format!("{kind} keychain ({path})"))
+}
+
+/// Human-readable location of the live credentials Claude Code reads.
+pub fn live_location() -> String {
+    match live_file_path() {
+        Some(path) => path.display().to_string(),
+        None => "keychain entry \"Claude Code-credentials\"".to_string(),
+    }
+}
+
+/// Read an account's backup, refusing one that no longer matches its checksum.
+/// The keychain protects its own entries and each lookup is a `security` call,
+/// so there the checksum is left to `ccswitch verify`.
 pub fn read_backup(num: u32, email: &str) -> Result<String> {
-    match detect() {
-        Platform::MacOS => keychain_read(&account_service(num, email)),
-        Platform::Linux | Platform::Wsl => {
+    let creds = read_backup_unverified(num, email)?;
+    if backend() != CredentialBackend::Keychain {
+        verify_backup_checksum(num, email, &creds)?;
+    }
+    Ok(creds)
+}
+
+/// Read an account's backup without checking its checksum; `ccswitch verify`
+/// reports a mismatch separately from an unreadable backup.
+pub fn read_backup_unverified(num: u32, email: &str) -> Result<String> {
+    with_backup_backend(|b| b.read(num, email))
+}
+
+/// Unwrap a stored backup, upgrading older formats (see `migration`).
+fn decode_backup(raw: String) -> Result<String> {
+    crate::migration::decode(&raw)
+}
+
+/// Write an account's backup along with its SHA-256 checksum.
+pub fn write_backup(num: u32, email: &str, credentials: &str) -> Result<()> {
+    with_backup_backend(|b| b.write(num, email, credentials))
+}
+
+fn write_backup_in(
+    backend: CredentialBackend,
+    num: u32,
+    email: &str,
+    credentials: &str,
+) -> Result<()> {
+    let stored = crate::migration::encode(credentials);
+    match backend {
+        CredentialBackend::File => {
             let path = cred_backup_path(num, email);
-            fs::read_to_string(&path)
-                .with_context(|| format!("Cannot read backup credentials from {}", path.display()))
+            // Claude Code reads a linked backup directly, so it stays bare.
+            let content = if is_linked(&path) { credentials } else { &stored };
+            write_file_600(&path, content)?
         }
+        other => keyring_write(other, &account_service(num, email), &stored)?,
     }
+    write_backup_checksum(backend, num, email, credentials)
 }
 
-pub fn write_backup(num: u32, email: &str, credentials: &str) -> Result<()> {
-    match detect() {
-        Platform::MacOS => keychain_write(&account_service(num, email), credentials),
-        Platform::Linux | Platform::Wsl => write_file_600(&cred_backup_path(num, email), credentials),
+/// Read an account's backup from one specific backend, with no file fallback.
+fn read_backup_in(backend: CredentialBackend, num: u32, email: &str) -> Result<String> {
+    match backend {
+        CredentialBackend::File => read_backup_file(num, email),
+        other => decode_backup(keyring_read(other, &account_service(num, email))?),
     }
 }
 
-pub fn delete_backup(num: u32, email: &str) -> Result<()> {
-    match detect() {
-        Platform::MacOS => {
-            // Ignore errors — entry may not exist
-            let _ = Command::new("security")
-                .args(["delete-generic-password", "-s", &account_service(num, email)])
-                .output();
+/// Delete an account's backup and checksum from one specific backend.
+fn delete_backup_in(backend: CredentialBackend, num: u32, email: &str) -> Result<()> {
+    match backend {
+        CredentialBackend::File => delete_backup_file(num, email),
+        other => {
+            let service = account_service(num, email);
+            keyring_delete(other, &service)?;
+            let _ = keyring_delete(other, &checksum_service(&service));
             Ok(())
         }
-        Platform::Linux | Platform::Wsl => {
-            let path = cred_backup_path(num, email);
-            if path.exists() {
-                fs::remove_file(&path)?;
-            }
-            Ok(())
+    }
+}
+
+pub fn delete_backup(num: u32, email: &str) -> Result<()> {
+    with_backup_backend(|b| b.delete(num, email))
+}
+
+// ── Backup backends ───────────────────────────────────────────────────────────
+//
+// `read_backup`, `write_backup` and `delete_backup` go through a
+// `BackupBackend` picked from the configured `CredentialBackend`. Unit tests
+// can put an `InMemoryBackend` in its place for the current thread with
+// `override_backup_backend`, so no keychain or backup file is touched.
+
+/// Stores account backups. `read` returns the credentials as written, with
+/// no checksum check; `write` records a checksum where the store keeps one.
+pub trait BackupBackend: Send + Sync {
+    fn read(&self, num: u32, email: &str) -> Result<String>;
+    fn write(&self, num: u32, email: &str, credentials: &str) -> Result<()>;
+    fn delete(&self, num: u32, email: &str) -> Result<()>;
+}
+
+/// Backup files under `credentials/`, mode 0600.
+pub struct FileBackend;
+
+impl BackupBackend for FileBackend {
+    fn read(&self, num: u32, email: &str) -> Result<String> {
+        read_backup_file(num, email)
+    }
+
+    fn write(&self, num: u32, email: &str, credentials: &str) -> Result<()> {
+        write_backup_in(CredentialBackend::File, num, email, credentials)
+    }
+
+    fn delete(&self, num: u32, email: &str) -> Result<()> {
+        delete_backup_file(num, email)
+    }
+}
+
+/// The macOS login keychain, via `security(1)`.
+pub struct MacOsKeychain;
+
+impl BackupBackend for MacOsKeychain {
+    fn read(&self, num: u32, email: &str) -> Result<String> {
+        decode_backup(keychain_read(&account_service(num, email))?)
+    }
+
+    fn write(&self, num: u32, email: &str, credentials: &str) -> Result<()> {
+        write_backup_in(CredentialBackend::Keychain, num, email, credentials)
+    }
+
+    fn delete(&self, num: u32, email: &str) -> Result<()> {
+        // Ignore errors — entry may not exist
+        let service = account_service(num, email);
+        let _ = keyring_delete(CredentialBackend::Keychain, &service);
+        let _ = keyring_delete(CredentialBackend::Keychain, &checksum_service(&service));
+        Ok(())
+    }
+}
+
+/// pass, KWallet, Secret Service or the Windows Credential Manager. Reads
+/// fall back to a file backup that has not been migrated yet.
+pub struct KeyringBackend(pub CredentialBackend);
+
+impl BackupBackend for KeyringBackend {
+    fn read(&self, num: u32, email: &str) -> Result<String> {
+        // Only a missing entry falls back to a backup not yet migrated from a
+        // file; a keyring that fails outright must not be papered over.
+        match keyring_read(self.0, &account_service(num, email)) {
+            Ok(raw) => decode_backup(raw),
+            Err(e) if is_entry_not_found(&e) => read_backup_file(num, email),
+            Err(e) => Err(e.context(format!(
+                "Cannot read the Account {num} backup from {}",
+                self.0
+            ))),
+        }
+    }
+
+    fn write(&self, num: u32, email: &str, credentials: &str) -> Result<()> {
+        write_backup_in(self.0, num, email, credentials)
+    }
+
+    fn delete(&self, num: u32, email: &str) -> Result<()> {
+        // Ignore errors — entry may not exist
+        let service = account_service(num, email);
+        let _ = keyring_delete(self.0, &service);
+        let _ = keyring_delete(self.0, &checksum_service(&service));
+        delete_backup_file(num, email)
+    }
+}
+
+/// Backups held in a map, for tests. Nothing is checksummed.
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct InMemoryBackend {
+    entries: std::sync::Mutex<std::collections::HashMap<(u32, String), String>>,
+}
+
+#[cfg(test)]
+impl InMemoryBackend {
+    /// Number of backups currently stored.
+    pub(crate) fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+}
+
+#[cfg(test)]
+impl BackupBackend for InMemoryBackend {
+    fn read(&self, num: u32, email: &str) -> Result<String> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .get(&(num, email.to_string()))
+            .cloned()
+            .with_context(|| format!("No backup for Account {num} ({email})"))
+    }
+
+    fn write(&self, num: u32, email: &str, credentials: &str) -> Result<()> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.insert((num, email.to_string()), credentials.to_string());
+        Ok(())
+    }
+
+    fn delete(&self, num: u32, email: &str) -> Result<()> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.remove(&(num, email.to_string()));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    static BACKEND_OVERRIDE: std::cell::RefCell<Option<std::sync::Arc<dyn BackupBackend>>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+/// Use `store` for this thread's backups until the returned guard is dropped.
+#[cfg(test)]
+pub(crate) fn override_backup_backend(
+    store: std::sync::Arc<dyn BackupBackend>,
+) -> BackendOverride {
+    let previous = BACKEND_OVERRIDE.with(|o| o.replace(Some(store)));
+    BackendOverride { previous }
+}
+
+/// Restores the previous backend on drop; see `override_backup_backend`.
+#[cfg(test)]
+pub(crate) struct BackendOverride {
+    previous: Option<std::sync::Arc<dyn BackupBackend>>,
+}
+
+#[cfg(test)]
+impl Drop for BackendOverride {
+    fn drop(&mut self) {
+        BACKEND_OVERRIDE.with(|o| *o.borrow_mut() = self.previous.take());
+    }
+}
+
+/// The `BackupBackend` for a configured `CredentialBackend`.
+pub fn backup_backend_for(backend: CredentialBackend) -> Box<dyn BackupBackend> {
+    match backend {
+        CredentialBackend::File => Box::new(FileBackend),
+        CredentialBackend::Keychain => Box::new(MacOsKeychain),
+        other => Box::new(KeyringBackend(other)),
+    }
+}
+
+/// Run `f` with the configured backend, or in tests this thread's override.
+fn with_backup_backend<T>(f: impl FnOnce(&dyn BackupBackend) -> T) -> T {
+    #[cfg(test)]
+    if let Some(store) = BACKEND_OVERRIDE.with(|o| o.borrow().clone()) {
+        return f(store.as_ref());
+    }
+    f(backup_backend_for(backend()).as_ref())
+}
+
+// ── Backup checksums ──────────────────────────────────────────────────────────
+//
+// Each backup has a SHA-256 of its contents beside it: a `.sha256` sidecar
+// for file backups, a `<service>-checksum` entry for keyring backups. A
+// truncated or edited backup then fails loudly instead of restoring garbage.
+// Backups written before checksums existed have none and are trusted.
+//
+// The keychain and KWallet hand back a value without its trailing newline, so
+// the checksum covers the credentials with one trailing `\n` removed.
+
+/// SHA-256 of `credentials` as any backend reads them back.
+fn backup_checksum(credentials: &str) -> String {
+    sha256_fingerprint(credentials.strip_suffix('\n').unwrap_or(credentials))
+}
+
+/// Sidecar holding the checksum of a file backup.
+pub fn checksum_file_path(backup: &Path) -> PathBuf {
+    let mut name = backup.as_os_str().to_owned();
+    name.push(".sha256");
+    PathBuf::from(name)
+}
+
+/// Keyring entry holding the checksum of a keyring backup.
+fn checksum_service(service: &str) -> String {
+    format!("{service}-checksum")
+}
+
+fn write_backup_checksum(
+    backend: CredentialBackend,
+    num: u32,
+    email: &str,
+    credentials: &str,
+) -> Result<()> {
+    let checksum = backup_checksum(credentials);
+    match backend {
+        CredentialBackend::File => {
+            write_file_600(&checksum_file_path(&cred_backup_path(num, email)), &checksum)
+        }
+        other => keyring_write(other, &checksum_service(&account_service(num, email)), &checksum),
+    }
+}
+
+/// The checksum stored for an account's backup, if any. Keyring backends fall
+/// back to the sidecar of a file backup that has not been migrated yet.
+fn stored_checksum(num: u32, email: &str) -> Option<String> {
+    let from_file = || fs::read_to_string(checksum_file_path(&cred_backup_path(num, email))).ok();
+    let stored = match backend() {
+        CredentialBackend::File => from_file(),
+        CredentialBackend::Keychain => {
+            keychain_read(&checksum_service(&account_service(num, email))).ok()
+        }
+        other => keyring_read(other, &checksum_service(&account_service(num, email)))
+            .ok()
+            .or_else(from_file),
+    };
+    stored.map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+/// Check `credentials`, as read from account `num`'s backup, against the
+/// stored checksum. Passes when no checksum was stored.
+pub fn verify_backup_checksum(num: u32, email: &str, credentials: &str) -> Result<()> {
+    let Some(expected) = stored_checksum(num, email) else { return Ok(()) };
+    let actual = backup_checksum(credentials);
+    // Checksums written before normalisation cover a trailing newline too.
+    if actual != expected && sha256_fingerprint(credentials) != expected {
+        anyhow::bail!(
+            "Credentials backup for Account {num} does not match its checksum \
+             (expected {}…, found {}…); it may be corrupted or modified. \
+             Re-add the account with `ccswitch add`.",
+            &expected[..expected.len().min(12)],
+            &actual[..12]
+        );
+    }
+    Ok(())
+}
+
+/// Copy an account's backup from `from` to `to`, then delete the original.
+/// The original is only deleted once the new copy reads back intact, so a
+/// failure at any step leaves it where it was. Returns `false` when `from`
+/// holds no backup for the account.
+pub fn migrate_backup(
+    num: u32,
+    email: &str,
+    from: CredentialBackend,
+    to: CredentialBackend,
+) -> Result<bool> {
+    if from == to {
+        anyhow::bail!("Source and destination are both the {from} backend");
+    }
+    let creds = match from {
+        CredentialBackend::File if !cred_backup_path(num, email).exists() => return Ok(false),
+        CredentialBackend::File => read_backup_file(num, email)?,
+        other => match keyring_read(other, &account_service(num, email)) {
+            Ok(raw) => decode_backup(raw)?,
+            Err(e) if is_entry_not_found(&e) => return Ok(false),
+            Err(e) => return Err(e),
+        },
+    };
+    write_backup_in(to, num, email, &creds)
+        .with_context(|| format!("Failed to write Account {num} to the {to} backend"))?;
+    if read_backup_in(to, num, email)? != creds {
+        anyhow::bail!("Migrated credentials for Account {num} did not read back correctly");
+    }
+    delete_backup_in(from, num, email)
+        .with_context(|| format!("Account {num} was copied to {to} but not removed from {from}"))?;
+    Ok(true)
+}
+
+fn read_backup_file(num: u32, email: &str) -> Result<String> {
+    let path = cred_backup_path(num, email);
+    let raw = fs::read_to_string(&path)
+        .with_context(|| format!("Cannot read backup credentials from {}", path.display()))?;
+    decode_backup(raw)
+}
+
+fn delete_backup_file(num: u32, email: &str) -> Result<()> {
+    let path = cred_backup_path(num, email);
+    for path in [checksum_file_path(&path), path] {
+        if path.exists() {
+            fs::remove_file(&path)?;
         }
     }
+    Ok(())
 }
 
 // ── Active-token slot (kept for verification / backwards compat) ──────────────
 
 /// Write the currently-active token to the platform secure store.
 /// macOS: keychain entry "ccswitch-active-token".
-/// Linux/WSL: ~/.claude-switch-backup/active-token (mode 0600).
+/// Linux/WSL/Windows: `active-token` in the data directory (mode 0600 on Unix).
 /// This is no longer the primary auth mechanism — it's kept so that
 /// `security find-generic-password -s ccswitch-active-token -w` still works
 /// as a quick verification command.
 pub fn write_active_token(token: &str) -> Result<()> {
     match detect() {
         Platform::MacOS => keychain_write(ACTIVE_TOKEN_SERVICE, token),
-        Platform::Linux | Platform::Wsl => write_file_600(&active_token_file_path(), token),
+        Platform::Linux | Platform::Wsl | Platform::Windows => {
+            write_file_600(&active_token_file_path(), token)
+        }
     }
 }
 
@@ -124,29 +759,127 @@ pub fn write_live_token(token: &str) -> Result<()> {
 
 /// Path to the active-token file used on Linux/WSL.
 pub fn active_token_file_path() -> PathBuf {
-    backup_dir().join("active-token")
+    crate::paths::env_path(crate::paths::BACKUP_DIR_ENV)
+        .unwrap_or_else(crate::profiles::root_dir)
+        .join("active-token")
 }
 
 /// Path to the shell-sourced rc file managed by ccswitch.
 pub fn ccswitchrc_path() -> PathBuf {
-    dirs::home_dir()
+    #[cfg(test)]
+    if let Ok(dir) = std::env::var("CCSWITCH_TEST_DIR") {
+        return PathBuf::from(dir).join(".ccswitchrc");
+    }
+    crate::paths::home_dir()
         .expect("Cannot find home directory")
         .join(".ccswitchrc")
 }
 
+/// Shell variable the rc file sets to the active account's email.
+const ACTIVE_ACCOUNT_VAR: &str = "CCSWITCH_ACTIVE_ACCOUNT";
+
 /// ccswitch now manages all accounts via the system credentials keychain.
 /// The rc file only needs to unset CLAUDE_CODE_OAUTH_TOKEN so Claude Code
-/// reads from the keychain instead of an env var override.
-fn ccswitchrc_content() -> &'static str {
-    concat!(
-        "# Managed by ccswitch — do not edit manually\n",
-        "# ccswitch writes all credentials directly to the system keychain.\n",
-        "# Unsetting CLAUDE_CODE_OAUTH_TOKEN ensures Claude Code reads from\n",
-        "# the keychain so account switches take effect on next restart.\n",
-        "unset CLAUDE_CODE_OAUTH_TOKEN\n",
+/// reads from the keychain instead of an env var override, and to export the
+/// active account for scripts.
+fn ccswitchrc_content() -> String {
+    format!(
+        "{}{}",
+        concat!(
+            "# Managed by ccswitch — do not edit manually\n",
+            "# ccswitch writes all credentials directly to the system keychain.\n",
+            "# Unsetting CLAUDE_CODE_OAUTH_TOKEN ensures Claude Code reads from\n",
+            "# the keychain so account switches take effect on next restart.\n",
+            "unset CLAUDE_CODE_OAUTH_TOKEN\n",
+        ),
+        active_account_export()
+    )
+}
+
+/// Export of `ACTIVE_ACCOUNT_VAR`, read from `.active` when the shell starts
+/// so the rc file never needs rewriting on a switch.
+fn active_account_export() -> String {
+    let path = crate::sequence::active_account_path();
+    format!(
+        "export {ACTIVE_ACCOUNT_VAR}=\"$(cat '{}' 2>/dev/null)\"\n",
+        path.display().to_string().replace('\'', "'\\''")
     )
 }
 
+/// Overwrite ~/.ccswitchrc (mode 0600), keeping the fish copy in step once
+/// `shell-setup` has created it.
+pub fn write_ccswitchrc(content: &str) -> Result<()> {
+    write_file_600(&ccswitchrc_path(), content)?;
+    let fish = fish_env_path();
+    if fish.exists() {
+        write_file_600(&fish, &fish_env_content(content))?;
+    }
+    Ok(())
+}
+
+/// Fish can't source ~/.ccswitchrc, so fish users source this translation.
+pub fn fish_env_path() -> PathBuf {
+    crate::paths::config_dir().join("env.fish")
+}
+
+/// Write `fish_env_path()` from the current ~/.ccswitchrc.
+pub fn ensure_fish_env() -> Result<PathBuf> {
+    ensure_ccswitchrc()?;
+    let rc = fs::read_to_string(ccswitchrc_path())
+        .with_context(|| format!("Cannot read {}", ccswitchrc_path().display()))?;
+    let path = fish_env_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    write_file_600(&path, &fish_env_content(&rc))?;
+    Ok(path)
+}
+
+/// Translate the rc file's `unset`/`export` lines to fish. Values are
+/// re-quoted, since fish treats `\\` and `\'` inside single quotes as escapes
+/// where a POSIX shell keeps them literally.
+fn fish_env_content(rc: &str) -> String {
+    rc.lines()
+        .map(|line| {
+            if let Some(var) = line.strip_prefix("unset ") {
+                format!("set -e {var}\n")
+            } else if line.starts_with(&format!("export {ACTIVE_ACCOUNT_VAR}=")) {
+                let path = crate::sequence::active_account_path();
+                format!(
+                    "set -gx {ACTIVE_ACCOUNT_VAR} (cat {} 2>/dev/null)\n",
+                    fish_quote(&path.display().to_string())
+                )
+            } else if let Some((key, value)) =
+                line.strip_prefix("export ").and_then(|l| l.split_once('='))
+            {
+                format!("set -gx {key} {}\n", fish_quote(&posix_unquote(value)))
+            } else {
+                format!("{line}\n")
+            }
+        })
+        .collect()
+}
+
+/// The word a POSIX shell reads from `s`: single-quoted runs are literal and
+/// a backslash outside quotes escapes the next character.
+fn posix_unquote(s: &str) -> String {
+    let mut out = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => out.extend(chars.by_ref().take_while(|&c| c != '\'')),
+            '\\' => out.extend(chars.next()),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Single-quote `s` for fish.
+fn fish_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
 /// Write ~/.ccswitchrc if it does not exist, or upgrade it if it is outdated.
 /// Returns true only when the file is newly created (caller may show a hint).
 pub fn ensure_ccswitchrc() -> Result<bool> {
@@ -154,8 +887,7 @@ pub fn ensure_ccswitchrc() -> Result<bool> {
     let content = ccswitchrc_content();
 
     if !path.exists() {
-        fs::write(&path, content)
-            .with_context(|| format!("Cannot write {}", path.display()))?;
+        write_ccswitchrc(&content)?;
         return Ok(true);
     }
 
@@ -166,8 +898,15 @@ pub fn ensure_ccswitchrc() -> Result<bool> {
     if existing.contains("export CLAUDE_CODE_OAUTH_TOKEN")
         || !existing.contains("unset CLAUDE_CODE_OAUTH_TOKEN")
     {
-        fs::write(&path, content)
-            .with_context(|| format!("Cannot write {}", path.display()))?;
+        write_ccswitchrc(&content)?;
+    } else if !existing.contains(&format!("export {ACTIVE_ACCOUNT_VAR}=")) {
+        // Written before the active-account export: add it, keeping the env section.
+        let unset = "unset CLAUDE_CODE_OAUTH_TOKEN\n";
+        let upgraded = match existing.split_once(unset) {
+            Some((head, tail)) => format!("{head}{unset}{}{tail}", active_account_export()),
+            None => format!("{existing}\n{}", active_account_export()),
+        };
+        write_ccswitchrc(&upgraded)?;
     }
 
     Ok(false)
@@ -179,8 +918,32 @@ pub fn ensure_ccswitchrc() -> Result<bool> {
 /// OAuth credentials JSON blob.  Returns `None` for token accounts or if the
 /// field is absent.
 pub fn oauth_expires_at(creds_json: &str) -> Option<i64> {
+    parse_expiry(creds_json).map(|t| t.timestamp_millis())
+}
+
+/// When a credentials blob expires. Looks in `claudeAiOauth` first, then at the
+/// top level, for `expiresAt` (epoch milliseconds, epoch seconds or an RFC 3339
+/// string) or `expires_in` (seconds from now, as in a raw OAuth token response).
+pub fn parse_expiry(creds_json: &str) -> Option<chrono::DateTime<chrono::Utc>> {
     let v: serde_json::Value = serde_json::from_str(creds_json).ok()?;
-    v.get("claudeAiOauth")?.get("expiresAt")?.as_i64()
+    let obj = v.get("claudeAiOauth").filter(|o| o.is_object()).unwrap_or(&v);
+    match obj.get("expiresAt") {
+        // Anything below 10^11 is too small for milliseconds, so read it as seconds.
+        Some(serde_json::Value::Number(n)) => n.as_i64().and_then(|n| {
+            if n < 100_000_000_000 {
+                chrono::DateTime::from_timestamp(n, 0)
+            } else {
+                chrono::DateTime::from_timestamp_millis(n)
+            }
+        }),
+        Some(serde_json::Value::String(s)) => chrono::DateTime::parse_from_rfc3339(s)
+            .ok()
+            .map(|t| t.with_timezone(&chrono::Utc)),
+        _ => obj
+            .get("expires_in")?
+            .as_i64()
+            .map(|secs| chrono::Utc::now() + chrono::Duration::seconds(secs)),
+    }
 }
 
 /// Returns `true` if the OAuth session is still active (not yet expired).
@@ -281,6 +1044,26 @@ pub fn refresh_oauth_creds(creds_json: &str) -> Result<String> {
     serde_json::to_string(&v).context("Failed to serialize updated credentials")
 }
 
+/// Send `token` to the Claude API (`GET /v1/models`) and return the HTTP
+/// status. Errors only when the API could not be reached within 10 seconds.
+pub fn check_token(token: &str) -> Result<u16> {
+    let base = std::env::var(API_BASE_ENV).unwrap_or_else(|_| DEFAULT_API_BASE.to_string());
+    let url = format!("{}/v1/models", base.trim_end_matches('/'));
+    let resp = ureq::AgentBuilder::new()
+        .timeout(VALIDATE_TIMEOUT)
+        .build()
+        .get(&url)
+        .set("Authorization", &format!("Bearer {token}"))
+        .set("anthropic-version", "2023-06-01")
+        .set("anthropic-beta", OAUTH_BETA_HEADER)
+        .call();
+    match resp {
+        Ok(r) => Ok(r.status()),
+        Err(ureq::Error::Status(code, _)) => Ok(code),
+        Err(e) => anyhow::bail!("Could not reach {url}: {e}"),
+    }
+}
+
 // ── Format sentinel ───────────────────────────────────────────────────────────
 
 /// Compute a fingerprint of the top-level keys inside `claudeAiOauth`.
@@ -302,37 +1085,249 @@ pub fn credential_field_fingerprint(creds_str: &str) -> String {
     keys.join("|")
 }
 
-// ── Helpers ───────────────────────────────────────────────────────────────────
-
-fn account_service(num: u32, email: &str) -> String {
-    format!("Claude Code-Account-{num}-{email}")
+/// Hex SHA-256 of a credentials blob, safe to display in place of the secret.
+pub fn sha256_fingerprint(raw: &str) -> String {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(raw.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
 }
 
-fn creds_file_path() -> PathBuf {
-    #[cfg(test)]
-    if let Ok(dir) = std::env::var("CCSWITCH_TEST_DIR") {
-        return PathBuf::from(dir).join(".credentials.json");
+/// Check that a stored credentials blob has a shape ccswitch can restore:
+/// either `{"token": "..."}` (token accounts) or a `claudeAiOauth` object
+/// with `accessToken`, `refreshToken` and a numeric `expiresAt`.
+pub fn validate_credential_format(raw: &str) -> Result<()> {
+    let v: serde_json::Value = serde_json::from_str(raw).context("Credentials are not valid JSON")?;
+
+    if let Some(token) = v.get("token") {
+        return match token.as_str() {
+            Some(t) if !t.trim().is_empty() => Ok(()),
+            _ => anyhow::bail!("`token` is empty or not a string"),
+        };
     }
-    dirs::home_dir()
-        .unwrap()
-        .join(".claude")
-        .join(".credentials.json")
+
+    let oauth = v
+        .get("claudeAiOauth")
+        .and_then(|o| o.as_object())
+        .context("Neither a `token` key nor a `claudeAiOauth` object is present")?;
+    for key in ["accessToken", "refreshToken"] {
+        if !oauth.get(key).is_some_and(|t| t.is_string()) {
+            anyhow::bail!("`claudeAiOauth.{key}` is missing or not a string");
+        }
+    }
+    if !oauth.get("expiresAt").is_some_and(|t| t.is_i64()) {
+        anyhow::bail!("`claudeAiOauth.expiresAt` is missing or not a number");
+    }
+    Ok(())
 }
 
-fn cred_backup_path(num: u32, email: &str) -> PathBuf {
-    backup_dir()
-        .join("credentials")
+/// Like `validate_credential_format`, but also require the shape that matches
+/// the account's kind, so a token blob stored for an OAuth account is caught.
+pub fn validate_credentials_shape(raw: &str, kind: &AuthKind) -> Result<()> {
+    let v: serde_json::Value = serde_json::from_str(raw).context("Credentials are not valid JSON")?;
+    match kind {
+        AuthKind::Token if v.get("token").is_none() => {
+            anyhow::bail!("`token` key is missing (expected a token account backup)")
+        }
+        AuthKind::Oauth if v.get("claudeAiOauth").is_none() => {
+            anyhow::bail!("`claudeAiOauth` object is missing (expected an OAuth account backup)")
+        }
+        _ => validate_credential_format(raw),
+    }
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────────
+
+/// Keyring entry name for an account backup. Non-default profiles get their
+/// own prefix so account numbers can repeat across profiles.
+fn account_service(num: u32, email: &str) -> String {
+    format!("{}{num}-{email}", account_service_prefix())
+}
+
+fn account_service_prefix() -> String {
+    let profile = crate::profiles::current();
+    if crate::profiles::is_default(&profile) {
+        "Claude Code-Account-".to_string()
+    } else {
+        format!("Claude Code-{profile}-Account-")
+    }
+}
+
+/// Split a backup name of the form `<prefix><num>-<email>[<suffix>]` into its
+/// account number and email.
+pub fn parse_backup_name(name: &str, prefix: &str, suffix: &str) -> Option<(u32, String)> {
+    let rest = name.strip_prefix(prefix)?.strip_suffix(suffix)?;
+    let (num, email) = rest.split_once('-')?;
+    Some((num.parse().ok()?, email.to_string()))
+}
+
+/// Account number and email of a keychain service belonging to the current profile.
+pub fn parse_account_service(service: &str) -> Option<(u32, String)> {
+    parse_backup_name(service, &account_service_prefix(), "")
+}
+
+/// Service names from `security dump-keychain` output (the `"svce"` attribute).
+fn parse_keychain_services(dump: &str) -> Vec<String> {
+    dump.lines()
+        .filter_map(|line| line.trim().strip_prefix("\"svce\"<blob>=\""))
+        .filter_map(|rest| rest.strip_suffix('"'))
+        .map(String::from)
+        .collect()
+}
+
+/// Keychain entries holding account backups for the current profile.
+/// Empty unless the keychain backend is in use.
+pub fn keychain_account_services() -> Result<Vec<String>> {
+    if backend() != CredentialBackend::Keychain {
+        return Ok(Vec::new());
+    }
+    let output = run_security(&["dump-keychain"])?;
+    if !output.status.success() {
+        anyhow::bail!("`security dump-keychain` failed");
+    }
+    let mut services: Vec<String> = parse_keychain_services(&String::from_utf8_lossy(&output.stdout))
+        .into_iter()
+        .filter(|s| !s.ends_with("-checksum") && parse_account_service(s).is_some())
+        .collect();
+    services.sort();
+    services.dedup();
+    Ok(services)
+}
+
+/// Delete a keychain entry found by `keychain_account_services`, and its checksum.
+pub fn delete_keychain_service(service: &str) -> Result<()> {
+    keyring_delete(CredentialBackend::Keychain, service)?;
+    let _ = keyring_delete(CredentialBackend::Keychain, &checksum_service(service));
+    Ok(())
+}
+
+/// Directory holding file-based credential backups for the current profile.
+pub fn backup_files_dir() -> PathBuf {
+    backup_dir().join("credentials")
+}
+
+fn creds_file_path() -> PathBuf {
+    #[cfg(test)]
+    if let Ok(dir) = std::env::var("CCSWITCH_TEST_DIR") {
+        return PathBuf::from(dir).join(".credentials.json");
+    }
+    crate::paths::home_dir()
+        .unwrap()
+        .join(".claude")
+        .join(".credentials.json")
+}
+
+fn cred_backup_path(num: u32, email: &str) -> PathBuf {
+    backup_files_dir()
         .join(format!(".claude-credentials-{num}-{email}.json"))
 }
 
+/// Run `security` with the configured keychain timeout.
+fn run_security(args: &[&str]) -> Result<Output> {
+    run_store_command("security", args, None)
+}
+
+/// Run a secure-store CLI with the configured keychain timeout, optionally
+/// feeding `input` on stdin.
+///
+/// A keychain prompt (e.g. Touch ID on a headless build agent, or a locked
+/// login keyring) can block indefinitely, so the child is polled and killed
+/// once the timeout elapses.
+fn run_store_command(program: &str, args: &[&str], input: Option<&str>) -> Result<Output> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run `{program}` command"))?;
+
+    // Feed stdin from its own thread: a helper that never reads it would
+    // otherwise block the write before the timeout below starts counting.
+    // Dropping stdin at the end of the thread closes the pipe so the child
+    // sees EOF.
+    let writer = match input {
+        Some(input) => {
+            let mut stdin = child.stdin.take().context("Failed to open stdin")?;
+            let input = input.to_owned();
+            Some(std::thread::spawn(move || stdin.write_all(input.as_bytes())))
+        }
+        None => None,
+    };
+
+    // Drain stdout and stderr while waiting: a helper that fills a pipe buffer
+    // (e.g. `security dump-keychain`) would otherwise block until killed.
+    let stdout = child.stdout.take().map(read_to_end_in_thread);
+    let stderr = child.stderr.take().map(read_to_end_in_thread);
+
+    let timeout_ms = KEYCHAIN_TIMEOUT_MS.load(Ordering::Relaxed);
+    let status = if timeout_ms > 0 {
+        let waited = child
+            .wait_timeout(Duration::from_millis(timeout_ms))
+            .with_context(|| format!("Failed to wait for `{program}` command"))?;
+        match waited {
+            Some(status) => status,
+            None => {
+                let _ = child.kill();
+                let _ = child.wait();
+                let waited = if timeout_ms.is_multiple_of(1000) {
+                    format!("{}s", timeout_ms / 1000)
+                } else {
+                    format!("{timeout_ms}ms")
+                };
+                anyhow::bail!(
+                    "Keychain operation timed out after {waited}. \
+                     Is a keychain prompt waiting for approval?"
+                );
+            }
+        }
+    } else {
+        child
+            .wait()
+            .with_context(|| format!("Failed to wait for `{program}` command"))?
+    };
+
+    let collect = |reader: Option<std::thread::JoinHandle<io::Result<Vec<u8>>>>| {
+        reader.map_or(Ok(Vec::new()), |reader| {
+            reader
+                .join()
+                .map_err(|_| anyhow::anyhow!("Reading from `{program}` panicked"))?
+                .with_context(|| format!("Failed to read `{program}` command output"))
+        })
+    };
+    let output = Output { status, stdout: collect(stdout)?, stderr: collect(stderr)? };
+    if let Some(writer) = writer {
+        writer
+            .join()
+            .map_err(|_| anyhow::anyhow!("Writing to `{program}` panicked"))?
+            .with_context(|| format!("Failed to write to `{program}` command"))?;
+    }
+    Ok(output)
+}
+
+/// Read `pipe` to the end on its own thread.
+fn read_to_end_in_thread<R: io::Read + Send + 'static>(
+    mut pipe: R,
+) -> std::thread::JoinHandle<io::Result<Vec<u8>>> {
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        pipe.read_to_end(&mut buf).map(|_| buf)
+    })
+}
+
 fn keychain_read(service: &str) -> Result<String> {
-    let output = Command::new("security")
-        .args(["find-generic-password", "-s", service, "-w"])
-        .output()
-        .context("Failed to run `security` command")?;
+    let spinner = crate::spinner::Spinner::start("Reading keychain…");
+    let output = run_security(&["find-generic-password", "-s", service, "-w"])?;
+    drop(spinner);
 
+    // security(1) exits with errSecItemNotFound (44) for a missing item.
+    if output.status.code() == Some(44) {
+        return Err(entry_not_found("keychain", service));
+    }
     if !output.status.success() {
-        anyhow::bail!("No keychain entry found for service: {service}");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Failed to read keychain entry {service}: {stderr}");
     }
 
     let mut val = String::from_utf8(output.stdout).context("Keychain returned non-UTF8 data")?;
@@ -345,19 +1340,18 @@ fn keychain_read(service: &str) -> Result<String> {
 
 fn keychain_write(service: &str, value: &str) -> Result<()> {
     let user = std::env::var("USER").unwrap_or_default();
-    let output = Command::new("security")
-        .args([
-            "add-generic-password",
-            "-U",
-            "-s",
-            service,
-            "-a",
-            &user,
-            "-w",
-            value,
-        ])
-        .output()
-        .context("Failed to run `security` command")?;
+    let spinner = crate::spinner::Spinner::start("Writing keychain…");
+    let output = run_security(&[
+        "add-generic-password",
+        "-U",
+        "-s",
+        service,
+        "-a",
+        &user,
+        "-w",
+        value,
+    ])?;
+    drop(spinner);
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -366,6 +1360,356 @@ fn keychain_write(service: &str, value: &str) -> Result<()> {
     Ok(())
 }
 
+// ── Keyring backends ──────────────────────────────────────────────────────────
+
+/// The error a keyring read returns when the store has no such entry, as
+/// opposed to the store itself failing.
+fn entry_not_found(store: &str, service: &str) -> anyhow::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("No {store} entry found for service: {service}"),
+    )
+    .into()
+}
+
+fn is_entry_not_found(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<io::Error>()
+        .is_some_and(|e| e.kind() == io::ErrorKind::NotFound)
+}
+
+fn keyring_read(backend: CredentialBackend, service: &str) -> Result<String> {
+    match backend {
+        CredentialBackend::Keychain => keychain_read(service),
+        CredentialBackend::Pass => pass_read(service),
+        CredentialBackend::KWallet => kwallet_read(service),
+        CredentialBackend::SecretService => secret_tool_read(service),
+        CredentialBackend::WindowsCredentialManager => read_backup_windows(service),
+        CredentialBackend::File => anyhow::bail!("The file backend has no keyring"),
+    }
+}
+
+fn keyring_write(backend: CredentialBackend, service: &str, value: &str) -> Result<()> {
+    match backend {
+        CredentialBackend::Keychain => keychain_write(service, value),
+        CredentialBackend::Pass => pass_write(service, value),
+        CredentialBackend::KWallet => kwallet_write(service, value),
+        CredentialBackend::SecretService => secret_tool_write(service, value),
+        CredentialBackend::WindowsCredentialManager => write_backup_windows(service, value),
+        CredentialBackend::File => anyhow::bail!("The file backend has no keyring"),
+    }
+}
+
+fn keyring_delete(backend: CredentialBackend, service: &str) -> Result<()> {
+    match backend {
+        CredentialBackend::Keychain => {
+            run_security(&["delete-generic-password", "-s", service])?;
+            Ok(())
+        }
+        CredentialBackend::Pass => pass_delete(service),
+        CredentialBackend::KWallet => kwallet_delete(service),
+        CredentialBackend::SecretService => secret_tool_clear(service),
+        CredentialBackend::WindowsCredentialManager => delete_backup_windows(service),
+        CredentialBackend::File => Ok(()),
+    }
+}
+
+/// Program name for a secure-store CLI; tests point these at fake scripts
+/// via `CCSWITCH_TEST_BIN_<NAME>` (e.g. `CCSWITCH_TEST_BIN_SECRET_TOOL`).
+fn store_program(name: &str) -> String {
+    #[cfg(test)]
+    if let Ok(p) = std::env::var(format!(
+        "CCSWITCH_TEST_BIN_{}",
+        name.to_uppercase().replace('-', "_")
+    )) {
+        return p;
+    }
+    name.to_string()
+}
+
+// pass: entries live under `ccswitch/<service>` in the password store.
+
+fn pass_entry(service: &str) -> String {
+    format!("ccswitch/{service}")
+}
+
+fn pass_read(service: &str) -> Result<String> {
+    let output = run_store_command(&store_program("pass"), &["show", &pass_entry(service)], None)?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stderr.contains("is not in the password store") {
+        return Err(entry_not_found("pass", service));
+    }
+    if !output.status.success() {
+        anyhow::bail!("Failed to read pass entry {service}: {stderr}");
+    }
+    if output.stdout.is_empty() {
+        return Err(entry_not_found("pass", service));
+    }
+    let mut val = String::from_utf8(output.stdout).context("pass returned non-UTF8 data")?;
+    if val.ends_with('\n') {
+        val.pop();
+    }
+    Ok(val)
+}
+
+fn pass_write(service: &str, value: &str) -> Result<()> {
+    // --multiline reads the secret from stdin until EOF instead of prompting twice.
+    let output = run_store_command(
+        &store_program("pass"),
+        &["insert", "--force", "--multiline", &pass_entry(service)],
+        Some(value),
+    )?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Failed to write to pass: {stderr}");
+    }
+    Ok(())
+}
+
+fn pass_delete(service: &str) -> Result<()> {
+    run_store_command(&store_program("pass"), &["rm", "-f", &pass_entry(service)], None)?;
+    Ok(())
+}
+
+// KWallet: entries live in the "ccswitch" folder of the default wallet.
+const KWALLET_WALLET: &str = "kdewallet";
+const KWALLET_FOLDER: &str = "ccswitch";
+
+/// The running KWallet daemon (`kwalletd6` or `kwalletd5`), if any.
+fn running_kwalletd() -> Option<&'static str> {
+    let procs = fs::read_dir("/proc").ok()?;
+    let names: Vec<String> = procs
+        .flatten()
+        .filter_map(|e| fs::read_to_string(e.path().join("comm")).ok())
+        .map(|c| c.trim().to_string())
+        .collect();
+    ["kwalletd6", "kwalletd5"]
+        .into_iter()
+        .find(|d| names.iter().any(|n| n == d))
+}
+
+fn kwallet_read(service: &str) -> Result<String> {
+    let output = run_store_command(
+        &store_program("kwallet-query"),
+        &["-f", KWALLET_FOLDER, "-r", service, KWALLET_WALLET],
+        None,
+    )?;
+    // kwallet-query reports a missing folder or entry on stderr; anything
+    // else (e.g. the wallet failing to open) is a real failure.
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stderr.contains("does not exist") || stderr.contains("Failed to read entry") {
+        return Err(entry_not_found("KWallet", service));
+    }
+    if !output.status.success() {
+        anyhow::bail!("Failed to read KWallet entry {service}: {stderr}");
+    }
+    if output.stdout.is_empty() {
+        return Err(entry_not_found("KWallet", service));
+    }
+    let mut val = String::from_utf8(output.stdout).context("KWallet returned non-UTF8 data")?;
+    // Strip trailing newline added by kwallet-query
+    if val.ends_with('\n') {
+        val.pop();
+    }
+    Ok(val)
+}
+
+fn kwallet_write(service: &str, value: &str) -> Result<()> {
+    let output = run_store_command(
+        &store_program("kwallet-query"),
+        &["-f", KWALLET_FOLDER, "-w", service, KWALLET_WALLET],
+        Some(value),
+    )?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Failed to write to KWallet: {stderr}");
+    }
+    Ok(())
+}
+
+/// `kwallet-query` cannot delete, so talk to kwalletd over D-Bus directly:
+/// open the wallet to get a handle, then `removeEntry`.
+fn kwallet_delete(service: &str) -> Result<()> {
+    let daemon = running_kwalletd().unwrap_or("kwalletd6");
+    let dest = format!("--dest=org.kde.{daemon}");
+    let object = format!("/modules/{daemon}");
+    let dbus_send = store_program("dbus-send");
+    let base = ["--session", "--print-reply=literal", dest.as_str(), object.as_str()];
+
+    let wallet = format!("string:{KWALLET_WALLET}");
+    let app = format!("string:{KWALLET_FOLDER}");
+    let mut args = base.to_vec();
+    args.extend(["org.kde.KWallet.open", &wallet, "int64:0", &app]);
+    let output = run_store_command(&dbus_send, &args, None)?;
+    let handle = String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .last()
+        .and_then(|h| h.parse::<i32>().ok())
+        .filter(|h| *h >= 0)
+        .context("Failed to open KWallet over D-Bus")?;
+
+    let handle = format!("int32:{handle}");
+    let folder = format!("string:{KWALLET_FOLDER}");
+    let key = format!("string:{service}");
+    let mut args = base.to_vec();
+    args.extend(["org.kde.KWallet.removeEntry", &handle, &folder, &key, &app]);
+    let output = run_store_command(&dbus_send, &args, None);
+
+    // Release the handle whether or not the removal worked.
+    let mut args = base.to_vec();
+    args.extend(["org.kde.KWallet.close", &handle, "boolean:false", &app]);
+    let _ = run_store_command(&dbus_send, &args, None);
+
+    let output = output?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Failed to delete KWallet entry: {stderr}");
+    }
+    Ok(())
+}
+
+// libsecret attributes: every ccswitch item is tagged `application=ccswitch`
+// and keyed by the same service name the macOS keychain uses.
+
+
+fn secret_tool_read(service: &str) -> Result<String> {
+    let output = run_store_command(
+        &store_program("secret-tool"),
+        &["lookup", "application", "ccswitch", "service", service],
+        None,
+    )?;
+    // `secret-tool lookup` exits 1 without a message when nothing matches.
+    if !output.status.success() && !output.stderr.is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Failed to read Secret Service entry {service}: {stderr}");
+    }
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(entry_not_found("Secret Service", service));
+    }
+    String::from_utf8(output.stdout).context("Secret Service returned non-UTF8 data")
+}
+
+fn secret_tool_write(service: &str, value: &str) -> Result<()> {
+    let label = format!("--label=ccswitch: {service}");
+    let output = run_store_command(
+        &store_program("secret-tool"),
+        &["store", &label, "application", "ccswitch", "service", service],
+        Some(value),
+    )?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Failed to write to Secret Service: {stderr}");
+    }
+    Ok(())
+}
+
+fn secret_tool_clear(service: &str) -> Result<()> {
+    run_store_command(
+        &store_program("secret-tool"),
+        &["clear", "application", "ccswitch", "service", service],
+        None,
+    )?;
+    Ok(())
+}
+
+// Windows Credential Manager: generic credentials whose target name is the
+// service. Blobs are capped at CRED_MAX_CREDENTIAL_BLOB_SIZE (2560 bytes),
+// which comfortably fits an OAuth credentials document.
+
+#[cfg(windows)]
+fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+#[cfg(windows)]
+fn read_backup_windows(service: &str) -> Result<String> {
+    use windows_sys::Win32::Foundation::ERROR_NOT_FOUND;
+    use windows_sys::Win32::Security::Credentials::{
+        CredFree, CredReadW, CREDENTIALW, CRED_TYPE_GENERIC,
+    };
+
+    let target = wide(service);
+    let mut cred: *mut CREDENTIALW = std::ptr::null_mut();
+    // SAFETY: `target` is NUL-terminated and outlives the call; on success
+    // `cred` points at a buffer we own until CredFree.
+    unsafe {
+        if CredReadW(target.as_ptr(), CRED_TYPE_GENERIC, 0, &mut cred) == 0 {
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() == Some(ERROR_NOT_FOUND as i32) {
+                return Err(entry_not_found("Credential Manager", service));
+            }
+            return Err(err).with_context(|| {
+                format!("Failed to read Credential Manager entry {service}")
+            });
+        }
+        let blob = std::slice::from_raw_parts(
+            (*cred).CredentialBlob,
+            (*cred).CredentialBlobSize as usize,
+        );
+        let val = String::from_utf8(blob.to_vec());
+        CredFree(cred as *const std::ffi::c_void);
+        val.context("Credential Manager returned non-UTF8 data")
+    }
+}
+
+#[cfg(windows)]
+fn write_backup_windows(service: &str, value: &str) -> Result<()> {
+    use windows_sys::Win32::Security::Credentials::{
+        CredWriteW, CREDENTIALW, CRED_PERSIST_LOCAL_MACHINE, CRED_TYPE_GENERIC,
+    };
+
+    let mut target = wide(service);
+    let mut user = wide(&std::env::var("USERNAME").unwrap_or_default());
+    let mut blob = value.as_bytes().to_vec();
+    // SAFETY: every pointer in `cred` borrows a buffer that lives until the
+    // call returns; CredWriteW copies what it keeps.
+    unsafe {
+        let mut cred: CREDENTIALW = std::mem::zeroed();
+        cred.Type = CRED_TYPE_GENERIC;
+        cred.TargetName = target.as_mut_ptr();
+        cred.UserName = user.as_mut_ptr();
+        cred.CredentialBlobSize = blob.len() as u32;
+        cred.CredentialBlob = blob.as_mut_ptr();
+        cred.Persist = CRED_PERSIST_LOCAL_MACHINE;
+        if CredWriteW(&cred, 0) == 0 {
+            anyhow::bail!(
+                "Failed to write to Credential Manager: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn delete_backup_windows(service: &str) -> Result<()> {
+    use windows_sys::Win32::Security::Credentials::{CredDeleteW, CRED_TYPE_GENERIC};
+
+    let target = wide(service);
+    // SAFETY: `target` is NUL-terminated and outlives the call.
+    if unsafe { CredDeleteW(target.as_ptr(), CRED_TYPE_GENERIC, 0) } == 0 {
+        anyhow::bail!(
+            "Failed to delete Credential Manager entry: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn read_backup_windows(_service: &str) -> Result<String> {
+    anyhow::bail!("Windows Credential Manager is only available on Windows")
+}
+
+#[cfg(not(windows))]
+fn write_backup_windows(_service: &str, _value: &str) -> Result<()> {
+    anyhow::bail!("Windows Credential Manager is only available on Windows")
+}
+
+#[cfg(not(windows))]
+fn delete_backup_windows(_service: &str) -> Result<()> {
+    anyhow::bail!("Windows Credential Manager is only available on Windows")
+}
+
 fn write_file_600(path: &PathBuf, content: &str) -> Result<()> {
     fs::write(path, content)
         .with_context(|| format!("Cannot write to {}", path.display()))?;
@@ -379,6 +1723,7 @@ fn write_file_600(path: &PathBuf, content: &str) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Instant;
 
     fn make_oauth_creds(expires_at_ms: i64) -> String {
         serde_json::json!({
@@ -510,4 +1855,515 @@ mod tests {
             credential_field_fingerprint(changed)
         );
     }
+
+    #[test]
+    fn test_sha256_fingerprint_known_value() {
+        assert_eq!(
+            sha256_fingerprint("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_fish_env_content() {
+        let rc = "# Managed by ccswitch\nunset CLAUDE_CODE_OAUTH_TOKEN\nexport A='it'\\''s'\n";
+        assert_eq!(
+            fish_env_content(rc),
+            "# Managed by ccswitch\nset -e CLAUDE_CODE_OAUTH_TOKEN\nset -gx A 'it\\'s'\n"
+        );
+        // POSIX keeps a backslash in single quotes literally; fish needs it doubled.
+        assert_eq!(
+            fish_env_content(r"export P='C:\tmp\'"),
+            "set -gx P 'C:\\\\tmp\\\\'\n"
+        );
+    }
+
+    #[test]
+    fn test_ensure_ccswitchrc_adds_active_account_export() {
+        let _env = crate::test_utils::TestEnv::new();
+        let head = "# Managed by ccswitch\nunset CLAUDE_CODE_OAUTH_TOKEN\n";
+        let section = "# >>> ccswitch account env >>>\nexport A='1'\n# <<< ccswitch account env <<<\n";
+        let old = format!("{head}{section}");
+        fs::write(ccswitchrc_path(), &old).unwrap();
+
+        assert!(!ensure_ccswitchrc().unwrap());
+        let rc = fs::read_to_string(ccswitchrc_path()).unwrap();
+        let active = crate::sequence::active_account_path();
+        let export = format!(
+            "export CCSWITCH_ACTIVE_ACCOUNT=\"$(cat '{}' 2>/dev/null)\"\n",
+            active.display()
+        );
+        assert_eq!(rc, format!("{head}{export}{section}"));
+        assert!(fish_env_content(&rc).contains(&format!(
+            "set -gx CCSWITCH_ACTIVE_ACCOUNT (cat '{}' 2>/dev/null)\n",
+            active.display()
+        )));
+
+        // Already upgraded: left alone.
+        ensure_ccswitchrc().unwrap();
+        assert_eq!(fs::read_to_string(ccswitchrc_path()).unwrap(), rc);
+    }
+
+    #[test]
+    fn test_describe_keychain() {
+        assert_eq!(
+            describe_keychain("keychain: \"/Users/me/Library/Keychains/login.keychain-db\"\nclass: \"genp\"\n")
+                .as_deref(),
+            Some("user keychain (/Users/me/Library/Keychains/login.keychain-db)")
+        );
+        assert_eq!(
+            describe_keychain("keychain: \"/Library/Keychains/System.keychain\"\n").as_deref(),
+            Some("system keychain (/Library/Keychains/System.keychain)")
+        );
+        assert_eq!(describe_keychain("class: \"genp\"\n"), None);
+    }
+
+    #[test]
+    fn test_parse_keychain_services_and_account_names() {
+        let dump = r#"keychain: "/Users/me/Library/Keychains/login.keychain-db"
+class: "genp"
+attributes:
+    "svce"<blob>="Claude Code-Account-2-a-b@test.com"
+    "svce"<blob>="Claude Code-credentials"
+    "svce"<blob>=0x00FF
+"#;
+        let services = parse_keychain_services(dump);
+        assert_eq!(services, vec!["Claude Code-Account-2-a-b@test.com", "Claude Code-credentials"]);
+        assert_eq!(
+            parse_account_service(&services[0]),
+            Some((2, "a-b@test.com".to_string()))
+        );
+        assert_eq!(parse_account_service(&services[1]), None);
+        assert_eq!(
+            parse_backup_name(".claude-credentials-3-x@y.json", ".claude-credentials-", ".json"),
+            Some((3, "x@y".to_string()))
+        );
+        assert_eq!(parse_backup_name(".claude-credentials-x-y.json", ".claude-credentials-", ".json"), None);
+    }
+
+    #[test]
+    fn test_validate_credentials_shape_matches_kind() {
+        let oauth = make_oauth_creds(0);
+        let token = r#"{"token":"sk-ant-oat01-x"}"#;
+        assert!(validate_credentials_shape(&oauth, &AuthKind::Oauth).is_ok());
+        assert!(validate_credentials_shape(token, &AuthKind::Token).is_ok());
+        assert!(validate_credentials_shape(&oauth, &AuthKind::Token).is_err());
+        assert!(validate_credentials_shape(token, &AuthKind::Oauth).is_err());
+        assert!(validate_credentials_shape(r#"{"token":""}"#, &AuthKind::Token).is_err());
+    }
+
+    #[test]
+    fn test_validate_credential_format_accepts_oauth_and_token() {
+        assert!(validate_credential_format(&make_oauth_creds(0)).is_ok());
+        assert!(validate_credential_format(r#"{"token":"sk-ant-oat01-x"}"#).is_ok());
+    }
+
+    #[test]
+    fn test_validate_credential_format_rejects_malformed() {
+        for raw in [
+            "not json",
+            r#"{"token":""}"#,
+            r#"{"other":1}"#,
+            r#"{"claudeAiOauth":{"accessToken":"a","expiresAt":1}}"#,
+            r#"{"claudeAiOauth":{"accessToken":"a","refreshToken":"r","expiresAt":"soon"}}"#,
+        ] {
+            assert!(validate_credential_format(raw).is_err(), "accepted: {raw}");
+        }
+    }
+
+    // ── Secret Service backend (fake `secret-tool`) ───────────────────────────
+
+    /// Install a shell script that mimics `secret-tool`, keeping each secret in
+    /// a file named after the `service` attribute (always the last argument).
+    #[cfg(unix)]
+    fn use_fake_secret_tool(env: &crate::test_utils::TestEnv) -> PathBuf {
+        let store = env.dir.path().join("keyring");
+        fs::create_dir_all(&store).unwrap();
+        let script = env.dir.path().join("secret-tool");
+        fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\ncmd=$1\nfor a; do key=$a; done\nf=\"{}/$key\"\n\
+                 case $cmd in\n  store) cat > \"$f\";;\n  \
+                 lookup) [ -f \"$f\" ] && cat \"$f\" || exit 1;;\n  \
+                 clear) rm -f \"$f\";;\nesac\n",
+                store.display()
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        std::env::set_var("CCSWITCH_TEST_BIN_SECRET_TOOL", &script);
+        std::env::set_var("CCSWITCH_TEST_BACKEND", "secret-service");
+        store
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_secret_service_backup_roundtrip() {
+        let env = crate::test_utils::TestEnv::new();
+        let store = use_fake_secret_tool(&env);
+
+        write_backup(1, "a@test.com", r#"{"token":"t"}"#).unwrap();
+        assert_eq!(read_backup(1, "a@test.com").unwrap(), r#"{"token":"t"}"#);
+        assert!(store.join("Claude Code-Account-1-a@test.com").exists());
+        assert!(!cred_backup_path(1, "a@test.com").exists(), "no plain file written");
+        assert_eq!(backup_file_path(1, "a@test.com"), None);
+
+        delete_backup(1, "a@test.com").unwrap();
+        assert!(read_backup(1, "a@test.com").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_secret_service_reads_unmigrated_file_backup() {
+        let env = crate::test_utils::TestEnv::new();
+        write_file_600(&cred_backup_path(2, "b@test.com"), "legacy").unwrap();
+        use_fake_secret_tool(&env);
+
+        assert_eq!(read_backup(2, "b@test.com").unwrap(), "legacy");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_backup_round_trips_through_envelope_and_stays_bare_when_linked() {
+        let _env = crate::test_utils::TestEnv::new();
+        let creds = r#"{"claudeAiOauth":{"accessToken":"a"}}"#;
+        let path = cred_backup_path(1, "a@test.com");
+
+        write_backup(1, "a@test.com", creds).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), crate::migration::encode(creds));
+        assert_eq!(read_backup(1, "a@test.com").unwrap(), creds);
+
+        link_live(1, "a@test.com").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), creds);
+        let refreshed = r#"{"claudeAiOauth":{"accessToken":"b"}}"#;
+        write_backup(1, "a@test.com", refreshed).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), refreshed);
+        assert_eq!(read_backup(1, "a@test.com").unwrap(), refreshed);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_secret_service_failure_does_not_fall_back_to_file_backup() {
+        let env = crate::test_utils::TestEnv::new();
+        write_file_600(&cred_backup_path(2, "b@test.com"), "legacy").unwrap();
+        use_fake_secret_tool(&env);
+        let script = env.dir.path().join("secret-tool");
+        fs::write(&script, "#!/bin/sh\necho 'Cannot autolaunch D-Bus' >&2\nexit 1\n").unwrap();
+
+        let err = format!("{:#}", read_backup(2, "b@test.com").unwrap_err());
+        assert!(err.contains("Account 2"), "{err}");
+        assert!(err.contains("Cannot autolaunch D-Bus"), "{err}");
+    }
+
+    #[test]
+    fn test_existing_file_backups_keep_the_file_backend() {
+        let _env = crate::test_utils::TestEnv::new();
+        let keyring = CredentialBackend::SecretService;
+        assert_eq!(keep_file_backups(keyring), keyring, "fresh installs use the keyring");
+
+        write_file_600(&cred_backup_path(1, "a@test.com"), "{}").unwrap();
+        assert_eq!(keep_file_backups(keyring), CredentialBackend::File);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_migrate_backup_moves_between_file_and_keyring() {
+        let env = crate::test_utils::TestEnv::new();
+        write_file_600(&cred_backup_path(3, "c@test.com"), "secret").unwrap();
+        let store = use_fake_secret_tool(&env);
+
+        let keyring = CredentialBackend::SecretService;
+        assert!(migrate_backup(3, "c@test.com", CredentialBackend::File, keyring).unwrap());
+        assert!(!cred_backup_path(3, "c@test.com").exists());
+        assert_eq!(
+            fs::read_to_string(store.join("Claude Code-Account-3-c@test.com")).unwrap(),
+            crate::migration::encode("secret")
+        );
+        // Nothing left to migrate the second time
+        assert!(!migrate_backup(3, "c@test.com", CredentialBackend::File, keyring).unwrap());
+
+        // And back out of the keyring, checksum included.
+        assert!(migrate_backup(3, "c@test.com", keyring, CredentialBackend::File).unwrap());
+        assert!(!store.join("Claude Code-Account-3-c@test.com").exists());
+        assert_eq!(read_backup_file(3, "c@test.com").unwrap(), "secret");
+        assert!(checksum_file_path(&cred_backup_path(3, "c@test.com")).exists());
+    }
+
+    #[test]
+    fn test_backup_checksum_detects_tampering() {
+        let _env = crate::test_utils::TestEnv::new();
+        let path = cred_backup_path(1, "a@test.com");
+        write_backup(1, "a@test.com", r#"{"token":"t"}"#).unwrap();
+        assert_eq!(
+            fs::read_to_string(checksum_file_path(&path)).unwrap(),
+            sha256_fingerprint(r#"{"token":"t"}"#)
+        );
+        assert!(read_backup(1, "a@test.com").is_ok());
+
+        fs::write(&path, r#"{"token":"#).unwrap();
+        let err = read_backup(1, "a@test.com").unwrap_err();
+        assert!(err.to_string().contains("does not match its checksum"), "{err}");
+        assert_eq!(read_backup_unverified(1, "a@test.com").unwrap(), r#"{"token":"#);
+
+        delete_backup(1, "a@test.com").unwrap();
+        assert!(!checksum_file_path(&path).exists());
+
+        // Backups from before checksums existed are still readable.
+        write_file_600(&path, "legacy").unwrap();
+        assert_eq!(read_backup(1, "a@test.com").unwrap(), "legacy");
+
+        // Stores that drop a trailing newline on read still match.
+        write_backup(1, "a@test.com", "{}\n").unwrap();
+        assert!(verify_backup_checksum(1, "a@test.com", "{}").is_ok());
+        assert!(verify_backup_checksum(1, "a@test.com", "{}\n").is_ok());
+        // Checksums written before that covered the newline.
+        write_file_600(&checksum_file_path(&path), &sha256_fingerprint("{}\n")).unwrap();
+        assert!(read_backup(1, "a@test.com").is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_store_command_is_killed_after_timeout() {
+        let _env = crate::test_utils::TestEnv::new();
+        set_keychain_timeout_ms(1000);
+        let started = Instant::now();
+        let err = run_store_command("sleep", &["5"], None).unwrap_err();
+        set_keychain_timeout_ms(DEFAULT_KEYCHAIN_TIMEOUT_MS);
+        assert!(err.to_string().contains("timed out after 1s"), "{err}");
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_store_command_reads_output_larger_than_a_pipe_buffer() {
+        let _env = crate::test_utils::TestEnv::new();
+        set_keychain_timeout_ms(5000);
+        let script = "head -c 200000 /dev/zero; head -c 100000 /dev/zero >&2";
+        let output = run_store_command("sh", &["-c", script], None);
+        set_keychain_timeout_ms(DEFAULT_KEYCHAIN_TIMEOUT_MS);
+        let output = output.unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout.len(), 200_000);
+        assert_eq!(output.stderr.len(), 100_000);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_store_command_times_out_when_stdin_is_never_read() {
+        let _env = crate::test_utils::TestEnv::new();
+        set_keychain_timeout_ms(1000);
+        let started = Instant::now();
+        // Far more than a pipe buffer holds, so the write blocks until the kill.
+        let input = "x".repeat(1 << 20);
+        let err = run_store_command("sleep", &["5"], Some(&input)).unwrap_err();
+        set_keychain_timeout_ms(DEFAULT_KEYCHAIN_TIMEOUT_MS);
+        assert!(err.to_string().contains("timed out after 1s"), "{err}");
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+
+    #[test]
+    fn test_migrate_backup_rejects_same_backend() {
+        let _env = crate::test_utils::TestEnv::new();
+        write_file_600(&cred_backup_path(1, "a@test.com"), "x").unwrap();
+        let file = CredentialBackend::File;
+        assert!(migrate_backup(1, "a@test.com", file, file).is_err());
+        assert!(cred_backup_path(1, "a@test.com").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_migrate_backup_keeps_original_when_write_fails() {
+        let env = crate::test_utils::TestEnv::new();
+        write_file_600(&cred_backup_path(1, "a@test.com"), "x").unwrap();
+        std::env::set_var("CCSWITCH_TEST_BIN_SECRET_TOOL", env.dir.path().join("missing"));
+        let keyring = CredentialBackend::SecretService;
+        assert!(migrate_backup(1, "a@test.com", CredentialBackend::File, keyring).is_err());
+        assert_eq!(read_backup_file(1, "a@test.com").unwrap(), "x");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_migrate_backup_propagates_keyring_read_failure() {
+        let env = crate::test_utils::TestEnv::new();
+        let keyring = CredentialBackend::SecretService;
+        use_fake_secret_tool(&env);
+        // A missing entry is nothing to migrate...
+        assert!(!migrate_backup(1, "a@test.com", keyring, CredentialBackend::File).unwrap());
+
+        // ...but a failing store is an error, not an empty one.
+        let script = env.dir.path().join("secret-tool");
+        write_script(&script, "echo 'Cannot autolaunch D-Bus' >&2\nexit 1\n");
+        let err = migrate_backup(1, "a@test.com", keyring, CredentialBackend::File).unwrap_err();
+        assert!(err.to_string().contains("Cannot autolaunch D-Bus"), "{err}");
+    }
+
+
+    // ── KWallet backend (fake `kwallet-query` / `dbus-send`) ──────────────────
+
+    #[cfg(unix)]
+    fn write_script(path: &std::path::Path, body: &str) {
+        fs::write(path, format!("#!/bin/sh\n{body}")).unwrap();
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    /// Fake wallet: `kwallet-query -f F -r|-w KEY WALLET` keeps entries as files,
+    /// and `dbus-send` answers `open` with a handle and performs `removeEntry`.
+    #[cfg(unix)]
+    fn use_fake_kwallet(env: &crate::test_utils::TestEnv) -> PathBuf {
+        let store = env.dir.path().join("wallet");
+        fs::create_dir_all(&store).unwrap();
+        let query = env.dir.path().join("kwallet-query");
+        write_script(
+            &query,
+            &format!(
+                "f=\"{}/$4\"\ncase $3 in\n  -r) [ -f \"$f\" ] && cat \"$f\" && echo || \
+                 {{ echo \"Failed to read entry $4 value from the $5 wallet.\" >&2; exit 1; }};;\n  \
+                 -w) cat > \"$f\";;\nesac\n",
+                store.display()
+            ),
+        );
+        let dbus = env.dir.path().join("dbus-send");
+        write_script(
+            &dbus,
+            &format!(
+                "echo \"$*\" >> \"{calls}\"\ncase \"$*\" in\n  *KWallet.open*) echo '   int32 7';;\n  \
+                 *KWallet.removeEntry*) eval key=\\${{$(($# - 1))}}; rm -f \"{store}/${{key#string:}}\";;\nesac\n",
+                calls = env.dir.path().join("dbus-calls").display(),
+                store = store.display()
+            ),
+        );
+        std::env::set_var("CCSWITCH_TEST_BIN_KWALLET_QUERY", &query);
+        std::env::set_var("CCSWITCH_TEST_BIN_DBUS_SEND", &dbus);
+        std::env::set_var("CCSWITCH_TEST_BACKEND", "kwallet");
+        store
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_kwallet_backup_roundtrip_and_delete() {
+        let env = crate::test_utils::TestEnv::new();
+        let store = use_fake_kwallet(&env);
+
+        write_backup(1, "k@test.com", r#"{"token":"t"}"#).unwrap();
+        assert!(store.join("Claude Code-Account-1-k@test.com").exists());
+        // Trailing newline from kwallet-query is stripped
+        assert_eq!(read_backup(1, "k@test.com").unwrap(), r#"{"token":"t"}"#);
+
+        delete_backup(1, "k@test.com").unwrap();
+        assert!(!store.join("Claude Code-Account-1-k@test.com").exists());
+        assert!(read_backup(1, "k@test.com").is_err());
+        // Every handle opened for a delete is closed again.
+        let calls = fs::read_to_string(env.dir.path().join("dbus-calls")).unwrap();
+        assert_eq!(
+            calls.matches("KWallet.open").count(),
+            calls.matches("KWallet.close int32:7").count()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_migrate_file_backup_into_kwallet() {
+        let env = crate::test_utils::TestEnv::new();
+        write_file_600(&cred_backup_path(2, "m@test.com"), "secret").unwrap();
+        use_fake_kwallet(&env);
+
+        assert!(migrate_backup(2, "m@test.com", CredentialBackend::File, backend()).unwrap());
+        assert!(!cred_backup_path(2, "m@test.com").exists());
+        assert_eq!(read_backup(2, "m@test.com").unwrap(), "secret");
+    }
+
+    // ── pass backend (fake `pass`) ────────────────────────────────────────────
+
+    /// Fake `pass`: entries are files under the store; `show` appends a newline
+    /// like real pass does for single-line secrets.
+    #[cfg(unix)]
+    fn use_fake_pass(env: &crate::test_utils::TestEnv) -> PathBuf {
+        let store = env.dir.path().join("password-store");
+        fs::create_dir_all(store.join("ccswitch")).unwrap();
+        let script = env.dir.path().join("pass");
+        write_script(
+            &script,
+            &format!(
+                "cmd=$1\nfor a; do key=$a; done\nf=\"{}/$key\"\ncase $cmd in\n  \
+                 show) [ -f \"$f\" ] && cat \"$f\" && echo || \
+                 {{ echo \"Error: $key is not in the password store.\" >&2; exit 1; }};;\n  \
+                 insert) cat > \"$f\";;\n  rm) rm -f \"$f\";;\nesac\n",
+                store.display()
+            ),
+        );
+        std::env::set_var("CCSWITCH_TEST_BIN_PASS", &script);
+        std::env::set_var("CCSWITCH_TEST_BACKEND", "pass");
+        store
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_pass_backup_roundtrip_and_delete() {
+        let env = crate::test_utils::TestEnv::new();
+        let store = use_fake_pass(&env);
+
+        write_backup(1, "p@test.com", r#"{"token":"t"}"#).unwrap();
+        let entry = store.join("ccswitch").join("Claude Code-Account-1-p@test.com");
+        assert!(entry.exists(), "stored under ccswitch/<service>");
+        assert_eq!(read_backup(1, "p@test.com").unwrap(), r#"{"token":"t"}"#);
+
+        let checksum = store.join("ccswitch").join("Claude Code-Account-1-p@test.com-checksum");
+        fs::write(&entry, r#"{"token":"x"}"#).unwrap();
+        assert!(read_backup(1, "p@test.com").is_err(), "tampered keyring entry is rejected");
+
+        delete_backup(1, "p@test.com").unwrap();
+        assert!(!entry.exists());
+        assert!(!checksum.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_migrate_file_backup_into_pass() {
+        let env = crate::test_utils::TestEnv::new();
+        write_file_600(&cred_backup_path(4, "q@test.com"), "secret").unwrap();
+        use_fake_pass(&env);
+
+        assert!(migrate_backup(4, "q@test.com", CredentialBackend::File, backend()).unwrap());
+        assert!(!cred_backup_path(4, "q@test.com").exists());
+        assert_eq!(read_backup(4, "q@test.com").unwrap(), "secret");
+    }
+
+    #[test]
+    fn test_backend_setting_overrides_detection() {
+        let _env = crate::test_utils::TestEnv::new();
+        assert_eq!(backend(), CredentialBackend::File);
+        crate::config_user::set("credential_backend", "kwallet").unwrap();
+        assert_eq!(backend(), CredentialBackend::KWallet);
+    }
+
+    #[test]
+    fn test_parse_expiry_formats() {
+        let at = parse_expiry(r#"{"claudeAiOauth":{"expiresAt":1767225600000}}"#).unwrap();
+        assert_eq!(at.timestamp(), 1_767_225_600);
+        let at = parse_expiry(r#"{"expiresAt":1767225600}"#).unwrap();
+        assert_eq!(at.timestamp(), 1_767_225_600);
+        let at = parse_expiry(r#"{"expiresAt":"2026-01-01T00:00:00Z"}"#).unwrap();
+        assert_eq!(at.timestamp(), 1_767_225_600);
+        let at = parse_expiry(r#"{"expires_in":3600}"#).unwrap();
+        let secs = (at - chrono::Utc::now()).num_seconds();
+        assert!((3590..=3600).contains(&secs), "{}", secs);
+        assert!(parse_expiry(r#"{"token":"sk-ant-api03-x"}"#).is_none());
+    }
+
+    #[test]
+    fn test_credential_backend_parse_roundtrip() {
+        for b in [
+            CredentialBackend::Keychain,
+            CredentialBackend::Pass,
+            CredentialBackend::KWallet,
+            CredentialBackend::SecretService,
+            CredentialBackend::WindowsCredentialManager,
+            CredentialBackend::File,
+        ] {
+            assert_eq!(b.to_string().parse::<CredentialBackend>().unwrap(), b);
+        }
+        assert!("nope".parse::<CredentialBackend>().is_err());
+    }
+
 }
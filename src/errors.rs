@@ -0,0 +1,98 @@
+use std::{fmt, io};
+
+// ── Typed errors for the core account operations ──────────────────────────────
+//
+// `core_add`, `core_switch` and `core_remove` return these so the TUI can tell
+// failures apart; CLI wrappers turn them back into `anyhow::Error`.
+
+/// Why an add, switch or remove failed.
+#[derive(Debug)]
+pub enum CcswitchError {
+    /// No managed account matches the number or email.
+    AccountNotFound(String),
+    /// Claude Code has no logged-in account to work with.
+    NoActiveAccount,
+    /// A credential backup could not be read, or with no `account_num`, the
+    /// live credentials.
+    CredentialReadFailed { account_num: Option<u32>, source: io::Error },
+    /// A Claude config or config backup is not valid JSON, or lacks `oauthAccount`.
+    ConfigCorrupted(serde_json::Error),
+    /// `sequence.json` is not valid JSON.
+    SequenceCorrupted(serde_json::Error),
+    /// A keychain or keyring command failed.
+    KeychainFailed(String),
+    /// The pre-switch hook exited non-zero, so the switch did not happen.
+    HookAborted(anyhow::Error),
+    /// Anything else, with its original context chain.
+    Other(anyhow::Error),
+}
+
+impl CcswitchError {
+    /// Wrap a credential read failure, keeping an underlying I/O error when there is one.
+    /// `account_num` is `None` when the live credentials could not be read.
+    pub fn credential_read(account_num: Option<u32>, err: anyhow::Error) -> Self {
+        let source = err
+            .downcast::<io::Error>()
+            .unwrap_or_else(|e| io::Error::other(format!("{e:#}")));
+        CcswitchError::CredentialReadFailed { account_num, source }
+    }
+
+    /// Classify an error from the credential store by the backend in use.
+    pub fn from_store(err: anyhow::Error) -> Self {
+        use crate::credentials::{backend, CredentialBackend};
+        match backend() {
+            CredentialBackend::File => CcswitchError::Other(err),
+            _ => CcswitchError::KeychainFailed(format!("{err:#}")),
+        }
+    }
+
+    /// Classify an error from `sequence::load`.
+    pub fn from_sequence(err: anyhow::Error) -> Self {
+        match err.downcast::<serde_json::Error>() {
+            Ok(e) => CcswitchError::SequenceCorrupted(e),
+            Err(e) => CcswitchError::Other(e),
+        }
+    }
+}
+
+impl fmt::Display for CcswitchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CcswitchError::AccountNotFound(id) => write!(f, "Account {id} does not exist"),
+            CcswitchError::NoActiveAccount => write!(
+                f,
+                "No active Claude account found. Please log in to Claude Code first."
+            ),
+            CcswitchError::CredentialReadFailed { account_num: Some(num), .. } => {
+                write!(f, "Cannot read credentials for Account {num}")
+            }
+            CcswitchError::CredentialReadFailed { account_num: None, .. } => {
+                write!(f, "Cannot read the live Claude credentials")
+            }
+            CcswitchError::ConfigCorrupted(_) => write!(f, "Claude config is invalid"),
+            CcswitchError::SequenceCorrupted(_) => write!(f, "sequence.json is corrupted"),
+            CcswitchError::KeychainFailed(msg) => write!(f, "Credential store failed: {msg}"),
+            CcswitchError::HookAborted(e) => {
+                write!(f, "Switch aborted by the pre-switch hook: {e}")
+            }
+            CcswitchError::Other(e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+impl std::error::Error for CcswitchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CcswitchError::CredentialReadFailed { source, .. } => Some(source),
+            CcswitchError::ConfigCorrupted(e) | CcswitchError::SequenceCorrupted(e) => Some(e),
+            CcswitchError::HookAborted(e) | CcswitchError::Other(e) => e.source(),
+            _ => None,
+        }
+    }
+}
+
+impl From<anyhow::Error> for CcswitchError {
+    fn from(err: anyhow::Error) -> Self {
+        CcswitchError::Other(err)
+    }
+}
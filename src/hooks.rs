@@ -0,0 +1,163 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use crate::config_user;
+
+// ── Pre/post-switch hook scripts ──────────────────────────────────────────────
+
+/// Hook scripts configured in the `[hooks]` table of `config.toml`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Hooks {
+    pub pre_switch: Option<PathBuf>,
+    pub post_switch: Option<PathBuf>,
+}
+
+/// Read `[hooks]` from `config.toml`; a missing file means no hooks.
+pub fn load() -> Result<Hooks> {
+    let config = config_user::load_user_config()?;
+    let script = |s: Option<String>| s.filter(|s| !s.is_empty()).map(|s| expand_home(&s));
+    Ok(Hooks {
+        pre_switch: script(config.pre_switch_hook),
+        post_switch: script(config.post_switch_hook),
+    })
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), crate::paths::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Environment passed to both hooks for a switch from one account to another.
+pub fn switch_env(
+    from: u32,
+    from_email: &str,
+    to: u32,
+    to_email: &str,
+) -> Vec<(&'static str, String)> {
+    vec![
+        ("CCSWITCH_FROM_ACCOUNT", from.to_string()),
+        ("CCSWITCH_FROM_EMAIL", from_email.to_string()),
+        ("CCSWITCH_TO_ACCOUNT", to.to_string()),
+        ("CCSWITCH_TO_EMAIL", to_email.to_string()),
+    ]
+}
+
+/// How long a hook may run before it is killed.
+pub const HOOK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Run a hook script and wait for it, killing it after `HOOK_TIMEOUT`. Output
+/// is captured so the TUI stays intact; stderr is included in the error when
+/// the script fails.
+pub fn run_hook(path: &Path, env_vars: &[(&str, String)]) -> Result<()> {
+    run_hook_with_timeout(path, env_vars, HOOK_TIMEOUT)
+}
+
+fn run_hook_with_timeout(path: &Path, env_vars: &[(&str, String)], timeout: Duration) -> Result<()> {
+    let mut child = Command::new(path)
+        .envs(env_vars.iter().map(|(k, v)| (k, v)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run hook {}", path.display()))?;
+
+    // Drain stderr on a thread so a chatty hook can't fill the pipe and stall.
+    let mut stderr_pipe = child.stderr.take().context("Failed to capture hook stderr")?;
+    let reader = std::thread::spawn(move || {
+        let mut buf = String::new();
+        let _ = stderr_pipe.read_to_string(&mut buf);
+        buf
+    });
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!("Hook {} did not finish within {}s and was killed", path.display(), timeout.as_secs());
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+    let stderr = reader.join().unwrap_or_default();
+
+    if !status.success() {
+        let detail = stderr.trim();
+        let status = status
+            .code()
+            .map(|c| format!("exit status {c}"))
+            .unwrap_or_else(|| "a signal".to_string());
+        if detail.is_empty() {
+            bail!("Hook {} failed with {status}", path.display());
+        }
+        bail!("Hook {} failed with {status}: {detail}", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestEnv;
+
+    #[test]
+    fn test_load_missing_config_has_no_hooks() {
+        let _env = TestEnv::new();
+        assert_eq!(load().unwrap(), Hooks::default());
+    }
+
+    #[test]
+    fn test_load_reads_hooks_table() {
+        let _env = TestEnv::new();
+        std::fs::write(
+            config_user::path(),
+            "[hooks]\npre_switch = \"/opt/pre.sh\"\npost_switch = \"\"\n",
+        )
+        .unwrap();
+        let hooks = load().unwrap();
+        assert_eq!(hooks.pre_switch, Some(PathBuf::from("/opt/pre.sh")));
+        assert_eq!(hooks.post_switch, None);
+
+        std::fs::write(config_user::path(), "[hooks]\npre_switch = true\n").unwrap();
+        assert!(load().is_err());
+    }
+
+    #[test]
+    fn test_run_hook_reports_exit_status_and_stderr() {
+        let env = TestEnv::new();
+        let script = write_script(
+            env.dir.path(),
+            "fail.sh",
+            "echo \"no $CCSWITCH_TO_ACCOUNT\" >&2\nexit 3",
+        );
+        let err = run_hook(&script, &switch_env(1, "a@test.com", 2, "b@test.com")).unwrap_err();
+        assert!(err.to_string().contains("exit status 3: no 2"), "{err}");
+    }
+
+    #[test]
+    fn test_run_hook_kills_a_hung_script() {
+        let env = TestEnv::new();
+        let script = write_script(env.dir.path(), "hang.sh", "sleep 30");
+        let started = Instant::now();
+        let err = run_hook_with_timeout(&script, &[], Duration::from_millis(200)).unwrap_err();
+        assert!(err.to_string().contains("was killed"), "{err}");
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    fn write_script(dir: &Path, name: &str, body: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join(name);
+        std::fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+}
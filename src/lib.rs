@@ -0,0 +1,138 @@
+//! The account store and switching logic behind the `ccswitch` binary, for
+//! tools that want to embed the switcher instead of shelling out to it.
+//!
+//! The `core_*` functions do the work of the matching `ccswitch` command and
+//! return data instead of printing. They read and write the same files as the
+//! binary, so `CLAUDE_CONFIG_DIR`, profiles and the credential backend setting
+//! apply to them too.
+//!
+//! ```no_run
+//! let accounts = ccswitch::core_list(None)?;
+//! if let Some(next) = accounts.iter().find(|a| !a.active) {
+//!     let result = ccswitch::core_switch(next.number)?;
+//!     println!("{}", result.message);
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! The interactive switcher ([`tui::run`]) is behind the default `tui`
+//! feature; build with `default-features = false` to leave out ratatui and
+//! crossterm. The `sqlite` feature adds a SQLite account store, selected with
+//! `ccswitch config set store sqlite`.
+
+// The modules are public so the binary can reach them, but only the
+// re-exports below are a supported API.
+#[doc(hidden)]
+pub mod accounts;
+#[doc(hidden)]
+pub mod archive;
+#[doc(hidden)]
+pub mod completions;
+#[doc(hidden)]
+pub mod config;
+#[doc(hidden)]
+pub mod config_user;
+#[doc(hidden)]
+pub mod credentials;
+#[doc(hidden)]
+pub mod errors;
+#[doc(hidden)]
+pub mod hooks;
+#[doc(hidden)]
+pub mod metrics;
+mod migration;
+#[doc(hidden)]
+pub mod notifications;
+#[doc(hidden)]
+pub mod paths;
+#[doc(hidden)]
+#[cfg(feature = "tui")]
+pub mod picker;
+#[doc(hidden)]
+pub mod platform;
+#[doc(hidden)]
+pub mod profiles;
+#[doc(hidden)]
+pub mod schedule;
+#[doc(hidden)]
+pub mod sequence;
+#[doc(hidden)]
+pub mod spinner;
+#[doc(hidden)]
+pub mod store;
+#[doc(hidden)]
+pub mod template;
+#[doc(hidden)]
+pub mod transfer;
+/// The full-screen account switcher run by a bare `ccswitch`.
+#[cfg(feature = "tui")]
+pub mod tui;
+#[doc(hidden)]
+pub mod update;
+
+pub use accounts::{
+    core_add, core_diff, core_info, core_list, core_note, core_pin, core_refresh, core_remove,
+    core_rename, core_set_alias, core_status, core_switch, core_switch_tag, core_tag, core_unalias,
+    core_undo, core_unpin, core_untag, AccountDetails, AccountDiff, AccountInfo, AddOptions,
+    SortOrder, StatusReport, SwitchResult,
+};
+pub use credentials::{read_backup, read_live, write_backup, write_live};
+pub use errors::CcswitchError;
+pub use sequence::{AccountEntry, AuthKind, SequenceFile};
+
+#[cfg(test)]
+pub(crate) mod test_utils {
+    use std::sync::Mutex;
+
+    pub static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    pub struct TestEnv {
+        pub dir: tempfile::TempDir,
+        _lock: std::sync::MutexGuard<'static, ()>,
+    }
+
+    impl TestEnv {
+        pub fn new() -> Self {
+            let lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+            let dir = tempfile::TempDir::new().unwrap();
+            std::env::set_var("CCSWITCH_TEST_DIR", dir.path().to_str().unwrap());
+            std::env::set_var("CCSWITCH_TEST_PLATFORM", "linux");
+            let base = dir.path().join(crate::profiles::DEFAULT_PROFILE);
+            std::fs::create_dir_all(base.join("configs")).unwrap();
+            std::fs::create_dir_all(base.join("credentials")).unwrap();
+            // Set 0o700 on the directories so doctor's permission check passes.
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                for sub in ["", "configs", "credentials"] {
+                    let p = base.join(sub);
+                    std::fs::set_permissions(&p, std::fs::Permissions::from_mode(0o700)).unwrap();
+                }
+            }
+            TestEnv { dir, _lock: lock }
+        }
+    }
+
+    /// Run `f` with account backups kept in memory on this thread; `f` gets
+    /// the store to inspect what was written.
+    pub fn with_memory_backend<T>(f: impl FnOnce(&crate::credentials::InMemoryBackend) -> T) -> T {
+        let store = std::sync::Arc::new(crate::credentials::InMemoryBackend::default());
+        let _guard = crate::credentials::override_backup_backend(store.clone());
+        f(&store)
+    }
+
+    impl Drop for TestEnv {
+        fn drop(&mut self) {
+            std::env::remove_var("CCSWITCH_TEST_DIR");
+            std::env::remove_var("CCSWITCH_TEST_PLATFORM");
+            std::env::remove_var("CCSWITCH_TEST_BACKEND");
+            std::env::remove_var("CCSWITCH_TEST_PASSPHRASE");
+            std::env::remove_var(crate::paths::BACKUP_DIR_ENV);
+            std::env::remove_var(crate::paths::SEQUENCE_FILE_ENV);
+            for bin in ["SECRET_TOOL", "KWALLET_QUERY", "DBUS_SEND", "PASS"] {
+                std::env::remove_var(format!("CCSWITCH_TEST_BIN_{bin}"));
+            }
+        }
+    }
+}
+
@@ -1,55 +1,14 @@
-mod accounts;
-mod config;
-mod credentials;
-mod platform;
-mod sequence;
-mod transfer;
-mod tui;
-mod update;
+use ccswitch::{
+    accounts, archive, completions, config_user, credentials, paths, platform, profiles, schedule,
+    sequence, spinner, transfer, update,
+};
+#[cfg(feature = "tui")]
+use ccswitch::tui;
 
-#[cfg(test)]
-pub(crate) mod test_utils {
-    use std::sync::Mutex;
-
-    pub static ENV_LOCK: Mutex<()> = Mutex::new(());
-
-    pub struct TestEnv {
-        pub dir: tempfile::TempDir,
-        _lock: std::sync::MutexGuard<'static, ()>,
-    }
-
-    impl TestEnv {
-        pub fn new() -> Self {
-            let lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
-            let dir = tempfile::TempDir::new().unwrap();
-            std::env::set_var("CCSWITCH_TEST_DIR", dir.path().to_str().unwrap());
-            std::env::set_var("CCSWITCH_TEST_PLATFORM", "linux");
-            std::fs::create_dir_all(dir.path().join("configs")).unwrap();
-            std::fs::create_dir_all(dir.path().join("credentials")).unwrap();
-            // Set 0o700 on the directories so doctor's permission check passes.
-            #[cfg(unix)]
-            {
-                use std::os::unix::fs::PermissionsExt;
-                for sub in ["", "configs", "credentials"] {
-                    let p = dir.path().join(sub);
-                    std::fs::set_permissions(&p, std::fs::Permissions::from_mode(0o700)).unwrap();
-                }
-            }
-            TestEnv { dir, _lock: lock }
-        }
-    }
-
-    impl Drop for TestEnv {
-        fn drop(&mut self) {
-            std::env::remove_var("CCSWITCH_TEST_DIR");
-            std::env::remove_var("CCSWITCH_TEST_PLATFORM");
-        }
-    }
-}
-
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{CommandFactory, Parser, Subcommand};
 use colored::Colorize;
+use std::io::IsTerminal;
 
 #[derive(Parser)]
 #[command(
@@ -62,36 +21,222 @@ logging in and out each time.\n\
 \n\
 Run without arguments to open the interactive TUI.\n\
 \n\
-Accounts are stored in ~/.claude-switch-backup with credentials \
-kept in the system keychain (macOS) or encrypted files (Linux/WSL)."
+Accounts are stored in ~/.local/share/ccswitch/<profile> (Linux/WSL) or \
+~/.claude-switch-backup/<profile> (macOS) with credentials \
+kept in the system keychain (macOS) or encrypted files (Linux/WSL).\n\
+\n\
+Environment:\n  \
+$CCSWITCH_BACKUP_DIR     keep the profile's backups here instead (absolute path)\n  \
+$CCSWITCH_SEQUENCE_FILE  keep only sequence.json here instead (absolute path)\n  \
+$CCSWITCH_API_BASE       Claude API base URL for `token validate` (default https://api.anthropic.com)"
 )]
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+
+    /// Timeout for keychain and keyring commands in milliseconds (0 = wait forever)
+    #[arg(
+        long,
+        global = true,
+        value_name = "MS",
+        default_value_t = credentials::DEFAULT_KEYCHAIN_TIMEOUT_MS
+    )]
+    timeout_keychain: u64,
+
+    /// Same as --timeout-keychain, in seconds
+    #[arg(long, global = true, value_name = "SECS", conflicts_with = "timeout_keychain")]
+    keychain_timeout: Option<u64>,
+
+    /// How long to wait for another ccswitch process to finish, in milliseconds
+    #[arg(
+        long = "timeout-lock",
+        alias = "timeout-ms",
+        global = true,
+        value_name = "MS",
+        default_value_t = sequence::DEFAULT_LOCK_TIMEOUT_MS
+    )]
+    timeout_lock: u64,
+
+    /// Work on this profile instead of the active one (see `ccswitch profile`)
+    #[arg(long, global = true, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Use this directory instead of the real home directory (also: $CCSWITCH_HOME)
+    #[arg(long, global = true, value_name = "PATH")]
+    home: Option<std::path::PathBuf>,
+
+    /// Output format for commands that support machine-readable output
+    #[arg(long, global = true, value_enum, default_value_t = accounts::OutputFormat::Text)]
+    format: accounts::OutputFormat,
+
+    /// Print extra detail (e.g. files written during a switch)
+    #[arg(long, short, global = true)]
+    verbose: bool,
+
+    /// Print nothing but errors; scripts can rely on the exit code alone
+    #[arg(long, short, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Disable colored output (also: $NO_COLOR); color is off when stdout is not a terminal
+    #[arg(
+        long,
+        global = true,
+        env = "NO_COLOR",
+        value_parser = clap::builder::FalseyValueParser::new()
+    )]
+    no_color: bool,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Add the currently logged-in Claude account to managed accounts
-    Add,
+    Add {
+        /// Switch priority score (higher wins with `switch --highest-priority`)
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        priority: u32,
+        /// Add a token account from $CLAUDE_CODE_OAUTH_TOKEN, even when an OAuth account is logged in
+        #[arg(long)]
+        from_env: bool,
+        /// Label for the token account instead of asking for one
+        #[arg(long, value_name = "LABEL")]
+        label: Option<String>,
+        /// Never prompt; read the token from stdin when $CLAUDE_CODE_OAUTH_TOKEN
+        /// is unset, and fail when other input would be needed (for CI)
+        #[arg(long)]
+        no_prompt: bool,
+        /// Replace the token of the account with the same label, and succeed
+        /// when the token is already managed
+        #[arg(short = 'f', long)]
+        force: bool,
+    },
 
     /// Remove a managed account by number or email
     Remove {
         /// Account number (e.g. 2) or email address
         account: String,
+        /// Remove without asking for confirmation
+        #[arg(short = 'f', long)]
+        force: bool,
     },
 
     /// List all managed accounts
     #[command(alias = "ls")]
-    List,
+    List {
+        /// Handlebars output template (prefix with @ to read from a file)
+        #[arg(long, value_name = "TEMPLATE", conflicts_with = "json")]
+        template: Option<String>,
+        /// Print accounts as JSON (same as --format json)
+        #[arg(long)]
+        json: bool,
+        /// Sort accounts instead of showing them in rotation order
+        #[arg(long, value_enum)]
+        sort: Option<accounts::SortOrder>,
+        /// Print `<num>\t<email>\t<auth_kind>\t<active>` lines for scripts
+        ///
+        /// The format will not change across patch releases. Prefer --json
+        /// when you need more than these columns.
+        #[arg(long, conflicts_with_all = ["json", "template"])]
+        porcelain: bool,
+        /// Show accounts in a table instead of the list
+        #[arg(long, conflicts_with_all = ["json", "template", "porcelain"])]
+        table: bool,
+        /// Print only the active account's email (plus " [token]" for token
+        /// accounts) with no newline, for shell prompts; exits 1 if none is active
+        ///
+        /// zsh: setopt prompt_subst; PROMPT='$(ccswitch list --oneline) $ '
+        #[arg(long, conflicts_with_all = ["json", "template", "porcelain", "table", "sort"])]
+        oneline: bool,
+        /// Show only the active account (with --json, a single object); exits 1
+        /// if none is active
+        #[arg(long, alias = "active", conflicts_with_all = ["template", "porcelain", "oneline"])]
+        active_only: bool,
+        /// Show the active account like the others: no arrow, highlight or
+        /// "(active)", e.g. `ccswitch list --no-active | grep -c @company.com`
+        #[arg(long, conflicts_with_all = ["json", "template", "porcelain", "oneline", "active_only"])]
+        no_active: bool,
+        /// Print just the number of managed accounts, e.g.
+        /// `[ $(ccswitch list --count) -gt 1 ]`; with --active, 1 or 0 for
+        /// whether an account is active
+        #[arg(
+            long,
+            conflicts_with_all = ["json", "template", "porcelain", "table", "oneline", "no_active", "sort"]
+        )]
+        count: bool,
+    },
 
     /// Show the currently active account
-    Status,
+    ///
+    /// With --verbose, also print the config and credential paths, sizes and
+    /// account metadata; paste this into bug reports (no secrets are shown).
+    Status {
+        /// Print status as JSON (same as --format json)
+        #[arg(long)]
+        json: bool,
+        /// Print `<num>\t<email>\t<auth_kind>` for the active account, or an
+        /// empty line if none. Prefer --json when you need more than these columns.
+        #[arg(long, conflicts_with = "json")]
+        porcelain: bool,
+    },
 
     /// Switch accounts — rotates to next if no argument given
+    #[command(alias = "rotate")]
     Switch {
         /// Account number or email to switch to (optional; rotates if omitted)
         account: Option<String>,
+        /// Switch to the highest-priority account other than the current one
+        #[arg(long, conflicts_with = "account")]
+        highest_priority: bool,
+        /// Switch to the next account in rotation with this tag
+        #[arg(long, value_name = "TAG", conflicts_with_all = ["account", "highest_priority"])]
+        tag: Option<String>,
+        /// Rotate backwards, to the account before the current one
+        #[arg(short = 'b', long, conflicts_with_all = ["account", "highest_priority", "tag"])]
+        cycle_back: bool,
+        /// Pick the account from a filterable list (type to filter, ↑/↓, Enter; Esc aborts)
+        #[arg(
+            short = 'i',
+            long,
+            conflicts_with_all = ["account", "highest_priority", "tag", "cycle_back"]
+        )]
+        interactive: bool,
+        /// How to pick the next account when none is given (default:
+        /// round-robin, or `config set rotation_strategy`)
+        #[arg(
+            long,
+            value_enum,
+            conflicts_with_all = ["account", "highest_priority", "tag", "cycle_back", "interactive"]
+        )]
+        strategy: Option<accounts::RotationStrategy>,
+        /// Print switch timing and totals to stderr in Prometheus text format
+        #[arg(long)]
+        metrics: bool,
+        /// Write Prometheus metrics to a file (default: $CCSWITCH_METRICS_FILE)
+        #[arg(long, value_name = "PATH")]
+        metrics_file: Option<std::path::PathBuf>,
+        /// Show what would change (files, credentials, config diff) without switching
+        #[arg(long)]
+        dry_run: bool,
+        /// Don't print "Restart Claude Code to apply." (also: config set suppress_restart_hint true)
+        #[arg(long)]
+        no_restart_hint: bool,
+    },
+
+    /// Switch back to the account that was active before the last switch
+    Undo,
+
+    /// Show how two accounts' backups differ (what a switch from one to the other changes)
+    #[command(allow_missing_positional = true)]
+    Diff {
+        /// Account to compare from (default: the active account)
+        from: Option<String>,
+        /// Account number or email to compare to
+        to: String,
+    },
+
+    /// Write a .ccswitch file selecting an account for this directory and its subdirectories
+    Init {
+        /// Account number or email to use in this directory
+        account: String,
     },
 
     /// Refresh the OAuth session token for an account (active account if none given)
@@ -104,6 +249,7 @@ enum Commands {
     },
 
     /// Edit the label (email) of a managed account
+    #[command(alias = "rename")]
     Edit {
         /// Account number or email to edit
         account: String,
@@ -111,6 +257,148 @@ enum Commands {
         new_label: String,
     },
 
+    /// Duplicate a managed account under a new label
+    Clone {
+        /// Account number, email or alias to copy
+        account: String,
+        /// Label for the copy (default: <email>-copy)
+        new_label: Option<String>,
+    },
+
+    /// Move an account to a new position in the rotation order
+    Move {
+        /// Account number, email, or alias
+        account: String,
+        /// New 1-based position (clamped to the list length)
+        position: usize,
+    },
+
+    /// Exchange two accounts' positions in the rotation (numbers stay the same)
+    Swap {
+        /// Account number, email, or alias
+        a: String,
+        /// Account to trade places with
+        b: String,
+    },
+
+    /// Set the stored account UUID, e.g. for accounts added before ccswitch
+    /// recorded it (it is also filled in on switch when empty)
+    SetUuid {
+        /// Account number, email, or alias
+        account: String,
+        /// `oauthAccount.accountUuid` from ~/.claude.json (a version 4 UUID)
+        uuid: String,
+    },
+
+    /// Add a tag to an account (e.g. work, personal, prod)
+    Tag {
+        /// Account number, email, or alias
+        account: String,
+        /// Tag to add
+        tag: String,
+    },
+
+    /// Remove a tag from an account
+    Untag {
+        /// Account number, email, or alias
+        account: String,
+        /// Tag to remove
+        tag: String,
+    },
+
+    /// Switch to the next account in rotation with the given tag
+    SwitchTag {
+        /// Tag to rotate through
+        tag: String,
+    },
+
+    /// Manage account switch priorities
+    Priority {
+        #[command(subcommand)]
+        action: PriorityAction,
+    },
+
+    /// Run a command as an account without switching, e.g. `ccswitch run 2 -- claude`
+    Run {
+        /// Account number, email, or alias
+        account: String,
+        /// Command and arguments to run (after `--`)
+        #[arg(last = true, required = true, value_name = "COMMAND")]
+        command: Vec<String>,
+    },
+
+    /// Print shell statements exporting the active account's token
+    ///
+    /// Use as `eval "$(ccswitch env)"`. When stdout is not a terminal the
+    /// statements go to a private temp file and only a `source` line is
+    /// printed; pass --print to print the token itself.
+    #[command(verbatim_doc_comment)]
+    Env {
+        /// Shell syntax to emit (default: from $SHELL)
+        #[arg(long, value_enum)]
+        shell: Option<accounts::ShellKind>,
+        /// Print the export statements even when stdout is not a terminal
+        #[arg(long)]
+        print: bool,
+    },
+
+    /// Make your shell source ~/.ccswitchrc (fish: ~/.config/ccswitch/env.fish)
+    ///
+    /// Appends a `source` line to ~/.bashrc, ~/.zshrc or fish's config.fish
+    /// unless one is already there.
+    ShellSetup {
+        /// Shell to configure (default: from $SHELL)
+        #[arg(long, value_enum)]
+        shell: Option<accounts::ShellKind>,
+        /// Startup file to edit instead of the shell's default
+        #[arg(long, value_name = "PATH")]
+        rc_path: Option<std::path::PathBuf>,
+    },
+
+    /// Set an environment variable that ~/.ccswitchrc exports while an account is active
+    EnvSet {
+        /// Account number, email, or alias
+        account: String,
+        /// Variable as KEY=VALUE, e.g. ANTHROPIC_BASE_URL=https://example.com
+        var: String,
+    },
+
+    /// Remove an account's environment variable
+    EnvUnset {
+        /// Account number, email, or alias
+        account: String,
+        /// Variable name
+        key: String,
+    },
+
+    /// Print an account's environment variables as KEY=VALUE lines
+    EnvList {
+        /// Account number, email, or alias
+        account: String,
+    },
+
+    /// Show everything stored for one account (backups, fingerprint, usage)
+    Info {
+        /// Account number, email, or alias
+        account: String,
+    },
+
+    /// Show or set a free-form note on an account
+    Note {
+        /// Account number, email, or alias
+        account: String,
+        /// Note text to store (omit to print the current note; "" clears it)
+        #[arg(long)]
+        text: Option<String>,
+    },
+
+    /// Import token accounts from a legacy ~/.ccswitch.json file
+    Migrate {
+        /// Path to the legacy accounts file
+        #[arg(default_value = "~/.ccswitch.json")]
+        source: std::path::PathBuf,
+    },
+
     /// Set a short alias for an account
     Alias {
         /// Account number or email to alias
@@ -119,20 +407,213 @@ enum Commands {
         name: String,
     },
 
+    /// Remove an alias
+    Unalias {
+        /// Alias to remove
+        name: String,
+    },
+
+    /// Pin an account as the default that rotation starts from
+    Pin {
+        /// Account number, email or alias
+        account: String,
+    },
+
+    /// Clear the pinned account
+    Unpin,
+
+    /// Make ~/.claude/.credentials.json a symlink to an account's backup (Linux/WSL)
+    ///
+    /// Switches to the account first if needed. While linked, Claude Code
+    /// writes straight into the backup and switches re-point the link.
+    /// Needs file backups (credential_backend = file); OAuth accounts only.
+    Link {
+        /// Account number, email or alias
+        account: String,
+    },
+
+    /// Turn the linked credentials file back into a regular file
+    Unlink,
+
+    /// Record an account as active without switching credentials
+    ///
+    /// Low-level escape hatch for when sequence.json is out of sync with the
+    /// live credentials, e.g. after restoring them by hand. Nothing but
+    /// sequence.json is written.
+    SetActive {
+        /// Account number, email or alias
+        account: String,
+    },
+
     /// Generate shell completion script
+    ///
+    /// Account numbers, aliases, and emails complete dynamically.
+    ///
+    /// Install:
+    ///   bash:       echo 'source <(ccswitch completions bash)' >> ~/.bashrc
+    ///   zsh:        echo 'source <(ccswitch completions zsh)' >> ~/.zshrc
+    ///   fish:       ccswitch completions fish > ~/.config/fish/completions/ccswitch.fish
+    ///   powershell: ccswitch completions powershell | Out-String | Invoke-Expression
+    #[command(verbatim_doc_comment)]
     Completions {
         /// Shell to generate completions for
         shell: clap_complete::Shell,
     },
 
+    /// List account identifiers for shell completion scripts
+    #[command(name = "complete-accounts", hide = true)]
+    CompleteAccounts,
+
+    /// Show how often each account is used
+    Stats,
+
+    /// Show recent account switches from the switch log
+    History {
+        /// Number of entries to show
+        #[arg(long, short = 'n', value_name = "N", default_value_t = 20)]
+        lines: usize,
+    },
+
+    /// Check that backups and ~/.ccswitchrc are private (files 0600, directories 0700)
+    CheckPermissions {
+        /// Tighten any file or directory that is too open
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// Show the audit log of credential access (add, switch, remove, read)
+    Audit {
+        /// Show only the last N entries
+        #[arg(long, short = 'n', value_name = "N")]
+        lines: Option<usize>,
+        /// Write the entries as JSON lines to this file instead of printing them
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<std::path::PathBuf>,
+        /// Check the log's hash chain instead of printing it
+        #[arg(long, conflicts_with_all = ["lines", "output"])]
+        verify: bool,
+    },
+
+    /// Read or change ccswitch preferences (stored in config.toml)
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// Switch accounts automatically at set times (cron syntax, local time)
+    Schedule {
+        #[command(subcommand)]
+        action: ScheduleAction,
+    },
+
+    /// Manage the stored tokens of token accounts
+    Token {
+        #[command(subcommand)]
+        action: TokenAction,
+    },
+
+    /// Manage profiles: separate account sets, e.g. personal vs. client work
+    Profile {
+        #[command(subcommand)]
+        action: ProfileAction,
+    },
+
+    /// Move credential backups between storage backends (default: files into the keyring)
+    MigrateBackend {
+        /// Backend to move backups out of: keychain, pass, kwallet, secret-service, wincred, file
+        #[arg(default_value = "file")]
+        from: credentials::CredentialBackend,
+        /// Backend to move them into (default: the configured or auto-detected keyring)
+        to: Option<credentials::CredentialBackend>,
+    },
+
+    /// Write an encrypted archive of every account in the current profile
+    Backup {
+        /// Archive to create, e.g. ~/ccswitch-backup.tar.gz
+        #[arg(long, short)]
+        output: std::path::PathBuf,
+        /// Read the passphrase from the first line of stdin instead of prompting
+        #[arg(long = "passphrase-stdin")]
+        passphrase: bool,
+    },
+
+    /// Restore accounts from a `ccswitch backup` archive (merge or replace)
+    Restore {
+        /// Archive created by `ccswitch backup`
+        #[arg(long, short)]
+        input: std::path::PathBuf,
+        /// Read the passphrase from the first line of stdin instead of prompting
+        #[arg(long = "passphrase-stdin")]
+        passphrase: bool,
+    },
+
+    /// Move ~/.claude-switch-backup into the XDG data, config and state directories
+    MigrateXdg,
+
+    /// Check that every account's credential and config backups are readable and valid
+    Verify,
+
+    /// Renumber accounts 1, 2, 3… in rotation order, closing gaps left by removals
+    Renumber {
+        /// Show the old → new numbers without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Delete credential and config backups that no managed account refers to
+    Prune {
+        /// List the orphaned backups without deleting them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Find accounts holding identical credentials and merge them into one
+    ///
+    /// The lowest-numbered account of each group is kept and inherits the
+    /// tags of the ones removed.
+    Dedup {
+        /// List the duplicates without removing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
     /// Run health checks on all managed accounts and configuration
     Doctor,
 
+    /// Delete temp files left by interrupted writes, and empty backups
+    ///
+    /// Removes `*.tmp.<pid>` files under the backup directory older than
+    /// --older-than-hours (default 24), plus empty files in credentials/ and
+    /// configs/, and lists what it deleted.
+    Gc {
+        /// Only remove temp files last modified more than this many hours ago
+        #[arg(long, value_name = "HOURS")]
+        older_than_hours: Option<u64>,
+    },
+
+    /// Print the directory holding the current profile's backups
+    ///
+    /// Honours $CCSWITCH_BACKUP_DIR, --profile and the XDG directories, e.g.
+    /// `ls -la $(ccswitch backup-dir)/credentials/`. The directory may not
+    /// exist yet if no account has been added.
+    BackupDir,
+
+    /// Print the path of sequence.json, e.g. `cat $(ccswitch sequence-file)`
+    ///
+    /// Honours $CCSWITCH_SEQUENCE_FILE as well as everything backup-dir does.
+    SequenceFile,
+
+    /// Rebuild a corrupted sequence.json from what remains of it and the backups
+    Repair,
+
     /// Update ccswitch to the latest release
     Update,
 
-    /// Export one or more managed accounts as a portable blob (paste into remote VM)
+    /// Export one or more managed accounts as a passphrase-encrypted blob (paste into remote VM)
     Export {
+        /// Account number, email, or alias to export (same as --account)
+        #[arg(value_name = "ACCOUNT", conflicts_with_all = ["account", "all"])]
+        target: Option<String>,
         #[arg(long, value_name = "ID")]
         account: Option<String>,
         #[arg(long)]
@@ -140,16 +621,154 @@ enum Commands {
         /// Upload encrypted blob to a private GitHub Gist and print the import command
         #[arg(long)]
         gist: bool,
+        /// Print the encrypted blob to stdout instead of copying it (the
+        /// default when stdout is not a terminal)
+        #[arg(long, conflicts_with = "gist")]
+        print: bool,
+        /// Copy the encrypted blob to the clipboard without asking
+        #[arg(long, conflicts_with_all = ["gist", "print"])]
+        clipboard: bool,
+        /// Show a token account as a QR code holding a passphrase-encrypted export blob
+        #[arg(long, conflicts_with_all = ["all", "gist", "print", "clipboard"])]
+        qr: bool,
     },
 
-    /// Import accounts from an export blob (reads interactively — no shell history)
+    /// Import accounts from an export blob (stdin, --file, or interactive paste)
     Import {
         /// Download and decrypt from a GitHub Gist created by `export --gist`
         #[arg(long, value_name = "ID")]
         gist: Option<String>,
+        /// Add token accounts from a JSON array of {"email", "token"} objects
+        #[arg(long, value_name = "PATH", conflicts_with = "gist")]
+        from_json: Option<std::path::PathBuf>,
+        /// Read the export blob from a file instead of stdin / paste
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["gist", "from_json"])]
+        file: Option<std::path::PathBuf>,
+        /// Read the export blob from the system clipboard
+        #[arg(long, conflicts_with_all = ["gist", "from_json", "file"])]
+        clipboard: bool,
     },
 }
 
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Set a setting, e.g. `config set credential_backend kwallet`
+    ///
+    /// default_profile: profile name;
+    /// credential_backend: auto, keychain, pass, kwallet, secret-service, wincred, file;
+    /// color, confirm_switch, notifications, suppress_restart_hint: true or false;
+    /// pre_switch_hook, post_switch_hook: script path;
+    /// log_level: error, warn, info, debug, trace;
+    /// rotation_strategy: round-robin, lru, random;
+    /// store: json, sqlite (builds with the `sqlite` feature)
+    Set {
+        /// Setting name
+        key: String,
+        /// New value
+        value: String,
+    },
+    /// Print a setting
+    Get {
+        /// Setting name
+        key: String,
+    },
+    /// Print every setting with its current value
+    List,
+    /// Remove a setting so its default applies again
+    Reset {
+        /// Setting name
+        key: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ScheduleAction {
+    /// Switch to an account whenever a cron expression fires, e.g. "0 9 * * mon-fri"
+    Set {
+        /// Account number, email, or alias
+        account: String,
+        /// Five-field cron expression: minute hour day-of-month month day-of-week
+        cron: String,
+    },
+    /// Remove an account's schedule
+    Remove {
+        /// Account number, email, or alias
+        account: String,
+    },
+    /// List schedules
+    List,
+    /// Switch to the account whose schedule fired most recently (run from cron/launchd)
+    Run,
+    /// Run `schedule run` every minute via launchd (macOS) or crontab (Linux/WSL)
+    Install,
+}
+
+#[derive(Subcommand)]
+enum ProfileAction {
+    /// List profiles and mark the active one
+    List,
+    /// Create an empty profile
+    Create {
+        /// Profile name (letters, digits, '-' and '_')
+        name: String,
+    },
+    /// Delete an empty profile
+    Delete {
+        /// Profile name
+        name: String,
+    },
+    /// Make a profile the default for future commands
+    Use {
+        /// Profile name
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum TokenAction {
+    /// Paste a new token for a token account whose token was revoked or expired
+    Refresh {
+        /// Account number, email, or alias
+        account: String,
+    },
+    /// Check a token against the Claude API without storing it
+    Validate {
+        /// Check $CLAUDE_CODE_OAUTH_TOKEN instead of reading the token from stdin
+        #[arg(long)]
+        from_env: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum PriorityAction {
+    /// Set the switch priority of an account
+    Set {
+        /// Account number, email, or alias
+        account: String,
+        /// Priority score (higher wins)
+        priority: u32,
+    },
+}
+
+impl Cli {
+    fn wants_json(&self) -> bool {
+        self.format == accounts::OutputFormat::Json
+            || matches!(
+                self.command,
+                Some(Commands::List { json: true, .. }) | Some(Commands::Status { json: true, .. })
+            )
+    }
+}
+
+/// A per-command `--json` flag overrides the global `--format`.
+fn format_for(format: accounts::OutputFormat, json: bool) -> accounts::OutputFormat {
+    if json {
+        accounts::OutputFormat::Json
+    } else {
+        format
+    }
+}
+
 fn main() {
     if let Err(e) = run() {
         eprintln!("\n  {} {}", "Error:".red().bold(), e);
@@ -165,38 +784,458 @@ fn main() {
 }
 
 fn run() -> Result<()> {
-    if platform::is_root() && !platform::is_container() {
+    // Windows has no root; an elevated prompt still writes to the user's own profile.
+    if platform::detect() != platform::Platform::Windows
+        && platform::is_root()
+        && !platform::is_container()
+    {
         anyhow::bail!("Do not run as root (unless inside a container)");
     }
 
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
+    if let Some(home) = cli.home.clone() {
+        paths::set_home_override(home);
+    }
+    paths::check_env_overrides()?;
+    let user_config = config_user::load_user_config().unwrap_or_default();
+    cli.no_color |= !user_config.color;
+    // `--quiet` wins over a `log_level` of debug or trace.
+    if !cli.quiet {
+        cli.verbose |= user_config.verbose();
+    }
+    let no_color = cli.no_color || !std::io::stdout().is_terminal();
+    if no_color || cli.wants_json() {
+        // Strip ANSI codes entirely so nothing leaks into pipes or machine-readable output.
+        colored::control::set_override(false);
+    }
+    accounts::set_quiet(cli.quiet);
+    // The TUI owns the terminal, so spinners are for subcommands only.
+    spinner::set_enabled(cli.command.is_some() && !cli.quiet && std::io::stdout().is_terminal());
+    credentials::set_keychain_timeout_ms(cli.keychain_timeout_ms());
+    sequence::set_lock_timeout_ms(cli.timeout_lock);
+    if !cli.command.as_ref().is_some_and(Commands::skips_migrations) {
+        if !matches!(cli.command, Some(Commands::MigrateXdg)) {
+            for note in profiles::migrate_to_xdg(false)? {
+                eprintln!("  {} Moved {}", "·".cyan(), note);
+            }
+        }
+        profiles::migrate_legacy_layout()?;
+        config_user::migrate_settings_json()?;
+    }
+    if let Some(name) = cli.profile.as_deref() {
+        profiles::set_profile_override(name)?;
+        if !profiles::exists(name) && !matches!(cli.command, Some(Commands::Profile { .. })) {
+            anyhow::bail!(
+                "Profile '{name}' does not exist. Run `ccswitch profile create {name}` first."
+            );
+        }
+    }
 
     match cli.command {
-        None => tui::run(),
-        Some(Commands::Add) => accounts::add(),
-        Some(Commands::Remove { account }) => accounts::remove(&account),
-        Some(Commands::List) => accounts::list(),
-        Some(Commands::Status) => accounts::status(),
-        Some(Commands::Switch { account: None }) => accounts::switch_next(),
-        Some(Commands::Switch { account: Some(id) }) => accounts::switch_to(&id),
+        None if cli.quiet => {
+            anyhow::bail!("The interactive UI cannot run with --quiet; pass a subcommand")
+        }
+        #[cfg(feature = "tui")]
+        None => tui::run(cli.no_color),
+        #[cfg(not(feature = "tui"))]
+        None => {
+            anyhow::bail!("This build has no interactive UI (the tui feature is off); pass a subcommand")
+        }
+        Some(Commands::Add {
+            priority,
+            from_env,
+            label,
+            no_prompt,
+            force,
+        }) => accounts::add(&accounts::AddOptions {
+            priority,
+            from_env,
+            label,
+            no_prompt,
+            force,
+        }),
+        Some(Commands::Remove { account, force }) => accounts::remove(&account, force),
+        Some(Commands::List { oneline: true, .. }) => accounts::list_oneline(),
+        Some(Commands::List { count: true, active_only, .. }) => accounts::list_count(active_only),
+        Some(Commands::List { porcelain: true, sort, .. }) => accounts::list_porcelain(sort),
+        Some(Commands::List { template: Some(t), sort, .. }) => accounts::list_template(&t, sort),
+        Some(Commands::List { template: None, json, sort, table, active_only, no_active, .. }) => {
+            accounts::list(format_for(cli.format, json), sort, table, active_only, no_active)
+        }
+        Some(Commands::Status { porcelain: true, .. }) => accounts::status_porcelain(),
+        Some(Commands::Status { json, .. }) => {
+            accounts::status(format_for(cli.format, json), cli.verbose)
+        }
+        Some(Commands::Switch {
+            account,
+            highest_priority,
+            tag,
+            cycle_back,
+            interactive,
+            strategy,
+            metrics,
+            metrics_file,
+            dry_run,
+            no_restart_hint,
+        }) => {
+            let opts = accounts::SwitchOptions {
+                format: cli.format,
+                verbose: cli.verbose,
+                metrics,
+                metrics_file,
+                dry_run,
+                no_restart_hint: no_restart_hint || user_config.suppress_restart_hint,
+            };
+            match (account, tag) {
+                _ if highest_priority => accounts::switch_highest_priority(&opts),
+                #[cfg(feature = "tui")]
+                _ if interactive => accounts::switch_interactive(&opts, cli.no_color),
+                #[cfg(not(feature = "tui"))]
+                _ if interactive => {
+                    anyhow::bail!("--interactive needs the tui feature, which this build leaves out")
+                }
+                (_, Some(tag)) => accounts::switch_tag(&tag, &opts),
+                (None, None) if cycle_back => accounts::switch_prev(&opts),
+                (None, None) => accounts::switch_next(
+                    &opts,
+                    strategy.or(user_config.rotation_strategy()).unwrap_or_default(),
+                ),
+                (Some(id), None) => accounts::switch_to(&id, &opts),
+            }
+        }
+        Some(Commands::Priority {
+            action: PriorityAction::Set { account, priority },
+        }) => accounts::set_priority(&account, priority),
+        Some(Commands::Run { account, command }) => {
+            let num = accounts::resolve_account(&account)?;
+            let command: Vec<&str> = command.iter().map(String::as_str).collect();
+            accounts::run_as(num, &command)
+        }
+        Some(Commands::Env { shell, print }) => accounts::env(shell, print),
+        Some(Commands::ShellSetup { shell, rc_path }) => accounts::shell_setup(shell, rc_path),
+        Some(Commands::EnvSet { account, var }) => accounts::env_set(&account, &var),
+        Some(Commands::EnvUnset { account, key }) => accounts::env_unset(&account, &key),
+        Some(Commands::EnvList { account }) => accounts::env_list(&account),
+        Some(Commands::Info { account }) => accounts::info(&account, cli.format),
+        Some(Commands::Tag { account, tag }) => accounts::tag(&account, &tag),
+        Some(Commands::Untag { account, tag }) => accounts::untag(&account, &tag),
+        Some(Commands::SwitchTag { tag }) => accounts::switch_tag(&tag, &accounts::SwitchOptions {
+            format: cli.format,
+            verbose: cli.verbose,
+            no_restart_hint: user_config.suppress_restart_hint,
+            ..Default::default()
+        }),
+        Some(Commands::Undo) => accounts::undo(&accounts::SwitchOptions {
+            format: cli.format,
+            verbose: cli.verbose,
+            no_restart_hint: user_config.suppress_restart_hint,
+            ..Default::default()
+        }),
+        Some(Commands::Diff { from, to }) => {
+            let from = match from {
+                Some(from) => accounts::resolve_account(&from)?,
+                None => sequence::load()?
+                    .active_account_number
+                    .context("No active account. Name the account to compare from.")?,
+            };
+            accounts::diff(from, accounts::resolve_account(&to)?)
+        }
+        Some(Commands::Init { account }) => accounts::init_project(&account),
+        Some(Commands::Move { account, position }) => accounts::move_account(&account, position),
+        Some(Commands::Swap { a, b }) => accounts::swap(&a, &b),
+        Some(Commands::SetUuid { account, uuid }) => accounts::set_uuid(&account, &uuid),
         Some(Commands::Refresh { account, all }) => {
             accounts::refresh(account.as_deref(), all)
         }
         Some(Commands::Edit { account, new_label }) => accounts::edit_account(&account, &new_label),
+        Some(Commands::Clone { account, new_label }) => {
+            accounts::clone_account(&account, new_label.as_deref())
+        }
         Some(Commands::Alias { account, name }) => accounts::set_alias(&account, &name),
+        Some(Commands::Unalias { name }) => accounts::unalias(&name),
+        Some(Commands::Pin { account }) => accounts::pin(&account),
+        Some(Commands::Unpin) => accounts::unpin(),
+        Some(Commands::Link { account }) => accounts::link(&account),
+        Some(Commands::Unlink) => accounts::unlink(),
+        Some(Commands::SetActive { account }) => accounts::set_active(&account),
+        Some(Commands::Migrate { source }) => {
+            let source = match (source.strip_prefix("~"), paths::home_dir()) {
+                (Ok(rest), Some(home)) => home.join(rest),
+                _ => source,
+            };
+            accounts::migrate(&source)
+        }
         Some(Commands::Completions { shell }) => {
-            clap_complete::generate(shell, &mut Cli::command(), "ccswitch", &mut std::io::stdout());
+            completions::generate(shell, &mut Cli::command(), &mut std::io::stdout())
+        }
+        Some(Commands::CompleteAccounts) => completions::print_accounts(),
+        Some(Commands::Note { account, text }) => accounts::note(&account, text.as_deref()),
+        Some(Commands::Stats) => accounts::stats(cli.format),
+        Some(Commands::History { lines }) => accounts::history(lines, cli.format),
+        Some(Commands::CheckPermissions { fix }) => accounts::check_permissions(fix),
+        Some(Commands::Audit { verify: true, .. }) => accounts::audit_verify(),
+        Some(Commands::Audit { lines, output, .. }) => {
+            accounts::audit_log(lines, output.as_deref(), cli.format)
+        }
+        Some(Commands::Config { action: ConfigAction::Set { key, value } }) => {
+            config_user::set(&key, &value)?;
+            println!("\n  {} {} = {}\n", "✓".green().bold(), key, value);
+            Ok(())
+        }
+        Some(Commands::Config { action: ConfigAction::Get { key } }) => {
+            println!("{}", config_user::get(&key)?);
+            Ok(())
+        }
+        Some(Commands::Config { action: ConfigAction::List }) => config_user::list(cli.format),
+        Some(Commands::Config { action: ConfigAction::Reset { key } }) => {
+            config_user::reset(&key)?;
+            println!("\n  {} {} reset to its default\n", "✓".green().bold(), key);
+            Ok(())
+        }
+        Some(Commands::Schedule { action }) => match action {
+            ScheduleAction::Set { account, cron } => schedule::set(&account, &cron),
+            ScheduleAction::Remove { account } => schedule::remove(&account),
+            ScheduleAction::List => schedule::list(cli.format),
+            ScheduleAction::Run => schedule::run(),
+            ScheduleAction::Install => schedule::install(),
+        },
+        Some(Commands::Profile { action: ProfileAction::List }) => {
+            let active = profiles::current();
+            let names = profiles::list()?;
+            if cli.format == accounts::OutputFormat::Json {
+                let out = serde_json::json!({ "active": active, "profiles": names });
+                println!("{}", serde_json::to_string_pretty(&out)?);
+                return Ok(());
+            }
+            println!();
+            for name in names {
+                if name == active {
+                    println!("  {} {}", "▶".green(), name.green().bold());
+                } else {
+                    println!("    {name}");
+                }
+            }
+            println!();
+            Ok(())
+        }
+        Some(Commands::Profile { action: ProfileAction::Create { name } }) => {
+            profiles::create(&name)?;
+            println!("\n  {} Created profile '{}'.\n", "✓".green().bold(), name);
+            Ok(())
+        }
+        Some(Commands::Profile { action: ProfileAction::Delete { name } }) => {
+            profiles::delete(&name)?;
+            println!("\n  {} Deleted profile '{}'.\n", "✓".green().bold(), name);
+            Ok(())
+        }
+        Some(Commands::Profile { action: ProfileAction::Use { name } }) => {
+            profiles::set_active(&name)?;
+            println!("\n  {} Now using profile '{}'.\n", "✓".green().bold(), name);
             Ok(())
         }
+        Some(Commands::Token { action: TokenAction::Refresh { account } }) => {
+            accounts::refresh_token(accounts::resolve_account(&account)?)
+        }
+        Some(Commands::Token { action: TokenAction::Validate { from_env } }) => {
+            accounts::validate_token(from_env)
+        }
+        Some(Commands::MigrateBackend { from, to }) => accounts::migrate_backend(from, to),
+        Some(Commands::Backup { output, passphrase }) => archive::backup(&output, passphrase),
+        Some(Commands::Restore { input, passphrase }) => archive::restore(&input, passphrase),
+        Some(Commands::MigrateXdg) => {
+            let notes = profiles::migrate_to_xdg(true)?;
+            println!();
+            if notes.is_empty() {
+                println!("  {} Nothing to migrate.", "·".cyan());
+            }
+            for note in notes {
+                println!("  {} {}", "✓".green().bold(), note);
+            }
+            println!();
+            Ok(())
+        }
+        Some(Commands::Verify) => accounts::verify(cli.format),
+        Some(Commands::Prune { dry_run }) => accounts::prune(dry_run),
+        Some(Commands::Dedup { dry_run }) => accounts::dedup(dry_run),
+        Some(Commands::Renumber { dry_run }) => accounts::renumber(dry_run),
         Some(Commands::Doctor) => accounts::doctor(),
+        Some(Commands::Gc { older_than_hours }) => accounts::gc(older_than_hours.map_or(
+            sequence::DEFAULT_GC_AGE,
+            |h| std::time::Duration::from_secs(h * 60 * 60),
+        )),
+        Some(Commands::BackupDir) => {
+            println!("{}", sequence::backup_dir().display());
+            Ok(())
+        }
+        Some(Commands::SequenceFile) => {
+            println!("{}", sequence::sequence_path().display());
+            Ok(())
+        }
+        Some(Commands::Repair) => accounts::repair(),
         Some(Commands::Update) => update::update(),
-        Some(Commands::Export { account, all, gist: true }) => {
-            transfer::export_gist(account.as_deref(), all)
+        Some(Commands::Export {
+            target,
+            account,
+            all,
+            gist,
+            print,
+            clipboard,
+            qr,
+        }) => {
+            let account = target.or(account);
+            if qr {
+                transfer::export_qr(account.as_deref())
+            } else if gist {
+                transfer::export_gist(account.as_deref(), all)
+            } else {
+                transfer::export(account.as_deref(), all, print, clipboard)
+            }
         }
-        Some(Commands::Export { account, all, gist: false }) => {
-            transfer::export(account.as_deref(), all)
+        Some(Commands::Import {
+            from_json: Some(path),
+            ..
+        }) => transfer::import_from_json(&path),
+        Some(Commands::Import { gist: Some(id), .. }) => transfer::import_gist(&id),
+        Some(Commands::Import {
+            gist: None,
+            file,
+            clipboard,
+            ..
+        }) => transfer::import(file.as_deref(), clipboard),
+    }
+}
+
+impl Cli {
+    /// The keychain timeout in milliseconds, from `--keychain-timeout` (seconds)
+    /// when given, else `--timeout-keychain`.
+    fn keychain_timeout_ms(&self) -> u64 {
+        self.keychain_timeout
+            .map_or(self.timeout_keychain, |secs| secs.saturating_mul(1000))
+    }
+}
+
+impl Commands {
+    /// Commands that only print paths or shell glue, or that run on every
+    /// completion keypress. They leave the store where it is, so the start-up
+    /// migrations wait for the next command that reads or writes accounts.
+    fn skips_migrations(&self) -> bool {
+        matches!(
+            self,
+            Commands::Completions { .. }
+                | Commands::CompleteAccounts
+                | Commands::ShellSetup { .. }
+                | Commands::BackupDir
+                | Commands::SequenceFile
+                | Commands::Update
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap_complete::Shell;
+
+    // The completion scripts are generated from the binary's `Cli`, so their
+    // tests live here rather than in `completions`.
+
+    fn script_for(shell: Shell) -> String {
+        let mut cmd = <Cli as clap::CommandFactory>::command();
+        let mut buf = Vec::new();
+        completions::generate(shell, &mut cmd, &mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_bash_script_calls_account_lister() {
+        let script = script_for(Shell::Bash);
+        assert!(script.contains("_ccswitch_dynamic"));
+        assert!(script.contains("ccswitch complete-accounts"));
+        assert!(script.contains("complete -F _ccswitch_dynamic"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_bash_completes_accounts_after_global_flags() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ccswitch.bash");
+        std::fs::write(&path, script_for(Shell::Bash)).unwrap();
+
+        let complete = |words: &str| {
+            let out = std::process::Command::new("bash")
+                .arg("-c")
+                .arg(format!(
+                    "source {}; ccswitch() {{ echo 1; echo work; }}; \
+                     COMP_WORDS=({words} ''); COMP_CWORD=$(( ${{#COMP_WORDS[@]}} - 1 )); \
+                     _ccswitch_dynamic; echo \"${{COMPREPLY[*]}}\"",
+                    path.display()
+                ))
+                .output()
+                .unwrap();
+            String::from_utf8(out.stdout).unwrap().trim().to_string()
+        };
+        assert_eq!(complete("ccswitch switch"), "1 work");
+        assert_eq!(complete("ccswitch -q switch"), "1 work");
+        assert_eq!(complete("ccswitch --profile work switch -v"), "1 work");
+        assert_eq!(complete("ccswitch --profile = work switch"), "1 work");
+        assert_ne!(complete("ccswitch switch 1"), "1 work");
+    }
+
+    #[test]
+    fn test_zsh_script_calls_account_lister() {
+        let script = script_for(Shell::Zsh);
+        assert!(script.contains("ccswitch complete-accounts"));
+        assert!(script.trim_end().ends_with("compdef _ccswitch_dynamic ccswitch"));
+    }
+
+    #[test]
+    fn test_fish_script_calls_account_lister() {
+        let script = script_for(Shell::Fish);
+        assert!(script.contains("__fish_seen_subcommand_from switch remove refresh"));
+        assert!(script.contains("(ccswitch complete-accounts 2>/dev/null)"));
+    }
+
+    #[test]
+    fn test_powershell_script_injects_account_lister_per_subcommand() {
+        let script = script_for(Shell::PowerShell);
+        for sub in completions::ACCOUNT_SUBCOMMANDS {
+            let marker = format!("'ccswitch;{sub}' {{\n            & ccswitch complete-accounts");
+            assert!(script.contains(&marker), "missing hook for {sub}");
         }
-        Some(Commands::Import { gist: Some(id) }) => transfer::import_gist(&id),
-        Some(Commands::Import { gist: None }) => transfer::import(),
+    }
+
+    #[test]
+    fn test_keychain_timeout_accepts_seconds() {
+        let parse = |args: &[&str]| {
+            <Cli as clap::Parser>::try_parse_from([&["ccswitch"], args, &["list"]].concat())
+        };
+        let ms = |args: &[&str]| parse(args).unwrap().keychain_timeout_ms();
+        assert_eq!(ms(&["--keychain-timeout", "5"]), 5000);
+        assert_eq!(ms(&["--timeout-keychain", "250"]), 250);
+        assert_eq!(ms(&[]), credentials::DEFAULT_KEYCHAIN_TIMEOUT_MS);
+        assert!(parse(&["--keychain-timeout", "5", "--timeout-keychain", "250"]).is_err());
+    }
+
+    #[test]
+    fn test_only_store_commands_run_migrations() {
+        let skips = |args: &[&str]| {
+            let cli = <Cli as clap::Parser>::try_parse_from([&["ccswitch"], args].concat()).unwrap();
+            cli.command.as_ref().is_some_and(Commands::skips_migrations)
+        };
+        assert!(skips(&["completions", "bash"]));
+        assert!(skips(&["complete-accounts"]));
+        assert!(skips(&["backup-dir"]));
+        assert!(skips(&["sequence-file"]));
+        assert!(!skips(&["list"]));
+        assert!(!skips(&["switch", "1"]));
+        assert!(!skips(&[]));
+    }
+
+    #[test]
+    fn test_elvish_script_is_static() {
+        let mut raw = Vec::new();
+        let mut cmd = <Cli as clap::CommandFactory>::command();
+        clap_complete::generate(Shell::Elvish, &mut cmd, "ccswitch", &mut raw);
+        assert_eq!(script_for(Shell::Elvish).into_bytes(), raw);
     }
 }
@@ -0,0 +1,134 @@
+use anyhow::{Context, Result};
+use std::{fs, io::Write, path::Path, time::Duration};
+
+use crate::sequence::AuthKind;
+
+/// Environment variable naming a file for the Node Exporter textfile collector.
+pub const METRICS_FILE_ENV: &str = "CCSWITCH_METRICS_FILE";
+
+/// Labels attached to every switch metric.
+pub struct SwitchLabels<'a> {
+    pub hostname: &'a str,
+    pub account_number: u32,
+    /// The account's email, or the label of a token account.
+    pub account: &'a str,
+    pub auth_kind: &'a AuthKind,
+    pub ok: bool,
+}
+
+impl SwitchLabels<'_> {
+    fn render(&self) -> String {
+        let auth_kind = match self.auth_kind {
+            AuthKind::Oauth => "oauth",
+            AuthKind::Token => "token",
+        };
+        format!(
+            "account_number=\"{}\",account=\"{}\",auth_kind=\"{}\",\
+             hostname=\"{}\",status=\"{}\"",
+            self.account_number,
+            escape_label(self.account),
+            auth_kind,
+            escape_label(self.hostname),
+            if self.ok { "ok" } else { "error" }
+        )
+    }
+}
+
+/// Render switch metrics in the Prometheus text exposition format: the
+/// duration of the switch that just ran (`last`), and a running total for
+/// every label set in `totals`.
+pub fn render_switch_metrics(
+    last: &SwitchLabels,
+    duration: Duration,
+    totals: &[(SwitchLabels, u64)],
+) -> String {
+    let mut out = format!(
+        "# HELP ccswitch_switch_duration_seconds Time taken by the last account switch.\n\
+         # TYPE ccswitch_switch_duration_seconds gauge\n\
+         ccswitch_switch_duration_seconds{{{}}} {:.3}\n\
+         # HELP ccswitch_switch_total Account switches performed.\n\
+         # TYPE ccswitch_switch_total counter\n",
+        last.render(),
+        duration.as_secs_f64()
+    );
+    for (labels, total) in totals {
+        out.push_str(&format!("ccswitch_switch_total{{{}}} {total}\n", labels.render()));
+    }
+    out
+}
+
+/// Write metrics to `path` via a temp file + rename so a scraper never sees a
+/// partially written file.
+pub fn write_metrics_file(path: &Path, content: &str) -> Result<()> {
+    let temp_path = path.with_extension(format!("tmp.{}", std::process::id()));
+    {
+        let mut f = fs::File::create(&temp_path)
+            .with_context(|| format!("Cannot create temp file {}", temp_path.display()))?;
+        f.write_all(content.as_bytes())?;
+        f.flush()?;
+    }
+    fs::rename(&temp_path, path)
+        .with_context(|| format!("Cannot write metrics to {}", path.display()))
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(account_number: u32, account: &str, ok: bool) -> SwitchLabels<'_> {
+        SwitchLabels {
+            hostname: "box",
+            account_number,
+            account,
+            auth_kind: &AuthKind::Oauth,
+            ok,
+        }
+    }
+
+    #[test]
+    fn test_render_switch_metrics_ok() {
+        let totals = [(labels(1, "a@x.com", true), 4), (labels(2, "b@x.com", true), 7)];
+        let last = labels(2, "b@x.com", true);
+        let out = render_switch_metrics(&last, Duration::from_millis(342), &totals);
+        assert!(out.contains(
+            "ccswitch_switch_duration_seconds{account_number=\"2\",account=\"b@x.com\",auth_kind=\"oauth\",hostname=\"box\",status=\"ok\"} 0.342"
+        ), "unexpected: {out}");
+        assert!(out.contains(
+            "ccswitch_switch_total{account_number=\"1\",account=\"a@x.com\",auth_kind=\"oauth\",hostname=\"box\",status=\"ok\"} 4"
+        ), "unexpected: {out}");
+        assert!(out.contains("account=\"b@x.com\",auth_kind=\"oauth\",hostname=\"box\",status=\"ok\"} 7"));
+        assert_eq!(out.matches("# TYPE ccswitch_switch_total counter").count(), 1);
+    }
+
+    #[test]
+    fn test_render_switch_metrics_error_status() {
+        let token = SwitchLabels {
+            auth_kind: &AuthKind::Token,
+            ..labels(3, "ci \"bot\"", false)
+        };
+        let out = render_switch_metrics(&token, Duration::from_secs(1), &[]);
+        assert!(out.contains("account=\"ci \\\"bot\\\"\""), "unexpected: {out}");
+        assert!(out.contains("auth_kind=\"token\""));
+        assert!(out.contains("status=\"error\""));
+    }
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label(r#"a"b\c"#), r#"a\"b\\c"#);
+    }
+
+    #[test]
+    fn test_write_metrics_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("ccswitch.prom");
+        write_metrics_file(&path, "x 1\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "x 1\n");
+    }
+}
@@ -0,0 +1,99 @@
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+
+// ── Credential backup format versions ────────────────────────────────────────
+//
+// Version 1 (unversioned): the backup is the credentials themselves, exactly
+// as Claude Code or `ccswitch add` stored them.
+//
+// Version 2 wraps them in an envelope that records the format version:
+//
+//   {"version":2,"credentials":"<the credentials, verbatim>"}
+//
+// The credentials stay a string so they round-trip byte for byte; checksums
+// cover them, not the envelope. Older backups are upgraded in memory when
+// read and saved at `CURRENT_VERSION` the next time they are written. A
+// backup linked with `ccswitch link` stays version 1, since Claude Code
+// reads it directly.
+
+/// Format version `encode` writes.
+pub const CURRENT_VERSION: u32 = 2;
+
+/// Wrap `credentials` in a current-version envelope.
+pub fn encode(credentials: &str) -> String {
+    serde_json::json!({ "version": CURRENT_VERSION, "credentials": credentials }).to_string()
+}
+
+/// The credentials stored in a backup of any known version.
+pub fn decode(raw: &str) -> Result<String> {
+    let (mut version, mut doc) = match envelope(raw) {
+        Some((version, doc)) => (version, doc),
+        None => (1, Value::String(raw.to_string())),
+    };
+    if version > CURRENT_VERSION {
+        bail!(
+            "Credentials backup is format version {version}, but this ccswitch only reads up to \
+             version {CURRENT_VERSION}. Update ccswitch."
+        );
+    }
+    while version < CURRENT_VERSION {
+        doc = match version {
+            1 => migrate_credentials_v1_to_v2(doc),
+            _ => bail!("No migration from credentials backup format version {version}"),
+        };
+        version += 1;
+    }
+    doc.get("credentials")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .context("Credentials backup envelope has no credentials")
+}
+
+/// Parse `raw` as a versioned envelope: an object holding exactly a numeric
+/// `version` and a string `credentials`. Anything else is a version 1 backup.
+fn envelope(raw: &str) -> Option<(u32, Value)> {
+    let doc: Value = serde_json::from_str(raw).ok()?;
+    let obj = doc.as_object()?;
+    let version = u32::try_from(obj.get("version")?.as_u64()?).ok()?;
+    (obj.len() == 2 && obj.get("credentials")?.is_string()).then_some((version, doc))
+}
+
+/// Version 1 is the bare credentials, carried here as a JSON string.
+fn migrate_credentials_v1_to_v2(v: Value) -> Value {
+    let credentials = match v {
+        Value::String(s) => s,
+        other => other.to_string(),
+    };
+    serde_json::json!({ "version": 2, "credentials": credentials })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_migrates_unversioned_backups_verbatim() {
+        let oauth = r#"{"claudeAiOauth":{"refreshToken":"r","accessToken":"a","expiresAt":1}}"#;
+        for old in [oauth, r#"{"token":"sk-ant-oat01-x"}"#, "not json at all"] {
+            assert_eq!(decode(old).unwrap(), old);
+        }
+        assert_eq!(
+            migrate_credentials_v1_to_v2(Value::String(oauth.into())),
+            serde_json::json!({ "version": 2, "credentials": oauth })
+        );
+    }
+
+    #[test]
+    fn test_encode_round_trips_and_rejects_newer_versions() {
+        let creds = r#"{"token":"t"}"#;
+        let stored = encode(creds);
+        assert_eq!(stored, r#"{"credentials":"{\"token\":\"t\"}","version":2}"#);
+        assert_eq!(decode(&stored).unwrap(), creds);
+
+        let future = r#"{"version":3,"credentials":"x"}"#;
+        assert!(decode(future).unwrap_err().to_string().contains("version 3"));
+        // An object with other keys is someone's credentials, not an envelope.
+        let lookalike = r#"{"version":2,"credentials":"x","extra":true}"#;
+        assert_eq!(decode(lookalike).unwrap(), lookalike);
+    }
+}
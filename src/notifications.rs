@@ -0,0 +1,60 @@
+use anyhow::{bail, Context, Result};
+use std::process::{Command, Stdio};
+
+use crate::platform::{self, Platform};
+
+// ── Desktop notifications ─────────────────────────────────────────────────────
+//
+// Sent after a switch when `notifications` is on in `config.toml`. Callers
+// ignore the result: a missing notification daemon must never fail a switch.
+
+/// Show a desktop notification: `notify-send` on Linux/WSL, `osascript` on macOS.
+/// The command is started and left to finish on its own, so a slow or hung
+/// notification daemon never holds up the switch.
+pub fn send(title: &str, body: &str) -> Result<()> {
+    let mut cmd = match platform::detect() {
+        Platform::MacOS => {
+            let script = format!(
+                "display notification {} with title {}",
+                applescript_string(body),
+                applescript_string(title)
+            );
+            let mut cmd = Command::new("osascript");
+            cmd.args(["-e", &script]);
+            cmd
+        }
+        Platform::Linux | Platform::Wsl => {
+            if !platform::command_exists("notify-send") {
+                bail!("notify-send is not installed");
+            }
+            let mut cmd = Command::new("notify-send");
+            cmd.args(["--app-name=ccswitch", title, body]);
+            cmd
+        }
+        Platform::Windows => bail!("Desktop notifications are not supported on Windows"),
+    };
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("Failed to send desktop notification")?;
+    // Reap it off the calling thread so the TUI doesn't collect zombies.
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Quote `s` as an AppleScript string literal.
+fn applescript_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_applescript_string_escapes_quotes() {
+        assert_eq!(applescript_string(r#"a "b" \c"#), r#""a \"b\" \\c""#);
+    }
+}
@@ -0,0 +1,150 @@
+use anyhow::{bail, Result};
+use std::{path::PathBuf, sync::OnceLock};
+
+/// Environment variable that overrides the home directory (also set by `--home`).
+pub const HOME_ENV: &str = "CCSWITCH_HOME";
+
+/// Replaces the current profile's backup directory, e.g. for containers and CI.
+pub const BACKUP_DIR_ENV: &str = "CCSWITCH_BACKUP_DIR";
+
+/// Replaces only the location of `sequence.json`.
+pub const SEQUENCE_FILE_ENV: &str = "CCSWITCH_SEQUENCE_FILE";
+
+static HOME_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Override the home directory for this process (from the global `--home` flag).
+pub fn set_home_override(path: PathBuf) {
+    let _ = HOME_OVERRIDE.set(path);
+}
+
+/// True when `--home` or `$CCSWITCH_HOME` replaces the OS home directory.
+pub fn home_overridden() -> bool {
+    HOME_OVERRIDE.get().is_some() || std::env::var_os(HOME_ENV).is_some_and(|p| !p.is_empty())
+}
+
+/// The home directory ccswitch should use for all Claude and backup paths.
+///
+/// Resolution order: `--home`, then `$CCSWITCH_HOME`, then the OS home directory.
+pub fn home_dir() -> Option<PathBuf> {
+    if let Some(p) = HOME_OVERRIDE.get() {
+        return Some(p.clone());
+    }
+    if let Some(p) = std::env::var_os(HOME_ENV).filter(|p| !p.is_empty()) {
+        return Some(PathBuf::from(p));
+    }
+    dirs::home_dir()
+}
+
+/// `$var` as a path when it is set to an absolute path. Relative values are
+/// ignored here; `check_env_overrides` rejects them at startup.
+pub fn env_path(var: &str) -> Option<PathBuf> {
+    std::env::var_os(var)
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+}
+
+/// Fail on a relative `$CCSWITCH_BACKUP_DIR` or `$CCSWITCH_SEQUENCE_FILE`
+/// rather than resolving it against whatever directory ccswitch runs in.
+pub fn check_env_overrides() -> Result<()> {
+    for var in [BACKUP_DIR_ENV, SEQUENCE_FILE_ENV] {
+        if let Some(value) = std::env::var_os(var).filter(|p| !p.is_empty()) {
+            if !PathBuf::from(&value).is_absolute() {
+                bail!("${var} must be an absolute path, got '{}'", value.to_string_lossy());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `~/.claude-switch-backup`: where everything lived before XDG support, and
+/// still the data, config and state directory on macOS.
+pub fn legacy_dir() -> PathBuf {
+    home_dir()
+        .expect("Cannot find home directory")
+        .join(".claude-switch-backup")
+}
+
+#[cfg(test)]
+fn test_dir() -> Option<PathBuf> {
+    std::env::var_os("CCSWITCH_TEST_DIR").map(PathBuf::from)
+}
+
+/// Account data: `$XDG_DATA_HOME/ccswitch` on Linux/WSL.
+pub fn data_dir() -> PathBuf {
+    #[cfg(test)]
+    if let Some(dir) = test_dir() {
+        return dir;
+    }
+    xdg_or_legacy(crate::platform::xdg_data_home())
+}
+
+/// User configuration (`config.toml`): `$XDG_CONFIG_HOME/ccswitch` on Linux/WSL.
+pub fn config_dir() -> PathBuf {
+    #[cfg(test)]
+    if let Some(dir) = test_dir() {
+        return dir;
+    }
+    xdg_or_legacy(crate::platform::xdg_config_home())
+}
+
+/// Logs and other state: `$XDG_STATE_HOME/ccswitch` on Linux/WSL.
+pub fn state_dir() -> PathBuf {
+    #[cfg(test)]
+    if let Some(dir) = test_dir() {
+        return dir;
+    }
+    xdg_or_legacy(crate::platform::xdg_state_home())
+}
+
+fn xdg_or_legacy(xdg_base: Option<PathBuf>) -> PathBuf {
+    match xdg_base {
+        Some(base) if crate::platform::uses_xdg() => base.join("ccswitch"),
+        _ => legacy_dir(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_home_dir_env_override() {
+        let _env = crate::test_utils::TestEnv::new();
+        std::env::set_var(HOME_ENV, "/tmp/ccswitch-home");
+        let home = home_dir();
+        std::env::remove_var(HOME_ENV);
+        assert_eq!(home, Some(PathBuf::from("/tmp/ccswitch-home")));
+    }
+
+    #[test]
+    fn test_home_dir_empty_env_ignored() {
+        let _env = crate::test_utils::TestEnv::new();
+        std::env::set_var(HOME_ENV, "");
+        let home = home_dir();
+        std::env::remove_var(HOME_ENV);
+        assert_eq!(home, dirs::home_dir());
+    }
+
+    #[test]
+    fn test_backup_dir_and_sequence_file_overrides() {
+        let env = crate::test_utils::TestEnv::new();
+        let backups = env.dir.path().join("backups");
+        let seq_file = env.dir.path().join("index").join("seq.json");
+        std::env::set_var(BACKUP_DIR_ENV, &backups);
+        std::env::set_var(SEQUENCE_FILE_ENV, &seq_file);
+
+        assert_eq!(crate::sequence::backup_dir(), backups);
+        assert_eq!(crate::sequence::sequence_path(), seq_file);
+        assert_eq!(crate::credentials::active_token_file_path(), backups.join("active-token"));
+        assert!(crate::accounts::config_backup_path(1, "a@b.c").starts_with(&backups));
+        crate::sequence::setup_dirs().unwrap();
+        crate::sequence::save(&crate::sequence::SequenceFile::default()).unwrap();
+        assert!(seq_file.exists());
+        assert!(check_env_overrides().is_ok());
+
+        std::env::set_var(SEQUENCE_FILE_ENV, "relative/seq.json");
+        let err = check_env_overrides().unwrap_err();
+        assert!(err.to_string().contains("must be an absolute path"), "unexpected: {err}");
+    }
+}
@@ -0,0 +1,172 @@
+use anyhow::Result;
+use crossterm::{
+    event::{self, Event, KeyCode, KeyModifiers},
+    terminal::{disable_raw_mode, enable_raw_mode},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Position},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{List, ListItem, ListState, Paragraph},
+    Terminal, TerminalOptions, Viewport,
+};
+use std::io;
+
+// ── Inline account picker ─────────────────────────────────────────────────────
+//
+// `switch --interactive`: an fzf-style prompt drawn below the cursor in an
+// inline viewport rather than the alternate screen. The lines it used are
+// cleared on exit, so only the switch output is left behind.
+
+/// Rows of accounts shown at once; longer lists scroll.
+const MAX_ROWS: usize = 8;
+
+/// One pickable account.
+pub struct Choice {
+    pub number: u32,
+    pub email: String,
+    pub active: bool,
+}
+
+/// Let the user pick one of `choices`; `None` when they press Esc or Ctrl+C.
+pub fn pick(choices: &[Choice], no_color: bool) -> Result<Option<u32>> {
+    let height = choices.len().clamp(1, MAX_ROWS) as u16 + 1;
+    enable_raw_mode()?;
+    let mut terminal = Terminal::with_options(
+        CrosstermBackend::new(io::stdout()),
+        TerminalOptions {
+            viewport: Viewport::Inline(height),
+        },
+    )?;
+
+    let result = pick_loop(&mut terminal, choices, no_color);
+
+    // Always restore the terminal and wipe the picker's lines.
+    disable_raw_mode()?;
+    terminal.clear()?;
+    terminal.show_cursor()?;
+
+    result
+}
+
+fn pick_loop(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    choices: &[Choice],
+    no_color: bool,
+) -> Result<Option<u32>> {
+    let mut query = String::new();
+    let mut matches = filter(choices, &query);
+    let mut state = ListState::default().with_selected(Some(0));
+
+    loop {
+        terminal.draw(|f| {
+            let [input_area, list_area] = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Min(0)])
+                .areas(f.area());
+
+            f.render_widget(
+                Paragraph::new(Line::from(vec![
+                    Span::styled("> ", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+                    Span::raw(query.as_str()),
+                    Span::styled(
+                        format!("  {}/{}", matches.len(), choices.len()),
+                        Style::default().add_modifier(Modifier::DIM),
+                    ),
+                ])),
+                input_area,
+            );
+            f.set_cursor_position(Position::new(
+                input_area.x + 2 + query.chars().count() as u16,
+                input_area.y,
+            ));
+
+            let items: Vec<ListItem> = matches
+                .iter()
+                .map(|&i| {
+                    let choice = &choices[i];
+                    let mut spans = vec![
+                        Span::raw(format!("{:>2}  ", choice.number)),
+                        Span::raw(choice.email.as_str()),
+                    ];
+                    if choice.active {
+                        spans.push(Span::styled(
+                            "  (active)",
+                            Style::default().fg(Color::Green).add_modifier(Modifier::DIM),
+                        ));
+                    }
+                    ListItem::new(Line::from(spans))
+                })
+                .collect();
+            f.render_stateful_widget(
+                List::new(items)
+                    .highlight_symbol("▶ ")
+                    .highlight_style(
+                        Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD | Modifier::REVERSED),
+                    ),
+                list_area,
+                &mut state,
+            );
+
+            if no_color {
+                crate::tui::strip_colors(f.buffer_mut());
+            }
+        })?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        let selected = state.selected().unwrap_or(0);
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(None),
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Enter => return Ok(matches.get(selected).map(|&i| choices[i].number)),
+            KeyCode::Up => state.select(Some(selected.saturating_sub(1))),
+            KeyCode::Down => state.select(Some((selected + 1).min(matches.len().saturating_sub(1)))),
+            KeyCode::Backspace => {
+                query.pop();
+                matches = filter(choices, &query);
+                state.select(Some(0));
+            }
+            KeyCode::Char(c) => {
+                query.push(c);
+                matches = filter(choices, &query);
+                state.select(Some(0));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Indices of the choices whose email contains `query`, ignoring case.
+fn filter(choices: &[Choice], query: &str) -> Vec<usize> {
+    let query = query.to_lowercase();
+    choices
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.email.to_lowercase().contains(&query))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_matches_email_substring_ignoring_case() {
+        let choices: Vec<Choice> = ["me@home.com", "Work@Company.com", "ci@company.com"]
+            .iter()
+            .enumerate()
+            .map(|(i, email)| Choice {
+                number: i as u32 + 1,
+                email: email.to_string(),
+                active: false,
+            })
+            .collect();
+        assert_eq!(filter(&choices, ""), [0, 1, 2]);
+        assert_eq!(filter(&choices, "COMPANY"), [1, 2]);
+        assert!(filter(&choices, "nope").is_empty());
+    }
+}
@@ -1,10 +1,15 @@
-use std::{env, fs, path::Path};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Platform {
     MacOS,
     Linux,
     Wsl,
+    /// Native Windows (not WSL).
+    Windows,
 }
 
 impl std::fmt::Display for Platform {
@@ -13,6 +18,7 @@ impl std::fmt::Display for Platform {
             Platform::MacOS => write!(f, "macOS"),
             Platform::Linux => write!(f, "Linux"),
             Platform::Wsl => write!(f, "WSL"),
+            Platform::Windows => write!(f, "Windows"),
         }
     }
 }
@@ -31,10 +37,43 @@ pub fn detect() -> Platform {
                 Platform::Linux
             }
         }
+        "windows" => Platform::Windows,
         _ => Platform::Linux,
     }
 }
 
+/// Linux and WSL follow the XDG Base Directory spec; macOS and Windows keep
+/// `~/.claude-switch-backup`.
+pub fn uses_xdg() -> bool {
+    matches!(detect(), Platform::Linux | Platform::Wsl)
+}
+
+/// `$XDG_DATA_HOME`, falling back to `~/.local/share`.
+pub fn xdg_data_home() -> Option<PathBuf> {
+    xdg_dir("XDG_DATA_HOME", ".local/share")
+}
+
+/// `$XDG_CONFIG_HOME`, falling back to `~/.config`.
+pub fn xdg_config_home() -> Option<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// `$XDG_STATE_HOME`, falling back to `~/.local/state`.
+pub fn xdg_state_home() -> Option<PathBuf> {
+    xdg_dir("XDG_STATE_HOME", ".local/state")
+}
+
+/// The spec ignores relative values. Under `--home`/`$CCSWITCH_HOME` the XDG
+/// variables are ignored too, so everything stays inside the chosen home.
+fn xdg_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    if !crate::paths::home_overridden() {
+        if let Some(p) = env::var_os(var).map(PathBuf::from).filter(|p| p.is_absolute()) {
+            return Some(p);
+        }
+    }
+    crate::paths::home_dir().map(|h| h.join(fallback))
+}
+
 pub fn is_container() -> bool {
     if Path::new("/.dockerenv").exists() {
         return true;
@@ -52,6 +91,14 @@ pub fn is_container() -> bool {
     env::var("CONTAINER").is_ok() || env::var("container").is_ok()
 }
 
+/// On Windows this reports an elevated (administrator) process instead.
+#[cfg(windows)]
+pub fn is_root() -> bool {
+    // SAFETY: IsUserAnAdmin takes no arguments and only inspects the process token.
+    unsafe { windows_sys::Win32::UI::Shell::IsUserAnAdmin() != 0 }
+}
+
+#[cfg(not(windows))]
 pub fn is_root() -> bool {
     std::process::Command::new("id")
         .arg("-u")
@@ -61,3 +108,87 @@ pub fn is_root() -> bool {
         .map(|s| s.trim() == "0")
         .unwrap_or(false)
 }
+
+/// True if `name` is an executable file somewhere on `$PATH`.
+pub fn command_exists(name: &str) -> bool {
+    env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).any(|dir| dir.join(name).is_file()))
+        .unwrap_or(false)
+}
+
+/// Best-effort hostname for labelling output; falls back to "unknown".
+pub fn hostname() -> String {
+    if let Ok(h) = env::var("HOSTNAME") {
+        if !h.trim().is_empty() {
+            return h.trim().to_string();
+        }
+    }
+    if let Ok(h) = fs::read_to_string("/etc/hostname") {
+        if !h.trim().is_empty() {
+            return h.trim().to_string();
+        }
+    }
+    std::process::Command::new("hostname")
+        .output()
+        .ok()
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// First 8 hex digits of the machine ID: `/etc/machine-id` on Linux/WSL, the
+/// `IOPlatformUUID` from `ioreg` on macOS. `None` on Windows or when unreadable.
+pub fn machine_id() -> Option<String> {
+    let raw = match detect() {
+        Platform::MacOS => std::process::Command::new("ioreg")
+            .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+            .output()
+            .ok()
+            .and_then(|o| String::from_utf8(o.stdout).ok())
+            .and_then(|out| ioreg_platform_uuid(&out)),
+        Platform::Linux | Platform::Wsl => ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+            .iter()
+            .find_map(|p| fs::read_to_string(p).ok()),
+        Platform::Windows => None,
+    }?;
+    short_machine_id(&raw)
+}
+
+/// The value of `"IOPlatformUUID" = "…"` in `ioreg` output.
+fn ioreg_platform_uuid(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find(|l| l.contains("\"IOPlatformUUID\""))
+        .and_then(|l| l.split('=').nth(1))
+        .map(|v| v.trim().trim_matches('"').to_string())
+}
+
+fn short_machine_id(raw: &str) -> Option<String> {
+    let hex: String = raw
+        .chars()
+        .filter(|c| *c != '-')
+        .map(|c| c.to_ascii_lowercase())
+        .take_while(|c| c.is_ascii_hexdigit())
+        .take(8)
+        .collect();
+    (hex.len() == 8).then_some(hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_machine_id_from_ioreg_and_machine_id_file() {
+        let ioreg = r#"+-o J314sAP  <class IOPlatformExpertDevice>
+    {
+      "IOPlatformSerialNumber" = "C02XXXXX"
+      "IOPlatformUUID" = "5A3C1D2E-0B4F-4C6A-9E8D-7F6A5B4C3D2E"
+    }"#;
+        let uuid = ioreg_platform_uuid(ioreg).unwrap();
+        assert_eq!(short_machine_id(&uuid).as_deref(), Some("5a3c1d2e"));
+        assert_eq!(short_machine_id("0123456789abcdef\n").as_deref(), Some("01234567"));
+        assert_eq!(short_machine_id("abc\n"), None);
+    }
+}
@@ -0,0 +1,441 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use crate::{paths, platform, sequence};
+
+// ── Profiles: independent account sets under <data dir>/<name> ────────────────
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Entries that lived directly in `~/.claude-switch-backup` before profiles
+/// existed; they belong to the default profile now.
+const LEGACY_ENTRIES: &[&str] = &[
+    "sequence.json",
+    "configs",
+    "credentials",
+    "switch.log",
+];
+
+/// Layout of the data directory: 2 is one directory per profile. Recorded in
+/// `.layout-version` once `migrate_legacy_layout` has run.
+const LAYOUT_VERSION: u32 = 2;
+
+static PROFILE_OVERRIDE: OnceLock<String> = OnceLock::new();
+
+/// Use this profile for the rest of the process (from the global `--profile` flag).
+pub fn set_profile_override(name: &str) -> Result<()> {
+    validate_name(name)?;
+    let _ = PROFILE_OVERRIDE.set(name.to_string());
+    Ok(())
+}
+
+/// The data directory: holds one directory per profile plus global files.
+pub fn root_dir() -> PathBuf {
+    paths::data_dir()
+}
+
+fn active_profile_path() -> PathBuf {
+    root_dir().join(".active-profile")
+}
+
+fn profile_dir(name: &str) -> PathBuf {
+    root_dir().join(name)
+}
+
+/// The profile in effect: `--profile`, then `.active-profile`, then the
+/// `default_profile` setting, then `default`.
+pub fn current() -> String {
+    if let Some(name) = PROFILE_OVERRIDE.get() {
+        return name.clone();
+    }
+    fs::read_to_string(active_profile_path())
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| validate_name(s).is_ok())
+        .or_else(|| {
+            crate::config_user::load_user_config()
+                .ok()?
+                .default_profile
+                .filter(|name| validate_name(name).is_ok() && exists(name))
+        })
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+pub fn is_default(name: &str) -> bool {
+    name == DEFAULT_PROFILE
+}
+
+pub fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.len() > 64 {
+        bail!("Profile name must be 1–64 characters");
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        bail!("Profile name '{name}' may only contain letters, digits, '-' and '_'");
+    }
+    if LEGACY_ENTRIES.contains(&name) {
+        bail!("'{name}' is reserved and cannot be used as a profile name");
+    }
+    Ok(())
+}
+
+pub fn exists(name: &str) -> bool {
+    is_default(name) || profile_dir(name).is_dir()
+}
+
+/// All profiles, sorted, always including `default`.
+pub fn list() -> Result<Vec<String>> {
+    let mut names = vec![DEFAULT_PROFILE.to_string()];
+    let root = root_dir();
+    if root.is_dir() {
+        for entry in fs::read_dir(&root)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if entry.file_type()?.is_dir() && validate_name(&name).is_ok() {
+                names.push(name);
+            }
+        }
+    }
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+pub fn create(name: &str) -> Result<()> {
+    validate_name(name)?;
+    if exists(name) {
+        bail!("Profile '{name}' already exists");
+    }
+    sequence::setup_dirs_at(&profile_dir(name))
+}
+
+pub fn delete(name: &str) -> Result<()> {
+    validate_name(name)?;
+    if is_default(name) {
+        bail!("The default profile cannot be deleted");
+    }
+    if !exists(name) {
+        bail!("Profile '{name}' does not exist");
+    }
+    if current() == name {
+        bail!("Profile '{name}' is active. Run `ccswitch profile use <other>` first.");
+    }
+    let seq_path = profile_dir(name).join("sequence.json");
+    if seq_path.exists() {
+        let content = fs::read_to_string(&seq_path)
+            .with_context(|| format!("Failed to read {}", seq_path.display()))?;
+        let seq: sequence::SequenceFile = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid JSON in {}", seq_path.display()))?;
+        if !seq.accounts.is_empty() {
+            bail!(
+                "Profile '{name}' still has {} account(s). Remove them first with `ccswitch --profile {name} remove`.",
+                seq.accounts.len()
+            );
+        }
+    }
+    fs::remove_dir_all(profile_dir(name))
+        .with_context(|| format!("Failed to delete profile '{name}'"))
+}
+
+/// Make `name` the profile used when `--profile` is not given.
+pub fn set_active(name: &str) -> Result<()> {
+    validate_name(name)?;
+    if !exists(name) {
+        bail!("Profile '{name}' does not exist. Run `ccswitch profile create {name}` first.");
+    }
+    fs::create_dir_all(root_dir())?;
+    fs::write(active_profile_path(), format!("{name}\n"))
+        .with_context(|| format!("Failed to write {}", active_profile_path().display()))
+}
+
+fn layout_version_path(root: &Path) -> PathBuf {
+    root.join(".layout-version")
+}
+
+fn layout_version(root: &Path) -> u32 {
+    fs::read_to_string(layout_version_path(root))
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(1)
+}
+
+/// Move a pre-profiles layout into `default/`, under the lock older releases
+/// took at the root. Returns at once when the layout is already current.
+pub fn migrate_legacy_layout() -> Result<()> {
+    let root = root_dir();
+    if !root.is_dir() || layout_version(&root) >= LAYOUT_VERSION {
+        return Ok(());
+    }
+    let _lock = sequence::acquire_lock_at(&root.join(".ccswitch.lock"))?;
+    if layout_version(&root) >= LAYOUT_VERSION {
+        return Ok(());
+    }
+    move_legacy_layout(&root)?;
+    let path = layout_version_path(&root);
+    fs::write(&path, format!("{LAYOUT_VERSION}\n"))
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn move_legacy_layout(root: &Path) -> Result<()> {
+    let target = root.join(DEFAULT_PROFILE);
+    if target.exists() || !root.join("sequence.json").exists() {
+        return Ok(());
+    }
+    sequence::setup_dirs_at(&target)?;
+    for entry in LEGACY_ENTRIES {
+        let from = root.join(entry);
+        if !from.exists() {
+            continue;
+        }
+        let to = target.join(entry);
+        if to.is_dir() {
+            fs::remove_dir(&to)?;
+        }
+        fs::rename(&from, &to).with_context(|| {
+            format!("Failed to move {} into {}", from.display(), target.display())
+        })?;
+    }
+    Ok(())
+}
+
+// ── XDG migration ─────────────────────────────────────────────────────────────
+
+/// Source and destination directories for `migrate_to_xdg`.
+struct StorageDirs {
+    legacy: PathBuf,
+    data: PathBuf,
+    config: PathBuf,
+    state: PathBuf,
+}
+
+/// Move `~/.claude-switch-backup` into the XDG data, config and state
+/// directories. Runs automatically when the new data directory does not exist
+/// yet; `force` merges into an existing one, skipping entries already there.
+/// Returns one line per moved or skipped item.
+pub fn migrate_to_xdg(force: bool) -> Result<Vec<String>> {
+    if !platform::uses_xdg() {
+        if force {
+            bail!("XDG directories are only used on Linux and WSL");
+        }
+        return Ok(Vec::new());
+    }
+    migrate_dirs(
+        &StorageDirs {
+            legacy: paths::legacy_dir(),
+            data: paths::data_dir(),
+            config: paths::config_dir(),
+            state: paths::state_dir(),
+        },
+        force,
+    )
+}
+
+fn migrate_dirs(dirs: &StorageDirs, force: bool) -> Result<Vec<String>> {
+    let mut notes = Vec::new();
+    if dirs.legacy == dirs.data || !dirs.legacy.is_dir() {
+        return Ok(notes);
+    }
+
+    if !dirs.data.exists() {
+        if let Some(parent) = dirs.data.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        move_path(&dirs.legacy, &dirs.data)?;
+        notes.push(format!("{} → {}", dirs.legacy.display(), dirs.data.display()));
+    } else if force {
+        for entry in fs::read_dir(&dirs.legacy)? {
+            let from = entry?.path();
+            let to = dirs.data.join(from.file_name().unwrap_or_default());
+            if to.exists() {
+                notes.push(format!("skipped {} ({} exists)", from.display(), to.display()));
+            } else {
+                move_path(&from, &to)?;
+                notes.push(format!("{} → {}", from.display(), to.display()));
+            }
+        }
+        // Only succeeds once everything has been moved.
+        let _ = fs::remove_dir(&dirs.legacy);
+    } else {
+        return Ok(notes);
+    }
+
+    move_legacy_layout(&dirs.data)?;
+
+    let (from, to) = (dirs.data.join("config.toml"), dirs.config.join("config.toml"));
+    if from.exists() && !to.exists() {
+        fs::create_dir_all(&dirs.config)?;
+        move_path(&from, &to)?;
+        notes.push(format!("{} → {}", from.display(), to.display()));
+    }
+
+    for entry in fs::read_dir(&dirs.data)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let from = entry.path().join("switch.log");
+        let to = dirs.state.join(entry.file_name()).join("switch.log");
+        if from.exists() && !to.exists() {
+            fs::create_dir_all(to.parent().unwrap_or(&dirs.state))?;
+            move_path(&from, &to)?;
+            notes.push(format!("{} → {}", from.display(), to.display()));
+        }
+    }
+    Ok(notes)
+}
+
+/// Rename, falling back to copy-and-delete across filesystems.
+fn move_path(from: &Path, to: &Path) -> Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    copy_recursive(from, to)
+        .with_context(|| format!("Failed to move {} to {}", from.display(), to.display()))?;
+    if from.is_dir() {
+        fs::remove_dir_all(from)?;
+    } else {
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+fn copy_recursive(from: &Path, to: &Path) -> Result<()> {
+    if !from.is_dir() {
+        fs::copy(from, to)?;
+        return Ok(());
+    }
+    fs::create_dir_all(to)?;
+    fs::set_permissions(to, fs::metadata(from)?.permissions())?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestEnv;
+
+    #[test]
+    fn test_current_defaults_and_follows_active_file() {
+        let _env = TestEnv::new();
+        assert_eq!(current(), DEFAULT_PROFILE);
+        assert_eq!(sequence::backup_dir(), root_dir().join(DEFAULT_PROFILE));
+
+        create("client").unwrap();
+        set_active("client").unwrap();
+        assert_eq!(current(), "client");
+        assert_eq!(sequence::backup_dir(), root_dir().join("client"));
+        assert_eq!(list().unwrap(), vec!["client", "default"]);
+    }
+
+    #[test]
+    fn test_validate_name_rejects_paths_and_reserved() {
+        assert!(validate_name("work_2").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("../x").is_err());
+        assert!(validate_name(".hidden").is_err());
+        assert!(validate_name("configs").is_err());
+    }
+
+    #[test]
+    fn test_delete_refuses_default_active_and_nonempty() {
+        let _env = TestEnv::new();
+        assert!(delete(DEFAULT_PROFILE).is_err());
+        assert!(delete("missing").is_err());
+
+        create("client").unwrap();
+        set_active("client").unwrap();
+        assert!(delete("client").is_err());
+
+        set_active(DEFAULT_PROFILE).unwrap();
+        delete("client").unwrap();
+        assert!(!exists("client"));
+    }
+
+    fn storage_dirs(base: &Path) -> StorageDirs {
+        StorageDirs {
+            legacy: base.join("home/.claude-switch-backup"),
+            data: base.join("share/ccswitch"),
+            config: base.join("config/ccswitch"),
+            state: base.join("state/ccswitch"),
+        }
+    }
+
+    #[test]
+    fn test_migrate_dirs_splits_data_config_and_state() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let dirs = storage_dirs(tmp.path());
+        // A pre-profiles install with hooks and a switch log.
+        fs::create_dir_all(dirs.legacy.join("credentials")).unwrap();
+        fs::write(dirs.legacy.join("sequence.json"), "{}").unwrap();
+        fs::write(dirs.legacy.join("switch.log"), "log\n").unwrap();
+        fs::write(dirs.legacy.join("config.toml"), "[hooks]\n").unwrap();
+        fs::write(dirs.legacy.join("settings.json"), "{}").unwrap();
+
+        let notes = migrate_dirs(&dirs, false).unwrap();
+        assert_eq!(notes.len(), 3, "{notes:?}");
+
+        assert!(!dirs.legacy.exists());
+        assert!(dirs.data.join("default/sequence.json").exists());
+        assert!(dirs.data.join("default/credentials").is_dir());
+        assert!(dirs.data.join("settings.json").exists());
+        assert_eq!(fs::read_to_string(dirs.config.join("config.toml")).unwrap(), "[hooks]\n");
+        assert_eq!(fs::read_to_string(dirs.state.join("default/switch.log")).unwrap(), "log\n");
+
+        // Nothing left to do on the next run.
+        assert!(migrate_dirs(&dirs, false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_migrate_dirs_only_merges_into_existing_data_when_forced() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let dirs = storage_dirs(tmp.path());
+        fs::create_dir_all(dirs.legacy.join("client")).unwrap();
+        fs::write(dirs.legacy.join("settings.json"), "old").unwrap();
+        fs::create_dir_all(&dirs.data).unwrap();
+        fs::write(dirs.data.join("settings.json"), "new").unwrap();
+
+        assert!(migrate_dirs(&dirs, false).unwrap().is_empty());
+        assert!(dirs.legacy.join("client").exists());
+
+        let notes = migrate_dirs(&dirs, true).unwrap();
+        assert!(notes.iter().any(|n| n.starts_with("skipped")), "{notes:?}");
+        assert!(dirs.data.join("client").is_dir());
+        assert_eq!(fs::read_to_string(dirs.data.join("settings.json")).unwrap(), "new");
+        assert!(dirs.legacy.join("settings.json").exists());
+    }
+
+    #[test]
+    fn test_migrate_legacy_layout_moves_into_default() {
+        let env = TestEnv::new();
+        let root = env.dir.path();
+        fs::remove_dir_all(root.join(DEFAULT_PROFILE)).unwrap();
+        fs::write(root.join("sequence.json"), "{}").unwrap();
+        fs::create_dir_all(root.join("configs")).unwrap();
+        fs::write(root.join("configs").join("a.json"), "{}").unwrap();
+        fs::write(root.join("settings.json"), "{}").unwrap();
+
+        migrate_legacy_layout().unwrap();
+
+        let default = root.join(DEFAULT_PROFILE);
+        assert!(default.join("sequence.json").exists());
+        assert!(default.join("configs").join("a.json").exists());
+        assert!(default.join("credentials").is_dir());
+        assert!(!root.join("sequence.json").exists());
+        // Global files stay at the root.
+        assert!(root.join("settings.json").exists());
+        assert_eq!(layout_version(root), LAYOUT_VERSION);
+
+        // Once the layout is current, a stray legacy file is left alone.
+        fs::remove_dir_all(&default).unwrap();
+        fs::write(root.join("sequence.json"), "{}").unwrap();
+        migrate_legacy_layout().unwrap();
+        assert!(!default.exists());
+    }
+}
@@ -0,0 +1,646 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike};
+use colored::Colorize;
+use std::{io::Write, path::PathBuf, process::Command, process::Stdio};
+
+use crate::{
+    accounts::{self, OutputFormat, SwitchOptions},
+    platform::{self, Platform},
+    profiles,
+    sequence::{self, now_utc, ScheduleEntry},
+};
+
+// ── Cron expressions ──────────────────────────────────────────────────────────
+
+const MONTH_NAMES: &[&str] = &[
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const DOW_NAMES: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// How far back `last_fire` looks for a matching minute.
+const LOOKBACK_DAYS: i64 = 366;
+
+/// A standard five-field cron expression (`minute hour day-of-month month day-of-week`),
+/// evaluated in local time. Each field is a bitmask of the values it matches.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronExpr {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// Cron matches a day if *either* day field matches when both are restricted.
+    dom_restricted: bool,
+    dow_restricted: bool,
+}
+
+impl CronExpr {
+    pub fn parse(expr: &str) -> Result<Self> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [min, hour, dom, month, dow] = fields[..] else {
+            bail!("Cron expression '{expr}' must have 5 fields: minute hour day-of-month month day-of-week");
+        };
+        let mut days_of_week = parse_field(dow, 0, 7, DOW_NAMES, 0)
+            .with_context(|| format!("Invalid day-of-week field '{dow}'"))?;
+        // Both 0 and 7 mean Sunday.
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week |= 1;
+        }
+        Ok(CronExpr {
+            minutes: parse_field(min, 0, 59, &[], 0)
+                .with_context(|| format!("Invalid minute field '{min}'"))?,
+            hours: parse_field(hour, 0, 23, &[], 0)
+                .with_context(|| format!("Invalid hour field '{hour}'"))?,
+            days_of_month: parse_field(dom, 1, 31, &[], 0)
+                .with_context(|| format!("Invalid day-of-month field '{dom}'"))?,
+            months: parse_field(month, 1, 12, MONTH_NAMES, 1)
+                .with_context(|| format!("Invalid month field '{month}'"))?,
+            days_of_week,
+            dom_restricted: !dom.starts_with('*'),
+            dow_restricted: !dow.starts_with('*'),
+        })
+    }
+
+    fn matches_day(&self, day: NaiveDate) -> bool {
+        if !bit(self.months, day.month()) {
+            return false;
+        }
+        let dom = bit(self.days_of_month, day.day());
+        let dow = bit(self.days_of_week, day.weekday().num_days_from_sunday());
+        match (self.dom_restricted, self.dow_restricted) {
+            (true, true) => dom || dow,
+            _ => dom && dow,
+        }
+    }
+
+    #[cfg(test)]
+    fn matches(&self, t: NaiveDateTime) -> bool {
+        self.matches_day(t.date()) && bit(self.hours, t.hour()) && bit(self.minutes, t.minute())
+    }
+
+    /// The most recent minute at or before `now` that this expression matches.
+    pub fn last_fire(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        for back in 0..=LOOKBACK_DAYS {
+            let day = now.date() - Duration::days(back);
+            if !self.matches_day(day) {
+                continue;
+            }
+            let max_hour = if back == 0 { now.hour() } else { 23 };
+            for h in (0..=max_hour).rev().filter(|&h| bit(self.hours, h)) {
+                let max_min = if back == 0 && h == now.hour() {
+                    now.minute()
+                } else {
+                    59
+                };
+                if let Some(m) = (0..=max_min).rev().find(|&m| bit(self.minutes, m)) {
+                    return day.and_hms_opt(h, m, 0);
+                }
+            }
+        }
+        None
+    }
+}
+
+fn bit(mask: u64, n: u32) -> bool {
+    mask & (1 << n) != 0
+}
+
+/// Parse one comma-separated cron field (`*`, `a`, `a-b`, each with optional `/step`).
+/// `names` are accepted in place of numbers, the first name standing for `name_base`.
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str], name_base: u32) -> Result<u64> {
+    let value = |s: &str| -> Result<u32> {
+        let n = match names.iter().position(|n| n.eq_ignore_ascii_case(s)) {
+            Some(i) => i as u32 + name_base,
+            None => s
+                .parse()
+                .with_context(|| format!("'{s}' is not a number"))?,
+        };
+        if n < min || n > max {
+            bail!("{n} is outside {min}-{max}");
+        }
+        Ok(n)
+    };
+
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((r, s)) => (
+                r,
+                s.parse::<u32>()
+                    .ok()
+                    .filter(|&s| s > 0)
+                    .with_context(|| format!("Invalid step '{s}'"))?,
+            ),
+            None => (part, 1),
+        };
+        let (lo, hi) = match range {
+            "*" => (min, max),
+            r => match r.split_once('-') {
+                Some((a, b)) => (value(a)?, value(b)?),
+                None if part.contains('/') => (value(r)?, max),
+                None => (value(r)?, value(r)?),
+            },
+        };
+        if lo > hi {
+            bail!("Range {lo}-{hi} is backwards");
+        }
+        for n in (lo..=hi).step_by(step as usize) {
+            mask |= 1 << n;
+        }
+    }
+    Ok(mask)
+}
+
+// ── Schedules in sequence.json ────────────────────────────────────────────────
+
+pub(crate) fn core_set(num: u32, cron_expr: &str) -> Result<()> {
+    CronExpr::parse(cron_expr)?;
+    let _lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load()?;
+    if !seq.accounts.contains_key(&num.to_string()) {
+        bail!("Account {num} does not exist");
+    }
+    let cron_expr = cron_expr.split_whitespace().collect::<Vec<_>>().join(" ");
+    match seq.schedules.iter_mut().find(|s| s.account_num == num) {
+        Some(existing) => existing.cron_expr = cron_expr,
+        None => seq.schedules.push(ScheduleEntry {
+            account_num: num,
+            cron_expr,
+        }),
+    }
+    seq.last_updated = now_utc();
+    sequence::save(&seq)
+}
+
+pub(crate) fn core_remove(num: u32) -> Result<bool> {
+    let _lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load()?;
+    let before = seq.schedules.len();
+    seq.schedules.retain(|s| s.account_num != num);
+    if seq.schedules.len() == before {
+        return Ok(false);
+    }
+    seq.last_updated = now_utc();
+    sequence::save(&seq)?;
+    Ok(true)
+}
+
+/// The schedule that fired most recently at or before `now`, with its fire time.
+/// Ties go to the schedule listed first.
+pub(crate) fn latest_fired(
+    schedules: &[ScheduleEntry],
+    now: DateTime<Local>,
+) -> Option<(u32, DateTime<Local>)> {
+    let mut best: Option<(u32, DateTime<Local>)> = None;
+    for s in schedules {
+        let Ok(expr) = CronExpr::parse(&s.cron_expr) else {
+            continue;
+        };
+        let Some(fired) = expr
+            .last_fire(now.naive_local())
+            .and_then(|t| Local.from_local_datetime(&t).earliest())
+        else {
+            continue;
+        };
+        if best.is_none_or(|(_, t)| fired > t) {
+            best = Some((s.account_num, fired));
+        }
+    }
+    best
+}
+
+/// Which account `schedule run` should switch to, if any. A schedule is only
+/// applied once: if it fired before the previous run, a manual switch made
+/// since then is left alone.
+pub(crate) fn due_switch(seq: &sequence::SequenceFile, now: DateTime<Local>) -> Option<u32> {
+    let (num, fired) = latest_fired(&seq.schedules, now)?;
+    let last_run = seq
+        .schedule_last_run
+        .as_deref()
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok());
+    if last_run.is_some_and(|t| fired <= t) {
+        return None;
+    }
+    if seq.active_account_number == Some(num) || !seq.accounts.contains_key(&num.to_string()) {
+        return None;
+    }
+    Some(num)
+}
+
+fn record_run() -> Result<()> {
+    let _lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load()?;
+    seq.schedule_last_run = Some(now_utc());
+    sequence::save(&seq)
+}
+
+// ── Commands ──────────────────────────────────────────────────────────────────
+
+pub fn set(account: &str, cron_expr: &str) -> Result<()> {
+    let num = accounts::resolve_account(account)?;
+    core_set(num, cron_expr)?;
+    println!(
+        "\n  {} Account {} will be switched to at '{}'.\n",
+        "✓".green().bold(),
+        num,
+        cron_expr
+    );
+    Ok(())
+}
+
+pub fn remove(account: &str) -> Result<()> {
+    let num = accounts::resolve_account(account)?;
+    if core_remove(num)? {
+        println!(
+            "\n  {} Removed the schedule for Account {}.\n",
+            "✓".green().bold(),
+            num
+        );
+    } else {
+        println!("\n  {} Account {} has no schedule.\n", "·".yellow(), num);
+    }
+    Ok(())
+}
+
+pub fn list(format: OutputFormat) -> Result<()> {
+    let seq = sequence::load()?;
+    let now = Local::now();
+
+    if format == OutputFormat::Json {
+        let rows: Vec<_> = seq
+            .schedules
+            .iter()
+            .map(|s| {
+                let last = CronExpr::parse(&s.cron_expr)
+                    .ok()
+                    .and_then(|e| e.last_fire(now.naive_local()))
+                    .map(|t| t.format("%Y-%m-%dT%H:%M").to_string());
+                serde_json::json!({
+                    "account_num": s.account_num,
+                    "email": seq.accounts.get(&s.account_num.to_string()).map(|a| a.email.clone()),
+                    "cron_expr": s.cron_expr,
+                    "last_fired": last,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+        return Ok(());
+    }
+
+    if seq.schedules.is_empty() {
+        println!(
+            "\n  {} No schedules. Add one with `ccswitch schedule set <account> \"<cron>\"`.\n",
+            "·".yellow()
+        );
+        return Ok(());
+    }
+
+    let due = latest_fired(&seq.schedules, now).map(|(n, _)| n);
+    println!();
+    for s in &seq.schedules {
+        let email = seq
+            .accounts
+            .get(&s.account_num.to_string())
+            .map(|a| a.email.as_str())
+            .unwrap_or("(missing)");
+        let marker = if due == Some(s.account_num) {
+            "  ← current".green().to_string()
+        } else {
+            String::new()
+        };
+        println!(
+            "  {:>3}  {:<32} {}{}",
+            s.account_num,
+            email,
+            s.cron_expr.cyan(),
+            marker
+        );
+    }
+    println!();
+    Ok(())
+}
+
+/// Apply the schedule that fired most recently. Meant to be run every minute
+/// from cron or launchd (see `ccswitch schedule install`). A failed switch is
+/// not recorded as a run, so the next run tries again.
+pub fn run() -> Result<()> {
+    let seq = sequence::load()?;
+    if let Some(num) = due_switch(&seq, Local::now()) {
+        accounts::switch_to(&num.to_string(), &SwitchOptions::default())?;
+    }
+    record_run()
+}
+
+// ── Installing the runner ─────────────────────────────────────────────────────
+
+/// Arguments to `ccswitch` for the periodic runner, pinned to the current profile.
+fn runner_args() -> Vec<String> {
+    let mut args = vec!["schedule".to_string(), "run".to_string()];
+    let profile = profiles::current();
+    if !profiles::is_default(&profile) {
+        args.extend(["--profile".to_string(), profile]);
+    }
+    args
+}
+
+fn runner_label() -> String {
+    let profile = profiles::current();
+    if profiles::is_default(&profile) {
+        "com.ccswitch.schedule".to_string()
+    } else {
+        format!("com.ccswitch.schedule.{profile}")
+    }
+}
+
+fn shell_quote(s: &str) -> String {
+    if s.chars()
+        .all(|c| c.is_ascii_alphanumeric() || "/-_.".contains(c))
+    {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', r"'\''"))
+    }
+}
+
+/// `existing` crontab with the ccswitch runner line added, replacing any
+/// previous line carrying the same marker.
+pub(crate) fn crontab_with_runner(existing: &str, command: &str, marker: &str) -> String {
+    let mut lines: Vec<&str> = existing.lines().filter(|l| !l.ends_with(marker)).collect();
+    let entry = format!("* * * * * {command} >/dev/null 2>&1 {marker}");
+    lines.push(&entry);
+    let mut out = lines.join("\n");
+    out.push('\n');
+    out
+}
+
+pub(crate) fn launchd_plist(label: &str, program: &str, args: &[String]) -> String {
+    let escape = |s: &str| {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    };
+    let mut program_args = format!("        <string>{}</string>\n", escape(program));
+    for a in args {
+        program_args.push_str(&format!("        <string>{}</string>\n", escape(a)));
+    }
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{label}</string>
+    <key>ProgramArguments</key>
+    <array>
+{program_args}    </array>
+    <key>StartInterval</key>
+    <integer>60</integer>
+    <key>RunAtLoad</key>
+    <true/>
+</dict>
+</plist>
+"#
+    )
+}
+
+fn install_launchd(exe: &str) -> Result<PathBuf> {
+    let label = runner_label();
+    let path = crate::paths::home_dir()
+        .context("Cannot find home directory")?
+        .join("Library/LaunchAgents")
+        .join(format!("{label}.plist"));
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, launchd_plist(&label, exe, &runner_args()))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let path_str = path.to_string_lossy();
+    let _ = Command::new("launchctl")
+        .args(["unload", &path_str])
+        .output();
+    let status = Command::new("launchctl")
+        .args(["load", "-w", &path_str])
+        .status()
+        .context("Failed to run `launchctl`")?;
+    if !status.success() {
+        bail!("`launchctl load {}` failed", path.display());
+    }
+    Ok(path)
+}
+
+fn install_crontab(exe: &str) -> Result<()> {
+    let current = Command::new("crontab")
+        .arg("-l")
+        .output()
+        .context("Failed to run `crontab`. Is cron installed?")?;
+    // `crontab -l` exits non-zero when the user has no crontab yet.
+    let existing = if current.status.success() {
+        String::from_utf8_lossy(&current.stdout).into_owned()
+    } else {
+        String::new()
+    };
+
+    let command = std::iter::once(exe.to_string())
+        .chain(runner_args())
+        .map(|a| shell_quote(&a))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let updated = crontab_with_runner(&existing, &command, &format!("# {}", runner_label()));
+
+    let mut child = Command::new("crontab")
+        .arg("-")
+        .stdin(Stdio::piped())
+        .spawn()
+        .context("Failed to run `crontab`")?;
+    child
+        .stdin
+        .take()
+        .context("Failed to open stdin")?
+        .write_all(updated.as_bytes())?;
+    if !child.wait()?.success() {
+        bail!("`crontab -` rejected the updated crontab");
+    }
+    Ok(())
+}
+
+/// Register `ccswitch schedule run` to run every minute (launchd on macOS, cron elsewhere).
+pub fn install() -> Result<()> {
+    let exe = std::env::current_exe().context("Cannot locate the ccswitch binary")?;
+    let exe = exe.to_string_lossy();
+    match platform::detect() {
+        Platform::MacOS => {
+            let path = install_launchd(&exe)?;
+            println!(
+                "\n  {} Installed launchd agent {}\n",
+                "✓".green().bold(),
+                path.display()
+            );
+        }
+        Platform::Windows => anyhow::bail!(
+            "`schedule install` is not supported on Windows. \
+             Use Task Scheduler to run `{exe} schedule run` every minute."
+        ),
+        Platform::Linux | Platform::Wsl => {
+            install_crontab(&exe)?;
+            println!(
+                "\n  {} Added a crontab entry running `ccswitch schedule run` every minute.\n",
+                "✓".green().bold()
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TestEnv;
+    use crate::sequence::AccountEntry;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_parse_fields_ranges_steps_and_names() {
+        let e = CronExpr::parse("*/15 9-17 * * mon-fri").unwrap();
+        // 2026-10-14 is a Wednesday, 2026-10-17 a Saturday.
+        assert!(e.matches(at("2026-10-14 09:45")));
+        assert!(!e.matches(at("2026-10-14 09:50")));
+        assert!(!e.matches(at("2026-10-14 18:00")));
+        assert!(!e.matches(at("2026-10-17 10:00")));
+
+        let sunday = CronExpr::parse("0 0 * * 7").unwrap();
+        assert!(sunday.matches(at("2026-10-18 00:00")));
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_expressions() {
+        assert!(CronExpr::parse("* * * *").is_err());
+        assert!(CronExpr::parse("60 * * * *").is_err());
+        assert!(CronExpr::parse("* * * foo *").is_err());
+        assert!(CronExpr::parse("5-1 * * * *").is_err());
+        assert!(CronExpr::parse("*/0 * * * *").is_err());
+    }
+
+    #[test]
+    fn test_dom_and_dow_match_either_when_both_restricted() {
+        let e = CronExpr::parse("0 12 1 * fri").unwrap();
+        assert!(e.matches(at("2026-10-01 12:00"))); // Thursday the 1st
+        assert!(e.matches(at("2026-10-16 12:00"))); // a Friday
+        assert!(!e.matches(at("2026-10-15 12:00")));
+    }
+
+    #[test]
+    fn test_last_fire_looks_back_across_days() {
+        let e = CronExpr::parse("0 9 * * mon-fri").unwrap();
+        assert_eq!(
+            e.last_fire(at("2026-10-14 10:30")),
+            Some(at("2026-10-14 09:00"))
+        );
+        assert_eq!(
+            e.last_fire(at("2026-10-14 08:59")),
+            Some(at("2026-10-13 09:00"))
+        );
+        // Saturday morning falls back to Friday.
+        assert_eq!(
+            e.last_fire(at("2026-10-17 12:00")),
+            Some(at("2026-10-16 09:00"))
+        );
+    }
+
+    fn local(s: &str) -> DateTime<Local> {
+        Local.from_local_datetime(&at(s)).earliest().unwrap()
+    }
+
+    #[test]
+    fn test_due_switch_picks_latest_and_applies_once() {
+        let mut seq = sequence::SequenceFile::default();
+        for n in [1u32, 2] {
+            seq.accounts.insert(n.to_string(), Default::default());
+            seq.sequence.push(n);
+        }
+        seq.active_account_number = Some(2);
+        seq.schedules = vec![
+            ScheduleEntry {
+                account_num: 1,
+                cron_expr: "0 9 * * *".into(),
+            },
+            ScheduleEntry {
+                account_num: 2,
+                cron_expr: "0 17 * * *".into(),
+            },
+        ];
+
+        assert_eq!(due_switch(&seq, local("2026-10-14 10:00")), Some(1));
+        assert_eq!(due_switch(&seq, local("2026-10-14 18:00")), None);
+
+        // Once a run has happened after 09:00, a manual switch back is respected.
+        seq.schedule_last_run = Some(local("2026-10-14 09:01").to_utc().to_rfc3339());
+        assert_eq!(due_switch(&seq, local("2026-10-14 10:00")), None);
+    }
+
+    #[test]
+    fn test_run_does_not_record_a_failed_switch() {
+        let _env = TestEnv::new();
+        let mut seq = sequence::SequenceFile::default();
+        for n in [1u32, 2] {
+            let entry = AccountEntry { email: format!("{n}@test.com"), ..Default::default() };
+            seq.accounts.insert(n.to_string(), entry);
+            seq.sequence.push(n);
+        }
+        seq.active_account_number = Some(2);
+        seq.schedules = vec![ScheduleEntry { account_num: 1, cron_expr: "* * * * *".into() }];
+        sequence::save(&seq).unwrap();
+
+        // Account 1 has no credentials backup, so the switch fails.
+        assert!(run().is_err());
+        assert_eq!(sequence::load().unwrap().schedule_last_run, None);
+
+        seq.schedules.clear();
+        sequence::save(&seq).unwrap();
+        run().unwrap();
+        assert!(sequence::load().unwrap().schedule_last_run.is_some());
+    }
+
+    #[test]
+    fn test_set_replaces_and_remove_account_drops_schedule() {
+        let _env = TestEnv::new();
+        let mut seq = sequence::SequenceFile::default();
+        seq.accounts.insert("1".into(), Default::default());
+        seq.sequence.push(1);
+        sequence::save(&seq).unwrap();
+
+        core_set(1, "0 9 * * *").unwrap();
+        core_set(1, "0  10 * * *").unwrap();
+        let seq = sequence::load().unwrap();
+        assert_eq!(seq.schedules.len(), 1);
+        assert_eq!(seq.schedules[0].cron_expr, "0 10 * * *");
+        assert!(core_set(2, "0 9 * * *").is_err());
+
+        accounts::core_remove(1, "").unwrap();
+        assert!(sequence::load().unwrap().schedules.is_empty());
+    }
+
+    #[test]
+    fn test_crontab_with_runner_replaces_previous_line() {
+        let existing = "0 1 * * * backup\n* * * * * old >/dev/null 2>&1 # com.ccswitch.schedule\n";
+        let out = crontab_with_runner(
+            existing,
+            "/bin/ccswitch schedule run",
+            "# com.ccswitch.schedule",
+        );
+        assert_eq!(
+            out,
+            "0 1 * * * backup\n* * * * * /bin/ccswitch schedule run >/dev/null 2>&1 # com.ccswitch.schedule\n"
+        );
+    }
+
+    #[test]
+    fn test_launchd_plist_lists_arguments() {
+        let plist = launchd_plist("com.ccswitch.schedule", "/bin/ccswitch", &runner_args());
+        assert!(plist.contains("<string>/bin/ccswitch</string>\n        <string>schedule</string>\n        <string>run</string>"));
+        assert!(plist.contains("<integer>60</integer>"));
+    }
+}
@@ -1,36 +1,128 @@
 use anyhow::{Context, Result};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fs, io::Write, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
 
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 
+/// How an account authenticates with Claude.
 #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum AuthKind {
+    /// A claude.ai login whose OAuth credentials are swapped on switch.
     #[default]
     Oauth,
+    /// A long-lived token exported as `CLAUDE_CODE_OAUTH_TOKEN`.
     Token,
 }
 
+/// One managed account in `sequence.json`.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AccountEntry {
+    /// Email of the account, or the label given to a token account.
     pub email: String,
+    /// `oauthAccount.accountUuid` from the Claude config; empty for token accounts.
     pub uuid: String,
+    /// RFC 3339 timestamp of when the account was added.
     pub added: String,
     #[serde(default)]
     pub auth_kind: AuthKind,
+    /// Whether a config backup was stored for this account.  Token accounts
+    /// added without an `oauthAccount` skip it; older entries always had one.
+    #[serde(default = "default_true")]
+    pub has_config_backup: bool,
+    /// Switch priority; `switch --highest-priority` prefers larger values.
+    #[serde(default)]
+    pub priority: u32,
+    /// Free-form annotation set with `ccswitch note`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// RFC 3339 timestamp of the last switch to this account.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used: Option<String>,
+    /// Number of times this account has been switched to.
+    #[serde(default)]
+    pub switch_count: u32,
+    /// Lower-case labels set with `ccswitch tag`, e.g. `work` or `prod`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// RFC 3339 expiry of the OAuth session, as of the last add or switch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    /// `user@hostname` that added the account; absent on older entries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<String>,
+    /// First 8 hex digits of the adding machine's ID (see `platform::machine_id`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub machine_id: Option<String>,
+    /// Exported from `~/.ccswitchrc` while this account is active (`ccswitch env-set`).
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env_vars: HashMap<String, String>,
+}
+
+impl Default for AccountEntry {
+    fn default() -> Self {
+        AccountEntry {
+            email: String::new(),
+            uuid: String::new(),
+            added: String::new(),
+            auth_kind: AuthKind::default(),
+            has_config_backup: true,
+            priority: 0,
+            notes: None,
+            last_used: None,
+            switch_count: 0,
+            tags: Vec::new(),
+            expires_at: None,
+            created_by: None,
+            machine_id: None,
+            env_vars: HashMap::new(),
+        }
+    }
 }
 
+pub(crate) fn default_true() -> bool {
+    true
+}
+
+/// The contents of `sequence.json`: every managed account and the rotation
+/// order, keyed by account number.
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct SequenceFile {
+    /// The account Claude Code is using, as of the last switch.
     #[serde(rename = "activeAccountNumber")]
     pub active_account_number: Option<u32>,
+    /// The account that was active before the last switch; `ccswitch undo` returns to it.
+    #[serde(
+        rename = "previousAccountNumber",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub previous_account_number: Option<u32>,
+    /// Preferred default set with `ccswitch pin`; rotation starts here when no
+    /// account is known to be active.
+    #[serde(
+        rename = "pinnedAccount",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub pinned_account: Option<u32>,
+    /// RFC 3339 timestamp of the last write.
     #[serde(rename = "lastUpdated")]
     pub last_updated: String,
+    /// Account numbers in rotation order.
     pub sequence: Vec<u32>,
+    /// Accounts keyed by their number as a string.
     pub accounts: HashMap<String, AccountEntry>,
+    /// Names set with `ccswitch alias`, mapped to account numbers.
     #[serde(default)]
     pub aliases: HashMap<String, u32>,
     /// Fingerprint of the `claudeAiOauth` key set recorded at `ccswitch add`
@@ -41,6 +133,32 @@ pub struct SequenceFile {
         skip_serializing_if = "Option::is_none"
     )]
     pub format_fingerprint: Option<String>,
+    /// Time-based switches set with `ccswitch schedule set`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schedules: Vec<ScheduleEntry>,
+    /// When `ccswitch schedule run` last ran; schedules that fired earlier are
+    /// not applied again.
+    #[serde(
+        rename = "scheduleLastRun",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub schedule_last_run: Option<String>,
+    /// Set by `ccswitch link`: the live credentials file is a symlink to the
+    /// active account's backup, and switches re-point it instead of copying.
+    #[serde(
+        rename = "liveIsSymlink",
+        default,
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub live_is_symlink: bool,
+}
+
+/// Switch to `account_num` whenever the cron expression fires.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScheduleEntry {
+    pub account_num: u32,
+    pub cron_expr: String,
 }
 
 impl SequenceFile {
@@ -64,7 +182,40 @@ impl SequenceFile {
         self.accounts.values().any(|a| a.email == email)
     }
 
-    /// Resolve an account identifier (number, email, or alias) to an account number.
+    /// The highest-priority account in rotation order, skipping `exclude`.
+    /// Ties go to the account that appears first in the sequence.
+    pub fn highest_priority_excluding(&self, exclude: Option<u32>) -> Option<u32> {
+        self.sequence
+            .iter()
+            .filter(|&&num| Some(num) != exclude)
+            .filter_map(|&num| self.accounts.get(&num.to_string()).map(|e| (num, e.priority)))
+            .fold(None, |best: Option<(u32, u32)>, (num, p)| match best {
+                Some((_, bp)) if bp >= p => best,
+                _ => Some((num, p)),
+            })
+            .map(|(num, _)| num)
+    }
+
+    /// The next account after `current` in rotation order that carries `tag`,
+    /// wrapping around. `current` itself is never returned.
+    pub fn next_with_tag(&self, tag: &str, current: Option<u32>) -> Option<u32> {
+        let start = current
+            .and_then(|c| self.sequence.iter().position(|&n| n == c))
+            .map_or(0, |i| i + 1);
+        (0..self.sequence.len())
+            .map(|offset| self.sequence[(start + offset) % self.sequence.len()])
+            .filter(|&num| Some(num) != current)
+            .find(|num| {
+                self.accounts
+                    .get(&num.to_string())
+                    .is_some_and(|e| e.tags.iter().any(|t| t == tag))
+            })
+    }
+
+    /// Resolve an account identifier to an account number: an exact number,
+    /// email or alias, else a unique case-insensitive email prefix, else a
+    /// unique email substring. Ambiguous partial matches resolve to `None`;
+    /// `partial_matches` lists the candidates.
     pub fn resolve(&self, identifier: &str) -> Option<u32> {
         if let Ok(num) = identifier.parse::<u32>() {
             if self.accounts.contains_key(&num.to_string()) {
@@ -72,11 +223,41 @@ impl SequenceFile {
             }
             return None;
         }
-        self.find_by_email(identifier).or_else(|| {
-            self.aliases.get(identifier).copied().filter(|num| {
-                self.accounts.contains_key(&num.to_string())
+        self.find_by_email(identifier)
+            .or_else(|| {
+                self.aliases.get(identifier).copied().filter(|num| {
+                    self.accounts.contains_key(&num.to_string())
+                })
+            })
+            .or_else(|| match self.partial_matches(identifier)[..] {
+                [num] => Some(num),
+                _ => None,
             })
-        })
+    }
+
+    /// Accounts whose email starts with `identifier` (ignoring case), or if
+    /// none does, whose email contains it. Sorted by account number.
+    pub fn partial_matches(&self, identifier: &str) -> Vec<u32> {
+        let needle = identifier.to_lowercase();
+        if needle.is_empty() {
+            return Vec::new();
+        }
+        let matching = |pred: &dyn Fn(&str) -> bool| {
+            let mut nums: Vec<u32> = self
+                .accounts
+                .iter()
+                .filter(|(_, e)| pred(&e.email.to_lowercase()))
+                .filter_map(|(k, _)| k.parse().ok())
+                .collect();
+            nums.sort();
+            nums
+        };
+        let prefix = matching(&|email| email.starts_with(&needle));
+        if prefix.is_empty() {
+            matching(&|email| email.contains(&needle))
+        } else {
+            prefix
+        }
     }
 }
 
@@ -84,28 +265,54 @@ pub fn now_utc() -> String {
     Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string()
 }
 
+/// Directory holding the current profile's accounts: `$CCSWITCH_BACKUP_DIR`,
+/// else `<data dir>/<profile>`.
 pub fn backup_dir() -> PathBuf {
-    #[cfg(test)]
-    if let Ok(dir) = std::env::var("CCSWITCH_TEST_DIR") {
-        return PathBuf::from(dir);
-    }
-    dirs::home_dir()
-        .expect("Cannot find home directory")
-        .join(".claude-switch-backup")
+    crate::paths::env_path(crate::paths::BACKUP_DIR_ENV)
+        .unwrap_or_else(|| crate::profiles::root_dir().join(crate::profiles::current()))
 }
 
+/// `$CCSWITCH_SEQUENCE_FILE`, else `sequence.json` in `backup_dir()`.
 pub fn sequence_path() -> PathBuf {
-    backup_dir().join("sequence.json")
+    crate::paths::env_path(crate::paths::SEQUENCE_FILE_ENV)
+        .unwrap_or_else(|| backup_dir().join("sequence.json"))
+}
+
+/// Plain-text file holding the active account's email, for shell scripts:
+/// `<data dir>/.active`, shared by every profile like `active-token`.
+pub fn active_account_path() -> PathBuf {
+    crate::paths::env_path(crate::paths::BACKUP_DIR_ENV)
+        .unwrap_or_else(crate::profiles::root_dir)
+        .join(".active")
+}
+
+/// Record `email` as the active account in `active_account_path()` (mode 0644;
+/// it holds no secret). Written when the live credentials change hands, not on
+/// every save, since other profiles' sequences do not describe the live login.
+pub fn write_active_account_file(email: &str) -> Result<()> {
+    let path = active_account_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    write_file_atomic(&path, &format!("{email}\n"), 0o644)
+        .with_context(|| format!("Cannot write {}", path.display()))
 }
 
 pub fn setup_dirs() -> Result<()> {
-    let base = backup_dir();
+    setup_dirs_at(&backup_dir())?;
+    if let Some(parent) = sequence_path().parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(())
+}
+
+pub fn setup_dirs_at(base: &Path) -> Result<()> {
     fs::create_dir_all(base.join("configs"))?;
     fs::create_dir_all(base.join("credentials"))?;
 
     #[cfg(unix)]
     {
-        fs::set_permissions(&base, fs::Permissions::from_mode(0o700))?;
+        fs::set_permissions(base, fs::Permissions::from_mode(0o700))?;
         fs::set_permissions(base.join("configs"), fs::Permissions::from_mode(0o700))?;
         fs::set_permissions(base.join("credentials"), fs::Permissions::from_mode(0o700))?;
     }
@@ -113,29 +320,377 @@ pub fn setup_dirs() -> Result<()> {
     Ok(())
 }
 
+// ── Locking ───────────────────────────────────────────────────────────────────
+
+/// Default time to wait for another ccswitch process to release the lock.
+pub const DEFAULT_LOCK_TIMEOUT_MS: u64 = 2_000;
+
+static LOCK_TIMEOUT_MS: AtomicU64 = AtomicU64::new(DEFAULT_LOCK_TIMEOUT_MS);
+
+/// Set how long `acquire_lock` waits before giving up.
+pub fn set_lock_timeout_ms(ms: u64) {
+    LOCK_TIMEOUT_MS.store(ms, Ordering::Relaxed);
+}
+
+pub fn lock_path() -> PathBuf {
+    lock_path_in(&backup_dir())
+}
+
+/// The lock file of the profile directory `dir`.
+pub fn lock_path_in(dir: &Path) -> PathBuf {
+    dir.join(".ccswitch.lock")
+}
+
+/// Advisory lock guarding read-modify-write cycles on `sequence.json`.
+/// Released on drop; the OS also releases it if the process is killed.
+pub struct SequenceLock {
+    file: fs::File,
+}
+
+impl Drop for SequenceLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+/// Take the exclusive lock, polling until the configured timeout elapses.
+pub fn acquire_lock() -> Result<SequenceLock> {
+    acquire_lock_at(&lock_path())
+}
+
+/// Take the exclusive lock on the lock file at `path`.
+pub fn acquire_lock_at(path: &Path) -> Result<SequenceLock> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+        .with_context(|| format!("Cannot open lock file {}", path.display()))?;
+
+    let deadline = Instant::now() + Duration::from_millis(LOCK_TIMEOUT_MS.load(Ordering::Relaxed));
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Ok(SequenceLock { file }),
+            Err(fs::TryLockError::WouldBlock) if Instant::now() < deadline => {
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            Err(fs::TryLockError::WouldBlock) => {
+                anyhow::bail!("Another ccswitch operation is in progress")
+            }
+            Err(fs::TryLockError::Error(e)) => {
+                return Err(e).with_context(|| format!("Cannot lock {}", path.display()))
+            }
+        }
+    }
+}
+
+/// Read the account index from the current `store`.
 pub fn load() -> Result<SequenceFile> {
-    let path = sequence_path();
+    crate::store::open()?.load()
+}
+
+/// Write the account index to the current `store`.
+pub fn save(seq: &SequenceFile) -> Result<()> {
+    crate::store::open()?.save(seq)
+}
+
+/// Best-effort parse of a damaged `sequence.json`: every account object that
+/// is still complete, plus `activeAccountNumber`, `sequence` and `aliases` if
+/// they survived. Everything else is left at its default.
+pub fn salvage(content: &str) -> SequenceFile {
+    if let Ok(seq) = serde_json::from_str::<SequenceFile>(content) {
+        return seq;
+    }
+    /// First complete JSON value of type `T` at the start of `s`.
+    fn leading<T: serde::de::DeserializeOwned>(s: &str) -> Option<T> {
+        serde_json::Deserializer::from_str(s).into_iter::<T>().next()?.ok()
+    }
+
+    let mut seq = SequenceFile::default();
+    let mut rest = content;
+    // Walk `"key": value` pairs. Strings are assumed to contain no escaped
+    // quotes, which holds for everything ccswitch writes.
+    while let Some(open) = rest.find('"') {
+        rest = &rest[open + 1..];
+        let Some(close) = rest.find('"') else { break };
+        let key = &rest[..close];
+        rest = &rest[close + 1..];
+        let Some(value) = rest.trim_start().strip_prefix(':').map(str::trim_start) else {
+            continue;
+        };
+        match key {
+            "activeAccountNumber" => seq.active_account_number = leading(value),
+            "previousAccountNumber" => seq.previous_account_number = leading(value),
+            "pinnedAccount" => seq.pinned_account = leading(value),
+            "sequence" => seq.sequence = leading(value).unwrap_or_default(),
+            "aliases" => seq.aliases = leading(value).unwrap_or_default(),
+            "schedules" => seq.schedules = leading(value).unwrap_or_default(),
+            "scheduleLastRun" => seq.schedule_last_run = leading(value),
+            _ => {
+                let Ok(num) = key.parse::<u32>() else { continue };
+                if let Some(entry) = leading::<AccountEntry>(value) {
+                    seq.accounts.insert(num.to_string(), entry);
+                }
+            }
+        }
+    }
+    seq
+}
+
+// ── Switch log ────────────────────────────────────────────────────────────────
+
+/// One line of `switch.log`, written after every switch attempt.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SwitchLogEntry {
+    pub timestamp: String,
+    pub from: u32,
+    pub to: u32,
+    pub ok: bool,
+}
+
+pub fn switch_log_path() -> PathBuf {
+    crate::paths::state_dir()
+        .join(crate::profiles::current())
+        .join("switch.log")
+}
+
+/// Append a JSON-lines record to `switch.log`, creating it with mode 0600.
+pub fn append_switch_log(from: u32, to: u32, ok: bool) -> Result<()> {
+    let entry = SwitchLogEntry {
+        timestamp: now_utc(),
+        from,
+        to,
+        ok,
+    };
+    append_json_line(&switch_log_path(), &entry)
+}
+
+/// Append `entry` as one JSON line to `path`, creating the file with mode 0600.
+fn append_json_line(path: &Path, entry: &impl Serialize) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut opts = fs::OpenOptions::new();
+    opts.create(true).append(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
+    }
+    let mut f = opts
+        .open(path)
+        .with_context(|| format!("Cannot open {}", path.display()))?;
+    writeln!(f, "{}", serde_json::to_string(entry)?)
+        .with_context(|| format!("Cannot write to {}", path.display()))
+}
+
+/// The last `n` entries of `switch.log`, oldest first. Unparseable lines are skipped.
+pub fn read_switch_log(n: usize) -> Result<Vec<SwitchLogEntry>> {
+    let path = switch_log_path();
     if !path.exists() {
-        return Ok(SequenceFile::default());
+        return Ok(Vec::new());
     }
     let content = fs::read_to_string(&path)
         .with_context(|| format!("Failed to read {}", path.display()))?;
-    serde_json::from_str(&content)
-        .with_context(|| format!("Invalid JSON in {}", path.display()))
+    let entries: Vec<SwitchLogEntry> = content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    Ok(entries[entries.len().saturating_sub(n)..].to_vec())
 }
 
-pub fn save(seq: &SequenceFile) -> Result<()> {
-    let path = sequence_path();
-    let content = serde_json::to_string_pretty(seq)?;
-    write_atomic(&path, &content)
+// ── Audit log ─────────────────────────────────────────────────────────────────
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditAction {
+    Add,
+    Switch,
+    Remove,
+    /// A credential backup was read without switching (`run`, `export`).
+    Read,
+}
+
+impl std::fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuditAction::Add => write!(f, "add"),
+            AuditAction::Switch => write!(f, "switch"),
+            AuditAction::Remove => write!(f, "remove"),
+            AuditAction::Read => write!(f, "read"),
+        }
+    }
+}
+
+/// One line of `audit.log`: who touched which account's credentials, and how.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuditEvent {
+    pub timestamp: String,
+    pub action: AuditAction,
+    pub account_num: u32,
+    pub account_email: String,
+    /// Profile the account belongs to. Empty in lines written before it was recorded.
+    #[serde(default)]
+    pub profile: String,
+    /// `false` for an attempt that failed (only switches record those).
+    #[serde(default = "default_true")]
+    pub ok: bool,
+    pub user: String,
+    pub pid: u32,
+    /// SHA-256 of the previous line, chaining the log; empty on the first
+    /// line and in lines written before the chain existed.
+    #[serde(default)]
+    pub prev: String,
+}
+
+impl AuditEvent {
+    /// A successful event stamped with the current time, profile, user and process.
+    pub fn new(action: AuditAction, account_num: u32, account_email: &str) -> Self {
+        AuditEvent {
+            timestamp: now_utc(),
+            action,
+            account_num,
+            account_email: account_email.to_string(),
+            profile: crate::profiles::current(),
+            ok: true,
+            user: current_user(),
+            pid: std::process::id(),
+            prev: String::new(),
+        }
+    }
+}
+
+/// The login name: `$USER` / `$USERNAME`, else `whoami`, looked up once per process.
+pub(crate) fn current_user() -> String {
+    static USER: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+    USER.get_or_init(|| {
+        std::env::var("USER")
+            .ok()
+            .or_else(|| std::env::var("USERNAME").ok())
+            .filter(|s| !s.is_empty())
+            .or_else(|| {
+                std::process::Command::new("whoami")
+                    .output()
+                    .ok()
+                    .filter(|o| o.status.success())
+                    .and_then(|o| String::from_utf8(o.stdout).ok())
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+            })
+            .unwrap_or_else(|| "unknown".to_string())
+    })
+    .clone()
+}
+
+/// Shared by all profiles, unlike `switch.log`.
+pub fn audit_log_path() -> PathBuf {
+    crate::paths::state_dir().join("audit.log")
+}
+
+/// Append a JSON-lines record to `audit.log`, creating it with mode 0600.
+/// The event's `prev` is set to the hash of the current last line; the file
+/// is locked while that line is read and the new one written, so concurrent
+/// ccswitch processes cannot fork the chain.
+pub fn append_audit_log(event: AuditEvent) -> Result<()> {
+    crate::store::open()?.append_log(event)
+}
+
+/// `append_audit_log` for the log at `path`.
+pub(crate) fn append_audit_log_to(path: &Path, mut event: AuditEvent) -> Result<()> {
+    use std::io::{Read, Seek, SeekFrom};
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut opts = fs::OpenOptions::new();
+    opts.create(true).read(true).append(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
+    }
+    let mut f = opts
+        .open(path)
+        .with_context(|| format!("Cannot open {}", path.display()))?;
+    f.lock().with_context(|| format!("Cannot lock {}", path.display()))?;
+
+    let mut content = String::new();
+    f.seek(SeekFrom::Start(0))?;
+    f.read_to_string(&mut content)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    event.prev = content.lines().last().map(audit_line_hash).unwrap_or_default();
+    writeln!(f, "{}", serde_json::to_string(&event)?)
+        .with_context(|| format!("Cannot write to {}", path.display()))
+}
+
+fn audit_line_hash(line: &str) -> String {
+    crate::credentials::sha256_fingerprint(line)
+}
+
+/// Check the hash chain in `audit.log`. Returns the 1-based number of the
+/// first line that is unreadable or whose `prev` does not match the line
+/// before it, or `None` when the whole chain holds. Lines from before the
+/// chain existed are accepted only at the start of the file.
+///
+/// This catches lines that were edited, inserted or removed. It does not
+/// catch lines cut off the end of the file, or a whole file rewritten by
+/// someone who recomputes every hash.
+pub fn verify_audit_log() -> Result<Option<usize>> {
+    let path = audit_log_path();
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut chained = false;
+    let mut previous: Option<&str> = None;
+    for (i, line) in content.lines().enumerate() {
+        let Ok(event) = serde_json::from_str::<AuditEvent>(line) else {
+            return Ok(Some(i + 1));
+        };
+        let intact = match previous {
+            _ if event.prev.is_empty() => !chained,
+            Some(prev_line) => event.prev == audit_line_hash(prev_line),
+            None => false,
+        };
+        if !intact {
+            return Ok(Some(i + 1));
+        }
+        chained |= !event.prev.is_empty();
+        previous = Some(line);
+    }
+    Ok(None)
+}
+
+/// The last `n` entries of `audit.log` (all of them for `None`), oldest first.
+/// Unparseable lines are skipped.
+pub fn read_audit_log(n: Option<usize>) -> Result<Vec<AuditEvent>> {
+    let path = audit_log_path();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let entries: Vec<AuditEvent> = content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    let skip = n.map_or(0, |n| entries.len().saturating_sub(n));
+    Ok(entries[skip..].to_vec())
 }
 
 /// Atomically write a JSON file: validate → temp file → rename → chmod 600.
-pub fn write_atomic(path: &PathBuf, content: &str) -> Result<()> {
+pub fn write_atomic(path: &Path, content: &str) -> Result<()> {
     // Validate JSON before touching the real file
     let _: serde_json::Value =
         serde_json::from_str(content).context("Refusing to write invalid JSON")?;
+    write_file_atomic(path, content, 0o600)
+}
 
+/// Temp file → rename → chmod `mode`, so readers never see a partial file.
+fn write_file_atomic(path: &Path, content: &str, mode: u32) -> Result<()> {
     let temp_path = path.with_extension(format!("tmp.{}", std::process::id()));
 
     {
@@ -149,21 +704,148 @@ pub fn write_atomic(path: &PathBuf, content: &str) -> Result<()> {
         .with_context(|| format!("Cannot finalize file at {}", path.display()))?;
 
     #[cfg(unix)]
-    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    #[cfg(not(unix))]
+    let _ = mode;
 
     Ok(())
 }
 
+// ── Garbage collection ────────────────────────────────────────────────────────
+
+/// Default age before `gc` removes a temp file.
+pub const DEFAULT_GC_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// `*.tmp.<pid>` files under `backup_dir()` last modified more than
+/// `older_than` ago: leftovers from atomic writes that were interrupted.
+pub fn stale_temp_files(older_than: Duration) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    let mut pending = vec![backup_dir()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(meta) = entry.metadata() else { continue };
+            if meta.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            let is_temp = is_temp_name(&entry.file_name().to_string_lossy());
+            let age = meta.modified().ok().and_then(|m| m.elapsed().ok());
+            if is_temp && age.is_some_and(|age| age > older_than) {
+                found.push(path);
+            }
+        }
+    }
+    found.sort();
+    found
+}
+
+/// True for names ending in `.tmp.<digits>`, as `write_atomic` names its temp
+/// files. Emails may contain `.tmp.` themselves, so only the suffix counts.
+fn is_temp_name(name: &str) -> bool {
+    name.rsplit_once(".tmp.")
+        .is_some_and(|(_, pid)| !pid.is_empty() && pid.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Empty files in `credentials/` and `configs/`, which a failed write can
+/// leave behind and no reader accepts.
+pub fn empty_backup_files() -> Vec<PathBuf> {
+    let mut found: Vec<PathBuf> = ["credentials", "configs"]
+        .iter()
+        .filter_map(|sub| fs::read_dir(backup_dir().join(sub)).ok())
+        .flat_map(|entries| entries.flatten())
+        .filter(|e| e.metadata().is_ok_and(|m| m.is_file() && m.len() == 0))
+        .map(|e| e.path())
+        .collect();
+    found.sort();
+    found
+}
+
+/// Delete stale temp files and empty backups; returns what was removed.
+pub fn gc(older_than: Duration) -> Result<Vec<PathBuf>> {
+    let _lock = acquire_lock()?;
+    let mut removed = stale_temp_files(older_than);
+    removed.extend(empty_backup_files());
+    for path in &removed {
+        fs::remove_file(path).with_context(|| format!("Cannot remove {}", path.display()))?;
+    }
+    Ok(removed)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_gc_removes_old_temp_files_and_empty_backups() {
+        let _env = crate::test_utils::TestEnv::new();
+        setup_dirs().unwrap();
+        let dir = backup_dir();
+        let temp = dir.join("sequence.tmp.4242");
+        let empty = dir.join("credentials/.claude-credentials-1-a@b.com.json");
+        let kept = dir.join("configs/.claude-config-1-a@b.com.json");
+        let dotted = dir.join("credentials/.claude-credentials-1-me@corp.tmp.io.json");
+        fs::write(&temp, "{").unwrap();
+        fs::write(&empty, "").unwrap();
+        fs::write(&kept, "{}").unwrap();
+        fs::write(&dotted, "{}").unwrap();
+
+        // Fresh temp files may belong to a write in progress.
+        assert!(stale_temp_files(Duration::from_secs(3600)).is_empty());
+        assert_eq!(gc(Duration::from_secs(3600)).unwrap(), std::slice::from_ref(&empty));
+
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(gc(Duration::from_millis(10)).unwrap(), std::slice::from_ref(&temp));
+        assert!(!temp.exists() && !empty.exists() && kept.exists() && dotted.exists());
+    }
+
+    #[test]
+    fn test_salvage_truncated_file() {
+        let full = r#"{
+  "activeAccountNumber": 2,
+  "pinnedAccount": 1,
+  "lastUpdated": "2026-01-01T00:00:00Z",
+  "sequence": [1, 2, 3],
+  "schedules": [{"account_num": 2, "cron_expr": "0 9 * * 1-5"}],
+  "scheduleLastRun": "2026-01-01T09:00:00Z",
+  "accounts": {
+    "1": {"email": "a@test.com", "uuid": "u1", "added": "2026-01-01T00:00:00Z"},
+    "2": {"email": "b@test.com", "uuid": "u2", "added": "2026-01-01T00:00:00Z", "auth_kind": "token"},
+    "3": {"email": "c@test.com", "uu"#;
+        let seq = salvage(full);
+        assert_eq!(seq.active_account_number, Some(2));
+        assert_eq!(seq.sequence, [1, 2, 3]);
+        assert_eq!(seq.pinned_account, Some(1));
+        assert_eq!(seq.schedules.len(), 1);
+        assert_eq!(seq.schedules[0].cron_expr, "0 9 * * 1-5");
+        assert_eq!(seq.schedule_last_run.as_deref(), Some("2026-01-01T09:00:00Z"));
+        assert_eq!(seq.accounts.len(), 2);
+        assert_eq!(seq.accounts["1"].email, "a@test.com");
+        assert_eq!(seq.accounts["2"].auth_kind, AuthKind::Token);
+    }
+
+    #[test]
+    fn test_acquire_lock_times_out_while_held() {
+        let _env = crate::test_utils::TestEnv::new();
+        set_lock_timeout_ms(50);
+        let guard = acquire_lock().unwrap();
+
+        let err = acquire_lock().err().expect("second lock should fail");
+        assert!(err.to_string().contains("in progress"), "unexpected: {err}");
+
+        drop(guard);
+        assert!(acquire_lock().is_ok());
+        set_lock_timeout_ms(DEFAULT_LOCK_TIMEOUT_MS);
+    }
+
     fn make_entry(email: &str) -> AccountEntry {
         AccountEntry {
             email: email.to_string(),
             uuid: "test-uuid".to_string(),
             added: now_utc(),
             auth_kind: AuthKind::Oauth,
+            ..Default::default()
         }
     }
 
@@ -252,6 +934,78 @@ mod tests {
         assert_eq!(seq.resolve("dup@test.com"), Some(1));
     }
 
+    #[test]
+    fn test_resolve_partial_email() {
+        let mut seq = SequenceFile::default();
+        seq.accounts.insert("1".into(), make_entry("first.last+work@longcompany.com"));
+        seq.accounts.insert("2".into(), make_entry("first.last@gmail.com"));
+        seq.accounts.insert("3".into(), make_entry("ops@longcompany.com"));
+
+        // Unique prefix, ignoring case.
+        assert_eq!(seq.resolve("First.Last+"), Some(1));
+        assert_eq!(seq.resolve("ops"), Some(3));
+        // Unique substring when no prefix matches.
+        assert_eq!(seq.resolve("gmail"), Some(2));
+        // Ambiguous prefix and substring.
+        assert_eq!(seq.resolve("first"), None);
+        assert_eq!(seq.partial_matches("first"), [1, 2]);
+        assert_eq!(seq.resolve("longcompany"), None);
+        assert_eq!(seq.partial_matches("longcompany"), [1, 3]);
+        // A prefix match shadows substring matches elsewhere.
+        seq.accounts.insert("4".into(), make_entry("x-ops@test.com"));
+        assert_eq!(seq.resolve("ops"), Some(3));
+        assert!(seq.partial_matches("").is_empty());
+        assert_eq!(seq.resolve("nobody"), None);
+    }
+
+    #[test]
+    fn test_resolve_exact_beats_partial() {
+        let mut seq = SequenceFile::default();
+        seq.accounts.insert("1".into(), make_entry("a@test.com"));
+        seq.accounts.insert("2".into(), make_entry("a@test.com.au"));
+        seq.aliases.insert("a@test".into(), 2);
+        assert_eq!(seq.resolve("a@test.com"), Some(1));
+        assert_eq!(seq.resolve("a@test"), Some(2));
+    }
+
+    #[test]
+    fn test_highest_priority_excluding() {
+        let mut seq = SequenceFile::default();
+        for (num, p) in [(1, 5), (2, 9), (3, 9), (4, 1)] {
+            let mut e = make_entry(&format!("u{num}@test.com"));
+            e.priority = p;
+            seq.accounts.insert(num.to_string(), e);
+            seq.sequence.push(num);
+        }
+        // Tie between 2 and 3 goes to the earlier one in the sequence.
+        assert_eq!(seq.highest_priority_excluding(None), Some(2));
+        assert_eq!(seq.highest_priority_excluding(Some(2)), Some(3));
+    }
+
+    #[test]
+    fn test_highest_priority_excluding_only_current() {
+        let mut seq = SequenceFile::default();
+        seq.accounts.insert("1".into(), make_entry("a@test.com"));
+        seq.sequence.push(1);
+        assert_eq!(seq.highest_priority_excluding(Some(1)), None);
+    }
+
+    #[test]
+    fn test_next_with_tag_wraps_and_skips_current() {
+        let mut seq = SequenceFile::default();
+        for (num, tags) in [(1, vec!["work"]), (2, vec![]), (3, vec!["work", "prod"])] {
+            let mut e = make_entry(&format!("u{num}@test.com"));
+            e.tags = tags.into_iter().map(String::from).collect();
+            seq.accounts.insert(num.to_string(), e);
+            seq.sequence.push(num);
+        }
+        assert_eq!(seq.next_with_tag("work", Some(1)), Some(3));
+        assert_eq!(seq.next_with_tag("work", Some(3)), Some(1));
+        assert_eq!(seq.next_with_tag("work", None), Some(1));
+        assert_eq!(seq.next_with_tag("prod", Some(3)), None);
+        assert_eq!(seq.next_with_tag("nope", Some(1)), None);
+    }
+
     #[test]
     fn test_save_load_roundtrip() {
         let _env = crate::test_utils::TestEnv::new();
@@ -276,6 +1030,109 @@ mod tests {
         assert_eq!(seq.active_account_number, None);
     }
 
+    #[test]
+    fn test_has_config_backup_defaults_true_when_absent() {
+        let json = r#"{"email":"a@test.com","uuid":"u","added":"2024-01-01T00:00:00Z","auth_kind":"token"}"#;
+        let entry: AccountEntry = serde_json::from_str(json).unwrap();
+        assert!(entry.has_config_backup);
+    }
+
+    #[test]
+    fn test_notes_default_none_and_omitted_when_unset() {
+        let json = r#"{"email":"a@test.com","uuid":"u","added":"2024-01-01T00:00:00Z"}"#;
+        let entry: AccountEntry = serde_json::from_str(json).unwrap();
+        assert_eq!(entry.notes, None);
+        assert!(!serde_json::to_string(&entry).unwrap().contains("notes"));
+    }
+
+    #[test]
+    fn test_usage_fields_default_when_absent() {
+        let json = r#"{"email":"a@test.com","uuid":"u","added":"2024-01-01T00:00:00Z"}"#;
+        let entry: AccountEntry = serde_json::from_str(json).unwrap();
+        assert_eq!(entry.last_used, None);
+        assert_eq!(entry.switch_count, 0);
+    }
+
+    #[test]
+    fn test_audit_log_append_and_tail() {
+        let _env = crate::test_utils::TestEnv::new();
+        assert!(read_audit_log(None).unwrap().is_empty());
+
+        append_audit_log(AuditEvent::new(AuditAction::Add, 1, "a@test.com")).unwrap();
+        append_audit_log(AuditEvent::new(AuditAction::Switch, 2, "b@test.com")).unwrap();
+
+        let all = read_audit_log(None).unwrap();
+        assert_eq!(all.len(), 2);
+        assert!(!all[0].user.is_empty());
+        assert_eq!(all[0].profile, crate::profiles::DEFAULT_PROFILE);
+        assert!(all[0].prev.is_empty());
+        let last = read_audit_log(Some(1)).unwrap();
+        assert_eq!((last[0].action, last[0].account_num), (AuditAction::Switch, 2));
+
+        let line = fs::read_to_string(audit_log_path()).unwrap();
+        assert!(line.starts_with("{\"timestamp\""));
+        assert!(line.contains("\"action\":\"add\""));
+
+        #[cfg(unix)]
+        assert_eq!(
+            fs::metadata(audit_log_path()).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+    }
+
+    #[test]
+    fn test_audit_log_chain_detects_edits() {
+        let _env = crate::test_utils::TestEnv::new();
+        // A line from before the chain existed starts the file.
+        let legacy = r#"{"timestamp":"2024-01-01T00:00:00Z","action":"add","account_num":1,"account_email":"a@test.com","user":"u","pid":1}"#;
+        fs::create_dir_all(audit_log_path().parent().unwrap()).unwrap();
+        fs::write(audit_log_path(), format!("{legacy}\n")).unwrap();
+        for num in 2..=4 {
+            append_audit_log(AuditEvent::new(AuditAction::Switch, num, "b@test.com")).unwrap();
+        }
+        assert_eq!(verify_audit_log().unwrap(), None);
+        assert_eq!(read_audit_log(None).unwrap()[1].prev, crate::credentials::sha256_fingerprint(legacy));
+
+        let content = fs::read_to_string(audit_log_path()).unwrap();
+        fs::write(audit_log_path(), content.replace("\"account_num\":3", "\"account_num\":9"))
+            .unwrap();
+        assert_eq!(verify_audit_log().unwrap(), Some(4), "the line after the edit breaks");
+
+        let mut lines: Vec<&str> = content.lines().collect();
+        lines.remove(1);
+        fs::write(audit_log_path(), lines.join("\n") + "\n").unwrap();
+        assert_eq!(verify_audit_log().unwrap(), Some(2), "a removed line breaks the chain");
+    }
+
+    #[test]
+    fn test_switch_log_append_and_tail() {
+        let _env = crate::test_utils::TestEnv::new();
+        assert!(read_switch_log(20).unwrap().is_empty());
+
+        append_switch_log(1, 2, true).unwrap();
+        append_switch_log(2, 3, false).unwrap();
+        append_switch_log(3, 1, true).unwrap();
+
+        let tail = read_switch_log(2).unwrap();
+        assert_eq!(tail.len(), 2);
+        assert_eq!((tail[0].from, tail[0].to, tail[0].ok), (2, 3, false));
+        assert_eq!((tail[1].from, tail[1].to, tail[1].ok), (3, 1, true));
+
+        #[cfg(unix)]
+        assert_eq!(
+            fs::metadata(switch_log_path()).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+    }
+
+    #[test]
+    fn test_previous_account_number_absent_deserialises_as_none() {
+        let json = r#"{"activeAccountNumber":1,"lastUpdated":"","sequence":[1],"accounts":{}}"#;
+        let seq: SequenceFile = serde_json::from_str(json).unwrap();
+        assert_eq!(seq.previous_account_number, None);
+        assert!(!serde_json::to_string(&seq).unwrap().contains("previousAccountNumber"));
+    }
+
     #[test]
     fn test_format_fingerprint_default_none() {
         let seq = SequenceFile::default();
@@ -285,9 +1142,10 @@ mod tests {
     #[test]
     fn test_format_fingerprint_roundtrip() {
         let _env = crate::test_utils::TestEnv::new();
-        let mut seq = SequenceFile::default();
-        seq.format_fingerprint =
-            Some("accessToken|expiresAt|refreshToken|scopes".to_string());
+        let seq = SequenceFile {
+            format_fingerprint: Some("accessToken|expiresAt|refreshToken|scopes".to_string()),
+            ..Default::default()
+        };
         save(&seq).unwrap();
 
         let loaded = load().unwrap();
@@ -0,0 +1,64 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use indicatif::{ProgressBar, ProgressState, ProgressStyle};
+
+// ── Progress spinner ──────────────────────────────────────────────────────────
+//
+// Shown on stderr while a keychain command runs, since `security` can sit for
+// seconds on a locked keychain. Off unless `set_enabled(true)` was called, so
+// the TUI, pipes and `--quiet` never see it.
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+const FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const TICK: Duration = Duration::from_millis(80);
+/// Operations that finish sooner than this never draw, so fast reads don't flicker.
+const DELAY: Duration = Duration::from_millis(200);
+
+/// Allow spinners for the rest of the process. `main` enables them for
+/// subcommands when stdout is a terminal and `--quiet` is off.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// A running spinner; the line is cleared when it is dropped.
+pub struct Spinner(ProgressBar);
+
+impl Spinner {
+    /// Start a spinner labelled `message`, or `None` when spinners are disabled.
+    pub fn start(message: &str) -> Option<Spinner> {
+        if !ENABLED.load(Ordering::Relaxed) {
+            return None;
+        }
+        // The line stays empty until DELAY has passed.
+        let message = message.to_string();
+        let line = move |state: &ProgressState, w: &mut dyn std::fmt::Write| {
+            let elapsed = state.elapsed();
+            if elapsed >= DELAY {
+                let frame = (elapsed.as_millis() / TICK.as_millis()) as usize;
+                let _ = write!(w, "{} {message}", FRAMES[frame % FRAMES.len()]);
+            }
+        };
+        let bar = ProgressBar::new_spinner()
+            .with_style(ProgressStyle::with_template("{line}").ok()?.with_key("line", line));
+        bar.enable_steady_tick(TICK);
+        Some(Spinner(bar))
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        self.0.finish_and_clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled_spinner_does_not_start() {
+        assert!(Spinner::start("Reading keychain…").is_none());
+    }
+}
@@ -0,0 +1,474 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::sequence::{self, AuditEvent, SequenceFile};
+
+// ── Account storage ───────────────────────────────────────────────────────────
+//
+// Where the account index is kept, chosen with `ccswitch config set store
+// <kind>`: `sequence.json` (the default) or, in builds with the `sqlite`
+// feature, `sequence.db` beside it. `sequence::load`, `save` and
+// `append_audit_log` go through `open()`. The audit log is shared by every
+// profile, so both stores append to the same hash-chained `audit.log`.
+
+/// Loads and saves the account index and appends to the audit log.
+pub trait Store {
+    fn load(&self) -> Result<SequenceFile>;
+    fn save(&self, seq: &SequenceFile) -> Result<()>;
+    fn append_log(&self, event: AuditEvent) -> Result<()>;
+    /// The file the index lives in; its mtime changes on every save.
+    fn path(&self) -> &Path;
+}
+
+/// `sequence.json` written atomically, plus the hash-chained `audit.log`.
+pub struct JsonStore {
+    pub sequence_path: PathBuf,
+    pub audit_log_path: PathBuf,
+}
+
+impl JsonStore {
+    /// The files of the current profile, honouring `CCSWITCH_SEQUENCE_FILE`.
+    pub fn current() -> Self {
+        JsonStore::at(sequence::sequence_path())
+    }
+
+    fn at(sequence_path: PathBuf) -> Self {
+        JsonStore {
+            sequence_path,
+            audit_log_path: sequence::audit_log_path(),
+        }
+    }
+}
+
+impl Store for JsonStore {
+    fn load(&self) -> Result<SequenceFile> {
+        let path = &self.sequence_path;
+        if !path.exists() {
+            return Ok(SequenceFile::default());
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid JSON in {}", path.display()))
+    }
+
+    fn save(&self, seq: &SequenceFile) -> Result<()> {
+        let content = serde_json::to_string_pretty(seq)?;
+        sequence::write_atomic(&self.sequence_path, &content)
+    }
+
+    fn append_log(&self, event: AuditEvent) -> Result<()> {
+        sequence::append_audit_log_to(&self.audit_log_path, event)
+    }
+
+    fn path(&self) -> &Path {
+        &self.sequence_path
+    }
+}
+
+/// Values of the `store` setting.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum StoreKind {
+    #[default]
+    Json,
+    Sqlite,
+}
+
+impl std::str::FromStr for StoreKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(StoreKind::Json),
+            "sqlite" => Ok(StoreKind::Sqlite),
+            other => bail!("Unknown store '{other}'. Expected one of: json, sqlite"),
+        }
+    }
+}
+
+impl std::fmt::Display for StoreKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            StoreKind::Json => "json",
+            StoreKind::Sqlite => "sqlite",
+        })
+    }
+}
+
+/// Fail unless this build can open a `kind` store.
+pub fn check_available(kind: StoreKind) -> Result<()> {
+    if kind == StoreKind::Sqlite && !cfg!(feature = "sqlite") {
+        bail!(
+            "This ccswitch was built without the `sqlite` feature; \
+             only the `json` store is available"
+        );
+    }
+    Ok(())
+}
+
+/// The store selected in `config.toml`.
+pub fn configured_kind() -> Result<StoreKind> {
+    match crate::config_user::load_user_config()?.store {
+        Some(name) => name.parse(),
+        None => Ok(StoreKind::default()),
+    }
+}
+
+/// A `kind` store for the index whose JSON form is at `sequence_path`.
+fn open_at(kind: StoreKind, sequence_path: PathBuf) -> Result<Box<dyn Store>> {
+    check_available(kind)?;
+    let json = JsonStore::at(sequence_path);
+    match kind {
+        StoreKind::Json => Ok(Box::new(json)),
+        #[cfg(feature = "sqlite")]
+        StoreKind::Sqlite => Ok(Box::new(SqliteStore::beside(json))),
+        #[cfg(not(feature = "sqlite"))]
+        StoreKind::Sqlite => unreachable!("refused by check_available"),
+    }
+}
+
+/// The store the current profile's accounts live in.
+pub fn open() -> Result<Box<dyn Store>> {
+    open_at(configured_kind()?, sequence::sequence_path())
+}
+
+/// Copy every profile's accounts from the configured store into a `to` store,
+/// so changing the `store` setting keeps them. With `CCSWITCH_BACKUP_DIR` or
+/// `CCSWITCH_SEQUENCE_FILE` set only that index is copied.
+pub fn convert(to: StoreKind) -> Result<()> {
+    let from = configured_kind()?;
+    check_available(to)?;
+    if from == to {
+        return Ok(());
+    }
+
+    use crate::paths::{env_path, BACKUP_DIR_ENV, SEQUENCE_FILE_ENV};
+    let indexes = if env_path(BACKUP_DIR_ENV).is_some() || env_path(SEQUENCE_FILE_ENV).is_some() {
+        vec![(sequence::lock_path(), sequence::sequence_path())]
+    } else {
+        let root = crate::profiles::root_dir();
+        crate::profiles::list()?
+            .into_iter()
+            .map(|name| {
+                let dir = root.join(name);
+                (sequence::lock_path_in(&dir), dir.join("sequence.json"))
+            })
+            .collect()
+    };
+
+    for (lock_path, sequence_path) in indexes {
+        let source = open_at(from, sequence_path.clone())?;
+        if !source.path().exists() {
+            continue;
+        }
+        let _lock = sequence::acquire_lock_at(&lock_path)?;
+        let seq = source.load()?;
+        open_at(to, sequence_path)?.save(&seq)?;
+    }
+    Ok(())
+}
+
+// ── SQLite ────────────────────────────────────────────────────────────────────
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use super::*;
+    use rusqlite::{params, Connection};
+    use std::collections::HashMap;
+
+    use crate::sequence::{AccountEntry, AuthKind};
+
+    const SCHEMA: &str = "
+        CREATE TABLE IF NOT EXISTS accounts (
+            num               INTEGER PRIMARY KEY,
+            email             TEXT NOT NULL,
+            uuid              TEXT NOT NULL,
+            added             TEXT NOT NULL,
+            auth_kind         TEXT NOT NULL,
+            notes             TEXT,
+            tags              TEXT NOT NULL,
+            last_used         TEXT,
+            switch_count      INTEGER NOT NULL,
+            priority          INTEGER NOT NULL,
+            has_config_backup INTEGER NOT NULL,
+            expires_at        TEXT,
+            created_by        TEXT,
+            machine_id        TEXT,
+            env_vars          TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS sequence (
+            position    INTEGER PRIMARY KEY,
+            account_num INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS meta (
+            key   TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );";
+
+    /// `sequence.db` in WAL mode: one row per account in `accounts`, the
+    /// rotation order in `sequence`, and every other `sequence.json` field as
+    /// a JSON value in `meta`. A save replaces all three in one transaction.
+    pub struct SqliteStore {
+        pub db_path: PathBuf,
+        /// Read while `sequence.db` does not exist yet, and owns `audit.log`.
+        json: JsonStore,
+    }
+
+    impl SqliteStore {
+        pub(super) fn beside(json: JsonStore) -> Self {
+            SqliteStore {
+                db_path: json.sequence_path.with_extension("db"),
+                json,
+            }
+        }
+
+        fn connect(&self) -> Result<Connection> {
+            let path = &self.db_path;
+            let conn = Connection::open(path)
+                .with_context(|| format!("Cannot open {}", path.display()))?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+            }
+            conn.busy_timeout(std::time::Duration::from_secs(5))?;
+            conn.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+            conn.execute_batch(SCHEMA)
+                .with_context(|| format!("Cannot create tables in {}", path.display()))?;
+            Ok(conn)
+        }
+    }
+
+    fn read_account(row: &rusqlite::Row) -> rusqlite::Result<(i64, [String; 3], AccountEntry)> {
+        let entry = AccountEntry {
+            email: row.get("email")?,
+            uuid: row.get("uuid")?,
+            added: row.get("added")?,
+            notes: row.get("notes")?,
+            last_used: row.get("last_used")?,
+            switch_count: row.get("switch_count")?,
+            priority: row.get("priority")?,
+            has_config_backup: row.get("has_config_backup")?,
+            expires_at: row.get("expires_at")?,
+            created_by: row.get("created_by")?,
+            machine_id: row.get("machine_id")?,
+            ..Default::default()
+        };
+        // JSON-encoded columns, decoded by the caller so errors keep their context.
+        let json = [row.get("auth_kind")?, row.get("tags")?, row.get("env_vars")?];
+        Ok((row.get("num")?, json, entry))
+    }
+
+    impl Store for SqliteStore {
+        fn load(&self) -> Result<SequenceFile> {
+            if !self.db_path.exists() {
+                return self.json.load();
+            }
+            let conn = self.connect()?;
+            let bad = || format!("Invalid data in {}", self.db_path.display());
+
+            let mut meta = serde_json::Map::new();
+            let mut stmt = conn.prepare("SELECT key, value FROM meta")?;
+            for row in stmt.query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))?
+            {
+                let (key, value) = row?;
+                meta.insert(key, serde_json::from_str(&value).with_context(bad)?);
+            }
+            meta.insert("accounts".into(), serde_json::json!({}));
+            meta.insert("sequence".into(), serde_json::json!([]));
+            meta.entry("lastUpdated").or_insert_with(|| "".into());
+            let mut seq: SequenceFile =
+                serde_json::from_value(serde_json::Value::Object(meta)).with_context(bad)?;
+
+            let mut stmt = conn.prepare("SELECT * FROM accounts")?;
+            for row in stmt.query_map([], read_account)? {
+                let (num, [auth_kind, tags, env_vars], mut entry) = row?;
+                entry.auth_kind =
+                    serde_json::from_value::<AuthKind>(auth_kind.into()).with_context(bad)?;
+                entry.tags = serde_json::from_str(&tags).with_context(bad)?;
+                entry.env_vars =
+                    serde_json::from_str::<HashMap<_, _>>(&env_vars).with_context(bad)?;
+                seq.accounts.insert(num.to_string(), entry);
+            }
+
+            let mut stmt = conn.prepare("SELECT account_num FROM sequence ORDER BY position")?;
+            seq.sequence = stmt.query_map([], |r| r.get(0))?.collect::<Result<_, _>>()?;
+            Ok(seq)
+        }
+
+        fn save(&self, seq: &SequenceFile) -> Result<()> {
+            let mut conn = self.connect()?;
+            let tx = conn.transaction()?;
+            tx.execute_batch("DELETE FROM accounts; DELETE FROM sequence; DELETE FROM meta;")?;
+
+            let mut insert = tx.prepare(
+                "INSERT INTO accounts (num, email, uuid, added, auth_kind, notes, tags, last_used,
+                    switch_count, priority, has_config_backup, expires_at, created_by, machine_id,
+                    env_vars)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            )?;
+            for (num, e) in &seq.accounts {
+                let num: u32 = num
+                    .parse()
+                    .with_context(|| format!("Account number '{num}' is not a number"))?;
+                let auth_kind = serde_json::to_value(&e.auth_kind)?;
+                insert.execute(params![
+                    num,
+                    e.email,
+                    e.uuid,
+                    e.added,
+                    auth_kind.as_str(),
+                    e.notes,
+                    serde_json::to_string(&e.tags)?,
+                    e.last_used,
+                    e.switch_count,
+                    e.priority,
+                    e.has_config_backup,
+                    e.expires_at,
+                    e.created_by,
+                    e.machine_id,
+                    serde_json::to_string(&e.env_vars)?,
+                ])?;
+            }
+            drop(insert);
+
+            let mut insert =
+                tx.prepare("INSERT INTO sequence (position, account_num) VALUES (?1, ?2)")?;
+            for (position, num) in seq.sequence.iter().enumerate() {
+                insert.execute(params![position, num])?;
+            }
+            drop(insert);
+
+            let serde_json::Value::Object(mut meta) = serde_json::to_value(seq)? else {
+                unreachable!("SequenceFile serializes to an object");
+            };
+            meta.remove("accounts");
+            meta.remove("sequence");
+            let mut insert = tx.prepare("INSERT INTO meta (key, value) VALUES (?1, ?2)")?;
+            for (key, value) in &meta {
+                insert.execute(params![key, value.to_string()])?;
+            }
+            drop(insert);
+
+            tx.commit()
+                .with_context(|| format!("Cannot write {}", self.db_path.display()))
+        }
+
+        fn append_log(&self, event: AuditEvent) -> Result<()> {
+            self.json.append_log(event)
+        }
+
+        fn path(&self) -> &Path {
+            &self.db_path
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequence::AuditAction;
+
+    #[test]
+    fn test_json_store_round_trips_and_chains_the_log() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = JsonStore {
+            sequence_path: dir.path().join("sequence.json"),
+            audit_log_path: dir.path().join("audit.log"),
+        };
+        assert!(store.load().unwrap().accounts.is_empty(), "missing file reads as empty");
+
+        let seq = SequenceFile { sequence: vec![2, 1], ..Default::default() };
+        store.save(&seq).unwrap();
+        assert_eq!(store.load().unwrap().sequence, [2, 1]);
+
+        store.append_log(AuditEvent::new(AuditAction::Add, 1, "a@test.com")).unwrap();
+        store.append_log(AuditEvent::new(AuditAction::Remove, 1, "a@test.com")).unwrap();
+        let log = fs::read_to_string(&store.audit_log_path).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let second: AuditEvent = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(second.prev, crate::credentials::sha256_fingerprint(lines[0]));
+    }
+
+    #[test]
+    fn test_store_setting_defaults_to_json() {
+        let _env = crate::test_utils::TestEnv::new();
+        assert_eq!(configured_kind().unwrap(), StoreKind::Json);
+        assert_eq!(crate::config_user::get("store").unwrap(), "json");
+        assert!("mysql".parse::<StoreKind>().is_err());
+        #[cfg(not(feature = "sqlite"))]
+        assert!(crate::config_user::set("store", "sqlite").is_err());
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_store_round_trips_every_field() {
+        use crate::sequence::{AccountEntry, AuthKind, ScheduleEntry};
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = SqliteStore::beside(JsonStore {
+            sequence_path: dir.path().join("sequence.json"),
+            audit_log_path: dir.path().join("audit.log"),
+        });
+        assert!(store.load().unwrap().accounts.is_empty(), "missing db reads as empty");
+
+        let mut seq = SequenceFile {
+            active_account_number: Some(2),
+            pinned_account: Some(1),
+            last_updated: "2026-03-03T12:00:00Z".into(),
+            sequence: vec![2, 1],
+            aliases: [("work".to_string(), 2)].into(),
+            schedules: vec![ScheduleEntry { account_num: 1, cron_expr: "0 9 * * *".into() }],
+            ..Default::default()
+        };
+        let a = AccountEntry { email: "a@test.com".into(), ..Default::default() };
+        seq.accounts.insert("1".into(), a);
+        seq.accounts.insert(
+            "2".into(),
+            AccountEntry {
+                email: "ci".into(),
+                auth_kind: AuthKind::Token,
+                has_config_backup: false,
+                tags: vec!["prod".into()],
+                notes: Some("shared".into()),
+                switch_count: 7,
+                env_vars: [("A".to_string(), "1".to_string())].into(),
+                ..Default::default()
+            },
+        );
+        store.save(&seq).unwrap();
+        seq.sequence = vec![1, 2];
+        store.save(&seq).unwrap();
+
+        let loaded = store.load().unwrap();
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(&seq).unwrap()
+        );
+        assert!(!dir.path().join("sequence.json").exists());
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_config_set_store_copies_accounts_both_ways() {
+        let _env = crate::test_utils::TestEnv::new();
+        let seq = SequenceFile { sequence: vec![4], ..Default::default() };
+        sequence::save(&seq).unwrap();
+
+        crate::config_user::set("store", "sqlite").unwrap();
+        assert!(open().unwrap().path().ends_with("sequence.db"));
+        assert_eq!(sequence::load().unwrap().sequence, [4]);
+
+        sequence::save(&SequenceFile { sequence: vec![5], ..Default::default() }).unwrap();
+        crate::config_user::reset("store").unwrap();
+        assert!(open().unwrap().path().ends_with("sequence.json"));
+        assert_eq!(sequence::load().unwrap().sequence, [5]);
+    }
+}
@@ -0,0 +1,113 @@
+use anyhow::{Context, Result};
+use handlebars::Handlebars;
+use serde_json::Value;
+
+// ── `ccswitch list --template` ────────────────────────────────────────────────
+//
+// Templates are Handlebars, rendered without HTML escaping since the output
+// goes to a terminal or a script. Strict mode is off, so a missing field
+// renders as nothing.
+
+/// Render `template` against `data`.
+pub fn render(template: &str, data: &Value) -> Result<String> {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_escape_fn(handlebars::no_escape);
+    handlebars
+        .render_template(template, data)
+        .context("Invalid template")
+}
+
+/// Translate `\n` and `\t` so templates passed on the command line can span
+/// lines. Templates read from a file are used as written.
+pub fn unescape(s: &str) -> String {
+    s.replace("\\n", "\n").replace("\\t", "\t")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_plain_text() {
+        assert_eq!(render("hello", &json!({})).unwrap(), "hello");
+    }
+
+    #[test]
+    fn test_render_variable_and_nested_path() {
+        let data = json!({ "a": { "b": "deep" }, "n": 3 });
+        assert_eq!(render("{{a.b}}-{{n}}", &data).unwrap(), "deep-3");
+    }
+
+    #[test]
+    fn test_render_missing_variable_is_empty() {
+        assert_eq!(render("[{{nope}}]", &json!({})).unwrap(), "[]");
+    }
+
+    #[test]
+    fn test_render_each_with_newline_escape() {
+        let data = json!({ "accounts": [
+            { "number": 1, "email": "a@test.com" },
+            { "number": 2, "email": "b@test.com" }
+        ]});
+        let tpl = unescape("{{#each accounts}}{{number}}: {{email}}\\n{{/each}}");
+        let out = render(&tpl, &data).unwrap();
+        assert_eq!(out, "1: a@test.com\n2: b@test.com\n");
+        // Without unescaping, as for `@file` templates, `\n` stays literal.
+        assert_eq!(render("{{email}}\\n", &json!({ "email": "a" })).unwrap(), "a\\n");
+    }
+
+    #[test]
+    fn test_render_each_index_and_this() {
+        let data = json!({ "xs": ["a", "b"] });
+        assert_eq!(
+            render("{{#each xs}}{{@index}}={{this}} {{/each}}", &data).unwrap(),
+            "0=a 1=b "
+        );
+    }
+
+    #[test]
+    fn test_render_if_else() {
+        let tpl = "{{#if active}}yes{{else}}no{{/if}}";
+        assert_eq!(render(tpl, &json!({ "active": true })).unwrap(), "yes");
+        assert_eq!(render(tpl, &json!({ "active": false })).unwrap(), "no");
+        assert_eq!(render(tpl, &json!({})).unwrap(), "no");
+    }
+
+    #[test]
+    fn test_render_unless() {
+        let tpl = "{{#unless alias}}-{{/unless}}";
+        assert_eq!(render(tpl, &json!({ "alias": null })).unwrap(), "-");
+        assert_eq!(render(tpl, &json!({ "alias": "work" })).unwrap(), "");
+    }
+
+    #[test]
+    fn test_render_root_lookup_inside_each() {
+        let data = json!({ "label": "L", "xs": [{ "v": 1 }] });
+        assert_eq!(
+            render("{{#each xs}}{{@root.label}}{{../label}}{{v}}{{/each}}", &data).unwrap(),
+            "LL1"
+        );
+    }
+
+    #[test]
+    fn test_render_unclosed_block_is_error() {
+        assert!(render("{{#each xs}}oops", &json!({})).is_err());
+    }
+
+    #[test]
+    fn test_render_mismatched_close_is_error() {
+        assert!(render("{{#if a}}x{{/each}}", &json!({})).is_err());
+    }
+
+    #[test]
+    fn test_render_does_not_html_escape() {
+        let data = json!({ "email": "a&b@test.com" });
+        assert_eq!(render("<{{email}}>", &data).unwrap(), "<a&b@test.com>");
+    }
+
+    #[test]
+    fn test_render_unknown_helper_is_error() {
+        assert!(render("{{#nope a}}x{{/nope}}", &json!({})).is_err());
+    }
+}
@@ -30,11 +30,24 @@ pub(crate) struct AccountExport {
     pub(crate) uuid: String,
     pub(crate) added: String,
     pub(crate) auth_kind: crate::sequence::AuthKind,
+    #[serde(default)]
+    pub(crate) priority: u32,
+    #[serde(default)]
+    pub(crate) notes: Option<String>,
+    #[serde(default)]
+    pub(crate) tags: Vec<String>,
     /// Raw JSON string of the credentials blob.
     pub(crate) credentials: String,
     /// Raw JSON string of the config backup. Defaults to "{}".
     #[serde(default = "default_empty_object")]
     pub(crate) config: String,
+    /// Whether the source account kept a config backup. Older exports
+    /// always carried one.
+    #[serde(default = "crate::sequence::default_true")]
+    pub(crate) has_config_backup: bool,
+    /// `user@hostname` that first added the account, if recorded.
+    #[serde(default)]
+    pub(crate) created_by: Option<String>,
 }
 
 fn default_empty_object() -> String {
@@ -45,7 +58,7 @@ fn default_empty_object() -> String {
 
 /// Encrypt `plaintext` with passphrase using PBKDF2-SHA256 + ChaCha20-Poly1305.
 /// Returns `base64(salt[16] ++ nonce[12] ++ ciphertext+tag)`.
-fn encrypt(plaintext: &[u8], passphrase: &str) -> Result<String> {
+pub(crate) fn encrypt(plaintext: &[u8], passphrase: &str) -> Result<String> {
     let mut salt = [0u8; 16];
     rand::thread_rng().fill_bytes(&mut salt);
 
@@ -71,7 +84,7 @@ fn encrypt(plaintext: &[u8], passphrase: &str) -> Result<String> {
 }
 
 /// Decrypt a bundle produced by `encrypt()`.
-fn decrypt(encoded: &str, passphrase: &str) -> Result<Vec<u8>> {
+pub(crate) fn decrypt(encoded: &str, passphrase: &str) -> Result<Vec<u8>> {
     let bundle = STANDARD
         .decode(encoded.trim().as_bytes())
         .context("Invalid base64 in encrypted blob")?;
@@ -160,6 +173,7 @@ fn build_export_payload(account: Option<&str>, all: bool) -> Result<ExportPayloa
 
         let creds = credentials::read_backup(num, &entry.email)
             .with_context(|| format!("Cannot read credentials backup for Account {num}"))?;
+        accounts::record_audit(sequence::AuditAction::Read, num, &entry.email);
 
         let config = accounts::read_config_backup(num, &entry.email)
             .unwrap_or_else(|_| "{}".to_string());
@@ -170,8 +184,13 @@ fn build_export_payload(account: Option<&str>, all: bool) -> Result<ExportPayloa
             uuid: entry.uuid.clone(),
             added: entry.added.clone(),
             auth_kind: entry.auth_kind.clone(),
+            priority: entry.priority,
+            notes: entry.notes.clone(),
+            tags: entry.tags.clone(),
             credentials: creds,
             config,
+            has_config_backup: entry.has_config_backup,
+            created_by: entry.created_by.clone(),
         });
     }
 
@@ -190,11 +209,48 @@ fn build_export_payload(account: Option<&str>, all: bool) -> Result<ExportPayloa
     })
 }
 
-pub fn export(account: Option<&str>, all: bool) -> Result<()> {
+/// Prompt for a new passphrase twice and make sure both entries match.
+pub(crate) fn prompt_new_passphrase() -> Result<String> {
+    let passphrase = rpassword::prompt_password("  Passphrase (to encrypt): ")
+        .context("Failed to read passphrase")?;
+    if passphrase.is_empty() {
+        anyhow::bail!("Passphrase must not be empty");
+    }
+    let confirm = rpassword::prompt_password("  Confirm passphrase: ")
+        .context("Failed to read passphrase")?;
+    if confirm != passphrase {
+        anyhow::bail!("Passphrases do not match");
+    }
+    Ok(passphrase)
+}
+
+/// Export accounts as a passphrase-encrypted blob.
+/// With `print`, the blob goes to stdout instead of the clipboard or a file.
+/// Without `print` or `clipboard`, a terminal gets the clipboard-or-file
+/// prompt and anything else (a pipe or redirect) gets the blob itself.
+pub fn export(account: Option<&str>, all: bool, print: bool, clipboard: bool) -> Result<()> {
     use std::io::IsTerminal;
     let payload = build_export_payload(account, all)?;
     let json = serde_json::to_string(&payload).context("Failed to serialize export payload")?;
-    let blob = STANDARD.encode(json.as_bytes());
+    let passphrase = prompt_new_passphrase()?;
+    let blob = encrypt(json.as_bytes(), &passphrase)?;
+
+    if print || (!clipboard && !std::io::stdout().is_terminal()) {
+        println!("{blob}");
+        return Ok(());
+    }
+
+    if clipboard {
+        if !copy_to_clipboard(&blob) {
+            anyhow::bail!("No clipboard tool found (tried {}).", CLIPBOARD_TOOLS);
+        }
+        println!(
+            "\n  {}  Copied to clipboard — run {} on the remote.\n",
+            "✓".green().bold(),
+            "ccswitch import --clipboard".cyan().bold()
+        );
+        return Ok(());
+    }
 
     println!();
     let use_file = if std::io::stdin().is_terminal() {
@@ -214,8 +270,9 @@ pub fn export(account: Option<&str>, all: bool) -> Result<()> {
     } else {
         #[cfg(not(target_os = "macos"))]
         eprintln!(
-            "  {}  No clipboard tool found (tried wl-copy, xclip, xsel).",
-            "⚠".yellow().bold()
+            "  {}  No clipboard tool found (tried {}).",
+            "⚠".yellow().bold(),
+            CLIPBOARD_TOOLS
         );
         write_blob_to_file(&blob)?;
     }
@@ -223,6 +280,70 @@ pub fn export(account: Option<&str>, all: bool) -> Result<()> {
     Ok(())
 }
 
+/// The single-account export payload for `export --qr`, refused for OAuth
+/// accounts, whose credentials are far too large to fit in a code a phone can
+/// read off a screen.
+fn qr_payload(account: Option<&str>) -> Result<(u32, String, ExportPayload)> {
+    let seq = sequence::load()?;
+    let num = match account {
+        Some(id) => seq.resolve(id).with_context(|| format!("Account '{id}' not found"))?,
+        None => seq
+            .active_account_number
+            .context("No active account. Pass the account to export.")?,
+    };
+    let entry = seq
+        .accounts
+        .get(&num.to_string())
+        .with_context(|| format!("Account {num} not found in sequence"))?;
+    if entry.auth_kind != sequence::AuthKind::Token {
+        anyhow::bail!(
+            "QR export only works for token accounts. Account {} ({}) uses OAuth, whose \
+             credentials are too large for a QR code; use `ccswitch export` instead.",
+            num,
+            entry.email
+        );
+    }
+    let payload = build_export_payload(Some(&num.to_string()), false)?;
+    Ok((num, entry.email.clone(), payload))
+}
+
+/// `blob` as half-block QR art. Light modules are drawn, so the code reads
+/// correctly on a dark terminal background.
+fn qr_code(blob: &str) -> Result<String> {
+    use qrcode::render::unicode::Dense1x2;
+    let code = qrcode::QrCode::with_error_correction_level(blob, qrcode::EcLevel::L)
+        .with_context(|| format!("{} bytes is too much for a QR code", blob.len()))?;
+    Ok(code
+        .render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .quiet_zone(true)
+        .build())
+}
+
+/// Show a token account as a terminal QR code. The code holds the same
+/// passphrase-encrypted blob as `export`, so the scanned text can be pasted
+/// into `ccswitch import` like any other export.
+pub fn export_qr(account: Option<&str>) -> Result<()> {
+    let (num, email, payload) = qr_payload(account)?;
+    let json = serde_json::to_string(&payload).context("Failed to serialize export payload")?;
+    let passphrase = prompt_new_passphrase()?;
+    let blob = encrypt(json.as_bytes(), &passphrase)?;
+    let code = qr_code(&blob)?;
+
+    println!();
+    for line in code.lines() {
+        println!("  {line}");
+    }
+    println!(
+        "\n  {} ({}) — paste the scanned text into {} and enter the passphrase.\n",
+        email.bold(),
+        format!("Account {num}").dimmed(),
+        "ccswitch import".cyan().bold()
+    );
+    Ok(())
+}
+
 pub fn export_gist(account: Option<&str>, all: bool) -> Result<()> {
     let payload = build_export_payload(account, all)?;
     let json = serde_json::to_string(&payload).context("Failed to serialize export payload")?;
@@ -328,6 +449,39 @@ fn pick_destination_interactive() -> bool {
 
 // ── clipboard / file helpers ──────────────────────────────────────────────────
 
+#[cfg(target_os = "macos")]
+const CLIPBOARD_TOOLS: &str = "pbcopy, pbpaste";
+#[cfg(not(target_os = "macos"))]
+const CLIPBOARD_TOOLS: &str = "wl-copy/wl-paste, xclip, xsel";
+
+/// Clipboard text from the first paste tool that works, or `None` if none does.
+fn read_clipboard() -> Option<String> {
+    use std::process::{Command, Stdio};
+
+    #[cfg(target_os = "macos")]
+    let candidates: &[(&str, &[&str])] = &[("pbpaste", &[])];
+
+    #[cfg(not(target_os = "macos"))]
+    let candidates: &[(&str, &[&str])] = &[
+        ("wl-paste", &["--no-newline"]),
+        ("xclip", &["-selection", "clipboard", "-o"]),
+        ("xsel", &["--clipboard", "--output"]),
+    ];
+
+    candidates.iter().find_map(|(bin, args)| {
+        let output = Command::new(bin)
+            .args(*args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+    })
+}
+
 fn copy_to_clipboard(blob: &str) -> bool {
     use std::io::Write;
     use std::process::{Command, Stdio};
@@ -367,7 +521,7 @@ fn copy_to_clipboard(blob: &str) -> bool {
 fn write_blob_to_file(blob: &str) -> Result<()> {
     use std::io::Write;
 
-    let default_path = dirs::home_dir()
+    let default_path = crate::paths::home_dir()
         .context("Cannot find home directory")?
         .join("ccswitch-export.blob");
     let default_str = default_path.display().to_string();
@@ -384,7 +538,7 @@ fn write_blob_to_file(blob: &str) -> Result<()> {
     }
 
     let path = if let Some(rest) = raw.strip_prefix("~/") {
-        dirs::home_dir()
+        crate::paths::home_dir()
             .context("Cannot find home directory")?
             .join(rest)
     } else {
@@ -436,19 +590,85 @@ fn parse_payload(blob: &str) -> Result<ExportPayload> {
     Ok(payload)
 }
 
+/// True when `raw` is an unencrypted (legacy) blob: base64 of a JSON document.
+fn is_plain_blob(raw: &str) -> bool {
+    STANDARD
+        .decode(raw.trim().as_bytes())
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<serde_json::Value>(&bytes).ok())
+        .is_some()
+}
+
+/// Decode an export blob, decrypting it with the passphrase from `passphrase`
+/// unless it is a legacy unencrypted blob. Nothing is written on failure.
+fn decode_blob(raw: &str, passphrase: impl FnOnce() -> Result<String>) -> Result<ExportPayload> {
+    if is_plain_blob(raw) {
+        return parse_payload(raw);
+    }
+    let plaintext = decrypt(raw, &passphrase()?)?;
+    parse_payload(&STANDARD.encode(&plaintext))
+}
+
+/// Ask whether an already-managed account should be overwritten by the import.
+fn prompt_overwrite(email: &str, local_num: u32) -> Result<bool> {
+    use std::io::Write;
+
+    print!(
+        "  {} {} is already managed (Account {}). [o]verwrite / [S]kip: ",
+        "!".yellow().bold(),
+        email,
+        local_num
+    );
+    std::io::stdout().flush()?;
+
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    Ok(matches!(input.trim().to_lowercase().as_str(), "o" | "overwrite"))
+}
+
 /// Apply an already-parsed export payload: write credentials, update sequence, activate account.
+/// Accounts that already exist locally are overwritten only after confirmation
+/// (non-interactive imports overwrite, matching earlier behaviour).
 fn do_import(payload: ExportPayload) -> Result<()> {
+    use std::io::IsTerminal;
+
     sequence::setup_dirs()?;
 
+    // Ask about overwrites before taking the lock so a pending prompt does not
+    // block other ccswitch operations; the sequence is reloaded under the lock.
+    let existing = sequence::load().unwrap_or_default();
+
+    let interactive = std::io::stdin().is_terminal();
+    let mut accounts: Vec<AccountExport> = Vec::new();
+    let mut skipped: Vec<String> = Vec::new();
+    for acct in payload.accounts {
+        match existing.find_by_email(&acct.email) {
+            Some(num) if interactive && !prompt_overwrite(&acct.email, num)? => {
+                skipped.push(acct.email);
+            }
+            _ => accounts.push(acct),
+        }
+    }
+
+    if accounts.is_empty() {
+        println!("\n  {}  Nothing imported — all accounts skipped.\n", "·".dimmed());
+        return Ok(());
+    }
+
+    let _lock = sequence::acquire_lock()?;
     let mut seq = sequence::load().unwrap_or_default();
 
-    let mapped_active_local = merge_sequence(&mut seq, &payload.accounts, payload.active_num);
+    let activate = accounts.iter().any(|a| a.num == payload.active_num);
+    let mapped_active_local = merge_sequence(&mut seq, &accounts, payload.active_num);
 
-    for acct in &payload.accounts {
+    for acct in &accounts {
         let local_num = seq.find_by_email(&acct.email).unwrap_or(1);
         credentials::write_backup(local_num, &acct.email, &acct.credentials)
             .with_context(|| format!("Failed to write credentials for {}", acct.email))?;
 
+        if !acct.has_config_backup {
+            continue;
+        }
         let config_path = accounts::config_backup_path(local_num, &acct.email);
         std::fs::write(&config_path, &acct.config)
             .with_context(|| format!("Failed to write config backup for {}", acct.email))?;
@@ -463,33 +683,43 @@ fn do_import(payload: ExportPayload) -> Result<()> {
         }
     }
 
-    let active_acct = payload
-        .accounts
-        .iter()
-        .find(|a| seq.find_by_email(&a.email) == Some(mapped_active_local))
-        .context("Cannot find the active account in the import payload")?;
-
-    credentials::write_live(&active_acct.credentials)
-        .context("Failed to write live credentials")?;
-
-    let _ = credentials::ensure_ccswitchrc();
-
-    if let Ok(config_json) = serde_json::from_str::<serde_json::Value>(&active_acct.config) {
-        if let Some(oauth_account) = config_json.get("oauthAccount").cloned() {
-            let mut live_config = crate::config::load().unwrap_or_else(|_| serde_json::json!({}));
-            live_config["oauthAccount"] = oauth_account;
-            let _ = crate::config::save(&live_config);
+    // Only activate when the exported active account was actually imported.
+    if activate {
+        let active_acct = accounts
+            .iter()
+            .find(|a| seq.find_by_email(&a.email) == Some(mapped_active_local))
+            .context("Cannot find the active account in the import payload")?;
+
+        credentials::write_live(&active_acct.credentials)
+            .context("Failed to write live credentials")?;
+
+        let _ = credentials::ensure_ccswitchrc();
+
+        if let Ok(config_json) = serde_json::from_str::<serde_json::Value>(&active_acct.config) {
+            if let Some(oauth_account) = config_json.get("oauthAccount").cloned() {
+                let mut live_config = crate::config::load_with_path().unwrap_or_else(|_| {
+                    crate::config::LoadedConfig {
+                        path: crate::config::path(),
+                        value: serde_json::json!({}),
+                    }
+                });
+                live_config.value["oauthAccount"] = oauth_account;
+                let _ = crate::config::save(&live_config.path, &live_config.value);
+            }
         }
-    }
 
-    seq.active_account_number = Some(mapped_active_local);
+        seq.active_account_number = Some(mapped_active_local);
+    }
     seq.last_updated = sequence::now_utc();
     sequence::save(&seq)?;
+    if let Some(entry) = seq.accounts.get(&mapped_active_local.to_string()).filter(|_| activate) {
+        let _ = sequence::write_active_account_file(&entry.email);
+    }
 
     println!();
-    for acct in &payload.accounts {
+    for acct in &accounts {
         let local_num = seq.find_by_email(&acct.email).unwrap_or(mapped_active_local);
-        let is_active = local_num == mapped_active_local;
+        let is_active = activate && local_num == mapped_active_local;
         if is_active {
             println!(
                 "  {}  Imported {} (Account {}) — active",
@@ -506,19 +736,63 @@ fn do_import(payload: ExportPayload) -> Result<()> {
             );
         }
     }
-    println!("\n  {}  Restart Claude Code to apply.\n", "✓".green().bold());
+    for email in &skipped {
+        println!("  {}  Skipped {} (already managed)", "·".dimmed(), email);
+    }
+    if activate {
+        println!("\n  {}  Restart Claude Code to apply.\n", "✓".green().bold());
+    } else {
+        println!();
+    }
 
     Ok(())
 }
 
-pub fn import() -> Result<()> {
-    let raw = rpassword::prompt_password("  Paste export blob: ")
-        .context("Failed to read blob from terminal")?;
+/// Import accounts without activating any of them (used by `restore` in merge mode).
+pub(crate) fn import_accounts(accounts: Vec<AccountExport>) -> Result<()> {
+    do_import(ExportPayload {
+        version: 1,
+        exported_at: sequence::now_utc(),
+        active_num: 0,
+        format_fingerprint: None,
+        accounts,
+    })
+}
+
+/// Import an export blob from `file`, piped stdin, or an interactive paste.
+pub fn import(file: Option<&std::path::Path>, clipboard: bool) -> Result<()> {
+    use std::io::{IsTerminal, Read};
+
+    let raw = match file {
+        Some(path) => std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read {}", path.display()))?,
+        None if clipboard => read_clipboard()
+            .with_context(|| format!("Cannot read the clipboard (tried {CLIPBOARD_TOOLS})"))?,
+        None if !std::io::stdin().is_terminal() => {
+            let mut buf = String::new();
+            std::io::stdin()
+                .read_to_string(&mut buf)
+                .context("Failed to read blob from stdin")?;
+            buf
+        }
+        None => rpassword::prompt_password("  Paste export blob: ")
+            .context("Failed to read blob from terminal")?,
+    };
 
-    let payload = parse_payload(&raw)?;
+    let payload = decode_blob(&raw, prompt_decrypt_passphrase)?;
     do_import(payload)
 }
 
+/// Ask for the passphrase an export was encrypted with. Tests supply it via
+/// `CCSWITCH_TEST_PASSPHRASE`.
+fn prompt_decrypt_passphrase() -> Result<String> {
+    #[cfg(test)]
+    if let Ok(passphrase) = std::env::var("CCSWITCH_TEST_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+    rpassword::prompt_password("  Passphrase (to decrypt): ").context("Failed to read passphrase")
+}
+
 pub fn import_gist(id: &str) -> Result<()> {
     let token = gh_token()?;
 
@@ -544,10 +818,7 @@ pub fn import_gist(id: &str) -> Result<()> {
         .as_str()
         .context("Gist does not contain a 'ccswitch.blob' file — is this a ccswitch gist?")?;
 
-    let passphrase = rpassword::prompt_password("  Passphrase (to decrypt): ")
-        .context("Failed to read passphrase")?;
-
-    let plaintext = decrypt(encrypted, &passphrase)?;
+    let plaintext = decrypt(encrypted, &prompt_decrypt_passphrase()?)?;
 
     let blob = STANDARD.encode(&plaintext);
     let payload = parse_payload(&blob)?;
@@ -572,6 +843,117 @@ pub fn import_gist(id: &str) -> Result<()> {
     Ok(())
 }
 
+// ── import --from-json ────────────────────────────────────────────────────────
+
+/// One entry of the plain JSON import format: `[{"email": …, "token": …}]`.
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct JsonTokenEntry {
+    pub(crate) email: String,
+    pub(crate) token: String,
+}
+
+/// Outcome of a JSON token import.
+#[derive(Debug, Default)]
+pub(crate) struct JsonImportSummary {
+    /// (local account number, email) for each account added.
+    pub(crate) added: Vec<(u32, String)>,
+    /// (email, reason) for each entry skipped.
+    pub(crate) skipped: Vec<(String, String)>,
+}
+
+/// Store each token as a managed token account without activating it.
+/// Entries whose email is already managed are skipped.
+pub(crate) fn core_import_tokens(entries: &[JsonTokenEntry]) -> Result<JsonImportSummary> {
+    sequence::setup_dirs()?;
+    let _lock = sequence::acquire_lock()?;
+    let mut seq = sequence::load()?;
+    let mut summary = JsonImportSummary::default();
+
+    for item in entries {
+        let email = item.email.trim();
+        let token = item.token.trim();
+        if email.is_empty() || token.is_empty() {
+            summary
+                .skipped
+                .push((item.email.clone(), "missing email or token".to_string()));
+            continue;
+        }
+        if seq.account_exists(email) {
+            summary
+                .skipped
+                .push((email.to_string(), "already managed".to_string()));
+            continue;
+        }
+
+        let num = seq.next_account_number();
+        let token_json = serde_json::json!({ "token": token }).to_string();
+        credentials::write_backup(num, email, &token_json)
+            .with_context(|| format!("Failed to write credentials for {email}"))?;
+
+        seq.accounts.insert(
+            num.to_string(),
+            sequence::AccountEntry {
+                email: email.to_string(),
+                uuid: String::new(),
+                added: sequence::now_utc(),
+                auth_kind: sequence::AuthKind::Token,
+                has_config_backup: false,
+                priority: 0,
+                notes: None,
+                last_used: None,
+                switch_count: 0,
+                tags: Vec::new(),
+                expires_at: None,
+                created_by: Some(accounts::created_by()),
+                machine_id: crate::platform::machine_id(),
+                env_vars: Default::default(),
+            },
+        );
+        seq.sequence.push(num);
+        summary.added.push((num, email.to_string()));
+    }
+
+    if !summary.added.is_empty() {
+        seq.last_updated = sequence::now_utc();
+        sequence::save(&seq)?;
+    }
+    Ok(summary)
+}
+
+pub fn import_from_json(path: &std::path::Path) -> Result<()> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Cannot read {}", path.display()))?;
+    let entries: Vec<JsonTokenEntry> = serde_json::from_str(&content).with_context(|| {
+        format!(
+            "Invalid JSON in {} — expected [{{\"email\": \"…\", \"token\": \"…\"}}]",
+            path.display()
+        )
+    })?;
+
+    let summary = core_import_tokens(&entries)?;
+
+    println!();
+    for (num, email) in &summary.added {
+        println!(
+            "  {}  Imported {} (Account {}) {}",
+            "✓".green().bold(),
+            email,
+            num,
+            "(token)".dimmed()
+        );
+    }
+    for (email, reason) in &summary.skipped {
+        println!("  {}  Skipped {} — {}", "·".dimmed(), email, reason);
+    }
+    println!(
+        "\n  Summary — imported: {}  skipped: {}\n",
+        summary.added.len().to_string().bold(),
+        summary.skipped.len().to_string().dimmed()
+    );
+
+    Ok(())
+}
+
 // ── pure helper (also used by tests) ─────────────────────────────────────────
 
 pub(crate) fn merge_sequence(
@@ -593,6 +975,17 @@ pub(crate) fn merge_sequence(
                     uuid: acct.uuid.clone(),
                     added: acct.added.clone(),
                     auth_kind: acct.auth_kind.clone(),
+                    has_config_backup: acct.has_config_backup,
+                    priority: acct.priority,
+                    notes: acct.notes.clone(),
+                    last_used: None,
+                    switch_count: 0,
+                    tags: acct.tags.clone(),
+                    expires_at: accounts::stored_expiry(&acct.credentials),
+                    // Exports from before `created_by` existed credit the importing user.
+                    created_by: acct.created_by.clone().or_else(|| Some(accounts::created_by())),
+                    machine_id: None,
+                    env_vars: Default::default(),
                 },
             );
             new_num
@@ -624,8 +1017,13 @@ mod tests {
             uuid: format!("uuid-{num}"),
             added: "2026-01-01T00:00:00Z".to_string(),
             auth_kind: AuthKind::Oauth,
+            priority: 0,
+            notes: None,
+            tags: Vec::new(),
             credentials: r#"{"claudeAiOauth":{"accessToken":"tok","refreshToken":"rtok","expiresAt":9999999999999,"scopes":[]}}"#.to_string(),
             config: "{}".to_string(),
+            has_config_backup: true,
+            created_by: None,
         }
     }
 
@@ -649,6 +1047,7 @@ mod tests {
                 uuid: "old-uuid".to_string(),
                 added: "2025-01-01T00:00:00Z".to_string(),
                 auth_kind: AuthKind::Oauth,
+                ..Default::default()
             },
         );
         seq.sequence.push(5);
@@ -673,6 +1072,39 @@ mod tests {
         assert_eq!(seq.accounts.len(), 1);
     }
 
+    #[test]
+    fn test_merge_sequence_keeps_has_config_backup() {
+        let mut seq = SequenceFile::default();
+        let accounts = vec![
+            AccountExport {
+                auth_kind: AuthKind::Token,
+                has_config_backup: false,
+                ..make_account_export(1, "token@example.com")
+            },
+            make_account_export(2, "oauth@example.com"),
+        ];
+        merge_sequence(&mut seq, &accounts, 1);
+
+        assert!(!seq.accounts["1"].has_config_backup);
+        assert!(seq.accounts["2"].has_config_backup);
+    }
+
+    #[test]
+    fn test_merge_sequence_keeps_or_sets_created_by() {
+        let mut seq = SequenceFile::default();
+        let exports = vec![
+            AccountExport {
+                created_by: Some("alice@laptop".to_string()),
+                ..make_account_export(1, "alice@example.com")
+            },
+            make_account_export(2, "old-export@example.com"),
+        ];
+        merge_sequence(&mut seq, &exports, 1);
+
+        assert_eq!(seq.accounts["1"].created_by.as_deref(), Some("alice@laptop"));
+        assert_eq!(seq.accounts["2"].created_by, Some(accounts::created_by()));
+    }
+
     #[test]
     fn test_export_payload_serde_roundtrip() {
         let payload = ExportPayload {
@@ -715,6 +1147,153 @@ mod tests {
             .contains("Unsupported export version 99"));
     }
 
+    #[test]
+    fn test_core_import_tokens_adds_and_skips() {
+        let _env = crate::test_utils::TestEnv::new();
+        let entries: Vec<JsonTokenEntry> = serde_json::from_str(
+            r#"[
+                {"email": "a@example.com", "token": "sk-ant-oat01-a"},
+                {"email": "a@example.com", "token": "sk-ant-oat01-dup"},
+                {"email": "b@example.com", "token": ""}
+            ]"#,
+        )
+        .unwrap();
+
+        let summary = core_import_tokens(&entries).unwrap();
+        assert_eq!(summary.added, vec![(1, "a@example.com".to_string())]);
+        assert_eq!(summary.skipped.len(), 2);
+
+        let seq = sequence::load().unwrap();
+        assert_eq!(seq.sequence, vec![1]);
+        assert_eq!(seq.accounts["1"].auth_kind, AuthKind::Token);
+        assert_eq!(seq.accounts["1"].created_by, Some(accounts::created_by()));
+        assert_eq!(seq.accounts["1"].machine_id, crate::platform::machine_id());
+        // Importing does not activate the new account.
+        assert_eq!(seq.active_account_number, None);
+        let stored = credentials::read_backup(1, "a@example.com").unwrap();
+        assert!(stored.contains("sk-ant-oat01-a"));
+    }
+
+    #[test]
+    fn test_import_from_json_invalid_json_is_error() {
+        let env = crate::test_utils::TestEnv::new();
+        let path = env.dir.path().join("tokens.json");
+        std::fs::write(&path, "{not json").unwrap();
+        let err = import_from_json(&path).unwrap_err();
+        assert!(err.to_string().contains("Invalid JSON"), "unexpected: {err}");
+    }
+
+    fn encrypted_blob(payload: &ExportPayload, passphrase: &str) -> String {
+        let json = serde_json::to_string(payload).unwrap();
+        encrypt(json.as_bytes(), passphrase).unwrap()
+    }
+
+    fn sample_payload() -> ExportPayload {
+        ExportPayload {
+            version: 1,
+            exported_at: "2026-03-03T12:00:00Z".to_string(),
+            active_num: 1,
+            format_fingerprint: None,
+            accounts: vec![make_account_export(1, "enc@example.com")],
+        }
+    }
+
+    #[test]
+    fn test_decode_blob_encrypted_roundtrip() {
+        let blob = encrypted_blob(&sample_payload(), "pass");
+        let payload = decode_blob(&blob, || Ok("pass".to_string())).unwrap();
+        assert_eq!(payload.accounts[0].email, "enc@example.com");
+    }
+
+    #[test]
+    fn test_decode_blob_encrypted_has_no_plaintext_token() {
+        let blob = encrypted_blob(&sample_payload(), "pass");
+        assert!(!is_plain_blob(&blob));
+        let raw = STANDARD.decode(blob.as_bytes()).unwrap();
+        assert!(!String::from_utf8_lossy(&raw).contains("rtok"));
+    }
+
+    #[test]
+    fn test_decode_blob_wrong_passphrase_is_error() {
+        let blob = encrypted_blob(&sample_payload(), "right");
+        let err = decode_blob(&blob, || Ok("wrong".to_string())).unwrap_err();
+        assert!(err.to_string().contains("Decryption failed"), "unexpected: {err}");
+    }
+
+    #[test]
+    fn test_decode_blob_legacy_plain_does_not_ask_passphrase() {
+        let json = serde_json::to_string(&sample_payload()).unwrap();
+        let blob = STANDARD.encode(json.as_bytes());
+        let payload =
+            decode_blob(&blob, || panic!("passphrase should not be requested")).unwrap();
+        assert_eq!(payload.active_num, 1);
+    }
+
+    #[test]
+    fn test_import_wrong_passphrase_writes_nothing() {
+        let env = crate::test_utils::TestEnv::new();
+        let path = env.dir.path().join("export.blob");
+        std::fs::write(&path, encrypted_blob(&sample_payload(), "right")).unwrap();
+        let files_before = files_under(env.dir.path());
+
+        std::env::set_var("CCSWITCH_TEST_PASSPHRASE", "wrong");
+        let err = import(Some(&path), false).unwrap_err();
+        assert!(err.to_string().contains("Decryption failed"), "unexpected: {err}");
+        assert_eq!(files_under(env.dir.path()), files_before, "nothing was written");
+
+        // The same path with the right passphrase does import.
+        std::env::set_var("CCSWITCH_TEST_PASSPHRASE", "right");
+        import(Some(&path), false).unwrap();
+        assert_eq!(sequence::load().unwrap().find_by_email("enc@example.com"), Some(1));
+    }
+
+    /// Every file below `dir`, recursively.
+    fn files_under(dir: &std::path::Path) -> Vec<std::path::PathBuf> {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(dir).unwrap().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                files.extend(files_under(&path));
+            } else {
+                files.push(path);
+            }
+        }
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test_qr_payload_is_an_importable_export() {
+        let _env = crate::test_utils::TestEnv::new();
+        let token = format!("sk-ant-oat01-{}", "x".repeat(95));
+        let mut seq = SequenceFile::default();
+        seq.accounts.insert(
+            "1".to_string(),
+            AccountEntry {
+                email: "qr.token.user@example.com".to_string(),
+                uuid: "2f1c5a9e-3b7d-4c8a-9e6f-1a2b3c4d5e6f".to_string(),
+                added: "2026-01-01T00:00:00Z".to_string(),
+                auth_kind: AuthKind::Token,
+                ..Default::default()
+            },
+        );
+        seq.sequence = vec![1];
+        seq.active_account_number = Some(1);
+        sequence::save(&seq).unwrap();
+        let creds = serde_json::json!({ "token": token }).to_string();
+        credentials::write_backup(1, "qr.token.user@example.com", &creds).unwrap();
+
+        let (num, _, payload) = qr_payload(None).unwrap();
+        assert_eq!(num, 1);
+        let blob = encrypted_blob(&payload, "pass");
+        assert!(!blob.contains("sk-ant-"));
+        assert!(qr_code(&blob).is_ok());
+
+        let decoded = decode_blob(&blob, || Ok("pass".to_string())).unwrap();
+        assert_eq!(decoded.accounts[0].credentials, creds);
+        assert_eq!(decoded.accounts[0].auth_kind, AuthKind::Token);
+    }
+
     #[test]
     fn test_encrypt_decrypt_roundtrip() {
         let plaintext = b"hello, this is a secret payload!";
@@ -1,6 +1,9 @@
 use anyhow::Result;
 use crossterm::{
-    event::{self, Event, KeyCode, KeyModifiers},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers,
+        MouseButton, MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -10,13 +13,19 @@ use ratatui::{
     style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{
-        Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph,
+        Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap,
     },
     Terminal,
 };
-use std::io;
+use std::{
+    collections::HashSet,
+    io,
+    path::PathBuf,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
 
-use crate::{accounts, config, sequence};
+use crate::{accounts, config, config_user, profiles, sequence};
 use crate::sequence::AuthKind;
 
 // ── State machine ─────────────────────────────────────────────────────────────
@@ -26,8 +35,26 @@ enum Mode {
     ConfirmSwitch { num: u32, email: String },
     ConfirmRemove { num: u32, email: String },
     ConfirmAdd { email: String },
+    /// Switch back to the previously active account.
+    ConfirmUndo { num: u32, email: String },
+    /// Pin the account, or unpin it when it is already pinned.
+    ConfirmPin { num: u32, email: String },
+    /// Inline label editor for the selected account.
+    Rename { num: u32, input: String },
+    /// Incremental email filter entered with `/`; a leading `#` filters by tag instead.
+    Search { query: String },
+    /// Moving account `num` within the rotation; `original` restores it on Esc.
+    Reorder { num: u32, original: Vec<u32> },
+    /// Picking the account to trade rotation positions with `num`, entered with `x`.
+    SelectSwapTarget { num: u32 },
     /// Shown when a refresh attempt fails with invalid_grant (expired refresh token).
     ExpiredAccount { num: u32, email: String },
+    /// Picking accounts for bulk removal, entered with `v`.
+    MultiSelect { selected: HashSet<u32> },
+    /// Remove every listed account, in rotation order.
+    ConfirmBulkRemove { accounts: Vec<(u32, String)> },
+    /// Full-screen keybinding reference opened with `?`.
+    Help,
     /// Switch (or other action) completed.
     Done,
 }
@@ -42,19 +69,110 @@ struct App {
     /// Display email: prefers OAuth config, falls back to seq.active_account_number
     /// so token users also see their active account in the header.
     current_email: Option<String>,
+    /// Profile whose accounts are shown; fixed for the session.
+    profile: String,
+    /// Account numbers in the order the list shows them: `seq.sequence`, or
+    /// sorted by `sort_order`. `selected` indexes into this.
+    rows: Vec<u32>,
     selected: usize,
     mode: Mode,
     flash: Option<Flash>,
     quit: bool,
     /// Set when the token add flow should run after the current event is processed.
     pending_token_add: bool,
+    /// Indices into `rows` matching the current search query.
+    filtered_indices: Vec<usize>,
+    /// Where the account list was last drawn, for mouse hit-testing.
+    list_area: Rect,
+    /// Kept across frames so the scroll offset is known when a click arrives.
+    list_state: ListState,
+    /// Time and row index of the last left click, to detect double-clicks.
+    last_click: Option<(Instant, usize)>,
+    /// `--no-color` / `$NO_COLOR`: draw with the terminal's default colors.
+    no_color: bool,
+    /// `confirm_switch` setting: ask before switching.
+    confirm_switch: bool,
+    /// `suppress_restart_hint` setting: leave the restart reminder out of the Done bar.
+    suppress_restart_hint: bool,
+    /// The last switch changed the env vars ~/.ccswitchrc exports.
+    env_changed: bool,
+    /// Display order picked with `s`; `None` shows the rotation order.
+    /// Only `rows` is sorted, so `seq.sequence` keeps the rotation order.
+    sort_order: Option<accounts::SortOrder>,
+}
+
+/// Every binding in Normal mode, as shown on the `?` help screen.
+const KEYBINDINGS: &[(&str, &str)] = &[
+    ("↑ / k", "move up"),
+    ("↓ / j", "move down"),
+    ("Enter / Space", "switch to the selected account"),
+    ("B", "switch to the previous account in rotation"),
+    ("/", "search accounts by email"),
+    ("#", "filter accounts by tag"),
+    ("m", "move the selected account in the rotation"),
+    ("x", "swap the selected account's rotation position with another"),
+    ("s", "cycle the sort order (rotation, number, email, …)"),
+    ("p", "pin or unpin the selected account"),
+    ("u", "undo the last switch"),
+    ("a", "add the current account"),
+    ("d / Delete", "remove the selected account"),
+    ("e", "rename the selected account"),
+    ("v", "select several accounts to remove at once"),
+    ("r", "refresh the selected account's OAuth session"),
+    ("R", "reload state from disk"),
+    ("?", "show this help"),
+    ("q / Esc", "quit"),
+    ("Ctrl+C", "quit from anywhere"),
+];
+
+/// The detail panel is shown beside the account list from this terminal width up.
+const DETAIL_MIN_WIDTH: u16 = 100;
+
+/// Two left clicks on the same row within this window count as a double-click.
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// Watches the store file (`sequence.json` or `sequence.db`) for writes by
+/// other processes. The directory is watched, not the file, because saves
+/// replace the file by renaming a temp file over it.
+struct SequenceWatcher {
+    path: PathBuf,
+    events: mpsc::Receiver<notify::Result<notify::Event>>,
+    _watcher: notify::RecommendedWatcher,
+}
+
+impl SequenceWatcher {
+    /// `None` when the platform cannot watch the directory; the TUI then
+    /// simply does not live-reload.
+    fn start() -> Option<Self> {
+        use notify::Watcher;
+        let path = crate::store::open().ok()?.path().to_path_buf();
+        let (tx, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx).ok()?;
+        watcher
+            .watch(path.parent()?, notify::RecursiveMode::NonRecursive)
+            .ok()?;
+        Some(SequenceWatcher { path, events, _watcher: watcher })
+    }
+
+    /// Whether a write to the store file arrived since the last call.
+    fn changed(&self) -> bool {
+        let is_write = |e: &notify::Event| {
+            matches!(e.kind, notify::EventKind::Modify(_) | notify::EventKind::Create(_))
+                && e.paths.contains(&self.path)
+        };
+        // Drain the whole channel; one reload covers any number of writes.
+        self.events.try_iter().filter(|event| event.as_ref().is_ok_and(is_write)).count() > 0
+    }
 }
 
 impl App {
     fn new() -> Result<Self> {
         let seq = sequence::load()?;
+        let config = config_user::load_user_config();
         let current_email = Self::resolve_display_email(&seq);
         Ok(App {
+            profile: profiles::current(),
+            rows: seq.sequence.clone(),
             seq,
             current_email,
             selected: 0,
@@ -62,19 +180,36 @@ impl App {
             flash: None,
             quit: false,
             pending_token_add: false,
+            filtered_indices: Vec::new(),
+            list_area: Rect::default(),
+            list_state: ListState::default(),
+            last_click: None,
+            no_color: false,
+            confirm_switch: config.as_ref().map(|c| c.confirm_switch).unwrap_or(true),
+            suppress_restart_hint: config.is_ok_and(|c| c.suppress_restart_hint),
+            env_changed: false,
+            sort_order: None,
         })
     }
 
     fn reload(&mut self) -> Result<()> {
         self.seq = sequence::load()?;
+        self.refresh_rows();
         self.current_email = Self::resolve_display_email(&self.seq);
         // clamp selection
-        if !self.seq.sequence.is_empty() && self.selected >= self.seq.sequence.len() {
-            self.selected = self.seq.sequence.len() - 1;
+        if !self.rows.is_empty() && self.selected >= self.rows.len() {
+            self.selected = self.rows.len() - 1;
         }
         Ok(())
     }
 
+    fn refresh_rows(&mut self) {
+        self.rows = match self.sort_order {
+            Some(order) => accounts::sorted_sequence(&self.seq, order),
+            None => self.seq.sequence.clone(),
+        };
+    }
+
     /// Determine the active email for display.
     /// Prefers seq state so token accounts (and recently-switched accounts)
     /// show correctly; falls back to live OAuth config.
@@ -85,8 +220,72 @@ impl App {
             .or_else(config::current_email)
     }
 
+    /// Reload after the watcher saw a write, reporting whether the accounts
+    /// differ from what is shown, so our own writes (which reload straight
+    /// away) are not reported twice.
+    fn reload_if_changed(&mut self) -> Result<bool> {
+        let before = serde_json::to_value(&self.seq)?;
+        self.reload()?;
+        Ok(serde_json::to_value(&self.seq)? != before)
+    }
+
+    /// Step to the next sort order (wrapping back to rotation order), keeping
+    /// the same account selected.
+    fn cycle_sort(&mut self) -> Result<()> {
+        let selected = self.selected_num();
+        let all = accounts::SortOrder::ALL;
+        self.sort_order = match self.sort_order {
+            None => Some(all[0]),
+            Some(order) => all.iter().skip_while(|&&o| o != order).nth(1).copied(),
+        };
+        self.reload()?;
+        if let Some(i) = selected.and_then(|n| self.rows.iter().position(|&m| m == n)) {
+            self.selected = i;
+        }
+        Ok(())
+    }
+
     fn selected_num(&self) -> Option<u32> {
-        self.seq.sequence.get(self.selected).copied()
+        self.rows.get(self.selected).copied()
+    }
+
+    fn selected_note(&self) -> Option<&str> {
+        let num = self.selected_num()?;
+        self.seq.accounts.get(&num.to_string())?.notes.as_deref()
+    }
+
+    /// Recompute `filtered_indices` for a case-insensitive email search,
+    /// or a tag search when the query starts with `#`.
+    fn update_filter(&mut self, query: &str) {
+        let query = query.to_lowercase();
+        let tag_query = query.strip_prefix('#');
+        self.filtered_indices = self
+            .rows
+            .iter()
+            .enumerate()
+            .filter(|(_, num)| {
+                self.seq.accounts.get(&num.to_string()).is_some_and(|e| match tag_query {
+                    Some(tag) => e.tags.iter().any(|t| t.contains(tag)),
+                    None => e.email.to_lowercase().contains(&query),
+                })
+            })
+            .map(|(i, _)| i)
+            .collect();
+    }
+
+    /// Index into `rows` of the list row under the given terminal cell.
+    fn index_at(&self, column: u16, row: u16) -> Option<usize> {
+        // Skip the list block's one-cell border.
+        let inner = Block::default().borders(Borders::ALL).inner(self.list_area);
+        if column < inner.x
+            || column >= inner.x + inner.width
+            || row < inner.y
+            || row >= inner.y + inner.height
+        {
+            return None;
+        }
+        let index = self.list_state.offset() + (row - inner.y) as usize;
+        (index < self.rows.len()).then_some(index)
     }
 
     fn active_num(&self) -> Option<u32> {
@@ -101,21 +300,27 @@ impl App {
 
 // ── Entry point ───────────────────────────────────────────────────────────────
 
-pub fn run() -> Result<()> {
+pub fn run(no_color: bool) -> Result<()> {
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, crossterm::cursor::Hide)?;
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        crossterm::cursor::Hide
+    )?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let result = run_loop(&mut terminal);
+    let result = run_loop(&mut terminal, no_color);
 
     // Always restore terminal
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
+        DisableMouseCapture,
         crossterm::cursor::Show
     )?;
     terminal.show_cursor()?;
@@ -123,8 +328,11 @@ pub fn run() -> Result<()> {
     result
 }
 
-fn run_loop(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<()> {
+fn run_loop(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, no_color: bool) -> Result<()> {
     let mut app = App::new()?;
+    app.no_color = no_color;
+    let watcher = SequenceWatcher::start();
+    let mut changed_on_disk = false;
 
     loop {
         terminal.draw(|f| ui(f, &mut app))?;
@@ -140,25 +348,54 @@ fn run_loop(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<()>
             continue; // redraw immediately after returning
         }
 
-        if !event::poll(std::time::Duration::from_millis(250))? {
+        // Changes seen mid-dialog wait until the user is back in Normal mode.
+        changed_on_disk |= watcher.as_ref().is_some_and(SequenceWatcher::changed);
+        if changed_on_disk && matches!(app.mode, Mode::Normal) {
+            changed_on_disk = false;
+            if app.reload_if_changed()? {
+                app.flash = Some(Flash {
+                    message: "Reloaded".to_string(),
+                    is_error: false,
+                });
+            }
+        }
+
+        if !event::poll(Duration::from_millis(250))? {
             continue;
         }
 
-        if let Event::Key(key) = event::read()? {
-            // Ctrl+C always quits
-            if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
-                break;
+        let key = match event::read()? {
+            Event::Key(key) => key,
+            Event::Mouse(mouse) => {
+                if matches!(app.mode, Mode::Normal) {
+                    handle_mouse(&mut app, mouse)?;
+                }
+                continue;
             }
+            _ => continue,
+        };
+        // Ctrl+C always quits
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            break;
+        }
 
-            match &app.mode {
-                Mode::Normal => handle_normal(&mut app, key.code)?,
-                Mode::ConfirmSwitch { .. }
-                | Mode::ConfirmRemove { .. }
-                | Mode::ConfirmAdd { .. } => handle_confirm(&mut app, key.code)?,
-                Mode::ExpiredAccount { .. } => handle_expired(&mut app, key.code)?,
-                Mode::Done => {
-                    app.quit = true;
-                }
+        match &app.mode {
+            Mode::Normal => handle_normal(&mut app, key.code)?,
+            Mode::ConfirmSwitch { .. }
+            | Mode::ConfirmRemove { .. }
+            | Mode::ConfirmAdd { .. }
+            | Mode::ConfirmUndo { .. }
+            | Mode::ConfirmPin { .. }
+            | Mode::ConfirmBulkRemove { .. } => handle_confirm(&mut app, key.code)?,
+            Mode::MultiSelect { .. } => handle_multi_select(&mut app, key.code),
+            Mode::ExpiredAccount { .. } => handle_expired(&mut app, key.code)?,
+            Mode::Rename { .. } => handle_rename(&mut app, key.code)?,
+            Mode::Search { .. } => handle_search(&mut app, key.code),
+            Mode::Reorder { .. } => handle_reorder(&mut app, key.code)?,
+            Mode::SelectSwapTarget { .. } => handle_swap_target(&mut app, key.code)?,
+            Mode::Help => app.mode = Mode::Normal,
+            Mode::Done => {
+                app.quit = true;
             }
         }
     }
@@ -171,15 +408,17 @@ fn run_token_add(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mu
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
+        DisableMouseCapture,
         crossterm::cursor::Show
     )?;
 
-    let result = accounts::add();
+    let result = accounts::add(&accounts::AddOptions::default());
 
     enable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
         EnterAlternateScreen,
+        EnableMouseCapture,
         crossterm::cursor::Hide
     )?;
     terminal.clear()?;
@@ -199,33 +438,29 @@ fn run_token_add(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mu
 
 fn handle_normal(app: &mut App, key: KeyCode) -> Result<()> {
     match key {
-        KeyCode::Up | KeyCode::Char('k') => {
-            if app.selected > 0 {
-                app.selected -= 1;
-            }
+        KeyCode::Up | KeyCode::Char('k') if app.selected > 0 => {
+            app.selected -= 1;
         }
-        KeyCode::Down | KeyCode::Char('j') => {
-            if app.selected + 1 < app.seq.sequence.len() {
-                app.selected += 1;
-            }
+        KeyCode::Down | KeyCode::Char('j') if app.selected + 1 < app.rows.len() => {
+            app.selected += 1;
         }
         KeyCode::Enter | KeyCode::Char(' ') => {
             if let Some(num) = app.selected_num() {
-                if let Some(entry) = app.seq.accounts.get(&num.to_string()) {
-                    if app.active_num() == Some(num) {
-                        app.flash = Some(Flash {
-                            message: "Already the active account".to_string(),
-                            is_error: false,
-                        });
-                    } else {
-                        app.mode = Mode::ConfirmSwitch {
-                            num,
-                            email: entry.email.clone(),
-                        };
-                    }
-                }
+                request_switch(app, num)?;
             }
         }
+        KeyCode::Char('B') => match app.active_num() {
+            Some(active) if app.seq.sequence.len() >= 2 => {
+                let num = accounts::rotation_neighbor(&app.seq, active, true);
+                request_switch(app, num)?;
+            }
+            _ => {
+                app.flash = Some(Flash {
+                    message: "No previous account to switch to".to_string(),
+                    is_error: false,
+                });
+            }
+        },
         KeyCode::Char('a') => {
             if let Some(ref email) = app.current_email.clone() {
                 if app.seq.account_exists(email) {
@@ -289,6 +524,87 @@ fn handle_normal(app: &mut App, key: KeyCode) -> Result<()> {
                 }
             }
         }
+        KeyCode::Char('e') => {
+            if let Some(num) = app.selected_num() {
+                if let Some(entry) = app.seq.accounts.get(&num.to_string()) {
+                    app.mode = Mode::Rename {
+                        num,
+                        input: entry.email.clone(),
+                    };
+                }
+            }
+        }
+        KeyCode::Char('v') if !app.rows.is_empty() => {
+            app.flash = None;
+            app.mode = Mode::MultiSelect {
+                selected: HashSet::new(),
+            };
+        }
+        KeyCode::Char('u') => match accounts::undo_target(&app.seq) {
+            Ok(num) => {
+                let email = app.seq.accounts[&num.to_string()].email.clone();
+                app.mode = Mode::ConfirmUndo { num, email };
+            }
+            Err(e) => {
+                app.flash = Some(Flash {
+                    message: e.to_string(),
+                    is_error: true,
+                });
+            }
+        },
+        KeyCode::Char('m') | KeyCode::Char('x') if app.sort_order.is_some() => {
+            app.flash = Some(Flash {
+                message: "Press s until the list is in rotation order to move accounts".to_string(),
+                is_error: true,
+            });
+        }
+        KeyCode::Char('s') => app.cycle_sort()?,
+        KeyCode::Char('m') => {
+            if let Some(num) = app.selected_num() {
+                app.mode = Mode::Reorder {
+                    num,
+                    original: app.seq.sequence.clone(),
+                };
+            }
+        }
+        KeyCode::Char('x') if app.seq.sequence.len() >= 2 => {
+            if let Some(num) = app.selected_num() {
+                app.flash = None;
+                app.mode = Mode::SelectSwapTarget { num };
+            }
+        }
+        KeyCode::Char('p') => {
+            if let Some(num) = app.selected_num() {
+                if let Some(entry) = app.seq.accounts.get(&num.to_string()) {
+                    app.mode = Mode::ConfirmPin {
+                        num,
+                        email: entry.email.clone(),
+                    };
+                }
+            }
+        }
+        KeyCode::Char('/') => {
+            app.update_filter("");
+            app.mode = Mode::Search {
+                query: String::new(),
+            };
+        }
+        KeyCode::Char('#') => {
+            app.update_filter("#");
+            app.mode = Mode::Search {
+                query: "#".to_string(),
+            };
+        }
+        KeyCode::Char('R') => {
+            app.reload()?;
+            app.flash = Some(Flash {
+                message: "State reloaded".to_string(),
+                is_error: false,
+            });
+        }
+        KeyCode::Char('?') => {
+            app.mode = Mode::Help;
+        }
         KeyCode::Char('q') | KeyCode::Esc => {
             app.quit = true;
         }
@@ -297,46 +613,190 @@ fn handle_normal(app: &mut App, key: KeyCode) -> Result<()> {
     Ok(())
 }
 
+fn handle_mouse(app: &mut App, mouse: MouseEvent) -> Result<()> {
+    match mouse.kind {
+        MouseEventKind::ScrollUp if app.selected > 0 => {
+            app.selected -= 1;
+        }
+        MouseEventKind::ScrollDown if app.selected + 1 < app.rows.len() => {
+            app.selected += 1;
+        }
+        MouseEventKind::Down(MouseButton::Left) => {
+            let Some(index) = app.index_at(mouse.column, mouse.row) else {
+                return Ok(());
+            };
+            app.selected = index;
+            let double = app
+                .last_click
+                .is_some_and(|(at, i)| i == index && at.elapsed() <= DOUBLE_CLICK_INTERVAL);
+            if double {
+                app.last_click = None;
+                // Same path as pressing Enter on the selected row.
+                handle_normal(app, KeyCode::Enter)?;
+            } else {
+                app.last_click = Some((Instant::now(), index));
+            }
+        }
+        MouseEventKind::Down(MouseButton::Right) => {
+            let Some(index) = app.index_at(mouse.column, mouse.row) else {
+                return Ok(());
+            };
+            app.selected = index;
+            handle_normal(app, KeyCode::Delete)?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// The switch went through; surface a failed post-switch hook on the Done bar.
+fn flash_post_hook_error(app: &mut App, err: Option<String>) {
+    app.flash = err.map(|err| Flash {
+        message: format!("Post-switch hook failed: {}", err),
+        is_error: true,
+    });
+}
+
+/// Switch to `num`, asking first when `confirm_switch` is on.
+fn request_switch(app: &mut App, num: u32) -> Result<()> {
+    let Some(entry) = app.seq.accounts.get(&num.to_string()) else { return Ok(()) };
+    if app.active_num() == Some(num) {
+        app.flash = Some(Flash {
+            message: "Already the active account".to_string(),
+            is_error: false,
+        });
+    } else if app.confirm_switch {
+        app.mode = Mode::ConfirmSwitch {
+            num,
+            email: entry.email.clone(),
+        };
+    } else {
+        switch_to(app, num)?;
+    }
+    Ok(())
+}
+
+/// "→ next" or "← prev" when `num` is beside the active account in rotation.
+fn rotation_direction(app: &App, num: u32) -> Option<&'static str> {
+    let active = app.active_num()?;
+    if app.seq.sequence.len() < 2 || !app.seq.sequence.contains(&active) {
+        return None;
+    }
+    if accounts::rotation_neighbor(&app.seq, active, false) == num {
+        Some("→ next")
+    } else if accounts::rotation_neighbor(&app.seq, active, true) == num {
+        Some("← prev")
+    } else {
+        None
+    }
+}
+
+fn switch_to(app: &mut App, num: u32) -> Result<()> {
+    match accounts::core_switch(num) {
+        Ok(result) => {
+            app.reload()?;
+            app.mode = Mode::Done;
+            app.env_changed = result.env_changed;
+            flash_post_hook_error(app, result.post_hook_error);
+        }
+        Err(e) => {
+            app.flash = Some(Flash {
+                message: format!("Switch failed: {}", e),
+                is_error: true,
+            });
+        }
+    }
+    Ok(())
+}
+
 fn handle_confirm(app: &mut App, key: KeyCode) -> Result<()> {
     match key {
         KeyCode::Char('y') | KeyCode::Char('Y') => {
             // Clone the mode data out before mutating app
             let mode = std::mem::replace(&mut app.mode, Mode::Normal);
             match mode {
-                Mode::ConfirmSwitch { num, email } => {
-                    match accounts::core_switch(num) {
+                Mode::ConfirmSwitch { num, .. } => switch_to(app, num)?,
+                Mode::ConfirmRemove { num, email } => {
+                    match accounts::core_remove(num, &email) {
                         Ok(_) => {
                             app.reload()?;
-                            app.mode = Mode::Done;
+                            app.flash = Some(Flash {
+                                message: format!("Removed Account {} ({})", num, email),
+                                is_error: false,
+                            });
                         }
                         Err(e) => {
                             app.flash = Some(Flash {
-                                message: format!("Switch failed: {}", e),
+                                message: format!("Remove failed: {}", e),
                                 is_error: true,
                             });
-                            let _ = email; // suppress warning
                         }
                     }
                 }
-                Mode::ConfirmRemove { num, email } => {
-                    match accounts::core_remove(num, &email) {
-                        Ok(_) => {
+                Mode::ConfirmBulkRemove { accounts } => {
+                    let mut failed = Vec::new();
+                    for (num, email) in &accounts {
+                        if let Err(e) = accounts::core_remove(*num, email) {
+                            failed.push(format!("{} ({})", num, e));
+                        }
+                    }
+                    app.reload()?;
+                    let removed = accounts.len() - failed.len();
+                    app.flash = Some(if failed.is_empty() {
+                        Flash {
+                            message: format!("Removed {} account(s)", removed),
+                            is_error: false,
+                        }
+                    } else {
+                        Flash {
+                            message: format!(
+                                "Removed {} account(s); failed: {}",
+                                removed,
+                                failed.join(", ")
+                            ),
+                            is_error: true,
+                        }
+                    });
+                }
+                Mode::ConfirmUndo { .. } => match accounts::core_undo() {
+                    Ok(result) => {
+                        app.reload()?;
+                        app.mode = Mode::Done;
+                        app.env_changed = result.env_changed;
+                        flash_post_hook_error(app, result.post_hook_error);
+                    }
+                    Err(e) => {
+                        app.reload()?;
+                        app.flash = Some(Flash {
+                            message: format!("Undo failed: {}", e),
+                            is_error: true,
+                        });
+                    }
+                },
+                Mode::ConfirmPin { num, .. } => {
+                    let result = if app.seq.pinned_account == Some(num) {
+                        accounts::core_unpin()
+                    } else {
+                        accounts::core_pin(num)
+                    };
+                    match result {
+                        Ok(msg) => {
                             app.reload()?;
                             app.flash = Some(Flash {
-                                message: format!("Removed Account {} ({})", num, email),
+                                message: msg,
                                 is_error: false,
                             });
                         }
                         Err(e) => {
                             app.flash = Some(Flash {
-                                message: format!("Remove failed: {}", e),
+                                message: format!("Pin failed: {}", e),
                                 is_error: true,
                             });
                         }
                     }
                 }
                 Mode::ConfirmAdd { email } => {
-                    match accounts::core_add() {
+                    match accounts::core_add(&accounts::AddOptions::default()) {
                         Ok(msg) => {
                             app.reload()?;
                             app.flash = Some(Flash {
@@ -368,6 +828,228 @@ fn handle_confirm(app: &mut App, key: KeyCode) -> Result<()> {
     Ok(())
 }
 
+fn handle_multi_select(app: &mut App, key: KeyCode) {
+    let active = app.active_num();
+    let highlighted = app.selected_num();
+    let Mode::MultiSelect { selected } = &mut app.mode else {
+        return;
+    };
+    match key {
+        KeyCode::Up | KeyCode::Char('k') if app.selected > 0 => {
+            app.selected -= 1;
+        }
+        KeyCode::Down | KeyCode::Char('j') if app.selected + 1 < app.rows.len() => {
+            app.selected += 1;
+        }
+        KeyCode::Char(' ') => {
+            let Some(num) = highlighted else { return };
+            if active == Some(num) {
+                app.flash = Some(Flash {
+                    message: "The active account can't be removed; switch away first".to_string(),
+                    is_error: true,
+                });
+            } else {
+                if !selected.remove(&num) {
+                    selected.insert(num);
+                }
+                app.flash = None;
+            }
+        }
+        KeyCode::Enter => {
+            if selected.is_empty() {
+                app.flash = Some(Flash {
+                    message: "Select accounts with Space first".to_string(),
+                    is_error: true,
+                });
+                return;
+            }
+            let accounts = app
+                .rows
+                .iter()
+                .filter(|n| selected.contains(n))
+                .filter_map(|n| Some((*n, app.seq.accounts.get(&n.to_string())?.email.clone())))
+                .collect();
+            app.mode = Mode::ConfirmBulkRemove { accounts };
+        }
+        KeyCode::Esc | KeyCode::Char('v') | KeyCode::Char('q') => {
+            app.mode = Mode::Normal;
+            app.flash = Some(Flash {
+                message: "Cancelled".to_string(),
+                is_error: false,
+            });
+        }
+        _ => {}
+    }
+}
+
+fn handle_rename(app: &mut App, key: KeyCode) -> Result<()> {
+    let Mode::Rename { num, input } = &mut app.mode else {
+        return Ok(());
+    };
+    match key {
+        KeyCode::Char(c) => input.push(c),
+        KeyCode::Backspace => {
+            input.pop();
+        }
+        KeyCode::Enter => {
+            let (num, label) = (*num, input.clone());
+            app.mode = Mode::Normal;
+            match accounts::core_rename(num, &label) {
+                Ok(msg) => {
+                    app.reload()?;
+                    app.flash = Some(Flash {
+                        message: msg,
+                        is_error: false,
+                    });
+                }
+                Err(e) => {
+                    app.flash = Some(Flash {
+                        message: format!("Rename failed: {}", e),
+                        is_error: true,
+                    });
+                }
+            }
+        }
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+            app.flash = Some(Flash {
+                message: "Cancelled".to_string(),
+                is_error: false,
+            });
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn handle_search(app: &mut App, key: KeyCode) {
+    let Mode::Search { query } = &mut app.mode else {
+        return;
+    };
+    match key {
+        KeyCode::Char(c) => {
+            query.push(c);
+            let query = query.clone();
+            app.update_filter(&query);
+        }
+        KeyCode::Backspace => {
+            query.pop();
+            let query = query.clone();
+            app.update_filter(&query);
+        }
+        KeyCode::Enter => {
+            match app.filtered_indices.first() {
+                Some(&i) => app.selected = i,
+                None => {
+                    app.flash = Some(Flash {
+                        message: format!("No account matches '{}'", query),
+                        is_error: true,
+                    });
+                }
+            }
+            app.mode = Mode::Normal;
+            app.filtered_indices.clear();
+        }
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+            app.filtered_indices.clear();
+        }
+        _ => {}
+    }
+}
+
+fn handle_swap_target(app: &mut App, key: KeyCode) -> Result<()> {
+    let Mode::SelectSwapTarget { num } = app.mode else {
+        return Ok(());
+    };
+    match key {
+        KeyCode::Up | KeyCode::Char('k') if app.selected > 0 => app.selected -= 1,
+        KeyCode::Down | KeyCode::Char('j') if app.selected + 1 < app.rows.len() => {
+            app.selected += 1;
+        }
+        KeyCode::Enter => {
+            let Some(target) = app.selected_num() else { return Ok(()) };
+            app.mode = Mode::Normal;
+            let result = accounts::core_swap(num, target);
+            app.reload()?;
+            app.flash = Some(match result {
+                Ok(msg) => Flash {
+                    message: msg,
+                    is_error: false,
+                },
+                Err(e) => Flash {
+                    message: format!("Swap failed: {}", e),
+                    is_error: true,
+                },
+            });
+        }
+        KeyCode::Esc => {
+            app.mode = Mode::Normal;
+            app.flash = Some(Flash {
+                message: "Cancelled".to_string(),
+                is_error: false,
+            });
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn handle_reorder(app: &mut App, key: KeyCode) -> Result<()> {
+    let Mode::Reorder { num, original } = &app.mode else {
+        return Ok(());
+    };
+    let num = *num;
+    match key {
+        // Shift+↑/↓ arrive as plain Up/Down, so they work here too.
+        KeyCode::Up | KeyCode::Char('k') | KeyCode::Char('K') if app.selected > 0 => {
+            app.seq.sequence.swap(app.selected, app.selected - 1);
+            app.refresh_rows();
+            app.selected -= 1;
+        }
+        KeyCode::Down | KeyCode::Char('j') | KeyCode::Char('J')
+            if app.selected + 1 < app.rows.len() =>
+        {
+            app.seq.sequence.swap(app.selected, app.selected + 1);
+            app.refresh_rows();
+            app.selected += 1;
+        }
+        KeyCode::Enter => {
+            let unchanged = app.seq.sequence == *original;
+            app.mode = Mode::Normal;
+            if unchanged {
+                return Ok(());
+            }
+            let result = accounts::core_move(num, app.selected + 1);
+            app.reload()?;
+            app.flash = Some(match result {
+                Ok(msg) => Flash {
+                    message: msg,
+                    is_error: false,
+                },
+                Err(e) => Flash {
+                    message: format!("Move failed: {}", e),
+                    is_error: true,
+                },
+            });
+        }
+        KeyCode::Esc => {
+            app.seq.sequence = original.clone();
+            app.refresh_rows();
+            if let Some(i) = app.rows.iter().position(|&n| n == num) {
+                app.selected = i;
+            }
+            app.mode = Mode::Normal;
+            app.flash = Some(Flash {
+                message: "Cancelled".to_string(),
+                is_error: false,
+            });
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
 fn handle_expired(app: &mut App, key: KeyCode) -> Result<()> {
     match key {
         KeyCode::Char('d') | KeyCode::Delete => {
@@ -405,17 +1087,29 @@ fn ui(f: &mut ratatui::Frame, app: &mut App) {
         .split(area);
 
     render_header(f, app, chunks[0]);
-    render_list(f, app, chunks[1]);
+    if area.width >= DETAIL_MIN_WIDTH {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(chunks[1]);
+        render_list(f, app, columns[0]);
+        render_detail(f, app, columns[1]);
+    } else {
+        render_list(f, app, chunks[1]);
+    }
     render_help(f, app, chunks[2]);
 
     // Overlay confirmation dialog if needed
     match &app.mode {
         Mode::ConfirmSwitch { num, email } => {
+            let direction = rotation_direction(app, *num)
+                .map(|d| format!("  {d}"))
+                .unwrap_or_default();
             render_confirm_dialog(
                 f,
                 area,
                 "Switch Account",
-                &format!("Switch to Account {}?", num),
+                &format!("Switch to Account {}?{}", num, direction),
                 email,
                 Color::Yellow,
             );
@@ -440,11 +1134,48 @@ fn ui(f: &mut ratatui::Frame, app: &mut App) {
                 Color::Yellow,
             );
         }
+        Mode::ConfirmUndo { num, email } => {
+            render_confirm_dialog(
+                f,
+                area,
+                "Undo Switch",
+                &format!("Switch back to Account {}?", num),
+                email,
+                Color::Yellow,
+            );
+        }
+        Mode::ConfirmPin { num, email } => {
+            let (title, question) = if app.seq.pinned_account == Some(*num) {
+                ("Unpin Account", format!("Unpin Account {}?", num))
+            } else {
+                ("Pin Account", format!("Pin Account {} as the default?", num))
+            };
+            render_confirm_dialog(f, area, title, &question, email, Color::Blue);
+        }
         Mode::ExpiredAccount { num, email } => {
             render_expired_dialog(f, area, *num, email);
         }
+        Mode::Rename { num, input } => {
+            render_rename_dialog(f, area, *num, input);
+        }
+        Mode::ConfirmBulkRemove { accounts } => {
+            render_bulk_remove_dialog(f, area, accounts);
+        }
+        Mode::Help => render_keybindings(f, area),
         _ => {}
     }
+
+    if app.no_color {
+        strip_colors(f.buffer_mut());
+    }
+}
+
+/// Reset every cell to the default colors, keeping bold/reverse etc. so the
+/// selection and highlights stay visible.
+pub(crate) fn strip_colors(buf: &mut ratatui::buffer::Buffer) {
+    for cell in buf.content.iter_mut() {
+        cell.set_fg(Color::Reset).set_bg(Color::Reset);
+    }
 }
 
 fn render_header(f: &mut ratatui::Frame, app: &App, area: Rect) {
@@ -454,7 +1185,7 @@ fn render_header(f: &mut ratatui::Frame, app: &App, area: Rect) {
         .unwrap_or("not logged in");
 
     let block = Block::default()
-        .title(" ccswitch ")
+        .title(format!(" ccswitch · {} ", app.profile))
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded)
         .border_style(Style::default().fg(Color::Cyan));
@@ -474,12 +1205,25 @@ fn render_header(f: &mut ratatui::Frame, app: &App, area: Rect) {
 }
 
 fn render_list(f: &mut ratatui::Frame, app: &mut App, area: Rect) {
-    let account_count = app.seq.sequence.len();
-    let title = if account_count == 1 {
-        " 1 account ".to_string()
-    } else {
-        format!(" {} accounts ", account_count)
+    app.list_area = area;
+    let query = match &app.mode {
+        Mode::Search { query } => Some(query.as_str()),
+        _ => None,
+    };
+    let (email_query, tag_query) = match query.map(|q| (q, q.strip_prefix('#'))) {
+        Some((_, Some(tag))) => (None, Some(tag)),
+        Some((q, None)) => (Some(q), None),
+        None => (None, None),
+    };
+    let account_count = app.rows.len();
+    let mut title = match query {
+        Some(_) => format!(" {} of {} accounts ", app.filtered_indices.len(), account_count),
+        None if account_count == 1 => " 1 account ".to_string(),
+        None => format!(" {} accounts ", account_count),
     };
+    if let Some(order) = app.sort_order {
+        title.push_str(&format!("· sorted by {order} "));
+    }
 
     let block = Block::default()
         .title(title)
@@ -487,7 +1231,7 @@ fn render_list(f: &mut ratatui::Frame, app: &mut App, area: Rect) {
         .border_type(BorderType::Rounded)
         .border_style(Style::default().fg(Color::DarkGray));
 
-    if app.seq.sequence.is_empty() {
+    if app.rows.is_empty() {
         let text = Paragraph::new(Line::from(vec![Span::styled(
             "  No accounts managed yet. Press [a] to add the current account.",
             Style::default().fg(Color::DarkGray),
@@ -498,12 +1242,23 @@ fn render_list(f: &mut ratatui::Frame, app: &mut App, area: Rect) {
     }
 
     let active_num = app.active_num();
+    let marked = match &app.mode {
+        Mode::MultiSelect { selected } => Some(selected),
+        _ => None,
+    };
+    let swap_source = match app.mode {
+        Mode::SelectSwapTarget { num } => Some(num),
+        _ => None,
+    };
+    let indices: Vec<usize> = match query {
+        Some(_) => app.filtered_indices.clone(),
+        None => (0..account_count).collect(),
+    };
 
-    let items: Vec<ListItem> = app
-        .seq
-        .sequence
+    let items: Vec<ListItem> = indices
         .iter()
-        .map(|&num| {
+        .map(|&i| {
+            let num = app.rows[i];
             let entry = match app.seq.accounts.get(&num.to_string()) {
                 Some(e) => e,
                 None => return ListItem::new(""),
@@ -511,45 +1266,74 @@ fn render_list(f: &mut ratatui::Frame, app: &mut App, area: Rect) {
 
             let is_active = active_num == Some(num);
             let is_token = entry.auth_kind == AuthKind::Token;
+            let is_pinned = app.seq.pinned_account == Some(num);
+            let checkbox = marked.map(|selected| {
+                if selected.contains(&num) {
+                    Span::styled("  [✓]", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
+                } else {
+                    Span::styled("  [ ]", Style::default().fg(Color::DarkGray))
+                }
+            });
 
-            if is_active {
-                let mut spans = vec![
-                    Span::styled(
-                        format!("  ▶  {:>2}  ", num),
+            let mut line = if is_active {
+                let mut spans = vec![Span::styled(
+                    format!("  ▶  {:>2}  ", num),
+                    Style::default()
+                        .fg(Color::Green)
+                        .add_modifier(Modifier::BOLD),
+                )];
+                spans.extend(email_spans(
+                    &entry.email,
+                    email_query,
+                    Style::default()
+                        .fg(Color::Green)
+                        .add_modifier(Modifier::BOLD),
+                ));
+                if is_token {
+                    spans.push(Span::styled(
+                        "  [token]",
                         Style::default()
                             .fg(Color::Green)
-                            .add_modifier(Modifier::BOLD),
-                    ),
-                    Span::styled(
-                        entry.email.clone(),
+                            .add_modifier(Modifier::DIM),
+                    ));
+                }
+                if is_pinned {
+                    spans.push(Span::styled("  [pin]", Style::default().fg(Color::Blue)));
+                }
+                if entry.priority > 0 {
+                    spans.push(Span::styled(
+                        format!("  [P:{}]", entry.priority),
                         Style::default()
                             .fg(Color::Green)
-                            .add_modifier(Modifier::BOLD),
-                    ),
-                ];
-                if is_token {
+                            .add_modifier(Modifier::DIM),
+                    ));
+                }
+                if entry.switch_count > 0 {
                     spans.push(Span::styled(
-                        "  [token]",
+                        format!("  [{}×]", entry.switch_count),
                         Style::default()
                             .fg(Color::Green)
                             .add_modifier(Modifier::DIM),
                     ));
                 }
+                spans.extend(tag_spans(&entry.tags, tag_query));
                 spans.push(Span::styled(
                     "  active",
                     Style::default()
                         .fg(Color::Green)
                         .add_modifier(Modifier::DIM),
                 ));
-                ListItem::new(Line::from(spans))
+                spans
             } else {
-                let mut spans = vec![
-                    Span::styled(
-                        format!("     {:>2}  ", num),
-                        Style::default().fg(Color::DarkGray),
-                    ),
-                    Span::styled(entry.email.clone(), Style::default().fg(Color::White)),
-                ];
+                let mut spans = vec![Span::styled(
+                    format!("     {:>2}  ", num),
+                    Style::default().fg(Color::DarkGray),
+                )];
+                spans.extend(email_spans(
+                    &entry.email,
+                    email_query,
+                    Style::default().fg(Color::White),
+                ));
                 if is_token {
                     spans.push(Span::styled(
                         "  [token]",
@@ -558,8 +1342,38 @@ fn render_list(f: &mut ratatui::Frame, app: &mut App, area: Rect) {
                             .add_modifier(Modifier::DIM),
                     ));
                 }
-                ListItem::new(Line::from(spans))
+                if is_pinned {
+                    spans.push(Span::styled("  [pin]", Style::default().fg(Color::Blue)));
+                }
+                if entry.priority > 0 {
+                    spans.push(Span::styled(
+                        format!("  [P:{}]", entry.priority),
+                        Style::default()
+                            .fg(Color::DarkGray)
+                            .add_modifier(Modifier::DIM),
+                    ));
+                }
+                if entry.switch_count > 0 {
+                    spans.push(Span::styled(
+                        format!("  [{}×]", entry.switch_count),
+                        Style::default()
+                            .fg(Color::DarkGray)
+                            .add_modifier(Modifier::DIM),
+                    ));
+                }
+                spans.extend(tag_spans(&entry.tags, tag_query));
+                spans
+            };
+            if let Some(checkbox) = checkbox {
+                line.insert(0, checkbox);
+            }
+            if swap_source == Some(num) {
+                line.push(Span::styled(
+                    "  ⇄ swapping",
+                    Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                ));
             }
+            ListItem::new(Line::from(line))
         })
         .collect();
 
@@ -572,10 +1386,139 @@ fn render_list(f: &mut ratatui::Frame, app: &mut App, area: Rect) {
         )
         .highlight_symbol("");
 
-    let mut list_state = ListState::default();
-    list_state.select(Some(app.selected));
+    match query {
+        // While searching, the highlight marks the match that Enter jumps to.
+        Some(_) => {
+            let mut list_state = ListState::default();
+            list_state.select((!indices.is_empty()).then_some(0));
+            f.render_stateful_widget(list, area, &mut list_state);
+        }
+        // The persistent state scrolls the list to keep the selection visible.
+        None => {
+            app.list_state.select(Some(app.selected));
+            f.render_stateful_widget(list, area, &mut app.list_state);
+        }
+    }
+}
+
+/// Everything stored for the highlighted account.
+fn render_detail(f: &mut ratatui::Frame, app: &App, area: Rect) {
+    let block = Block::default()
+        .title(" Account Detail ")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::DarkGray));
+
+    let Some((num, entry)) = app
+        .selected_num()
+        .and_then(|num| Some((num, app.seq.accounts.get(&num.to_string())?)))
+    else {
+        f.render_widget(block, area);
+        return;
+    };
+
+    let now = chrono::Utc::now();
+    let when = |ts: &str| match accounts::relative_time(ts, now) {
+        Some(ago) => format!("{ts} ({ago})"),
+        None => ts.to_string(),
+    };
+    let mut aliases: Vec<&str> = app
+        .seq
+        .aliases
+        .iter()
+        .filter(|(_, &n)| n == num)
+        .map(|(name, _)| name.as_str())
+        .collect();
+    aliases.sort();
+    let list_or_none = |items: Vec<String>| {
+        if items.is_empty() {
+            "—".to_string()
+        } else {
+            items.join(", ")
+        }
+    };
+
+    let rows = [
+        ("Account", num.to_string()),
+        ("Email", entry.email.clone()),
+        (
+            "Auth",
+            match entry.auth_kind {
+                AuthKind::Oauth => "OAuth".to_string(),
+                AuthKind::Token => "token".to_string(),
+            },
+        ),
+        ("UUID", if entry.uuid.is_empty() { "—".to_string() } else { entry.uuid.clone() }),
+        ("Added", when(&entry.added)),
+        ("Last used", entry.last_used.as_deref().map(when).unwrap_or_else(|| "never".to_string())),
+        ("Switches", entry.switch_count.to_string()),
+        ("Priority", entry.priority.to_string()),
+        ("Aliases", list_or_none(aliases.into_iter().map(String::from).collect())),
+        ("Tags", list_or_none(entry.tags.clone())),
+        ("Notes", entry.notes.clone().unwrap_or_else(|| "—".to_string())),
+    ];
+    let lines: Vec<Line> = rows
+        .into_iter()
+        .map(|(label, value)| {
+            Line::from(vec![
+                Span::styled(format!("  {label:<10} "), Style::default().fg(Color::DarkGray)),
+                Span::styled(value, Style::default().fg(Color::White)),
+            ])
+        })
+        .collect();
+
+    f.render_widget(Paragraph::new(lines).block(block).wrap(Wrap { trim: false }), area);
+}
+
+/// `[tag]` badges; tags matching an active `#tag` filter are highlighted.
+fn tag_spans(tags: &[String], tag_query: Option<&str>) -> Vec<Span<'static>> {
+    tags.iter()
+        .map(|t| {
+            let matched = tag_query.is_some_and(|q| !q.is_empty() && t.contains(&q.to_lowercase()));
+            let style = if matched {
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::UNDERLINED)
+            } else {
+                Style::default().fg(Color::Cyan)
+            };
+            Span::styled(format!("  [{}]", t), style)
+        })
+        .collect()
+}
 
-    f.render_stateful_widget(list, area, &mut list_state);
+/// Byte range in `text` of the first case-insensitive match of `query`.
+/// Compares char by char, so the offsets always fall on `text`'s own char
+/// boundaries even where lowercasing changes a char's length.
+fn find_ignore_case(text: &str, query: &str) -> Option<(usize, usize)> {
+    let same = |a: char, b: char| a.to_lowercase().eq(b.to_lowercase());
+    text.char_indices().find_map(|(start, _)| {
+        let mut rest = text[start..].char_indices();
+        for q in query.chars() {
+            match rest.next() {
+                Some((_, c)) if same(c, q) => {}
+                _ => return None,
+            }
+        }
+        let end = rest.next().map_or(text.len(), |(i, _)| start + i);
+        Some((start, end))
+    })
+}
+
+/// Split `email` into spans, highlighting the first case-insensitive match of `query`.
+fn email_spans(email: &str, query: Option<&str>, style: Style) -> Vec<Span<'static>> {
+    let range = query
+        .filter(|q| !q.is_empty())
+        .and_then(|q| find_ignore_case(email, q));
+    let Some((start, end)) = range else {
+        return vec![Span::styled(email.to_string(), style)];
+    };
+    vec![
+        Span::styled(email[..start].to_string(), style),
+        Span::styled(
+            email[start..end].to_string(),
+            style.fg(Color::Yellow).add_modifier(Modifier::UNDERLINED),
+        ),
+        Span::styled(email[end..].to_string(), style),
+    ]
 }
 
 fn render_help(f: &mut ratatui::Frame, app: &App, area: Rect) {
@@ -586,35 +1529,81 @@ fn render_help(f: &mut ratatui::Frame, app: &App, area: Rect) {
                 .border_type(BorderType::Rounded)
                 .border_style(Style::default().fg(Color::Green));
 
-            let spans = vec![
-                Span::styled(
-                    "  ✓ Done  ·  ",
+            let mut spans = vec![Span::styled(
+                "  ✓ Done  ·  ",
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            )];
+            if !app.suppress_restart_hint {
+                spans.push(Span::styled(
+                    "Restart Claude Code",
                     Style::default()
-                        .fg(Color::Green)
+                        .fg(Color::Yellow)
                         .add_modifier(Modifier::BOLD),
-                ),
-                Span::styled(
-                    "Restart Claude Code",
+                ));
+                spans.push(Span::styled("  ·  ", Style::default().fg(Color::Green)));
+            }
+            if app.env_changed {
+                spans.push(Span::styled(
+                    "Open a new shell for env vars",
                     Style::default()
                         .fg(Color::Yellow)
                         .add_modifier(Modifier::BOLD),
-                ),
-                Span::styled(
-                    "  ·  [any key] quit",
-                    Style::default().fg(Color::Green),
-                ),
-            ];
+                ));
+                spans.push(Span::styled("  ·  ", Style::default().fg(Color::Green)));
+            }
+            spans.push(Span::styled(
+                "[any key] quit",
+                Style::default().fg(Color::Green),
+            ));
+            if let Some(flash) = app.flash.as_ref().filter(|f| f.is_error) {
+                spans.push(Span::styled(
+                    format!("  ·  ✗ {}", flash.message),
+                    Style::default().fg(Color::Red),
+                ));
+            }
 
             let text = Paragraph::new(Line::from(spans)).block(block);
             f.render_widget(text, area);
         }
         _ => {
-            let block = Block::default()
+            let mut block = Block::default()
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded)
                 .border_style(Style::default().fg(Color::DarkGray));
 
-            let content = if let Some(flash) = &app.flash {
+            // Show the highlighted account's note on the help bar border
+            if matches!(app.mode, Mode::Normal) {
+                if let Some(note) = app.selected_note() {
+                    block = block.title(Span::styled(
+                        format!(" ✎ {} ", note),
+                        Style::default().fg(Color::Cyan),
+                    ));
+                }
+            }
+
+            let content = if let Mode::Reorder { num, .. } = &app.mode {
+                Line::from(vec![Span::styled(
+                    format!("  Moving Account {}  ·  ↑↓ / K J move  ·  ↵ save  ·  Esc cancel", num),
+                    Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                )])
+            } else if let Mode::SelectSwapTarget { num } = &app.mode {
+                Line::from(vec![Span::styled(
+                    format!("  Swap Account {} with…  ·  ↑↓ pick  ·  ↵ swap  ·  Esc cancel", num),
+                    Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                )])
+            } else if let Mode::Search { query } = &app.mode {
+                Line::from(vec![
+                    Span::styled("  / ", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+                    Span::styled(query.clone(), Style::default().fg(Color::Yellow)),
+                    Span::styled("▏", Style::default().fg(Color::Yellow)),
+                    Span::styled(
+                        format!("   {} match(es)  ·  ↵ jump  ·  Esc cancel", app.filtered_indices.len()),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ])
+            } else if let Some(flash) = &app.flash {
                 let color = if flash.is_error {
                     Color::Red
                 } else {
@@ -628,9 +1617,17 @@ fn render_help(f: &mut ratatui::Frame, app: &App, area: Rect) {
                     ),
                     Span::styled(flash.message.clone(), Style::default().fg(color)),
                 ])
+            } else if let Mode::MultiSelect { selected } = &app.mode {
+                Line::from(vec![Span::styled(
+                    format!(
+                        "  {} selected  ·  ↑↓ nav  ·  Space toggle  ·  ↵ remove selected  ·  Esc cancel",
+                        selected.len()
+                    ),
+                    Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                )])
             } else {
                 Line::from(vec![Span::styled(
-                    "  ↑↓ nav  ·  ↵ switch  ·  a add  ·  d remove  ·  r refresh  ·  q quit",
+                    "  ↑↓ nav  ·  ↵ switch  ·  B prev  ·  / search  ·  # tag  ·  m move  ·  x swap  ·  s sort  ·  p pin  ·  u undo  ·  a add  ·  d remove  ·  e rename  ·  v select  ·  r refresh  ·  R reload  ·  ? help  ·  q quit",
                     Style::default().fg(Color::DarkGray),
                 )])
             };
@@ -641,6 +1638,42 @@ fn render_help(f: &mut ratatui::Frame, app: &App, area: Rect) {
     }
 }
 
+fn render_keybindings(f: &mut ratatui::Frame, area: Rect) {
+    let key_width = KEYBINDINGS.iter().map(|(k, _)| k.chars().count()).max().unwrap_or(0);
+    let mut lines = vec![Line::from("")];
+    lines.extend(KEYBINDINGS.iter().map(|(key, action)| {
+        Line::from(vec![
+            Span::styled(
+                format!("   {key:<key_width$}   "),
+                Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(*action, Style::default().fg(Color::White)),
+        ])
+    }));
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "   Press any key to close",
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    let width = (area.width.saturating_sub(4)).min(64);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let dialog_area = Rect {
+        x: area.x + area.width.saturating_sub(width) / 2,
+        y: area.y + area.height.saturating_sub(height) / 2,
+        width,
+        height,
+    };
+
+    f.render_widget(Clear, dialog_area);
+    let block = Block::default()
+        .title(" Keyboard shortcuts ")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Cyan));
+    f.render_widget(Paragraph::new(lines).block(block), dialog_area);
+}
+
 fn render_confirm_dialog(
     f: &mut ratatui::Frame,
     area: Rect,
@@ -704,6 +1737,117 @@ fn render_confirm_dialog(
     f.render_widget(para, inner);
 }
 
+fn render_bulk_remove_dialog(f: &mut ratatui::Frame, area: Rect, accounts: &[(u32, String)]) {
+    let dialog_width = 54u16;
+    let dialog_height = (accounts.len() as u16 + 6).min(area.height);
+
+    let x = area.x + area.width.saturating_sub(dialog_width) / 2;
+    let y = area.y + area.height.saturating_sub(dialog_height) / 2;
+
+    let dialog_area = Rect {
+        x,
+        y,
+        width: dialog_width.min(area.width),
+        height: dialog_height,
+    };
+
+    f.render_widget(Clear, dialog_area);
+
+    let block = Block::default()
+        .title(" Remove Accounts ")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Red));
+
+    let inner = block.inner(dialog_area);
+    f.render_widget(block, dialog_area);
+
+    let mut text = vec![
+        Line::from(""),
+        Line::from(vec![Span::styled(
+            format!("   Remove {} account(s)?", accounts.len()),
+            Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        )]),
+    ];
+    text.extend(accounts.iter().map(|(num, email)| {
+        Line::from(vec![Span::styled(
+            format!("   {:>2}  {}", num, email),
+            Style::default().fg(Color::Yellow),
+        )])
+    }));
+    text.push(Line::from(""));
+    text.push(Line::from(vec![
+        Span::styled(
+            "   [y] confirm",
+            Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            "      [n / Esc] cancel",
+            Style::default().fg(Color::DarkGray),
+        ),
+    ]));
+
+    let para = Paragraph::new(text).alignment(Alignment::Left);
+    f.render_widget(para, inner);
+}
+
+fn render_rename_dialog(f: &mut ratatui::Frame, area: Rect, num: u32, input: &str) {
+    let dialog_width = 54u16;
+    let dialog_height = 7u16;
+
+    let x = area.x + area.width.saturating_sub(dialog_width) / 2;
+    let y = area.y + area.height.saturating_sub(dialog_height) / 2;
+
+    let dialog_area = Rect {
+        x,
+        y,
+        width: dialog_width.min(area.width),
+        height: dialog_height.min(area.height),
+    };
+
+    f.render_widget(Clear, dialog_area);
+
+    let block = Block::default()
+        .title(" Rename Account ")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Cyan));
+
+    let inner = block.inner(dialog_area);
+    f.render_widget(block, dialog_area);
+
+    let text = vec![
+        Line::from(""),
+        Line::from(vec![Span::styled(
+            format!("   New label for Account {}:", num),
+            Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        )]),
+        Line::from(vec![
+            Span::styled(format!("   {}", input), Style::default().fg(Color::Yellow)),
+            Span::styled("▏", Style::default().fg(Color::Yellow)),
+        ]),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled(
+                "   [↵] save",
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::styled("      [Esc] cancel", Style::default().fg(Color::DarkGray)),
+        ]),
+    ];
+
+    let para = Paragraph::new(text).alignment(Alignment::Left);
+    f.render_widget(para, inner);
+}
+
 fn render_expired_dialog(f: &mut ratatui::Frame, area: Rect, num: u32, email: &str) {
     let dialog_width = 66u16;
     let dialog_height = 10u16;
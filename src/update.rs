@@ -169,6 +169,12 @@ fn asset_name() -> Result<String> {
         crate::platform::Platform::Linux | crate::platform::Platform::Wsl => {
             "ccswitch-x86_64-unknown-linux-gnu.tar.gz"
         }
+        crate::platform::Platform::Windows => {
+            anyhow::bail!(
+                "No prebuilt Windows release; reinstall with \
+                 `cargo install --git https://github.com/vyshnavsdeepak/ccswitch`"
+            )
+        }
     };
     Ok(name.to_owned())
 }
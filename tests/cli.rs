@@ -0,0 +1,286 @@
+use std::process::Command;
+
+fn ccswitch(home: &std::path::Path) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_ccswitch"));
+    cmd.env("CCSWITCH_HOME", home)
+        // Ask `colored` to emit escapes even though stdout is a pipe.
+        .env("CLICOLOR_FORCE", "1")
+        .env_remove("NO_COLOR");
+    cmd
+}
+
+#[test]
+fn list_with_no_color_prints_no_ansi_escapes() {
+    let home = tempfile::TempDir::new().unwrap();
+
+    for args in [&["list"][..], &["--no-color", "list"][..]] {
+        let mut cmd = ccswitch(home.path());
+        if args.len() == 1 {
+            cmd.env("NO_COLOR", "1");
+        }
+        let output = cmd.args(args).output().unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("No accounts managed yet"), "{args:?}: {stdout}");
+        assert!(!stdout.contains('\x1b'), "{args:?} printed ANSI escapes: {stdout:?}");
+    }
+}
+
+#[test]
+fn quiet_suppresses_output_but_keeps_errors() {
+    let home = tempfile::TempDir::new().unwrap();
+
+    let output = ccswitch(home.path()).args(["-q", "list"]).output().unwrap();
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty(), "{:?}", String::from_utf8_lossy(&output.stdout));
+
+    let output = ccswitch(home.path()).args(["--quiet", "switch", "7"]).output().unwrap();
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(!output.stderr.is_empty());
+
+    let output = ccswitch(home.path()).arg("--quiet").output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--quiet"));
+}
+
+#[test]
+fn add_from_env_needs_no_input() {
+    let home = tempfile::TempDir::new().unwrap();
+
+    let output = ccswitch(home.path())
+        .args(["-q", "add", "--from-env", "--label", "ci-token", "--no-prompt"])
+        .env("CLAUDE_CODE_OAUTH_TOKEN", "sk-ant-oat01-ci")
+        .stdin(std::process::Stdio::null())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(output.stdout.is_empty());
+
+    let output = ccswitch(home.path()).args(["list", "--porcelain"]).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\tci-token\ttoken\t1\n");
+
+    // The same token again fails instead of asking for another one.
+    let output = ccswitch(home.path())
+        .args(["add", "--from-env", "--no-prompt"])
+        .env("CLAUDE_CODE_OAUTH_TOKEN", "sk-ant-oat01-ci")
+        .stdin(std::process::Stdio::null())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("already managed"));
+
+    // --force makes re-runs succeed and replaces the token of an existing label.
+    for token in ["sk-ant-oat01-ci", "sk-ant-oat01-rotated"] {
+        let output = ccswitch(home.path())
+            .args(["-q", "add", "--from-env", "--label", "ci-token", "--no-prompt", "--force"])
+            .env("CLAUDE_CODE_OAUTH_TOKEN", token)
+            .stdin(std::process::Stdio::null())
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    }
+    let output = ccswitch(home.path()).args(["list", "--porcelain"]).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\tci-token\ttoken\t1\n");
+    let output = ccswitch(home.path()).args(["env", "--print"]).output().unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).contains("sk-ant-oat01-rotated"));
+
+    let output = ccswitch(home.path())
+        .args(["add", "--from-env"])
+        .env_remove("CLAUDE_CODE_OAUTH_TOKEN")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--from-env"));
+}
+
+#[test]
+fn add_rejects_label_for_oauth_accounts() {
+    let home = tempfile::TempDir::new().unwrap();
+    std::fs::write(
+        home.path().join(".claude.json"),
+        r#"{"oauthAccount":{"emailAddress":"me@example.com","accountUuid":"u"}}"#,
+    )
+    .unwrap();
+
+    let output = ccswitch(home.path())
+        .args(["add", "--label", "work"])
+        .env_remove("CLAUDE_CODE_OAUTH_TOKEN")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--label only applies"));
+}
+
+#[test]
+fn list_table_renders_box() {
+    let home = tempfile::TempDir::new().unwrap();
+    let output = ccswitch(home.path())
+        .args(["-q", "add", "--from-env", "--label", "ci@example.com", "--no-prompt"])
+        .env("CLAUDE_CODE_OAUTH_TOKEN", "sk-ant-oat01-table")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let output = ccswitch(home.path())
+        .args(["--no-color", "list", "--table"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("| #   | Email          | Type  | Added"), "{stdout}");
+    assert!(stdout.contains("| ▶ 1 | ci@example.com | token |"), "{stdout}");
+}
+
+#[test]
+fn list_oneline_prints_active_account_for_prompts() {
+    let home = tempfile::TempDir::new().unwrap();
+    let output = ccswitch(home.path()).args(["list", "--oneline"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+
+    let output = ccswitch(home.path())
+        .args(["-q", "add", "--from-env", "--label", "ci@example.com", "--no-prompt"])
+        .env("CLAUDE_CODE_OAUTH_TOKEN", "sk-ant-oat01-prompt")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let output = ccswitch(home.path()).args(["list", "--oneline"]).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ci@example.com [token]");
+}
+
+#[test]
+fn list_active_only_json_is_one_object() {
+    let home = tempfile::TempDir::new().unwrap();
+    let output = ccswitch(home.path()).args(["list", "--active-only", "--json"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+
+    let output = ccswitch(home.path())
+        .args(["-q", "add", "--from-env", "--label", "ci@example.com", "--no-prompt"])
+        .env("CLAUDE_CODE_OAUTH_TOKEN", "sk-ant-oat01-active")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let output = ccswitch(home.path()).args(["list", "--active-only", "--json"]).output().unwrap();
+    assert!(output.status.success());
+    let active: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(active["email"], "ci@example.com");
+    assert_eq!(active["active"], true);
+}
+
+#[test]
+fn list_no_active_renders_every_account_alike() {
+    let home = tempfile::TempDir::new().unwrap();
+    let output = ccswitch(home.path())
+        .args(["-q", "add", "--from-env", "--label", "ci@example.com", "--no-prompt"])
+        .env("CLAUDE_CODE_OAUTH_TOKEN", "sk-ant-oat01-noactive")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let output = ccswitch(home.path()).args(["--no-color", "list"]).output().unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).contains("(active)"));
+
+    for extra in [&[][..], &["--table"]] {
+        let args = [&["--no-color", "list", "--no-active"][..], extra].concat();
+        let output = ccswitch(home.path()).args(&args).output().unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("ci@example.com"), "{args:?}: {stdout}");
+        assert!(!stdout.contains("(active)") && !stdout.contains('▶'), "{args:?}: {stdout}");
+    }
+}
+
+#[test]
+fn token_validate_reports_api_status() {
+    use std::io::{BufRead, BufReader, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        let mut auth = Vec::new();
+        for status in ["200 OK", "401 Unauthorized", "529 Overloaded"] {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                if let Some(v) = line.strip_prefix("Authorization: ") {
+                    auth.push(v.trim().to_string());
+                }
+                line.clear();
+            }
+            write!(reader.get_mut(), "HTTP/1.1 {status}\r\nContent-Length: 2\r\n\r\n{{}}").unwrap();
+        }
+        auth
+    });
+
+    let home = tempfile::TempDir::new().unwrap();
+    let validate = |token: &str| {
+        let mut child = ccswitch(home.path())
+            .args(["--no-color", "token", "validate"])
+            .env("CCSWITCH_API_BASE", &base)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        writeln!(child.stdin.take().unwrap(), "{token}").unwrap();
+        child.wait_with_output().unwrap()
+    };
+
+    let output = validate("sk-ant-oat01-good");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Token is valid"));
+
+    let output = ccswitch(home.path())
+        .args(["--no-color", "token", "validate", "--from-env"])
+        .env("CCSWITCH_API_BASE", &base)
+        .env("CLAUDE_CODE_OAUTH_TOKEN", "sk-ant-oat01-bad")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Token is invalid: HTTP 401"));
+
+    // A server error says nothing about the token.
+    let output = validate("sk-ant-oat01-maybe");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Could not verify the token: HTTP 529"));
+
+    assert_eq!(
+        server.join().unwrap(),
+        ["Bearer sk-ant-oat01-good", "Bearer sk-ant-oat01-bad", "Bearer sk-ant-oat01-maybe"]
+    );
+}
+
+#[test]
+fn backup_dir_and_sequence_file_follow_overrides() {
+    let home = tempfile::TempDir::new().unwrap();
+    let path_of = |args: &[&str], envs: &[(&str, &std::path::Path)]| {
+        let mut cmd = ccswitch(home.path());
+        cmd.env_remove("CCSWITCH_BACKUP_DIR").env_remove("CCSWITCH_SEQUENCE_FILE");
+        for (k, v) in envs {
+            cmd.env(k, v);
+        }
+        let output = cmd.args(args).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        std::path::PathBuf::from(String::from_utf8(output.stdout).unwrap().trim_end())
+    };
+
+    let dir = path_of(&["backup-dir"], &[]);
+    assert!(dir.starts_with(home.path()) && dir.ends_with("default"), "{}", dir.display());
+    assert_eq!(path_of(&["sequence-file"], &[]), dir.join("sequence.json"));
+
+    path_of(&["profile", "create", "work"], &[]);
+    let work = path_of(&["--profile", "work", "backup-dir"], &[]);
+    assert_eq!(work, dir.with_file_name("work"));
+
+    let custom = home.path().join("custom");
+    let seq = home.path().join("index.json");
+    let envs = [
+        ("CCSWITCH_BACKUP_DIR", custom.as_path()),
+        ("CCSWITCH_SEQUENCE_FILE", seq.as_path()),
+    ];
+    assert_eq!(path_of(&["backup-dir"], &envs), custom);
+    assert_eq!(path_of(&["sequence-file"], &envs), seq);
+}
@@ -0,0 +1,53 @@
+//! Drives the account store through the library API alone, the way an
+//! embedding tool would, without running the binary.
+
+use std::fs;
+use std::path::Path;
+
+fn log_in(home: &Path, email: &str, uuid: &str) {
+    let creds = serde_json::json!({
+        "claudeAiOauth": {
+            "accessToken": format!("sk-ant-oat01-{uuid}"),
+            "refreshToken": format!("sk-ant-ort01-{uuid}"),
+            "expiresAt": chrono::Utc::now().timestamp_millis() + 3_600_000
+        }
+    });
+    let config = serde_json::json!({
+        "oauthAccount": { "emailAddress": email, "accountUuid": uuid }
+    });
+    fs::create_dir_all(home.join(".claude")).unwrap();
+    fs::write(home.join(".claude/.credentials.json"), creds.to_string()).unwrap();
+    fs::write(home.join(".claude.json"), config.to_string()).unwrap();
+}
+
+#[test]
+fn add_list_and_switch_through_the_library() {
+    let home = tempfile::TempDir::new().unwrap();
+    std::env::set_var("CCSWITCH_HOME", home.path());
+    for var in ["CLAUDE_CONFIG_DIR", "CLAUDE_CODE_OAUTH_TOKEN", "PASSWORD_STORE_DIR"] {
+        std::env::remove_var(var);
+    }
+
+    log_in(home.path(), "me@home.com", "uuid-home");
+    let home_creds = ccswitch::read_live().unwrap();
+    let opts = ccswitch::AddOptions::default();
+    assert_eq!(ccswitch::core_add(&opts).unwrap(), "Added me@home.com as Account 1");
+    log_in(home.path(), "me@work.com", "uuid-work");
+    assert_eq!(ccswitch::core_add(&opts).unwrap(), "Added me@work.com as Account 2");
+
+    let accounts = ccswitch::core_list(None).unwrap();
+    let summary: Vec<_> = accounts.iter().map(|a| (a.number, a.email.as_str(), a.active)).collect();
+    assert_eq!(summary, [(1, "me@home.com", false), (2, "me@work.com", true)]);
+    assert_eq!(accounts[0].auth_kind, ccswitch::AuthKind::Oauth);
+
+    let result = ccswitch::core_switch(1).unwrap();
+    assert_eq!(result.active_account, 1);
+    assert_eq!(ccswitch::read_live().unwrap(), home_creds);
+
+    let status = ccswitch::core_status().unwrap();
+    assert_eq!(status.active.unwrap().email, "me@home.com");
+    assert!(matches!(
+        ccswitch::core_switch(9),
+        Err(ccswitch::CcswitchError::AccountNotFound(_))
+    ));
+}